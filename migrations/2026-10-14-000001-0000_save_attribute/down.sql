@@ -0,0 +1 @@
+DROP TABLE save_attribute
@@ -0,0 +1,7 @@
+CREATE TABLE save_attribute (
+    game_save_uuid TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (game_save_uuid, key),
+    FOREIGN KEY(game_save_uuid) REFERENCES game_save(uuid)
+    );
@@ -0,0 +1,2 @@
+ALTER TABLE game_path DROP COLUMN updated_at;
+ALTER TABLE game_executable DROP COLUMN updated_at;
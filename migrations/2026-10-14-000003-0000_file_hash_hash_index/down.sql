@@ -0,0 +1 @@
+DROP INDEX file_hash_hash_index;
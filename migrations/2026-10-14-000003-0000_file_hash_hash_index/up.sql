@@ -0,0 +1 @@
+CREATE INDEX file_hash_hash_index ON file_hash (hash);
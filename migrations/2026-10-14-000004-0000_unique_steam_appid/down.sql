@@ -0,0 +1,7 @@
+DROP INDEX game_metadata_steam_appid_index;
+
+UPDATE game_metadata SET steam_appid = (
+    SELECT steam_appid FROM steam_appid_conflict WHERE game_metadata_id = game_metadata.id
+    )
+WHERE id IN (SELECT game_metadata_id FROM steam_appid_conflict);
+DROP TABLE steam_appid_conflict;
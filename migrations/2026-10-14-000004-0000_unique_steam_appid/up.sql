@@ -0,0 +1,18 @@
+-- Every game but the oldest of a duplicated appid loses it, the dropped
+-- values are kept here and logged when the migration runs
+CREATE TABLE steam_appid_conflict (
+    game_metadata_id INTEGER NOT NULL PRIMARY KEY,
+    steam_appid TEXT NOT NULL
+    );
+INSERT INTO steam_appid_conflict (game_metadata_id, steam_appid)
+    SELECT id, steam_appid FROM game_metadata
+    WHERE steam_appid IS NOT NULL
+        AND id NOT IN (
+            SELECT MIN(id) FROM game_metadata WHERE steam_appid IS NOT NULL GROUP BY steam_appid
+        );
+
+UPDATE game_metadata SET steam_appid = NULL
+WHERE id IN (SELECT game_metadata_id FROM steam_appid_conflict);
+
+CREATE UNIQUE INDEX game_metadata_steam_appid_index ON game_metadata (steam_appid)
+WHERE steam_appid IS NOT NULL;
@@ -0,0 +1 @@
+ALTER TABLE file_hash DROP COLUMN algo;
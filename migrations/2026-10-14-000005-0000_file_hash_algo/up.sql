@@ -0,0 +1 @@
+ALTER TABLE file_hash ADD COLUMN algo TEXT NOT NULL DEFAULT 'sha256';
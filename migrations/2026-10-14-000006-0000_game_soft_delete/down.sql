@@ -0,0 +1,7 @@
+UPDATE game_metadata SET steam_appid = NULL WHERE is_deleted = 1;
+
+DROP INDEX game_metadata_steam_appid_index;
+CREATE UNIQUE INDEX game_metadata_steam_appid_index ON game_metadata (steam_appid)
+WHERE steam_appid IS NOT NULL;
+
+ALTER TABLE game_metadata DROP COLUMN is_deleted;
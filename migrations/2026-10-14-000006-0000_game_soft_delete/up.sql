@@ -0,0 +1,5 @@
+ALTER TABLE game_metadata ADD COLUMN is_deleted BOOLEAN NOT NULL DEFAULT 0;
+
+DROP INDEX game_metadata_steam_appid_index;
+CREATE UNIQUE INDEX game_metadata_steam_appid_index ON game_metadata (steam_appid)
+WHERE steam_appid IS NOT NULL AND is_deleted = 0;
@@ -0,0 +1,2 @@
+ALTER TABLE game_metadata DROP COLUMN updated_at;
+ALTER TABLE game_metadata DROP COLUMN created_at;
@@ -0,0 +1,3 @@
+DROP INDEX game_save_user_id_index;
+ALTER TABLE game_save DROP COLUMN user_id;
+DROP TABLE user;
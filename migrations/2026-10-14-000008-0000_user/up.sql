@@ -0,0 +1,8 @@
+CREATE TABLE user (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    token_hash TEXT NOT NULL UNIQUE
+    );
+
+ALTER TABLE game_save ADD COLUMN user_id INTEGER REFERENCES user(id);
+CREATE INDEX game_save_user_id_index ON game_save (user_id);
@@ -0,0 +1,2 @@
+DROP INDEX file_hash_game_save_uuid_index;
+DROP INDEX game_save_path_id_time_index;
@@ -0,0 +1,2 @@
+CREATE INDEX game_save_path_id_time_index ON game_save (path_id, time);
+CREATE INDEX file_hash_game_save_uuid_index ON file_hash (game_save_uuid);
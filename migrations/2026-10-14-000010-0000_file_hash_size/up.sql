@@ -0,0 +1 @@
+ALTER TABLE file_hash ADD COLUMN size BIGINT;
@@ -0,0 +1,10 @@
+ALTER TABLE game_save ADD COLUMN generation BIGINT NOT NULL DEFAULT 0;
+
+-- Number the saves already stored in upload order, per path
+UPDATE game_save SET generation = (
+    SELECT COUNT(*) FROM game_save AS older
+    WHERE older.path_id = game_save.path_id
+    AND (older.time < game_save.time OR (older.time = game_save.time AND older.uuid <= game_save.uuid))
+    );
+
+CREATE UNIQUE INDEX game_save_path_id_generation_index ON game_save (path_id, generation);
@@ -0,0 +1 @@
+ALTER TABLE game_save DROP COLUMN pinned;
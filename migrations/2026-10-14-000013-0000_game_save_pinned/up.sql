@@ -0,0 +1 @@
+ALTER TABLE game_save ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT 0;
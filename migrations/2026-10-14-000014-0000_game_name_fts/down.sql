@@ -0,0 +1,7 @@
+DROP TRIGGER game_alt_name_fts_delete;
+DROP TRIGGER game_alt_name_fts_update;
+DROP TRIGGER game_alt_name_fts_insert;
+DROP TRIGGER game_metadata_fts_delete;
+DROP TRIGGER game_metadata_fts_update;
+DROP TRIGGER game_metadata_fts_insert;
+DROP TABLE game_name_fts;
@@ -0,0 +1,45 @@
+-- One row per default or alternative name, `is_alt` tells them apart when
+-- a game uses the same text for both
+CREATE VIRTUAL TABLE game_name_fts USING fts5(
+    name,
+    game_metadata_id UNINDEXED,
+    is_alt UNINDEXED,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO game_name_fts (name, game_metadata_id, is_alt)
+SELECT default_name, id, 0 FROM game_metadata;
+INSERT INTO game_name_fts (name, game_metadata_id, is_alt)
+SELECT name, game_metadata_id, 1 FROM game_alt_name;
+
+CREATE TRIGGER game_metadata_fts_insert AFTER INSERT ON game_metadata BEGIN
+    INSERT INTO game_name_fts (name, game_metadata_id, is_alt)
+    VALUES (new.default_name, new.id, 0);
+END;
+
+CREATE TRIGGER game_metadata_fts_update AFTER UPDATE OF default_name ON game_metadata BEGIN
+    DELETE FROM game_name_fts WHERE game_metadata_id = old.id AND is_alt = 0;
+    INSERT INTO game_name_fts (name, game_metadata_id, is_alt)
+    VALUES (new.default_name, new.id, 0);
+END;
+
+CREATE TRIGGER game_metadata_fts_delete AFTER DELETE ON game_metadata BEGIN
+    DELETE FROM game_name_fts WHERE game_metadata_id = old.id;
+END;
+
+CREATE TRIGGER game_alt_name_fts_insert AFTER INSERT ON game_alt_name BEGIN
+    INSERT INTO game_name_fts (name, game_metadata_id, is_alt)
+    VALUES (new.name, new.game_metadata_id, 1);
+END;
+
+CREATE TRIGGER game_alt_name_fts_update AFTER UPDATE ON game_alt_name BEGIN
+    DELETE FROM game_name_fts
+    WHERE game_metadata_id = old.game_metadata_id AND is_alt = 1 AND name = old.name;
+    INSERT INTO game_name_fts (name, game_metadata_id, is_alt)
+    VALUES (new.name, new.game_metadata_id, 1);
+END;
+
+CREATE TRIGGER game_alt_name_fts_delete AFTER DELETE ON game_alt_name BEGIN
+    DELETE FROM game_name_fts
+    WHERE game_metadata_id = old.game_metadata_id AND is_alt = 1 AND name = old.name;
+END;
@@ -0,0 +1 @@
+DROP INDEX game_alt_name_nocase_index;
@@ -0,0 +1,9 @@
+-- Keep the first spelling of the names differing only by case, the index
+-- below then keeps a later insert from adding another one
+DELETE FROM game_alt_name
+WHERE rowid NOT IN (
+    SELECT MIN(rowid) FROM game_alt_name GROUP BY game_metadata_id, name COLLATE NOCASE
+);
+
+CREATE UNIQUE INDEX game_alt_name_nocase_index
+ON game_alt_name (game_metadata_id, name COLLATE NOCASE);
@@ -0,0 +1,2 @@
+ALTER TABLE game_metadata DROP COLUMN retention_max_age_days;
+ALTER TABLE game_metadata DROP COLUMN retention_keep;
@@ -0,0 +1,2 @@
+ALTER TABLE game_metadata ADD COLUMN retention_keep INTEGER;
+ALTER TABLE game_metadata ADD COLUMN retention_max_age_days INTEGER;
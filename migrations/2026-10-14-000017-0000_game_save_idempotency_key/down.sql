@@ -0,0 +1,2 @@
+DROP INDEX game_save_path_id_idempotency_key_index;
+ALTER TABLE game_save DROP COLUMN idempotency_key;
@@ -0,0 +1,3 @@
+ALTER TABLE game_save ADD COLUMN idempotency_key TEXT;
+CREATE UNIQUE INDEX game_save_path_id_idempotency_key_index ON game_save (path_id, idempotency_key)
+    WHERE idempotency_key IS NOT NULL;
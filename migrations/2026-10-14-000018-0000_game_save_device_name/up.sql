@@ -0,0 +1 @@
+ALTER TABLE game_save ADD COLUMN device_name TEXT;
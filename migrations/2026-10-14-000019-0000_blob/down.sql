@@ -0,0 +1,18 @@
+CREATE TABLE file_hash_expanded (
+    relative_path TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    game_save_uuid TEXT NOT NULL,
+    algo TEXT NOT NULL DEFAULT 'sha256',
+    size BIGINT,
+    PRIMARY KEY (relative_path, game_save_uuid)
+    FOREIGN KEY(game_save_uuid) REFERENCES game_save(uuid)
+    );
+INSERT INTO file_hash_expanded (relative_path, hash, game_save_uuid, algo, size)
+    SELECT f.relative_path, b.hash, f.game_save_uuid, b.algo, b.size
+    FROM file_hash f JOIN blob b ON b.id = f.blob_id;
+
+DROP TABLE file_hash;
+DROP TABLE blob;
+ALTER TABLE file_hash_expanded RENAME TO file_hash;
+CREATE INDEX file_hash_hash_index ON file_hash (hash);
+CREATE INDEX file_hash_game_save_uuid_index ON file_hash (game_save_uuid);
@@ -0,0 +1,33 @@
+CREATE TABLE blob (
+    id INTEGER NOT NULL PRIMARY KEY,
+    hash TEXT NOT NULL,
+    algo TEXT NOT NULL DEFAULT 'sha256',
+    size BIGINT,
+    UNIQUE (hash, algo)
+    );
+
+DROP INDEX file_hash_hash_index;
+DROP INDEX file_hash_game_save_uuid_index;
+ALTER TABLE file_hash RENAME TO file_hash_uncompacted;
+
+CREATE TABLE file_hash (
+    relative_path TEXT NOT NULL,
+    game_save_uuid TEXT NOT NULL,
+    blob_id INTEGER NOT NULL,
+    PRIMARY KEY (relative_path, game_save_uuid)
+    FOREIGN KEY(game_save_uuid) REFERENCES game_save(uuid)
+    FOREIGN KEY(blob_id) REFERENCES blob(id)
+    );
+CREATE INDEX file_hash_game_save_uuid_index ON file_hash (game_save_uuid);
+CREATE INDEX file_hash_blob_id_index ON file_hash (blob_id);
+
+-- One blob per distinct content, the largest recorded size wins for rows
+-- uploaded before sizes were known
+INSERT INTO blob (hash, algo, size)
+    SELECT hash, algo, MAX(size) FROM file_hash_uncompacted
+    GROUP BY hash, algo;
+INSERT INTO file_hash (relative_path, game_save_uuid, blob_id)
+    SELECT u.relative_path, u.game_save_uuid, b.id
+    FROM file_hash_uncompacted u
+    JOIN blob b ON b.hash = u.hash AND b.algo = u.algo;
+DROP TABLE file_hash_uncompacted;
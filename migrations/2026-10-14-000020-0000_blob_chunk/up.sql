@@ -0,0 +1,9 @@
+CREATE TABLE blob_chunk (
+    blob_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    chunk_hash TEXT NOT NULL,
+    chunk_size BIGINT NOT NULL,
+    PRIMARY KEY (blob_id, position)
+    FOREIGN KEY(blob_id) REFERENCES blob(id)
+    );
+CREATE INDEX blob_chunk_chunk_hash_index ON blob_chunk (chunk_hash);
@@ -0,0 +1,2 @@
+DROP INDEX game_metadata_canonical_name_index;
+ALTER TABLE game_metadata DROP COLUMN canonical_name;
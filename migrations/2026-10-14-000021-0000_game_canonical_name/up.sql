@@ -0,0 +1,5 @@
+-- canonical_game_name is registered by the server on its connections, the
+-- normalization lives in Rust
+ALTER TABLE game_metadata ADD COLUMN canonical_name TEXT;
+UPDATE game_metadata SET canonical_name = canonical_game_name(default_name);
+CREATE INDEX game_metadata_canonical_name_index ON game_metadata (canonical_name);
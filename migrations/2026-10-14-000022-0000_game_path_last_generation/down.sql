@@ -0,0 +1 @@
+ALTER TABLE game_path DROP COLUMN last_generation;
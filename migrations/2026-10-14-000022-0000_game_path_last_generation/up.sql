@@ -0,0 +1,8 @@
+-- Highest generation ever given to a save of the path, deleting that save
+-- must not let the next one take its generation again
+ALTER TABLE game_path ADD COLUMN last_generation BIGINT NOT NULL DEFAULT 0;
+
+UPDATE game_path SET last_generation = COALESCE(
+    (SELECT MAX(generation) FROM game_save WHERE game_save.path_id = game_path.id),
+    0
+);
@@ -0,0 +1,4 @@
+DROP INDEX game_save_path_id_user_id_idempotency_key_index;
+
+CREATE UNIQUE INDEX game_save_path_id_idempotency_key_index ON game_save (path_id, idempotency_key)
+    WHERE idempotency_key IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Keys only have to be unique among the saves one user uploaded to a path,
+-- saves of shared tokens have no user and count as one more user
+DROP INDEX game_save_path_id_idempotency_key_index;
+
+CREATE UNIQUE INDEX game_save_path_id_user_id_idempotency_key_index
+ON game_save (path_id, IFNULL(user_id, 0), idempotency_key)
+WHERE idempotency_key IS NOT NULL;
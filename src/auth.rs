@@ -0,0 +1,156 @@
+use axum::extract::Request;
+use axum::http::{StatusCode, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::DATABASE;
+use crate::const_var::API_TOKENS_ENV;
+use crate::database::interface::GameDatabase;
+
+/// Who a request was authenticated as, added to the request extensions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Caller {
+    /// A token from `API_TOKENS`, or any request when no token is configured
+    Shared,
+    User(i32),
+}
+
+impl Caller {
+    pub fn user_id(&self) -> Option<i32> {
+        match self {
+            Caller::Shared => None,
+            Caller::User(user_id) => Some(*user_id),
+        }
+    }
+}
+
+/// Where valid bearer tokens are looked up, implementations only ever see
+/// the SHA-256 hex digest of the presented token
+pub trait TokenStore: Send + Sync {
+    fn find_token_hash(&self, token_hash: &str) -> Option<Caller>;
+
+    fn is_empty(&self) -> bool;
+}
+
+impl TokenStore for HashSet<String> {
+    fn find_token_hash(&self, token_hash: &str) -> Option<Caller> {
+        self.contains(token_hash).then_some(Caller::Shared)
+    }
+
+    fn is_empty(&self) -> bool {
+        HashSet::is_empty(self)
+    }
+}
+
+impl TokenStore for GameDatabase {
+    fn find_token_hash(&self, token_hash: &str) -> Option<Caller> {
+        match self.get_user_id_by_token_hash(token_hash) {
+            Ok(user_id) => user_id.map(Caller::User),
+            Err(e) => {
+                eprintln!("Error looking up user token: {}", e);
+                None
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        // Failing closed, a database error must not open the API
+        self.count_users().is_ok_and(|users| users == 0)
+    }
+}
+
+/// Token handed out once to a new user, only its hash is stored
+pub fn generate_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+pub static API_TOKENS: Lazy<HashSet<String>> = Lazy::new(|| {
+    std::env::var(API_TOKENS_ENV)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(hash_token)
+        .collect()
+});
+
+fn authorize(stores: &[&dyn TokenStore], authorization: Option<&str>) -> Option<Caller> {
+    if stores.iter().all(|store| store.is_empty()) {
+        return Some(Caller::Shared);
+    }
+    let token_hash = hash_token(authorization?.strip_prefix("Bearer ")?.trim());
+    stores
+        .iter()
+        .find_map(|store| store.find_token_hash(&token_hash))
+}
+
+/// Rejects requests without a valid `Authorization: Bearer <token>`, the API
+/// stays open when no token or user is configured
+pub async fn require_bearer_token(
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    let Some(caller) = authorize(&[&*API_TOKENS, &*DATABASE], authorization) else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    request.extensions_mut().insert(caller);
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> HashSet<String> {
+        HashSet::from([hash_token("secret-token")])
+    }
+
+    #[test]
+    fn missing_header_is_rejected() {
+        assert_eq!(authorize(&[&store()], None), None);
+    }
+
+    #[test]
+    fn wrong_token_is_rejected() {
+        assert_eq!(authorize(&[&store()], Some("Bearer other-token")), None);
+        // The token has to come as a bearer token
+        assert_eq!(authorize(&[&store()], Some("secret-token")), None);
+    }
+
+    #[test]
+    fn valid_token_is_the_shared_caller() {
+        assert_eq!(
+            authorize(&[&store()], Some("Bearer secret-token")),
+            Some(Caller::Shared)
+        );
+    }
+
+    #[test]
+    fn no_configured_token_leaves_the_api_open() {
+        assert_eq!(authorize(&[&HashSet::new()], None), Some(Caller::Shared));
+    }
+
+    #[test]
+    fn user_token_is_found_in_the_database() {
+        let database = GameDatabase::new_in_memory().unwrap();
+        let user_id = database
+            .add_user("alice", &hash_token("alice-token"))
+            .unwrap();
+        assert_eq!(
+            authorize(&[&store(), &database], Some("Bearer alice-token")),
+            Some(Caller::User(user_id))
+        );
+    }
+}
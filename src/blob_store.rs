@@ -0,0 +1,696 @@
+use crate::chunking::{ChunkingOptions, chunk_hashing};
+use crate::const_var::{DEFAULT_BLOB_COMPRESSION_LEVEL, DEFAULT_BLOB_SHARD_DEPTH};
+use crate::datatype_endpoint::ChunkedFile;
+use crate::file_system::HashOptions;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
+use std::io::{BufReader, Error, ErrorKind, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// Suffix of blobs stored gzip compressed, the hash is always the one of
+/// the uncompressed content
+const GZIP_SUFFIX: &str = ".gz";
+
+/// Content addressable storage, every blob is named by the SHA-256 of its
+/// original content. The database and the routes only go through this
+/// trait, `FsBlobStore` is the local disk backend and another one such as
+/// object storage only has to provide the required methods
+pub trait BlobStore: Send + Sync {
+    /// Stores the stream and returns its SHA-256, storing content that is
+    /// already present is a no-op. The backend may compress it
+    fn put_stream(&self, reader: &mut dyn Read) -> std::io::Result<String>;
+
+    /// The original content read lazily, `None` when the blob is not stored
+    fn get_stream(&self, hash: &str) -> std::io::Result<Option<Box<dyn Read + Send>>>;
+
+    /// Deletes the blob, returns the bytes freed in the backend
+    fn delete(&self, hash: &str) -> std::io::Result<u64>;
+
+    fn exists(&self, hash: &str) -> std::io::Result<bool>;
+
+    /// Every stored blob with the time it was last written, sorted
+    fn list(&self) -> std::io::Result<Vec<(String, SystemTime)>>;
+
+    /// Bytes the blob takes in the backend, what `delete` frees
+    fn stored_size(&self, hash: &str) -> std::io::Result<u64>;
+
+    /// The blob as stored, possibly compressed, with a reader that can seek
+    /// so a byte range of it is served without reading what comes before
+    fn open_stored(&self, hash: &str) -> std::io::Result<Option<StoredBlob>>;
+
+    /// Same as `put_stream` for content already held in memory
+    fn put(&self, mut data: &[u8]) -> std::io::Result<String> {
+        self.put_stream(&mut data)
+    }
+
+    /// Same as `put_stream` without compressing, for content that already
+    /// is. A backend that never compresses keeps the default
+    fn put_raw_stream(&self, reader: &mut dyn Read) -> std::io::Result<String> {
+        self.put_stream(reader)
+    }
+
+    /// Files of at least this many bytes are stored as content-defined
+    /// chunks by `put_chunked` callers, `None` keeps every file whole
+    fn chunk_threshold(&self) -> Option<u64> {
+        None
+    }
+
+    fn chunking_options(&self) -> ChunkingOptions {
+        ChunkingOptions::default()
+    }
+
+    /// Whether a file of `size` bytes should go through `put_chunked`
+    fn should_chunk(&self, size: u64) -> bool {
+        self.chunk_threshold()
+            .is_some_and(|threshold| size >= threshold)
+    }
+
+    /// Splits the stream into content-defined chunks and stores each one
+    /// not stored yet as its own blob, only the chunks a change touched
+    /// take space again. The whole content is never stored, record the
+    /// returned chunk list so it can be read back
+    fn put_chunked(&self, reader: &mut dyn Read) -> std::io::Result<ChunkedFile> {
+        chunk_hashing(reader, self.chunking_options(), |data, hash| {
+            if !self.exists(hash)? {
+                self.put(data)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// SHA-256 of the content read back from the backend, `None` when the
+    /// blob is not stored. A blob that does not decode is an error
+    fn rehash(&self, hash: &str) -> std::io::Result<Option<String>> {
+        match self.get_stream(hash)? {
+            Some(reader) => Ok(Some(copy_hashing(
+                reader,
+                std::io::sink(),
+                HashOptions::default(),
+            )?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl dyn BlobStore + '_ {
+    /// Reads the blobs of `chunks` one after the other as a single stream,
+    /// each one is only opened once the previous one is read to its end. A
+    /// chunk that is not stored fails the read with `NotFound`
+    pub fn get_chunks_stream(&self, chunks: Vec<String>) -> Box<dyn Read + Send + '_> {
+        Box::new(ChunksReader {
+            store: self,
+            chunks: chunks.into_iter(),
+            current: None,
+        })
+    }
+}
+
+/// Blobs on local disk, with the default shard depth of 1 a blob lives at
+/// `<root>/<hash[..2]>/<hash[2..]>`, with a `.gz` suffix when it is stored
+/// compressed
+pub struct FsBlobStore {
+    root: PathBuf,
+    /// Levels of 2 hex digit directories in front of a blob, from 0 to 2,
+    /// the file name is what remains of the hash after them
+    shard_depth: usize,
+    /// gzip level from 0 to 9, 0 stores every blob as is
+    compression_level: u32,
+    /// Chunking of the streams written by `put_stream`
+    hash_options: HashOptions,
+    /// Files of at least this many bytes are stored as content-defined
+    /// chunks by `put_chunked` callers, `None` keeps every file whole
+    chunk_threshold: Option<u64>,
+    chunking_options: ChunkingOptions,
+}
+
+/// How a blob is laid out on disk
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlobEncoding {
+    Identity,
+    Gzip,
+}
+
+pub trait SeekRead: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> SeekRead for T {}
+
+/// Stored bytes of a blob from `BlobStore::open_stored`, `len` and the
+/// offsets of `reader` are in the stored encoding, not in the content
+pub struct StoredBlob {
+    pub reader: Box<dyn SeekRead>,
+    pub len: u64,
+    pub encoding: BlobEncoding,
+}
+
+pub(crate) fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64
+        && hash
+            .bytes()
+            .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Copies `reader` into `writer` in fixed size chunks, returns the SHA-256
+/// of everything read
+fn copy_hashing(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    options: HashOptions,
+) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut chunk = vec![0u8; options.chunk_size];
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&chunk[..read]);
+        writer.write_all(&chunk[..read])?;
+    }
+    writer.flush()?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+impl FsBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            shard_depth: DEFAULT_BLOB_SHARD_DEPTH,
+            compression_level: DEFAULT_BLOB_COMPRESSION_LEVEL,
+            hash_options: HashOptions::default(),
+            chunk_threshold: None,
+            chunking_options: ChunkingOptions::default(),
+        }
+    }
+
+    pub fn with_hash_options(mut self, hash_options: HashOptions) -> Self {
+        self.hash_options = hash_options;
+        self
+    }
+
+    pub fn with_compression_level(mut self, level: u32) -> Self {
+        self.compression_level = level.min(9);
+        self
+    }
+
+    /// Opts files of at least `threshold` bytes into chunked storage
+    pub fn with_chunk_threshold(mut self, threshold: Option<u64>) -> Self {
+        self.chunk_threshold = threshold;
+        self
+    }
+
+    /// Has to match the layout already on disk, use `reshard` to change it
+    /// for an existing store
+    pub fn with_shard_depth(mut self, depth: usize) -> Self {
+        self.shard_depth = depth.min(2);
+        self
+    }
+
+    fn blob_path(&self, hash: &str) -> std::io::Result<PathBuf> {
+        if !is_valid_hash(hash) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid blob hash: {hash}"),
+            ));
+        }
+        Ok(Self::sharded_path(&self.root, hash, self.shard_depth))
+    }
+
+    fn sharded_path(root: &Path, hash: &str, depth: usize) -> PathBuf {
+        let mut path = root.to_path_buf();
+        for level in 0..depth {
+            path.push(&hash[level * 2..level * 2 + 2]);
+        }
+        path.push(&hash[depth * 2..]);
+        path
+    }
+
+    fn gzip_path(path: &Path) -> PathBuf {
+        let mut gzip_path = path.as_os_str().to_owned();
+        gzip_path.push(GZIP_SUFFIX);
+        PathBuf::from(gzip_path)
+    }
+
+    /// Same as `put` without compressing, for content that already is
+    pub fn put_raw(&self, data: &[u8]) -> std::io::Result<String> {
+        let hash = format!("{:x}", Sha256::digest(data));
+        let path = self.blob_path(&hash)?;
+        if self.stored_path(&path).is_none() {
+            self.write_atomically(&path, data)?;
+        }
+        Ok(hash)
+    }
+
+    fn write_stream(&self, reader: impl Read, compression_level: u32) -> std::io::Result<String> {
+        std::fs::create_dir_all(&self.root)?;
+        let tmp_path = self.root.join(format!("{}.tmp", Uuid::new_v4()));
+        let result = (|| {
+            let file = std::fs::File::create(&tmp_path)?;
+            let hash = if compression_level == 0 {
+                copy_hashing(reader, file, self.hash_options)?
+            } else {
+                let mut encoder = GzEncoder::new(file, Compression::new(compression_level));
+                let hash = copy_hashing(reader, &mut encoder, self.hash_options)?;
+                encoder.finish()?;
+                hash
+            };
+
+            let path = self.blob_path(&hash)?;
+            if self.stored_path(&path).is_some() {
+                std::fs::remove_file(&tmp_path)?;
+                return Ok(hash);
+            }
+            std::fs::create_dir_all(path.parent().unwrap_or(&self.root))?;
+            let path = if compression_level == 0 {
+                path
+            } else {
+                Self::gzip_path(&path)
+            };
+            std::fs::rename(&tmp_path, path)?;
+            Ok(hash)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+        result
+    }
+
+    fn stored_path(&self, path: &Path) -> Option<(PathBuf, BlobEncoding)> {
+        let gzip_path = Self::gzip_path(path);
+        if gzip_path.exists() {
+            Some((gzip_path, BlobEncoding::Gzip))
+        } else if path.exists() {
+            Some((path.to_path_buf(), BlobEncoding::Identity))
+        } else {
+            None
+        }
+    }
+
+    fn write_atomically(&self, path: &Path, data: &[u8]) -> std::io::Result<()> {
+        let shard = path.parent().unwrap_or(&self.root);
+        std::fs::create_dir_all(shard)?;
+        let tmp_path = shard.join(format!("{}.tmp", Uuid::new_v4()));
+        std::fs::write(&tmp_path, data)?;
+        if let Err(e) = std::fs::rename(&tmp_path, path) {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Every blob file found at the configured depth with the hash it is
+    /// stored under, a blob kept in both encodings shows up twice
+    fn stored_files(&self) -> std::io::Result<Vec<(String, PathBuf)>> {
+        let mut files = Vec::new();
+        Self::collect_files(&self.root, String::new(), self.shard_depth, &mut files)?;
+        Ok(files)
+    }
+
+    fn collect_files(
+        dir: &Path,
+        prefix: String,
+        depth: usize,
+        files: &mut Vec<(String, PathBuf)>,
+    ) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if depth > 0 {
+                if file_type.is_dir() {
+                    let prefix = format!("{}{}", prefix, file_name);
+                    Self::collect_files(&entry.path(), prefix, depth - 1, files)?;
+                }
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+            let file_name = file_name.strip_suffix(GZIP_SUFFIX).unwrap_or(&file_name);
+            let hash = format!("{}{}", prefix, file_name);
+            if is_valid_hash(&hash) {
+                files.push((hash, entry.path()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves every blob of a store laid out with `from_depth` to the depth
+    /// this store is configured with, returns how many files were moved.
+    /// Files are only renamed, the content is neither read nor re-hashed,
+    /// and directories left empty are removed. A blob already present at
+    /// its new place is the same content, the old copy is deleted. Interrupted
+    /// halfway it can be run again, blobs already moved are not found at
+    /// `from_depth` anymore. Nothing may write to the store while it runs
+    pub fn reshard(&self, from_depth: usize) -> std::io::Result<usize> {
+        let from_depth = from_depth.min(2);
+        if from_depth == self.shard_depth || !self.root.exists() {
+            return Ok(0);
+        }
+        let mut files = Vec::new();
+        Self::collect_files(&self.root, String::new(), from_depth, &mut files)?;
+
+        let mut moved = 0;
+        for (hash, old_path) in files {
+            let mut new_path = self.blob_path(&hash)?;
+            if old_path.to_string_lossy().ends_with(GZIP_SUFFIX) {
+                new_path = Self::gzip_path(&new_path);
+            }
+            if new_path.exists() {
+                std::fs::remove_file(&old_path)?;
+                continue;
+            }
+            std::fs::create_dir_all(new_path.parent().unwrap_or(&self.root))?;
+            std::fs::rename(&old_path, &new_path)?;
+            moved += 1;
+        }
+        Self::remove_empty_dirs(&self.root)?;
+        Ok(moved)
+    }
+
+    /// Removes the empty directories below `dir`, `dir` itself is kept
+    fn remove_empty_dirs(dir: &Path) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                Self::remove_empty_dirs(&entry.path())?;
+                if std::fs::read_dir(entry.path())?.next().is_none() {
+                    std::fs::remove_dir(entry.path())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The original content, decompressed when it is stored compressed
+    pub fn get(&self, hash: &str) -> std::io::Result<Option<Vec<u8>>> {
+        match self.get_raw(hash)? {
+            Some((data, BlobEncoding::Identity)) => Ok(Some(data)),
+            Some((data, BlobEncoding::Gzip)) => {
+                let mut decompressed = Vec::new();
+                GzDecoder::new(data.as_slice()).read_to_end(&mut decompressed)?;
+                Ok(Some(decompressed))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The bytes as stored on disk along with how they are encoded, lets a
+    /// gzip blob be served as is to a client that accepts it
+    pub fn get_raw(&self, hash: &str) -> std::io::Result<Option<(Vec<u8>, BlobEncoding)>> {
+        let Some((path, encoding)) = self.stored_path(&self.blob_path(hash)?) else {
+            return Ok(None);
+        };
+        match std::fs::read(path) {
+            Ok(data) => Ok(Some((data, encoding))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl BlobStore for FsBlobStore {
+    /// The hash is computed while the stream is written to disk chunk by
+    /// chunk, the blob is gzip compressed unless the level is 0
+    fn put_stream(&self, reader: &mut dyn Read) -> std::io::Result<String> {
+        self.write_stream(reader, self.compression_level)
+    }
+
+    /// Reads the original content lazily, decompressing on the fly
+    fn get_stream(&self, hash: &str) -> std::io::Result<Option<Box<dyn Read + Send>>> {
+        let Some((path, encoding)) = self.stored_path(&self.blob_path(hash)?) else {
+            return Ok(None);
+        };
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(match encoding {
+            BlobEncoding::Identity => Box::new(file),
+            BlobEncoding::Gzip => Box::new(GzDecoder::new(BufReader::new(file))),
+        }))
+    }
+
+    /// Deletes both encodings of the blob, returns the bytes freed on disk
+    fn delete(&self, hash: &str) -> std::io::Result<u64> {
+        let path = self.blob_path(hash)?;
+        let mut freed = 0;
+        for path in [Self::gzip_path(&path), path] {
+            let len = match std::fs::metadata(&path) {
+                Ok(metadata) => metadata.len(),
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            match std::fs::remove_file(path) {
+                Ok(()) => freed += len,
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(freed)
+    }
+
+    /// Whether the blob is stored in either encoding
+    fn exists(&self, hash: &str) -> std::io::Result<bool> {
+        Ok(self.stored_path(&self.blob_path(hash)?).is_some())
+    }
+
+    /// Every blob file at the configured depth with its modification time
+    fn list(&self) -> std::io::Result<Vec<(String, SystemTime)>> {
+        let mut blobs = Vec::new();
+        for (hash, path) in self.stored_files()? {
+            blobs.push((hash, std::fs::metadata(path)?.modified()?));
+        }
+        blobs.sort();
+        Ok(blobs)
+    }
+
+    /// Bytes both encodings of the blob take on disk
+    fn stored_size(&self, hash: &str) -> std::io::Result<u64> {
+        let path = self.blob_path(hash)?;
+        let mut size = 0;
+        for path in [Self::gzip_path(&path), path] {
+            match std::fs::metadata(&path) {
+                Ok(metadata) => size += metadata.len(),
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(size)
+    }
+
+    fn open_stored(&self, hash: &str) -> std::io::Result<Option<StoredBlob>> {
+        let Some((path, encoding)) = self.stored_path(&self.blob_path(hash)?) else {
+            return Ok(None);
+        };
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(StoredBlob {
+            len: file.metadata()?.len(),
+            reader: Box::new(file),
+            encoding,
+        }))
+    }
+
+    /// The blob is gzip compressed unless that does not make it smaller
+    fn put(&self, data: &[u8]) -> std::io::Result<String> {
+        if self.compression_level == 0 {
+            return self.put_raw(data);
+        }
+        let hash = format!("{:x}", Sha256::digest(data));
+        let path = self.blob_path(&hash)?;
+        if self.stored_path(&path).is_some() {
+            return Ok(hash);
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::new(self.compression_level));
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        if compressed.len() >= data.len() {
+            self.write_atomically(&path, data)?;
+        } else {
+            self.write_atomically(&Self::gzip_path(&path), &compressed)?;
+        }
+        Ok(hash)
+    }
+
+    fn put_raw_stream(&self, reader: &mut dyn Read) -> std::io::Result<String> {
+        self.write_stream(reader, 0)
+    }
+
+    fn chunk_threshold(&self) -> Option<u64> {
+        self.chunk_threshold
+    }
+
+    fn chunking_options(&self) -> ChunkingOptions {
+        self.chunking_options
+    }
+
+    /// Reads with the configured hash options, a gzip blob that does not
+    /// decompress is an error
+    fn rehash(&self, hash: &str) -> std::io::Result<Option<String>> {
+        match self.get_stream(hash)? {
+            Some(reader) => Ok(Some(copy_hashing(
+                reader,
+                std::io::sink(),
+                self.hash_options,
+            )?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Stream of `BlobStore::get_chunks_stream`
+struct ChunksReader<'a> {
+    store: &'a dyn BlobStore,
+    chunks: std::vec::IntoIter<String>,
+    current: Option<Box<dyn Read + Send>>,
+}
+
+impl Read for ChunksReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if let Some(reader) = &mut self.current {
+                match reader.read(buf)? {
+                    0 if !buf.is_empty() => self.current = None,
+                    read => return Ok(read),
+                }
+            }
+            let Some(hash) = self.chunks.next() else {
+                return Ok(0);
+            };
+            match self.store.get_stream(&hash)? {
+                Some(reader) => self.current = Some(reader),
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::NotFound,
+                        format!("chunk {hash} is not stored"),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store() -> FsBlobStore {
+        FsBlobStore::new(std::env::temp_dir().join(format!("gss-test-{}", Uuid::new_v4())))
+    }
+
+    /// Two blobs kept as is and two compressed ones, with their content
+    fn fill(store: &FsBlobStore) -> Vec<(String, Vec<u8>)> {
+        let contents: Vec<Vec<u8>> = vec![
+            b"raw save".to_vec(),
+            b"another raw save".to_vec(),
+            b"compressed ".repeat(100),
+            b"compressed as well ".repeat(100),
+        ];
+        contents
+            .into_iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let hash = if i < 2 {
+                    store.put_raw(&content).unwrap()
+                } else {
+                    store.put(&content).unwrap()
+                };
+                (hash, content)
+            })
+            .collect()
+    }
+
+    fn assert_stored(store: &FsBlobStore, blobs: &[(String, Vec<u8>)]) {
+        for (hash, content) in blobs {
+            assert!(store.exists(hash).unwrap());
+            assert_eq!(store.get(hash).unwrap().as_ref(), Some(content));
+        }
+        let mut expected: Vec<&String> = blobs.iter().map(|(hash, _)| hash).collect();
+        expected.sort();
+        let listed = store.list().unwrap();
+        assert_eq!(
+            listed.iter().map(|(hash, _)| hash).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    fn has_subdirs(dir: &Path) -> bool {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .any(|entry| entry.unwrap().file_type().unwrap().is_dir())
+    }
+
+    #[test]
+    fn reshard_moves_every_encoding_and_prunes_empty_shards() {
+        let store = temp_store().with_shard_depth(1);
+        let blobs = fill(&store);
+        assert_eq!(
+            store.get_raw(&blobs[0].0).unwrap().unwrap().1,
+            BlobEncoding::Identity
+        );
+        assert_eq!(
+            store.get_raw(&blobs[2].0).unwrap().unwrap().1,
+            BlobEncoding::Gzip
+        );
+
+        let deeper = FsBlobStore::new(&store.root).with_shard_depth(2);
+        assert_eq!(deeper.reshard(1).unwrap(), blobs.len());
+        assert_stored(&deeper, &blobs);
+        for (hash, _) in &blobs {
+            assert!(!store.exists(hash).unwrap());
+        }
+
+        let flat = FsBlobStore::new(&store.root).with_shard_depth(0);
+        assert_eq!(flat.reshard(2).unwrap(), blobs.len());
+        assert_stored(&flat, &blobs);
+        assert!(!has_subdirs(&store.root));
+        assert_eq!(flat.reshard(0).unwrap(), 0);
+        std::fs::remove_dir_all(&store.root).unwrap();
+    }
+
+    #[test]
+    fn reshard_resumes_after_a_partial_move() {
+        let store = temp_store().with_shard_depth(1);
+        let blobs = fill(&store);
+        let deeper = FsBlobStore::new(&store.root).with_shard_depth(2);
+        // One blob moved before the interruption, one copied without the old
+        // file being removed yet
+        for (hash, copy) in [(&blobs[0].0, false), (&blobs[2].0, true)] {
+            let (old_path, encoding) = store.stored_path(&store.blob_path(hash).unwrap()).unwrap();
+            let mut new_path = deeper.blob_path(hash).unwrap();
+            if encoding == BlobEncoding::Gzip {
+                new_path = FsBlobStore::gzip_path(&new_path);
+            }
+            std::fs::create_dir_all(new_path.parent().unwrap()).unwrap();
+            if copy {
+                std::fs::copy(&old_path, &new_path).unwrap();
+            } else {
+                std::fs::rename(&old_path, &new_path).unwrap();
+            }
+        }
+
+        assert_eq!(deeper.reshard(1).unwrap(), 2);
+        assert_stored(&deeper, &blobs);
+        for (hash, _) in &blobs {
+            assert!(!store.exists(hash).unwrap());
+        }
+        // Every shard left at depth 1 holds a depth 2 directory, not a blob
+        for entry in std::fs::read_dir(&store.root).unwrap() {
+            for inner in std::fs::read_dir(entry.unwrap().path()).unwrap() {
+                assert!(inner.unwrap().file_type().unwrap().is_dir());
+            }
+        }
+        std::fs::remove_dir_all(&store.root).unwrap();
+    }
+}
@@ -0,0 +1,237 @@
+use crate::const_var::{DEFAULT_CHUNK_AVG_SIZE, DEFAULT_CHUNK_MAX_SIZE, DEFAULT_CHUNK_MIN_SIZE};
+use crate::datatype_endpoint::{ChunkedFile, FileChunk};
+use sha2::{Digest, Sha256};
+use std::io::{ErrorKind, Read};
+
+/// Bounds of the content-defined chunks a large file is split into. The
+/// same options have to be used by every client of a store, chunks cut
+/// with other bounds do not deduplicate against each other
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkingOptions {
+    /// No cut is looked for in the first `min_size` bytes of a chunk
+    min_size: usize,
+    /// Size the cut points are normalized towards
+    avg_size: usize,
+    /// A chunk reaching `max_size` is cut whatever its content
+    max_size: usize,
+}
+
+impl Default for ChunkingOptions {
+    fn default() -> Self {
+        Self {
+            min_size: DEFAULT_CHUNK_MIN_SIZE,
+            avg_size: DEFAULT_CHUNK_AVG_SIZE,
+            max_size: DEFAULT_CHUNK_MAX_SIZE,
+        }
+    }
+}
+
+impl ChunkingOptions {
+    /// Masks of the normalized chunking, two more bits than the average
+    /// before it is reached so cuts are rarer, two less after it so they
+    /// are likelier
+    fn masks(&self) -> (u64, u64) {
+        let bits = self.avg_size.max(1).ilog2();
+        (top_bits(bits + 2), top_bits(bits.saturating_sub(2)))
+    }
+
+    /// Length of the first chunk of `data`, `data` being everything left of
+    /// the stream up to `max_size` bytes
+    fn cut_point(&self, data: &[u8], masks: (u64, u64)) -> usize {
+        if data.len() <= self.min_size {
+            return data.len();
+        }
+        let end = data.len().min(self.max_size);
+        let normal = self.avg_size.min(end);
+        let (small_mask, large_mask) = masks;
+
+        let mut fingerprint: u64 = 0;
+        for (i, byte) in data.iter().enumerate().take(end).skip(self.min_size) {
+            fingerprint = (fingerprint << 1).wrapping_add(GEAR[*byte as usize]);
+            let mask = if i < normal { small_mask } else { large_mask };
+            if fingerprint & mask == 0 {
+                return i + 1;
+            }
+        }
+        end
+    }
+}
+
+/// `bits` set at the top of the word, the gear fingerprint shifts left so
+/// those depend on the most bytes
+const fn top_bits(bits: u32) -> u64 {
+    match bits {
+        0 => 0,
+        64.. => u64::MAX,
+        _ => u64::MAX << (64 - bits),
+    }
+}
+
+/// Random value per byte of the gear rolling hash, a fixed splitmix64
+/// sequence so every build cuts the same chunks
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x6761_6d65_7361_7665;
+    let mut i = 0;
+    while i < table.len() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Splits `reader` into FastCDC chunks and hands each to `chunk` in order.
+/// Cut points only depend on the bytes around them, so an edit only
+/// changes the chunks it touches and the next one or two. At most
+/// `2 * max_size` bytes are buffered, an empty stream has no chunk
+pub fn for_each_chunk(
+    mut reader: impl Read,
+    options: ChunkingOptions,
+    mut chunk: impl FnMut(&[u8]) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let masks = options.masks();
+    let mut buffer = vec![0u8; options.max_size * 2];
+    let mut start = 0;
+    let mut end = 0;
+    let mut eof = false;
+    loop {
+        while !eof && end - start < options.max_size {
+            if end == buffer.len() {
+                buffer.copy_within(start..end, 0);
+                end -= start;
+                start = 0;
+            }
+            match reader.read(&mut buffer[end..]) {
+                Ok(0) => eof = true,
+                Ok(read) => end += read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if start == end {
+            return Ok(());
+        }
+        let length = options.cut_point(&buffer[start..end], masks);
+        chunk(&buffer[start..start + length])?;
+        start += length;
+    }
+}
+
+/// Chunks `reader` and hashes each chunk along with the whole content,
+/// `chunk` gets every chunk with its sha256 before the next one is read
+pub fn chunk_hashing(
+    reader: impl Read,
+    options: ChunkingOptions,
+    mut chunk: impl FnMut(&[u8], &str) -> std::io::Result<()>,
+) -> std::io::Result<ChunkedFile> {
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    let mut chunks = Vec::new();
+    for_each_chunk(reader, options, |data| {
+        let hash = format!("{:x}", Sha256::digest(data));
+        chunk(data, &hash)?;
+        hasher.update(data);
+        size += data.len() as u64;
+        chunks.push(FileChunk {
+            hash,
+            size: data.len() as u64,
+        });
+        Ok(())
+    })?;
+    Ok(ChunkedFile {
+        hash: format!("{:x}", hasher.finalize()),
+        size,
+        chunks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMALL: ChunkingOptions = ChunkingOptions {
+        min_size: 64,
+        avg_size: 256,
+        max_size: 1024,
+    };
+
+    /// Deterministic xorshift bytes, content with cut points spread like a
+    /// real file's
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    fn chunks_of(data: &[u8]) -> Vec<Vec<u8>> {
+        let mut chunks = Vec::new();
+        for_each_chunk(data, SMALL, |chunk| {
+            chunks.push(chunk.to_vec());
+            Ok(())
+        })
+        .unwrap();
+        chunks
+    }
+
+    #[test]
+    fn every_chunk_but_the_last_is_within_the_bounds() {
+        let chunks = chunks_of(&noise(64 * 1024));
+        assert!(chunks.len() > 16);
+        let (last, rest) = chunks.split_last().unwrap();
+        for chunk in rest {
+            assert!((SMALL.min_size..=SMALL.max_size).contains(&chunk.len()));
+        }
+        assert!(!last.is_empty() && last.len() <= SMALL.max_size);
+        assert!(chunks_of(&[]).is_empty());
+    }
+
+    #[test]
+    fn chunks_add_up_to_the_input_and_its_hash() {
+        let data = noise(64 * 1024 + 17);
+        let mut joined = Vec::new();
+        let file = chunk_hashing(data.as_slice(), SMALL, |chunk, hash| {
+            assert_eq!(hash, format!("{:x}", Sha256::digest(chunk)));
+            joined.extend_from_slice(chunk);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(joined, data);
+        assert_eq!(file.hash, format!("{:x}", Sha256::digest(&data)));
+        assert_eq!(file.size, data.len() as u64);
+        assert_eq!(
+            file.chunks.iter().map(|chunk| chunk.size).sum::<u64>(),
+            file.size
+        );
+    }
+
+    #[test]
+    fn a_one_byte_edit_only_changes_the_chunks_around_it() {
+        let data = noise(64 * 1024);
+        let mut edited = data.clone();
+        edited[data.len() / 2] ^= 0xff;
+        let hashes = |data: &[u8]| {
+            chunk_hashing(data, SMALL, |_, _| Ok(()))
+                .unwrap()
+                .chunks
+                .into_iter()
+                .map(|chunk| chunk.hash)
+                .collect::<Vec<_>>()
+        };
+        let before = hashes(&data);
+        let changed = hashes(&edited)
+            .into_iter()
+            .filter(|hash| !before.contains(hash))
+            .count();
+        assert!((1..=2).contains(&changed), "{changed} chunks changed");
+    }
+}
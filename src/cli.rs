@@ -0,0 +1,558 @@
+use crate::auth::{generate_token, hash_token};
+use crate::chunking::ChunkingOptions;
+use crate::database::error::DbError;
+use crate::database::interface::GameDatabase;
+use crate::database::maintenance::{DerivedKind, MaintenanceOpts};
+use crate::datatype_endpoint::{CatalogRules, OS, expand_save_path, translate_save_path};
+use crate::file_system::{hash_file_chunked, hash_save_directory};
+use crate::{BLOB_STORE, DATABASE};
+use std::path::Path;
+use uuid::Uuid;
+
+const USAGE: &str = "usage: GameSaveServer [validate-catalog [--require-steam-appid] | expand-path <os> <path> | translate-path <from-os> <to-os> <path> | hash-dir <path> | hash-chunked <path> | verify-save <uuid> <path> | restore-save <uuid> <path> | rollback-save <uuid> <path> | snapshot-dir <path-id> <path> | gc-blobs | verify-store | reshard-blobs <from-depth> | optimize-db | rebuild-derived canonical-name | maintenance [--keep <n>] [--no-prune] [--no-gc] [--no-optimize] [--dry-run] | backup-db <path> | add-user <name> | enrich-steam <game-id> | migrate]";
+
+/// Runs an admin command instead of the server, returns the exit code
+pub fn run_command(args: &[String]) -> Option<i32> {
+    let (command, flags) = args.split_first()?;
+    match command.as_str() {
+        "validate-catalog" => Some(validate_catalog(flags)),
+        "expand-path" => Some(expand_path(flags)),
+        "translate-path" => Some(translate_path(flags)),
+        "hash-dir" => Some(hash_dir(flags)),
+        "hash-chunked" => Some(hash_chunked(flags)),
+        "verify-save" => Some(verify_save(flags)),
+        "restore-save" => Some(restore_save(flags)),
+        "rollback-save" => Some(rollback_save(flags)),
+        "snapshot-dir" => Some(snapshot_dir(flags)),
+        "gc-blobs" if flags.is_empty() => Some(gc_blobs()),
+        "verify-store" if flags.is_empty() => Some(verify_store()),
+        "reshard-blobs" => Some(reshard_blobs(flags)),
+        "optimize-db" if flags.is_empty() => Some(optimize_db()),
+        "rebuild-derived" => Some(rebuild_derived(flags)),
+        "maintenance" => Some(maintenance(flags)),
+        "backup-db" => Some(backup_db(flags)),
+        "add-user" => Some(add_user(flags)),
+        "enrich-steam" => Some(enrich_steam(flags)),
+        "migrate" if flags.is_empty() => Some(migrate()),
+        _ => {
+            eprintln!("{}", USAGE);
+            Some(2)
+        }
+    }
+}
+
+fn validate_catalog(flags: &[String]) -> i32 {
+    let mut rules = CatalogRules::default();
+    for flag in flags {
+        match flag.as_str() {
+            "--require-steam-appid" => rules.require_steam_appid = true,
+            _ => {
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+
+    match DATABASE.validate_catalog(&rules) {
+        Ok(issues) if issues.is_empty() => {
+            println!("catalog is valid");
+            0
+        }
+        Ok(issues) => {
+            for issue in &issues {
+                println!(
+                    "game {} ({}): {:?}",
+                    issue
+                        .game_id
+                        .map(|id| id.to_string())
+                        .unwrap_or_else(|| "?".to_string()),
+                    issue.game_name,
+                    issue.kind
+                );
+            }
+            eprintln!("{} catalog issue(s) found", issues.len());
+            1
+        }
+        Err(e) => {
+            eprintln!("Error validating catalog: {}", e);
+            1
+        }
+    }
+}
+
+fn expand_path(args: &[String]) -> i32 {
+    let [os, path] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let os: OS = match os.parse() {
+        Ok(os) => os,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    match expand_save_path(path, &os) {
+        Ok(expanded) => {
+            println!("{}", expanded.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("Error expanding save path: {}", e);
+            1
+        }
+    }
+}
+
+fn translate_path(args: &[String]) -> i32 {
+    let [from, to, path] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let (from, to): (OS, OS) = match (from.parse(), to.parse()) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    match translate_save_path(path, &from, &to) {
+        Some(translated) => {
+            println!("{}", translated);
+            0
+        }
+        None => {
+            eprintln!("No known equivalent of {} on {}", path, to);
+            1
+        }
+    }
+}
+
+fn hash_dir(args: &[String]) -> i32 {
+    let [root] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    let files_hash = match hash_save_directory(Path::new(root)) {
+        Ok(files_hash) => files_hash,
+        Err(e) => {
+            eprintln!("Error hashing save directory: {}", e);
+            return 1;
+        }
+    };
+    match serde_json::to_string(&files_hash) {
+        Ok(json) => {
+            println!("{}", json);
+            0
+        }
+        Err(e) => {
+            eprintln!("Error serializing file hashes: {}", e);
+            1
+        }
+    }
+}
+
+fn hash_chunked(args: &[String]) -> i32 {
+    let [path] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    let file = match hash_file_chunked(Path::new(path), ChunkingOptions::default()) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Error hashing file: {}", e);
+            return 1;
+        }
+    };
+    match serde_json::to_string(&file) {
+        Ok(json) => {
+            println!("{}", json);
+            0
+        }
+        Err(e) => {
+            eprintln!("Error serializing chunk list: {}", e);
+            1
+        }
+    }
+}
+
+fn verify_save(args: &[String]) -> i32 {
+    let [uuid, root] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let Ok(uuid) = Uuid::parse_str(uuid) else {
+        eprintln!("invalid uuid: {}", uuid);
+        return 2;
+    };
+
+    match DATABASE.verify_save_integrity(uuid, Path::new(root)) {
+        Ok(report) => {
+            for (label, files) in [
+                ("missing", &report.missing),
+                ("mismatched", &report.mismatched),
+                ("extra", &report.extra),
+                ("unverified", &report.unverified),
+            ] {
+                for file in files {
+                    println!("{}: {}", label, file);
+                }
+            }
+            if report.is_clean() {
+                println!("save matches the directory");
+                0
+            } else {
+                1
+            }
+        }
+        Err(e) => {
+            eprintln!("Error verifying save: {}", e);
+            1
+        }
+    }
+}
+
+fn restore_save(args: &[String]) -> i32 {
+    let [uuid, dest_root] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let Ok(uuid) = Uuid::parse_str(uuid) else {
+        eprintln!("invalid uuid: {}", uuid);
+        return 2;
+    };
+
+    match DATABASE.restore_save(uuid, &*BLOB_STORE, Path::new(dest_root)) {
+        Ok(()) => {
+            println!("save {} restored to {}", uuid, dest_root);
+            0
+        }
+        Err(e) => {
+            eprintln!("Error restoring save: {}", e);
+            1
+        }
+    }
+}
+
+fn rollback_save(args: &[String]) -> i32 {
+    let [uuid, live_root] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let Ok(uuid) = Uuid::parse_str(uuid) else {
+        eprintln!("invalid uuid: {}", uuid);
+        return 2;
+    };
+
+    match DATABASE.rollback_to_save(uuid, &*BLOB_STORE, Path::new(live_root)) {
+        Ok(before) => {
+            println!("save {} restored to {}", uuid, live_root);
+            println!(
+                "previous state kept as save {}, restore it to undo the rollback",
+                before.uuid
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("Error rolling back to save: {}", e);
+            1
+        }
+    }
+}
+
+fn snapshot_dir(args: &[String]) -> i32 {
+    let [path_id, root] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let Ok(path_id) = path_id.parse() else {
+        eprintln!("invalid path id: {}", path_id);
+        return 2;
+    };
+
+    match DATABASE.snapshot_directory(path_id, Path::new(root), &*BLOB_STORE) {
+        Ok(save) => {
+            println!(
+                "save {} created with {} file(s), {} bytes",
+                save.uuid,
+                save.files_hash.len(),
+                save.total_size_bytes
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("Error snapshotting directory: {}", e);
+            1
+        }
+    }
+}
+
+fn gc_blobs() -> i32 {
+    match DATABASE.gc_unreferenced_blobs(&*BLOB_STORE) {
+        Ok(removed) => {
+            for (hash, freed) in &removed {
+                println!("removed {} ({} bytes)", hash, freed);
+            }
+            let freed: u64 = removed.iter().map(|(_, freed)| freed).sum();
+            println!(
+                "{} unreferenced blob(s) removed, {} bytes freed",
+                removed.len(),
+                freed
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("Error collecting unreferenced blobs: {}", e);
+            1
+        }
+    }
+}
+
+fn verify_store() -> i32 {
+    match DATABASE.verify_store_consistency(&*BLOB_STORE) {
+        Ok(report) => {
+            for hash in &report.missing_blobs {
+                println!("missing {}", hash);
+            }
+            for hash in &report.corrupt_blobs {
+                println!("corrupt {}", hash);
+            }
+            for hash in &report.orphan_blobs {
+                println!("orphan {}", hash);
+            }
+            println!(
+                "{} referenced blob(s) checked: {} missing, {} corrupt, {} orphan",
+                report.referenced,
+                report.missing_blobs.len(),
+                report.corrupt_blobs.len(),
+                report.orphan_blobs.len()
+            );
+            if report.is_clean() { 0 } else { 1 }
+        }
+        Err(e) => {
+            eprintln!("Error verifying the blob store: {}", e);
+            1
+        }
+    }
+}
+
+fn reshard_blobs(args: &[String]) -> i32 {
+    let [from_depth] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let Some(from_depth) = from_depth.parse::<usize>().ok().filter(|depth| *depth <= 2) else {
+        eprintln!("invalid shard depth: {}", from_depth);
+        return 2;
+    };
+
+    match BLOB_STORE.reshard(from_depth) {
+        Ok(moved) => {
+            println!(
+                "{} blob file(s) moved from shard depth {}",
+                moved, from_depth
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("Error resharding the blob store: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(feature = "steam")]
+fn enrich_steam(args: &[String]) -> i32 {
+    let [game_id] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let Ok(game_id) = game_id.parse() else {
+        eprintln!("invalid game id: {}", game_id);
+        return 2;
+    };
+    let client = match crate::steam::HttpSteamClient::from_env() {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Error configuring the Steam client: {}", e);
+            return 2;
+        }
+    };
+
+    match DATABASE.enrich_from_steam(game_id, &client) {
+        Ok(game) => {
+            println!(
+                "{}, known as: {}",
+                game.metadata.default_name,
+                game.metadata.known_name.join(", ")
+            );
+            0
+        }
+        Err(DbError::NotFound) => {
+            eprintln!("no game with id {}", game_id);
+            1
+        }
+        Err(e) => {
+            eprintln!("Error enriching game from Steam: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(not(feature = "steam"))]
+fn enrich_steam(_args: &[String]) -> i32 {
+    eprintln!("enrich-steam needs a build with --features steam");
+    2
+}
+
+fn optimize_db() -> i32 {
+    match DATABASE.optimize() {
+        Ok(()) => {
+            println!("database optimized");
+            0
+        }
+        Err(e) => {
+            eprintln!("Error optimizing database: {}", e);
+            1
+        }
+    }
+}
+
+fn rebuild_derived(args: &[String]) -> i32 {
+    let which = match args {
+        [kind] if kind == "canonical-name" => DerivedKind::CanonicalName,
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    match DATABASE.rebuild_derived(which) {
+        Ok(rebuilt) => {
+            println!("{} row(s) rebuilt", rebuilt);
+            0
+        }
+        Err(e) => {
+            eprintln!("Error rebuilding derived column: {}", e);
+            1
+        }
+    }
+}
+
+fn maintenance(flags: &[String]) -> i32 {
+    let mut opts = MaintenanceOpts::default();
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--keep" => match flags.next().and_then(|keep| keep.parse().ok()) {
+                Some(keep) => opts.keep_latest = Some(keep),
+                None => {
+                    eprintln!("{}", USAGE);
+                    return 2;
+                }
+            },
+            "--no-prune" => opts.keep_latest = None,
+            "--no-gc" => opts.gc_blobs = false,
+            "--no-optimize" => opts.optimize = false,
+            "--dry-run" => opts.dry_run = true,
+            _ => {
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+
+    match DATABASE.run_maintenance(&*BLOB_STORE, opts) {
+        Ok(report) => {
+            let (prune, remove, outcome) = if report.dry_run {
+                ("would prune", "would remove", "would be ")
+            } else {
+                ("pruned", "removed", "")
+            };
+            for uuid in &report.saves_pruned {
+                println!("{} save {}", prune, uuid);
+            }
+            for hash in &report.blobs_removed {
+                println!("{} blob {}", remove, hash);
+            }
+            println!(
+                "{} save(s) {outcome}pruned, {} blob(s) {outcome}removed, {} bytes {outcome}reclaimed",
+                report.saves_pruned.len(),
+                report.blobs_removed.len(),
+                report.bytes_reclaimed,
+            );
+            if let Some(bytes) = report.database_bytes_reclaimed {
+                println!("database vacuumed, {} bytes reclaimed", bytes);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Error running maintenance: {}", e);
+            1
+        }
+    }
+}
+
+fn backup_db(args: &[String]) -> i32 {
+    let [dest_path] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    if let Err(e) = DATABASE.backup_to(dest_path) {
+        eprintln!("Error backing up database: {}", e);
+        return 1;
+    }
+    match GameDatabase::restore_from(dest_path).and_then(|backup| backup.count_games()) {
+        Ok(games) => {
+            println!("backup written to {} ({} games)", dest_path, games);
+            0
+        }
+        Err(e) => {
+            eprintln!("Error opening backup {}: {}", dest_path, e);
+            1
+        }
+    }
+}
+
+fn add_user(args: &[String]) -> i32 {
+    let [name] = args else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    let token = generate_token();
+    match DATABASE.add_user(name, &hash_token(&token)) {
+        Ok(user_id) => {
+            println!("user {} ({}) created, token: {}", name, user_id, token);
+            0
+        }
+        Err(DbError::UniqueViolation(_)) => {
+            eprintln!("user {} already exists", name);
+            1
+        }
+        Err(e) => {
+            eprintln!("Error adding user: {}", e);
+            1
+        }
+    }
+}
+
+fn migrate() -> i32 {
+    match DATABASE.run_migrations() {
+        Ok(applied) => {
+            for name in &applied {
+                println!("applied {}", name);
+            }
+            println!("{} migration(s) applied", applied.len());
+            0
+        }
+        Err(e) => {
+            eprintln!("Error running migrations: {}", e);
+            1
+        }
+    }
+}
@@ -1,7 +1,53 @@
 use const_format::concatcp;
 
+pub const API_TOKENS_ENV: &str = "API_TOKENS";
+pub const BLOB_DIR: &str = concatcp!(DATA_DIR, "/blobs");
+pub const BLOB_GC_GRACE_SECS: u64 = 60 * 60;
+pub const BLOB_SHARD_DEPTH_ENV: &str = "BLOB_SHARD_DEPTH";
+pub const BLOB_STREAM_CHUNK_SIZE: usize = 64 * 1024;
+pub const CHUNK_THRESHOLD_ENV: &str = "CHUNK_THRESHOLD_BYTES";
 pub const DATA_DIR: &str = "./data";
+pub const DEFAULT_BLOB_COMPRESSION_LEVEL: u32 = 6;
+pub const DEFAULT_BLOB_SHARD_DEPTH: usize = 1;
+pub const DEFAULT_CHUNK_AVG_SIZE: usize = 1024 * 1024;
+pub const DEFAULT_CHUNK_MAX_SIZE: usize = 4 * 1024 * 1024;
+pub const DEFAULT_CHUNK_MIN_SIZE: usize = 256 * 1024;
+pub const DEFAULT_FUZZY_MATCH_THRESHOLD: f64 = 0.85;
+pub const DEFAULT_HASH_CHUNK_SIZE: usize = 1024 * 1024;
+pub const DEFAULT_QUERY_LIMIT: i64 = 50;
+pub const DEFAULT_QUERY_TIMEOUT_MS: u64 = 30_000;
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+pub const MAINTENANCE_BUSY_BACKOFF_SECS: u64 = 30;
+pub const MAINTENANCE_INTERVAL_SECS_ENV: &str = "MAINTENANCE_INTERVAL_SECS";
+pub const MAX_DEVICE_NAME_LEN: usize = 64;
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 128;
 pub const MAX_BODY_SIZE: usize = 3 * 1024 * 1024 * 1024;
+pub const MAX_SAVES_PER_PATH: usize = 5;
+pub const MAX_SAVE_ATTRIBUTES_PER_SAVE: i64 = 32;
+pub const MAX_SAVE_ATTRIBUTE_KEY_LEN: usize = 64;
+pub const MAX_SAVE_ATTRIBUTE_VALUE_LEN: usize = 256;
+pub const MAX_SAVE_LABEL_LEN: usize = 128;
+pub const REBUILD_DERIVED_BATCH_SIZE: i64 = 500;
+pub const ROLLBACK_SNAPSHOT_LABEL: &str = "auto: before rollback";
 pub const ROOT_API_PATH: &str = "/v1";
 pub const SAVE_DIR: &str = concatcp!(DATA_DIR, "/saves");
+pub const QUERY_TIMEOUT_MS_ENV: &str = "QUERY_TIMEOUT_MS";
+pub const SKIP_MIGRATIONS_ENV: &str = "SKIP_MIGRATIONS";
+pub const SAVE_EVENTS_BUFFER: usize = 256;
+pub const SAVE_EVENTS_MAX_CLIENT_FRAME: usize = 64 * 1024;
+pub const SAVE_EVENTS_WRITE_TIMEOUT_SECS: u64 = 10;
+#[cfg(feature = "steam")]
+pub const DEFAULT_STEAM_STORE_URL: &str = "http://store.steampowered.com";
+#[cfg(feature = "steam")]
+pub const STEAM_LANGUAGES_ENV: &str = "STEAM_LANGUAGES";
+#[cfg(feature = "steam")]
+pub const STEAM_REQUEST_TIMEOUT_SECS: u64 = 10;
+#[cfg(feature = "steam")]
+pub const STEAM_STORE_URL_ENV: &str = "STEAM_STORE_URL";
 pub const TMP_DIR: &str = concatcp!(DATA_DIR, "/tmp");
+pub const WEBHOOK_URLS_ENV: &str = "WEBHOOK_URLS";
+pub const WEBHOOK_SECRET_ENV: &str = "WEBHOOK_SECRET";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-GameSaveSync-Signature";
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 4;
+pub const WEBHOOK_INITIAL_BACKOFF_MS: u64 = 500;
+pub const WEBHOOK_ATTEMPT_TIMEOUT_SECS: u64 = 10;
@@ -0,0 +1,99 @@
+use crate::database::error::DbError;
+use crate::database::interface::GameDatabase;
+use crate::datatype_endpoint::{
+    ChunkedFile, DbMetrics, GameBundle, GameMetadata, HealthStatus, LaunchProfile, OS,
+    SaveReference,
+};
+use uuid::Uuid;
+
+/// Runs `GameDatabase` queries on tokio's blocking pool so async handlers do
+/// not stall the executor while diesel waits on sqlite, cloning the database
+/// only clones its pool handle
+pub struct AsyncGameDatabase {
+    database: GameDatabase,
+}
+
+impl AsyncGameDatabase {
+    pub fn new(database: &GameDatabase) -> Self {
+        Self {
+            database: database.clone(),
+        }
+    }
+
+    async fn run<T, F>(&self, query: F) -> Result<T, DbError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&GameDatabase) -> Result<T, DbError> + Send + 'static,
+    {
+        let database = self.database.clone();
+        tokio::task::spawn_blocking(move || query(&database))
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+    }
+
+    pub async fn get_games_metadata(&self) -> Result<Vec<GameMetadata>, DbError> {
+        self.run(|database| database.get_games_metadata()).await
+    }
+
+    pub async fn get_games_metadata_paged(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<GameMetadata>, DbError> {
+        self.run(move |database| database.get_games_metadata_paged(offset, limit))
+            .await
+    }
+
+    pub async fn get_game_bundle(&self, game_id: i32) -> Result<Option<GameBundle>, DbError> {
+        self.run(move |database| database.get_game_bundle(game_id))
+            .await
+    }
+
+    pub async fn get_launch_profile(&self, game_id: i32, os: OS) -> Result<LaunchProfile, DbError> {
+        self.run(move |database| database.get_launch_profile(game_id, os))
+            .await
+    }
+
+    pub async fn get_game_metadata_by_id(
+        &self,
+        game_id: i32,
+    ) -> Result<Option<GameMetadata>, DbError> {
+        self.run(move |database| database.get_game_metadata_by_id(&game_id))
+            .await
+    }
+
+    pub async fn get_save_owner(&self, uuid: Uuid) -> Result<Option<Option<i32>>, DbError> {
+        self.run(move |database| database.get_save_owner(uuid))
+            .await
+    }
+
+    pub async fn get_game_id_by_path_id(&self, path_id: i32) -> Result<Option<i32>, DbError> {
+        self.run(move |database| database.get_game_id_by_path_id(path_id))
+            .await
+    }
+
+    pub async fn get_reference_to_save_by_path_id(
+        &self,
+        path_id: i32,
+    ) -> Result<Option<Vec<SaveReference>>, DbError> {
+        self.run(move |database| database.get_reference_to_save_by_path_id(path_id))
+            .await
+    }
+
+    pub async fn get_chunked_file(&self, hash: String) -> Result<Option<ChunkedFile>, DbError> {
+        self.run(move |database| database.get_chunked_file(&hash))
+            .await
+    }
+
+    pub async fn schema_version(&self) -> Result<i64, DbError> {
+        self.run(|database| database.schema_version()).await
+    }
+
+    pub async fn health_check(&self) -> Result<HealthStatus, DbError> {
+        self.run(|database| database.health_check()).await
+    }
+
+    pub async fn collect_metrics(&self) -> Result<DbMetrics, DbError> {
+        self.run(|database| database.collect_metrics()).await
+    }
+}
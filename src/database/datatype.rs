@@ -1,7 +1,8 @@
 use crate::database::schema::{
-    file_hash, game_alt_name, game_executable, game_metadata, game_path, game_save,
+    blob, blob_chunk, file_hash, game_alt_name, game_executable, game_metadata, game_path,
+    game_save, save_attribute, user,
 };
-use crate::datatype_endpoint::OS;
+use crate::datatype_endpoint::{HashAlgo, OS};
 use diesel::prelude::{Associations, Identifiable};
 use diesel::{Insertable, Queryable, Selectable};
 
@@ -11,6 +12,8 @@ pub struct DbGameMetadata {
     pub id: Option<i32>,
     pub steam_appid: Option<String>,
     pub default_name: String,
+    pub created_at: time::PrimitiveDateTime,
+    pub updated_at: time::PrimitiveDateTime,
 }
 
 #[derive(Identifiable, Insertable, Selectable, Queryable, PartialEq, Debug)]
@@ -28,6 +31,7 @@ pub struct DbGameExecutable {
     pub executable: String,
     pub operating_system: OS,
     pub game_metadata_id: i32,
+    pub updated_at: time::PrimitiveDateTime,
 }
 
 #[derive(Insertable, Selectable, Queryable, PartialEq)]
@@ -37,6 +41,7 @@ pub struct DbGamePath {
     pub path: String,
     pub operating_system: OS,
     pub game_metadata_id: i32,
+    pub updated_at: time::PrimitiveDateTime,
 }
 
 #[derive(Identifiable, Insertable, Selectable, Queryable, PartialEq, Debug)]
@@ -46,6 +51,15 @@ pub struct DbGameSave {
     pub uuid: String,
     pub path_id: i32,
     pub time: time::PrimitiveDateTime,
+    /// `None` for saves uploaded without a user token
+    pub user_id: Option<i32>,
+    /// Counts the saves of `path_id` from 1, never reused while the path has saves
+    pub generation: i64,
+    pub label: Option<String>,
+    /// Pinned saves are never pruned and do not count towards retention
+    pub pinned: bool,
+    /// Machine the save was uploaded from, as the client names it
+    pub device_name: Option<String>,
 }
 
 #[derive(Identifiable, Insertable, Selectable, Queryable, PartialEq, Associations, Debug)]
@@ -54,6 +68,49 @@ pub struct DbGameSave {
 #[diesel(table_name = file_hash)]
 pub struct DbFileHash {
     pub relative_path: String,
+    pub game_save_uuid: String,
+    pub blob_id: i32,
+}
+
+/// Content referenced by file hashes, stored once however many saves
+/// hold it
+#[derive(Identifiable, Selectable, Queryable, PartialEq, Debug)]
+#[diesel(table_name = blob)]
+pub struct DbBlob {
+    pub id: i32,
     pub hash: String,
+    pub algo: HashAlgo,
+    /// `None` until an upload reports the size
+    pub size: Option<i64>,
+}
+
+/// One chunk of a blob stored as content-defined chunks instead of whole
+#[derive(Identifiable, Insertable, Selectable, Queryable, PartialEq, Associations, Debug)]
+#[diesel(primary_key(blob_id, position))]
+#[diesel(belongs_to(DbBlob, foreign_key = blob_id))]
+#[diesel(table_name = blob_chunk)]
+pub struct DbBlobChunk {
+    pub blob_id: i32,
+    /// 0 based order of the chunk in the blob
+    pub position: i32,
+    pub chunk_hash: String,
+    pub chunk_size: i64,
+}
+
+#[derive(Identifiable, Insertable, Selectable, Queryable, PartialEq, Associations, Debug)]
+#[diesel(primary_key(game_save_uuid, key))]
+#[diesel(belongs_to(DbGameSave, foreign_key = game_save_uuid))]
+#[diesel(table_name = save_attribute)]
+pub struct DbSaveAttribute {
     pub game_save_uuid: String,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Insertable, Selectable, Queryable, PartialEq, Debug)]
+#[diesel(table_name = user)]
+pub struct DbUser {
+    pub id: Option<i32>,
+    pub name: String,
+    pub token_hash: String,
 }
@@ -0,0 +1,124 @@
+use diesel::r2d2::PoolError;
+use diesel::result::DatabaseErrorKind;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum DbError {
+    NotFound,
+    UniqueViolation(String),
+    ForeignKeyViolation(String),
+    Invalid(String),
+    Pool(PoolError),
+    Migration(String),
+    /// A stored value that no longer parses, e.g. a hand edited uuid
+    Corrupt(String),
+    /// Another connection held the lock for longer than the busy timeout
+    Busy,
+    /// A statement was aborted once the operation ran past the query timeout
+    Timeout,
+    /// The path already holds the given maximum of saves
+    QuotaExceeded(usize),
+    Diesel(diesel::result::Error),
+    /// Filesystem access done alongside a query, e.g. hashing restored files
+    Io(std::io::Error),
+    /// The database file could not be opened, detected before the pool is
+    /// built so the server can say why instead of timing out
+    OpenFailed {
+        path: String,
+        reason: OpenFailure,
+    },
+}
+
+#[derive(Debug)]
+pub enum OpenFailure {
+    MissingDirectory,
+    /// Only when opening read-only, a writable database is created
+    MissingFile,
+    /// The directory cannot be written, SQLite needs it for the journal
+    DirectoryNotWritable,
+    IsDirectory,
+    NotADatabase,
+    /// Any other error SQLite returned while opening, with its message
+    CannotOpen(String),
+}
+
+impl fmt::Display for OpenFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenFailure::MissingDirectory => write!(f, "parent directory does not exist"),
+            OpenFailure::MissingFile => write!(f, "file does not exist"),
+            OpenFailure::DirectoryNotWritable => {
+                write!(f, "permission denied, parent directory is not writable")
+            }
+            OpenFailure::IsDirectory => write!(f, "path is a directory"),
+            OpenFailure::NotADatabase => write!(f, "file is not a database"),
+            OpenFailure::CannotOpen(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::NotFound => write!(f, "record not found"),
+            DbError::UniqueViolation(message) => write!(f, "unique constraint violated: {message}"),
+            DbError::ForeignKeyViolation(message) => {
+                write!(f, "foreign key constraint violated: {message}")
+            }
+            DbError::Invalid(message) => write!(f, "invalid input: {message}"),
+            DbError::Pool(e) => write!(f, "connection pool error: {e}"),
+            DbError::Migration(message) => write!(f, "migration error: {message}"),
+            DbError::Corrupt(message) => write!(f, "corrupt row: {message}"),
+            DbError::Busy => write!(f, "database is busy"),
+            DbError::Timeout => write!(f, "query timed out"),
+            DbError::QuotaExceeded(limit) => write!(f, "save quota of {limit} exceeded"),
+            DbError::Diesel(e) => write!(f, "database error: {e}"),
+            DbError::Io(e) => write!(f, "io error: {e}"),
+            DbError::OpenFailed { path, reason } => {
+                write!(f, "cannot open save database at {path}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DbError::Pool(e) => Some(e),
+            DbError::Diesel(e) => Some(e),
+            DbError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<diesel::result::Error> for DbError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => DbError::NotFound,
+            diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                DbError::UniqueViolation(info.message().to_string())
+            }
+            diesel::result::Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) => {
+                DbError::ForeignKeyViolation(info.message().to_string())
+            }
+            // Only the query timeout's progress handler interrupts statements
+            diesel::result::Error::DatabaseError(_, info) if info.message() == "interrupted" => {
+                DbError::Timeout
+            }
+            e => DbError::Diesel(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for DbError {
+    fn from(e: std::io::Error) -> Self {
+        DbError::Io(e)
+    }
+}
+
+impl From<PoolError> for DbError {
+    fn from(e: PoolError) -> Self {
+        DbError::Pool(e)
+    }
+}
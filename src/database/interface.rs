@@ -1,352 +1,5805 @@
+use crate::blob_store::{BlobStore, is_valid_hash};
+use crate::const_var::{
+    BLOB_GC_GRACE_SECS, DEFAULT_FUZZY_MATCH_THRESHOLD, MAX_SAVE_ATTRIBUTES_PER_SAVE,
+    MAX_SAVES_PER_PATH, ROLLBACK_SNAPSHOT_LABEL,
+};
 use crate::database::datatype::{
-    DbFileHash, DbGameExecutable, DbGameMetadata, DbGameName, DbGamePath, DbGameSave,
+    DbBlob, DbBlobChunk, DbFileHash, DbGameExecutable, DbGameMetadata, DbGameName, DbGamePath,
+    DbGameSave, DbSaveAttribute, DbUser,
 };
+use crate::database::error::{DbError, OpenFailure};
 use crate::database::schema::{
-    file_hash, game_alt_name, game_executable, game_metadata, game_path, game_save,
+    blob, blob_chunk, file_hash, game_alt_name, game_executable, game_metadata, game_path,
+    game_save, save_attribute, steam_appid_conflict, user,
 };
+use crate::database::timeout;
+use crate::database::trace::traced;
 use crate::datatype_endpoint::{
-    Executable, ExecutableCreate, FileHash, GameMetadata, GameMetadataCreate, OS, SavePath,
-    SavePathCreate, SaveReference,
+    AdminSaveRow, CatalogChildChanges, CatalogRules, ChangedFile, ChunkedFile, ConflictStatus,
+    ConsistencyReport, DbMetrics, DuplicateGroup, DuplicateMatch, DuplicateReason, Executable,
+    ExecutableCreate, FileChunk, FileHash, FullRewrite, GameActivity, GameBundle, GameChildChanges,
+    GameMetadata, GameMetadataCreate, GameStorageStat, HashAlgo, HealthStatus, ImportSummary,
+    IntegrityReport, LaunchProfile, NameResolution, OS, RenamedFile, ResolveOutcome, ResolvedGame,
+    RetentionImpact, RetentionPolicy, SaveActivity, SaveDiff, SaveManifest, SavePath,
+    SavePathCreate, SaveReference, SaveSummary, StorageTotals, SyncStrategy, ValidationIssue,
+    ValidationIssueKind, translate_save_path, validate_device_name, validate_idempotency_key,
+    validate_retention_policy, validate_save_attribute, validate_save_fields, validate_save_label,
 };
+use crate::file_system::{hash_save_directory, sanitize_relative_path};
+use crate::steam::SteamClient;
+use diesel::connection::SimpleConnection;
+use diesel::dsl::{count, sql};
+use diesel::expression::SqlLiteral;
 use diesel::prelude::*;
-use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::r2d2::event::{CheckinEvent, CheckoutEvent, TimeoutEvent};
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, HandleEvent, Pool};
+use diesel::sql_types::{BigInt, Bool, Integer, Nullable, Text};
 use diesel::sqlite::SqliteConnection;
 use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::Read;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{debug_span, warn};
 use uuid::Uuid;
 
 pub type DbPool = Pool<ConnectionManager<SqliteConnection>>;
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
+/// Called with every save once its insert has committed
+pub type SaveCreatedCallback = dyn Fn(&SaveReference) + Send + Sync;
+
+#[derive(Clone)]
 pub struct GameDatabase {
     pub pool: DbPool,
+    busy_retries: u32,
+    /// Shared by every clone so a callback registered once sees every save
+    save_created_callbacks: Arc<RwLock<Vec<Arc<SaveCreatedCallback>>>>,
+    /// Fed by the pool's event handler, shared by every clone
+    pool_stats: Arc<PoolStats>,
 }
 
-impl GameDatabase {
+const SQLITE_BUSY_TIMEOUT_MS: u32 = 5000;
+const SLOW_CHECKOUT_THRESHOLD_MS: u64 = 1000;
+const SQLITE_BUSY_RETRIES: u32 = 3;
+const SQLITE_BUSY_INITIAL_BACKOFF_MS: u64 = 50;
+
+pub struct GameDatabaseConfig {
+    pub db_path: String,
+    pub max_size: u32,
+    /// `None` keeps `max_size` idle connections around
+    pub min_idle: Option<u32>,
+    pub connection_timeout: Duration,
+    pub busy_timeout_ms: u32,
+    /// Extra attempts for a write transaction that still hits a locked
+    /// database once `busy_timeout_ms` has run out
+    pub busy_retries: u32,
+    /// Apply pending migrations when the database is opened, turn it off
+    /// to run them deliberately with `run_migrations`
+    pub run_migrations: bool,
+    /// Open the file with SQLite's read-only flag, see `open_read_only` for
+    /// a handle that only offers reads
+    pub read_only: bool,
+    /// A connection checkout waiting longer than this logs a warning, a
+    /// sign that `max_size` is too small for the load
+    pub slow_checkout_threshold: Duration,
+    /// Longest an operation may hold its connection while statements run,
+    /// a statement still running past it is aborted with `DbError::Timeout`.
+    /// `None` lets queries run as long as they need
+    pub query_timeout: Option<Duration>,
+}
+
+impl GameDatabaseConfig {
     pub fn new(db_path: &str) -> Self {
-        let manager = ConnectionManager::<SqliteConnection>::new(db_path);
-        let pool = Pool::builder()
-            .build(manager)
-            .expect("Failed to create pool");
+        Self {
+            db_path: db_path.to_string(),
+            max_size: 10,
+            min_idle: None,
+            connection_timeout: Duration::from_secs(30),
+            busy_timeout_ms: SQLITE_BUSY_TIMEOUT_MS,
+            busy_retries: SQLITE_BUSY_RETRIES,
+            run_migrations: true,
+            read_only: false,
+            slow_checkout_threshold: Duration::from_millis(SLOW_CHECKOUT_THRESHOLD_MS),
+            query_timeout: None,
+        }
+    }
+}
 
-        {
-            let mut conn = pool.get().expect("Failed to get DB connection");
-            conn.run_pending_migrations(MIGRATIONS)
-                .expect("Failed to run database migrations");
+/// What happens to an upload once its path already holds the maximum of saves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuotaPolicy {
+    Reject,
+    PruneOldest,
+}
+
+/// Result of a save insert carrying an idempotency key
+pub enum SaveInsert<T> {
+    /// The save was stored, with what the insert returns
+    Created(T),
+    /// A save of the path already carries the key, nothing was stored
+    Replayed(SaveReference),
+}
+
+/// Saves removed by a prune or a bulk delete with the blob hashes nothing
+/// references anymore
+#[derive(Debug, Default)]
+pub struct PrunedSaves {
+    pub uuids: Vec<String>,
+    pub orphaned_hashes: Vec<String>,
+}
+
+#[derive(Debug)]
+struct SqlitePragmas {
+    busy_timeout_ms: u32,
+    read_only: bool,
+}
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for SqlitePragmas {
+    fn on_acquire(&self, connection: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        // Switching the journal mode is a write, a read-only connection keeps
+        // whatever mode the file was left in
+        let journal_mode = if self.read_only {
+            "PRAGMA query_only = ON;"
+        } else {
+            "PRAGMA journal_mode = WAL;"
+        };
+        connection
+            .batch_execute(&format!(
+                "{journal_mode} PRAGMA foreign_keys = ON; PRAGMA busy_timeout = {};",
+                self.busy_timeout_ms
+            ))
+            .map_err(diesel::r2d2::Error::QueryError)?;
+        register_sql_functions(connection).map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// Connection checkout counters since the pool was built
+#[derive(Debug, Default)]
+struct PoolStats {
+    checkouts: AtomicU64,
+    timeouts: AtomicU64,
+    max_wait_micros: AtomicU64,
+}
+
+#[derive(Debug)]
+struct PoolEvents {
+    stats: Arc<PoolStats>,
+    slow_checkout_threshold: Duration,
+    /// Deadline started on the checking out thread, r2d2 fires both events
+    /// on the thread that gets and drops the connection
+    query_timeout: Option<Duration>,
+}
+
+impl HandleEvent for PoolEvents {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        if let Some(timeout) = self.query_timeout {
+            timeout::enter(timeout);
+        }
+        let wait = event.duration();
+        self.stats.checkouts.fetch_add(1, Ordering::Relaxed);
+        self.stats
+            .max_wait_micros
+            .fetch_max(wait.as_micros() as u64, Ordering::Relaxed);
+        if wait > self.slow_checkout_threshold {
+            warn!(
+                wait_ms = wait.as_millis() as u64,
+                "database connection checkout was slow, the pool may be too small"
+            );
         }
+    }
 
-        Self { pool }
+    fn handle_checkin(&self, _event: CheckinEvent) {
+        if self.query_timeout.is_some() {
+            timeout::leave();
+        }
     }
 
-    pub fn add_game_metadata(
-        &self,
-        game_metadata: &GameMetadataCreate,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let connection = &mut self.pool.get()?;
+    fn handle_timeout(&self, event: TimeoutEvent) {
+        self.stats.timeouts.fetch_add(1, Ordering::Relaxed);
+        warn!(
+            timeout_ms = event.timeout().as_millis() as u64,
+            "database connection checkout timed out, the pool is saturated"
+        );
+    }
+}
 
-        connection.immediate_transaction(|connection| {
-            diesel::insert_into(game_metadata::table)
-                .values(DbGameMetadata {
-                    id: None,
-                    steam_appid: game_metadata.steam_appid.clone(),
-                    default_name: game_metadata.default_name.clone(),
-                })
-                .execute(connection)?;
+/// `file:` URI opening `db_path` with SQLITE_OPEN_READONLY, the characters
+/// SQLite reads as URI syntax are percent-encoded
+fn read_only_uri(db_path: &str) -> String {
+    let path = db_path
+        .replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23");
+    format!("file:{path}?mode=ro")
+}
 
-            let inserted_id: Option<i32> = game_metadata::table
-                .select(game_metadata::id)
-                .order(game_metadata::id.desc())
-                .first(connection)?;
+/// Checks done before building the pool, which would otherwise retry a file
+/// it cannot open until `connection_timeout` and report a bare timeout
+fn check_database_file(db_path: &str, database_url: &str, read_only: bool) -> Result<(), DbError> {
+    let open_failed = |reason| DbError::OpenFailed {
+        path: db_path.to_string(),
+        reason,
+    };
+    let path = Path::new(db_path);
+    if path.is_dir() {
+        return Err(open_failed(OpenFailure::IsDirectory));
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match std::fs::metadata(parent) {
+        Ok(metadata) if !metadata.is_dir() => {
+            return Err(open_failed(OpenFailure::MissingDirectory));
+        }
+        Ok(metadata) if !read_only && metadata.permissions().readonly() => {
+            return Err(open_failed(OpenFailure::DirectoryNotWritable));
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(open_failed(OpenFailure::MissingDirectory));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            return Err(open_failed(OpenFailure::CannotOpen(e.to_string())));
+        }
+        Err(e) => return Err(DbError::Io(e)),
+    }
+    if read_only && !path.exists() {
+        return Err(open_failed(OpenFailure::MissingFile));
+    }
 
-            let inserted_id = match inserted_id {
-                Some(id) => id,
-                None => return Err("Failed to get inserted id".into()),
-            };
+    // Opening is lazy in SQLite, the header is only read by the first statement
+    let mut probe = SqliteConnection::establish(database_url)
+        .map_err(|e| open_failed(OpenFailure::CannotOpen(e.to_string())))?;
+    probe
+        .batch_execute("PRAGMA schema_version;")
+        .map_err(|e| match e {
+            diesel::result::Error::DatabaseError(_, info)
+                if info.message().contains("file is not a database") =>
+            {
+                open_failed(OpenFailure::NotADatabase)
+            }
+            diesel::result::Error::DatabaseError(_, info)
+                if info.message().contains("unable to open") =>
+            {
+                open_failed(OpenFailure::CannotOpen(info.message().to_string()))
+            }
+            e => DbError::from(e),
+        })
+}
 
-            diesel::insert_into(game_alt_name::table)
-                .values(
-                    game_metadata
-                        .known_name
-                        .iter()
-                        .map(|name| DbGameName {
-                            name: name.to_string(),
-                            game_metadata_id: inserted_id,
-                        })
-                        .collect::<Vec<_>>(),
-                )
-                .execute(connection)?;
+/// Stay under SQLite's default bind parameter limit on `eq_any` and batched inserts
+const SQLITE_MAX_VARIABLES: usize = 999;
+/// Distinct hashes read per query by `verify_store_consistency`
+const CONSISTENCY_PAGE_SIZE: i64 = 1000;
+/// Games loaded per query by `for_each_game`
+const GAME_ITERATION_PAGE_SIZE: i64 = 500;
+/// `file_hash` and `blob` rows bind three values each
+const FILE_HASH_INSERT_CHUNK: usize = SQLITE_MAX_VARIABLES / 3;
+/// `game_path`, `game_executable` and `blob_chunk` rows bind four values each
+const GAME_CHILD_INSERT_CHUNK: usize = SQLITE_MAX_VARIABLES / 4;
 
-            Ok(())
+/// Words folder names and store listings add to a title without changing
+/// the game, ignored when matching names
+const GAME_NAME_NOISE_WORDS: &[&str] = &[
+    "the",
+    "goty",
+    "edition",
+    "definitive",
+    "complete",
+    "deluxe",
+    "remastered",
+    "enhanced",
+    "ultimate",
+    "digital",
+];
+/// Applied to the similarity of names with different numbers, "Witcher 2"
+/// should not pass for "Witcher 3"
+const GAME_NAME_NUMBER_MISMATCH_FACTOR: f64 = 0.8;
+
+/// Lowercase alphanumeric words of `name` without the edition noise, or
+/// every word when the name is nothing but noise. Roman numerals become
+/// digits so "III" and "3" compare equal
+fn normalize_game_name(name: &str) -> Vec<String> {
+    let lowered: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let lowered = lowered
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("game of the year", "goty");
+    let words: Vec<String> = lowered
+        .split(' ')
+        .map(|word| match word {
+            "ii" => "2",
+            "iii" => "3",
+            "iv" => "4",
+            "vi" => "6",
+            "vii" => "7",
+            "viii" => "8",
+            "ix" => "9",
+            word => word,
         })
+        .map(str::to_string)
+        .collect();
+    let meaningful: Vec<String> = words
+        .iter()
+        .filter(|word| !GAME_NAME_NOISE_WORDS.contains(&word.as_str()))
+        .cloned()
+        .collect();
+    if meaningful.is_empty() {
+        words
+    } else {
+        meaningful
     }
+}
 
-    pub fn get_game_metadata_by_name(
-        &self,
-        target_name: &str,
-    ) -> Result<Vec<GameMetadata>, Box<dyn std::error::Error + Send + Sync>> {
-        let connection = &mut self.pool.get()?;
-        let db_games: Vec<DbGameMetadata> = game_metadata::table
-            .filter(game_metadata::default_name.eq(target_name))
-            .select(DbGameMetadata::as_select())
-            .load(connection)?;
+/// Normalized words of `name` joined by a space, what the indexed
+/// `canonical_name` column holds so an exact lookup ignores everything the
+/// fuzzy match does
+fn canonical_game_name(name: &str) -> String {
+    normalize_game_name(name).join(" ")
+}
 
-        let mut games: Vec<GameMetadata> = Vec::with_capacity(db_games.len());
-        for db_game in db_games {
-            let known_name: Vec<String> = game_alt_name::table
-                .filter(game_alt_name::game_metadata_id.eq(db_game.id.unwrap()))
-                .select(game_alt_name::name)
-                .load(connection)?;
+define_sql_function! {
+    /// `canonical_game_name` in SQL, the migration adding `canonical_name`
+    /// fills the column with it
+    #[sql_name = "canonical_game_name"]
+    fn canonical_game_name_sql(name: Text) -> Text;
+}
 
-            games.push(GameMetadata {
-                id: db_game.id,
-                metadata: GameMetadataCreate {
-                    known_name,
-                    steam_appid: db_game.steam_appid,
-                    default_name: db_game.default_name,
-                },
-            });
+/// Functions the migrations and queries call, registered on every
+/// connection before it is used
+pub(crate) fn register_sql_functions(connection: &mut SqliteConnection) -> QueryResult<()> {
+    canonical_game_name_sql_utils::register_impl(connection, |name: String| {
+        canonical_game_name(&name)
+    })
+}
+
+/// Token set ratio of two normalized names in `[0, 1]`: the shared words
+/// are compared against each side's full word set, so extra words on one
+/// side such as a subtitle barely count. Names that disagree on their
+/// numbers are usually different entries of a series and are scored lower
+fn game_name_similarity(a: &[String], b: &[String]) -> f64 {
+    let a_words: BTreeSet<&str> = a.iter().map(String::as_str).collect();
+    let b_words: BTreeSet<&str> = b.iter().map(String::as_str).collect();
+    let shared_words: Vec<&str> = a_words.intersection(&b_words).copied().collect();
+    let with_rest = |words: &BTreeSet<&str>, other: &BTreeSet<&str>| {
+        let rest = words.difference(other).copied();
+        shared_words
+            .iter()
+            .copied()
+            .chain(rest)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let shared = shared_words.join(" ");
+    let a_full = with_rest(&a_words, &b_words);
+    let b_full = with_rest(&b_words, &a_words);
+
+    let mut score = strsim::normalized_levenshtein(&a_full, &b_full);
+    if !shared.is_empty() {
+        score = score
+            .max(strsim::normalized_levenshtein(&shared, &a_full))
+            .max(strsim::normalized_levenshtein(&shared, &b_full));
+    }
+
+    let numbers = |words: &BTreeSet<&str>| -> BTreeSet<String> {
+        words
+            .iter()
+            .filter(|word| word.chars().all(|c| c.is_ascii_digit()))
+            .map(|word| word.to_string())
+            .collect()
+    };
+    if numbers(&a_words) != numbers(&b_words) {
+        score *= GAME_NAME_NUMBER_MISMATCH_FACTOR;
+    }
+    score
+}
+
+fn load_games_metadata(
+    connection: &mut SqliteConnection,
+    mut db_games: Vec<DbGameMetadata>,
+) -> QueryResult<Vec<GameMetadata>> {
+    // The column is a rowid alias and cannot be NULL in a sound file, a row
+    // without one is skipped rather than sent without a usable id
+    db_games.retain(|db_game| {
+        if db_game.id.is_none() {
+            warn!(default_name = %db_game.default_name, "skipping game_metadata row without an id");
+        }
+        db_game.id.is_some()
+    });
+    let ids: Vec<i32> = db_games.iter().filter_map(|db_game| db_game.id).collect();
+
+    let mut known_names: HashMap<i32, Vec<String>> = HashMap::new();
+    for ids in ids.chunks(SQLITE_MAX_VARIABLES) {
+        let name_rows: Vec<(i32, String)> = game_alt_name::table
+            .filter(game_alt_name::game_metadata_id.eq_any(ids))
+            .select((game_alt_name::game_metadata_id, game_alt_name::name))
+            .load(connection)?;
+        for (game_metadata_id, name) in name_rows {
+            known_names.entry(game_metadata_id).or_default().push(name);
         }
-        Ok(games)
     }
 
-    pub fn get_game_metadata_by_id(
-        &self,
-        target_id: &i32,
-    ) -> Result<Option<GameMetadata>, Box<dyn std::error::Error>> {
-        let connection = &mut self.pool.get()?;
+    Ok(db_games
+        .into_iter()
+        .map(|db_game| GameMetadata {
+            id: db_game.id,
+            metadata: GameMetadataCreate {
+                known_name: db_game
+                    .id
+                    .and_then(|id| known_names.remove(&id))
+                    .unwrap_or_default(),
+                steam_appid: db_game.steam_appid,
+                default_name: db_game.default_name,
+            },
+            created_at: Some(db_game.created_at.assume_utc()),
+            updated_at: Some(db_game.updated_at.assume_utc()),
+        })
+        .collect())
+}
 
-        connection.immediate_transaction(|connection| {
-            let maybe_meta: Option<DbGameMetadata> = game_metadata::table
-                .filter(game_metadata::id.eq(target_id))
+/// The non-deleted games among `ids` in no particular order, the id list
+/// is chunked to stay under the bind limit
+fn load_games_by_ids(
+    connection: &mut SqliteConnection,
+    ids: &[i32],
+) -> QueryResult<Vec<GameMetadata>> {
+    let mut db_games: Vec<DbGameMetadata> = Vec::new();
+    for ids in ids.chunks(SQLITE_MAX_VARIABLES) {
+        db_games.extend(
+            game_metadata::table
+                .filter(game_metadata::id.eq_any(ids))
+                .filter(game_metadata::is_deleted.eq(false))
                 .select(DbGameMetadata::as_select())
-                .first(connection)
-                .optional()?;
+                .load(connection)?,
+        );
+    }
+    load_games_metadata(connection, db_games)
+}
 
-            let meta = match maybe_meta {
-                Some(meta) => meta,
-                None => return Ok(None),
-            };
+/// Case-insensitive (ASCII only) substring match on the default and known
+/// names, ordered by default name
+fn search_games_like(
+    connection: &mut SqliteConnection,
+    query: &str,
+) -> QueryResult<Vec<GameMetadata>> {
+    let pattern = like_pattern(query);
 
-            let id = match meta.id {
-                Some(id) => id,
-                None => return Ok(None),
-            };
+    let alt_name_matches = game_alt_name::table
+        .filter(game_alt_name::name.like(pattern.clone()).escape('\\'))
+        .select(game_alt_name::game_metadata_id.nullable());
+    let db_games: Vec<DbGameMetadata> = game_metadata::table
+        .filter(game_metadata::is_deleted.eq(false))
+        .filter(
+            game_metadata::default_name
+                .like(pattern)
+                .escape('\\')
+                .or(game_metadata::id.eq_any(alt_name_matches)),
+        )
+        .order(game_metadata::default_name.asc())
+        .select(DbGameMetadata::as_select())
+        .load(connection)?;
 
-            let name_rows: Vec<String> = game_alt_name::table
-                .filter(game_alt_name::game_metadata_id.eq(id))
-                .select(game_alt_name::name)
-                .load(connection)?;
+    load_games_metadata(connection, db_games)
+}
 
-            Ok(Some(GameMetadata {
-                id: Some(id),
-                metadata: GameMetadataCreate {
-                    known_name: name_rows,
-                    steam_appid: meta.steam_appid,
-                    default_name: meta.default_name,
-                },
-            }))
-        })
-    }
+/// `LIKE` pattern matching `query` anywhere, escaped with `\\`
+fn like_pattern(query: &str) -> String {
+    format!(
+        "%{}%",
+        query
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    )
+}
 
-    pub fn get_games_metadata(&self) -> Result<Vec<GameMetadata>, Box<dyn std::error::Error>> {
-        let connection = &mut self.pool.get()?;
-        let db_games: Vec<DbGameMetadata> = game_metadata::table
-            .select(DbGameMetadata::as_select())
-            .load(connection)?;
+/// Uuid, game name, OS, path, time and file count of a save
+type AdminSaveRowData = (
+    String,
+    String,
+    OS,
+    String,
+    time::PrimitiveDateTime,
+    Option<i64>,
+);
 
-        let mut games = Vec::with_capacity(db_games.len());
-        for db_game_metadata in db_games {
-            let known_name: Vec<String> = game_alt_name::table
-                .filter(game_alt_name::game_metadata_id.eq(db_game_metadata.id.unwrap()))
-                .select(game_alt_name::name)
-                .load(connection)?;
+#[derive(QueryableByName)]
+struct GameActivityRow {
+    #[diesel(sql_type = Integer)]
+    game_metadata_id: i32,
+    #[diesel(sql_type = BigInt)]
+    save_count: i64,
+    #[diesel(sql_type = diesel::sql_types::Timestamp)]
+    last_save_time: time::PrimitiveDateTime,
+}
 
-            games.push(GameMetadata {
-                id: db_game_metadata.id,
-                metadata: GameMetadataCreate {
-                    known_name,
-                    steam_appid: db_game_metadata.steam_appid,
-                    default_name: db_game_metadata.default_name,
-                },
-            });
+#[derive(QueryableByName)]
+struct RankedGameId {
+    #[diesel(sql_type = Integer)]
+    game_metadata_id: i32,
+}
+
+/// Ids of the games that are not deleted and have no row in `child_table`,
+/// only rows for `os` count when it is given. `child_table` is one of the
+/// tables with a `game_metadata_id` and an `operating_system` column
+fn games_missing_children(
+    connection: &mut SqliteConnection,
+    child_table: &str,
+    os: Option<OS>,
+) -> QueryResult<Vec<i32>> {
+    Ok(diesel::sql_query(format!(
+        "SELECT game_metadata.id AS game_metadata_id FROM game_metadata \
+         LEFT JOIN {child_table} AS child ON child.game_metadata_id = game_metadata.id \
+         AND (?1 IS NULL OR child.operating_system = ?1) \
+         WHERE game_metadata.is_deleted = 0 AND child.id IS NULL \
+         ORDER BY game_metadata.id"
+    ))
+    .bind::<Nullable<diesel::sql_types::Text>, _>(os)
+    .load::<RankedGameId>(connection)?
+    .into_iter()
+    .map(|row| row.game_metadata_id)
+    .collect())
+}
+
+/// Turns free text into an FTS5 query matching every word as a prefix, each
+/// word is quoted so characters like `-` or `:` are not read as operators.
+/// `None` when the text has no word to search for
+fn fts_name_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\"*"))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Errors from a database whose SQLite lacks FTS5 or has not run the
+/// migration creating `game_name_fts` yet
+fn is_fts_unavailable(error: &diesel::result::Error) -> bool {
+    match error {
+        diesel::result::Error::DatabaseError(_, info) => {
+            let message = info.message();
+            message.contains("no such module: fts5")
+                || message.contains("no such table: game_name_fts")
         }
+        _ => false,
+    }
+}
+
+pub(super) fn now_utc() -> time::PrimitiveDateTime {
+    let now = time::OffsetDateTime::now_utc();
+    time::PrimitiveDateTime::new(now.date(), now.time())
+}
 
-        Ok(games)
+/// Paths of the game in the order they were added, clients index into it
+pub(super) fn load_paths(
+    connection: &mut SqliteConnection,
+    game_id: i32,
+) -> QueryResult<Vec<SavePath>> {
+    let path_rows: Vec<(Option<i32>, String, OS)> = game_path::table
+        .filter(game_path::game_metadata_id.eq(game_id))
+        .order(game_path::id.asc())
+        .select((game_path::id, game_path::path, game_path::operating_system))
+        .load(connection)?;
+    let mut paths: Vec<SavePath> = Vec::with_capacity(path_rows.len());
+    for (id, path, os) in path_rows {
+        paths.push(SavePath {
+            id,
+            path: SavePathCreate {
+                path,
+                operating_system: os,
+            },
+        });
     }
+    Ok(paths)
+}
 
-    pub fn add_game_path(
-        &self,
-        game_id: i32,
-        path: &SavePathCreate,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let connection = &mut self.pool.get()?;
+/// Executables of the game in the order they were added
+pub(super) fn load_executables(
+    connection: &mut SqliteConnection,
+    game_id: i32,
+) -> QueryResult<Vec<Executable>> {
+    let executable_rows: Vec<(Option<i32>, String, OS)> = game_executable::table
+        .filter(game_executable::game_metadata_id.eq(game_id))
+        .order(game_executable::id.asc())
+        .select((
+            game_executable::id,
+            game_executable::executable,
+            game_executable::operating_system,
+        ))
+        .load(connection)?;
+    let mut executables: Vec<Executable> = Vec::with_capacity(executable_rows.len());
+    for (id, executable, os) in executable_rows {
+        executables.push(Executable {
+            id,
+            executable: ExecutableCreate {
+                executable,
+                operating_system: os,
+            },
+        });
+    }
+    Ok(executables)
+}
 
-        diesel::insert_into(game_path::table)
-            .values(DbGamePath {
+/// Trims the names and drops blanks, case-insensitive duplicates and copies
+/// of the default name, the first spelling seen is the one stored
+fn normalize_known_names(default_name: &str, known_name: &[String]) -> Vec<String> {
+    let mut seen: HashSet<String> = HashSet::from([default_name.trim().to_lowercase()]);
+    known_name
+        .iter()
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty() && seen.insert(name.to_lowercase()))
+        .map(str::to_string)
+        .collect()
+}
+
+pub(super) fn insert_game_metadata(
+    connection: &mut SqliteConnection,
+    game_metadata: &GameMetadataCreate,
+) -> Result<GameMetadata, DbError> {
+    let now = now_utc();
+    let inserted_id: Option<i32> = diesel::insert_into(game_metadata::table)
+        .values((
+            DbGameMetadata {
                 id: None,
-                path: path.path.clone(),
-                operating_system: path.operating_system,
-                game_metadata_id: game_id,
+                steam_appid: game_metadata.steam_appid.clone(),
+                default_name: game_metadata.default_name.clone(),
+                created_at: now,
+                updated_at: now,
+            },
+            game_metadata::canonical_name.eq(canonical_game_name(&game_metadata.default_name)),
+        ))
+        .returning(game_metadata::id)
+        .get_result(connection)?;
+
+    let inserted_id = match inserted_id {
+        Some(id) => id,
+        None => return Err(DbError::NotFound),
+    };
+
+    diesel::insert_or_ignore_into(game_alt_name::table)
+        .values(
+            normalize_known_names(&game_metadata.default_name, &game_metadata.known_name)
+                .into_iter()
+                .map(|name| DbGameName {
+                    name,
+                    game_metadata_id: inserted_id,
+                })
+                .collect::<Vec<_>>(),
+        )
+        .execute(connection)?;
+
+    let known_name: Vec<String> = game_alt_name::table
+        .filter(game_alt_name::game_metadata_id.eq(inserted_id))
+        .select(game_alt_name::name)
+        .load(connection)?;
+
+    Ok(GameMetadata {
+        id: Some(inserted_id),
+        metadata: GameMetadataCreate {
+            known_name,
+            steam_appid: game_metadata.steam_appid.clone(),
+            default_name: game_metadata.default_name.clone(),
+        },
+        created_at: Some(now.assume_utc()),
+        updated_at: Some(now.assume_utc()),
+    })
+}
+
+pub(super) fn insert_game_paths(
+    connection: &mut SqliteConnection,
+    game_id: i32,
+    paths: &[SavePathCreate],
+    updated_at: time::PrimitiveDateTime,
+) -> QueryResult<()> {
+    for paths in paths.chunks(GAME_CHILD_INSERT_CHUNK) {
+        let rows: Vec<_> = paths
+            .iter()
+            .map(|path| {
+                (
+                    game_path::path.eq(&path.path),
+                    game_path::operating_system.eq(&path.operating_system),
+                    game_path::game_metadata_id.eq(game_id),
+                    game_path::updated_at.eq(updated_at),
+                )
             })
+            .collect();
+        diesel::insert_into(game_path::table)
+            .values(rows)
             .execute(connection)?;
-        Ok(())
     }
-    pub fn get_paths_by_game_id_and_os(
-        &self,
-        game_id: i32,
-        os: OS,
-    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
-        let connection = &mut self.pool.get()?;
-        let paths: Vec<String> = game_path::table
-            .filter(game_path::game_metadata_id.eq(game_id))
-            .filter(game_path::operating_system.eq(os))
-            .select(game_path::path)
-            .load(connection)?;
-        Ok(paths)
+    Ok(())
+}
+
+pub(super) fn insert_game_executables(
+    connection: &mut SqliteConnection,
+    game_id: i32,
+    executables: &[ExecutableCreate],
+    updated_at: time::PrimitiveDateTime,
+) -> QueryResult<()> {
+    for executables in executables.chunks(GAME_CHILD_INSERT_CHUNK) {
+        let rows: Vec<_> = executables
+            .iter()
+            .map(|executable| {
+                (
+                    game_executable::executable.eq(&executable.executable),
+                    game_executable::operating_system.eq(&executable.operating_system),
+                    game_executable::game_metadata_id.eq(game_id),
+                    game_executable::updated_at.eq(updated_at),
+                )
+            })
+            .collect();
+        diesel::insert_into(game_executable::table)
+            .values(rows)
+            .execute(connection)?;
     }
+    Ok(())
+}
 
-    pub fn get_paths_by_game_id(
-        &self,
-        game_id: i32,
-    ) -> Result<Vec<SavePath>, Box<dyn std::error::Error>> {
-        let connection = &mut self.pool.get()?;
-        let path_rows: Vec<(Option<i32>, String, OS)> = game_path::table
-            .filter(game_path::game_metadata_id.eq(game_id))
-            .select((game_path::id, game_path::path, game_path::operating_system))
-            .load(connection)?;
-        let mut paths: Vec<SavePath> = Vec::with_capacity(path_rows.len());
-        for (id, path, os) in path_rows {
-            paths.push(SavePath {
-                id,
-                path: SavePathCreate {
-                    path,
-                    operating_system: os,
-                },
-            });
-        }
-        Ok(paths)
+fn update_game_metadata_row(
+    connection: &mut SqliteConnection,
+    game_id: i32,
+    update: &GameMetadataCreate,
+) -> Result<GameMetadata, DbError> {
+    let updated = diesel::update(
+        game_metadata::table
+            .filter(game_metadata::id.eq(game_id))
+            .filter(game_metadata::is_deleted.eq(false)),
+    )
+    .set((
+        game_metadata::default_name.eq(&update.default_name),
+        game_metadata::canonical_name.eq(canonical_game_name(&update.default_name)),
+        game_metadata::steam_appid.eq(&update.steam_appid),
+        game_metadata::updated_at.eq(now_utc()),
+    ))
+    .returning((game_metadata::created_at, game_metadata::updated_at))
+    .get_result::<(time::PrimitiveDateTime, time::PrimitiveDateTime)>(connection)
+    .optional()?;
+    let Some((created_at, updated_at)) = updated else {
+        return Err(DbError::NotFound);
+    };
+
+    let existing_names: HashSet<String> = game_alt_name::table
+        .filter(game_alt_name::game_metadata_id.eq(game_id))
+        .select(game_alt_name::name)
+        .load::<String>(connection)?
+        .into_iter()
+        .collect();
+    let wanted_names: HashSet<String> =
+        normalize_known_names(&update.default_name, &update.known_name)
+            .into_iter()
+            .collect();
+
+    let removed_names: Vec<&String> = existing_names
+        .iter()
+        .filter(|name| !wanted_names.contains(*name))
+        .collect();
+    diesel::delete(
+        game_alt_name::table
+            .filter(game_alt_name::game_metadata_id.eq(game_id))
+            .filter(game_alt_name::name.eq_any(removed_names)),
+    )
+    .execute(connection)?;
+
+    let added_names: Vec<DbGameName> = wanted_names
+        .into_iter()
+        .filter(|name| !existing_names.contains(name))
+        .map(|name| DbGameName {
+            name,
+            game_metadata_id: game_id,
+        })
+        .collect();
+    diesel::insert_or_ignore_into(game_alt_name::table)
+        .values(added_names)
+        .execute(connection)?;
+
+    let known_name: Vec<String> = game_alt_name::table
+        .filter(game_alt_name::game_metadata_id.eq(game_id))
+        .select(game_alt_name::name)
+        .load(connection)?;
+
+    Ok(GameMetadata {
+        id: Some(game_id),
+        metadata: GameMetadataCreate {
+            known_name,
+            steam_appid: update.steam_appid.clone(),
+            default_name: update.default_name.clone(),
+        },
+        created_at: Some(created_at.assume_utc()),
+        updated_at: Some(updated_at.assume_utc()),
+    })
+}
+
+/// Takes the next generation of the path from its counter, which only
+/// grows, so a deleted save's generation is never handed out again. Callers
+/// hold an immediate transaction, so no other insert can take the same one
+fn next_generation(connection: &mut SqliteConnection, path_id: i32) -> QueryResult<i64> {
+    diesel::update(game_path::table.filter(game_path::id.eq(path_id)))
+        .set(game_path::last_generation.eq(game_path::last_generation + 1))
+        .returning(game_path::last_generation)
+        .get_result(connection)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn insert_save_rows(
+    connection: &mut SqliteConnection,
+    uuid: &str,
+    path_id: i32,
+    user_id: Option<i32>,
+    files_hash: &[FileHash],
+    label: Option<&str>,
+    device_name: Option<&str>,
+    idempotency_key: Option<&str>,
+) -> QueryResult<DbGameSave> {
+    let generation = next_generation(connection, path_id)?;
+    let game_save = DbGameSave {
+        uuid: uuid.to_string(),
+        path_id,
+        time: now_utc(),
+        user_id,
+        generation,
+        label: label.map(str::to_string),
+        pinned: false,
+        device_name: device_name.map(str::to_string),
+    };
+    diesel::insert_into(game_save::table)
+        .values(&game_save)
+        .execute(connection)?;
+
+    let blob_ids = upsert_blobs(connection, files_hash)?;
+    let files_hash_db = files_hash
+        .iter()
+        .map(|file_hash| {
+            Ok(DbFileHash {
+                relative_path: file_hash.relative_path.clone(),
+                game_save_uuid: uuid.to_string(),
+                blob_id: *blob_ids
+                    .get(&(file_hash.algo, file_hash.hash.clone()))
+                    .ok_or(diesel::result::Error::NotFound)?,
+            })
+        })
+        .collect::<QueryResult<Vec<DbFileHash>>>()?;
+    for files_hash_db in files_hash_db.chunks(FILE_HASH_INSERT_CHUNK) {
+        diesel::insert_into(file_hash::table)
+            .values(files_hash_db)
+            .execute(connection)?;
     }
+    if let Some(idempotency_key) = idempotency_key {
+        diesel::update(game_save::table.find(uuid))
+            .set(game_save::idempotency_key.eq(idempotency_key))
+            .execute(connection)?;
+    }
+    Ok(game_save)
+}
 
-    pub fn add_game_executable(
-        &self,
-        game_id: i32,
-        executable: &ExecutableCreate,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let connection = &mut self.pool.get()?;
-        diesel::insert_into(game_executable::table)
-            .values(DbGameExecutable {
-                id: None,
-                executable: executable.executable.clone(),
-                operating_system: executable.operating_system,
-                game_metadata_id: game_id,
+/// Ids of the `blob` rows holding the content of `files_hash`, inserting
+/// the missing ones. A size only fills in a row that does not know it yet
+fn upsert_blobs(
+    connection: &mut SqliteConnection,
+    files_hash: &[FileHash],
+) -> QueryResult<HashMap<(HashAlgo, String), i32>> {
+    let mut blob_ids = HashMap::with_capacity(files_hash.len());
+    for files_hash in files_hash.chunks(FILE_HASH_INSERT_CHUNK) {
+        let rows: Vec<_> = files_hash
+            .iter()
+            .map(|file_hash| {
+                (
+                    blob::hash.eq(&file_hash.hash),
+                    blob::algo.eq(file_hash.algo),
+                    blob::size.eq(file_hash.size.map(|size| size as i64)),
+                )
             })
+            .collect();
+        diesel::insert_into(blob::table)
+            .values(&rows)
+            .on_conflict((blob::hash, blob::algo))
+            .do_update()
+            .set(blob::size.eq(sql::<Nullable<BigInt>>(
+                "COALESCE(blob.size, excluded.size)",
+            )))
             .execute(connection)?;
-        Ok(())
     }
-    pub fn get_executable_by_game_id_and_os(
-        &self,
-        game_id: i32,
-        os: OS,
-    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
-        let connection = &mut self.pool.get()?;
-        let paths: Vec<String> = game_executable::table
-            .filter(game_executable::game_metadata_id.eq(game_id))
-            .filter(game_executable::operating_system.eq(os))
-            .select(game_executable::executable)
-            .load(connection)?;
-        Ok(paths)
+    let hashes: BTreeSet<&str> = files_hash
+        .iter()
+        .map(|file_hash| file_hash.hash.as_str())
+        .collect();
+    let hashes: Vec<&str> = hashes.into_iter().collect();
+    for hashes in hashes.chunks(SQLITE_MAX_VARIABLES) {
+        blob_ids.extend(
+            blob::table
+                .filter(blob::hash.eq_any(hashes))
+                .select((blob::id, blob::algo, blob::hash))
+                .load::<(i32, HashAlgo, String)>(connection)?
+                .into_iter()
+                .map(|(id, algo, hash)| ((algo, hash), id)),
+        );
     }
+    Ok(blob_ids)
+}
 
-    pub fn get_executable_by_game_id(
-        &self,
-        game_id: i32,
-    ) -> Result<Vec<Executable>, Box<dyn std::error::Error>> {
-        let connection = &mut self.pool.get()?;
-        let executable_rows: Vec<(Option<i32>, String, OS)> = game_executable::table
-            .filter(game_executable::game_metadata_id.eq(game_id))
-            .select((
-                game_executable::id,
-                game_executable::executable,
-                game_executable::operating_system,
+/// Records the chunk list of `file` under its `blob` row, inserting the row
+/// when no save references the file yet. A blob that already has a chunk
+/// list keeps it, the same content always cuts the same chunks
+fn insert_chunk_list(connection: &mut SqliteConnection, file: &ChunkedFile) -> QueryResult<()> {
+    let whole = FileHash {
+        relative_path: String::new(),
+        hash: file.hash.clone(),
+        algo: HashAlgo::Sha256,
+        size: Some(file.size),
+    };
+    let blob_ids = upsert_blobs(connection, std::slice::from_ref(&whole))?;
+    let Some(&blob_id) = blob_ids.get(&(HashAlgo::Sha256, file.hash.clone())) else {
+        return Err(diesel::result::Error::NotFound);
+    };
+    let recorded: i64 = blob_chunk::table
+        .filter(blob_chunk::blob_id.eq(blob_id))
+        .count()
+        .get_result(connection)?;
+    if recorded > 0 {
+        return Ok(());
+    }
+
+    let rows: Vec<DbBlobChunk> = file
+        .chunks
+        .iter()
+        .enumerate()
+        .map(|(position, chunk)| DbBlobChunk {
+            blob_id,
+            position: position as i32,
+            chunk_hash: chunk.hash.clone(),
+            chunk_size: chunk.size as i64,
+        })
+        .collect();
+    for rows in rows.chunks(GAME_CHILD_INSERT_CHUNK) {
+        diesel::insert_into(blob_chunk::table)
+            .values(rows)
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+/// Save `user_id` stored on `path_id` with `idempotency_key`, keys of
+/// different users never match. Called in the same immediate transaction
+/// as the insert so two retries cannot both miss it
+fn find_idempotent_save(
+    connection: &mut SqliteConnection,
+    path_id: i32,
+    user_id: Option<i32>,
+    idempotency_key: Option<&str>,
+) -> Result<Option<SaveReference>, DbError> {
+    let Some(idempotency_key) = idempotency_key else {
+        return Ok(None);
+    };
+    let game_save = game_save::table
+        .filter(game_save::path_id.eq(path_id))
+        .filter(game_save::user_id.is(user_id))
+        .filter(game_save::idempotency_key.eq(idempotency_key))
+        .select(DbGameSave::as_select())
+        .first(connection)
+        .optional()?;
+    game_save
+        .map(|game_save| load_save_reference(connection, game_save))
+        .transpose()
+}
+
+/// Moves the saves of `from_path_id` to `to_path_id` oldest first, each
+/// taking the next generation of the target path. An idempotency key the
+/// same user already holds on the target path is cleared on the moved save
+fn move_saves_to_path(
+    connection: &mut SqliteConnection,
+    from_path_id: i32,
+    to_path_id: i32,
+) -> Result<(), DbError> {
+    let target_keys: HashSet<(Option<i32>, String)> = game_save::table
+        .filter(game_save::path_id.eq(to_path_id))
+        .filter(game_save::idempotency_key.is_not_null())
+        .select((
+            game_save::user_id,
+            game_save::idempotency_key.assume_not_null(),
+        ))
+        .load::<(Option<i32>, String)>(connection)?
+        .into_iter()
+        .collect();
+    let moved: Vec<(String, Option<i32>, Option<String>)> = game_save::table
+        .filter(game_save::path_id.eq(from_path_id))
+        .order((game_save::generation.asc(), game_save::uuid.asc()))
+        .select((
+            game_save::uuid,
+            game_save::user_id,
+            game_save::idempotency_key,
+        ))
+        .load(connection)?;
+
+    for (uuid, user_id, idempotency_key) in moved {
+        let generation = next_generation(connection, to_path_id)?;
+        let idempotency_key =
+            idempotency_key.filter(|key| !target_keys.contains(&(user_id, key.clone())));
+        diesel::update(game_save::table.find(&uuid))
+            .set((
+                game_save::path_id.eq(to_path_id),
+                game_save::generation.eq(generation),
+                game_save::idempotency_key.eq(idempotency_key),
             ))
-            .load(connection)?;
-        let mut executables: Vec<Executable> = Vec::with_capacity(executable_rows.len());
-        for (id, executable, os) in executable_rows {
-            executables.push(Executable {
-                id,
-                executable: ExecutableCreate {
-                    executable,
-                    operating_system: os,
-                },
-            });
-        }
-        Ok(executables)
+            .execute(connection)?;
     }
+    Ok(())
+}
 
-    pub fn add_reference_to_save(
-        &self,
-        uuid: Uuid,
-        path_id: i32,
-        files_hash: Vec<FileHash>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let connection = &mut self.pool.get()?;
-        let now = time::OffsetDateTime::now_utc();
+fn validate_device(device_name: Option<&str>) -> Result<(), DbError> {
+    match device_name {
+        Some(device_name) => validate_device_name(device_name).map_err(DbError::Invalid),
+        None => Ok(()),
+    }
+}
 
-        connection.immediate_transaction(|connection| {
-            diesel::insert_into(game_save::table)
-                .values(DbGameSave {
-                    uuid: uuid.to_string(),
-                    path_id,
-                    time: time::PrimitiveDateTime::new(now.date(), now.time()),
-                })
-                .execute(connection)?;
+fn validate_idempotency(idempotency_key: Option<&str>) -> Result<(), DbError> {
+    match idempotency_key {
+        Some(key) => validate_idempotency_key(key).map_err(DbError::Invalid),
+        None => Ok(()),
+    }
+}
 
-            for file_hash in files_hash {
-                diesel::insert_into(file_hash::table)
-                    .values(DbFileHash {
-                        relative_path: file_hash.relative_path,
-                        hash: file_hash.hash,
-                        game_save_uuid: uuid.to_string(),
-                    })
-                    .execute(connection)?;
-            }
-            Ok(())
-        })
+/// Removes the saves with their file hashes and attributes, and the blob
+/// rows nothing references anymore along with their chunk lists. Returns
+/// the `sha256` hashes of those blob rows and of the chunks no other
+/// content uses
+fn delete_save_rows(
+    connection: &mut SqliteConnection,
+    uuids: &[String],
+) -> QueryResult<Vec<String>> {
+    let mut blobs: BTreeMap<i32, (HashAlgo, String)> = BTreeMap::new();
+    for uuids in uuids.chunks(SQLITE_MAX_VARIABLES) {
+        blobs.extend(
+            file_hash::table
+                .inner_join(blob::table)
+                .filter(file_hash::game_save_uuid.eq_any(uuids))
+                .select((blob::id, blob::algo, blob::hash))
+                .distinct()
+                .load::<(i32, HashAlgo, String)>(connection)?
+                .into_iter()
+                .map(|(id, algo, hash)| (id, (algo, hash))),
+        );
     }
 
-    pub fn get_reference_to_save_by_path_id(
-        &self,
-        path_id: i32,
-    ) -> Result<Option<Vec<SaveReference>>, Box<dyn std::error::Error + Send + Sync>> {
-        let connection = &mut self.pool.get()?;
+    for uuids in uuids.chunks(SQLITE_MAX_VARIABLES) {
+        diesel::delete(file_hash::table.filter(file_hash::game_save_uuid.eq_any(uuids)))
+            .execute(connection)?;
+        diesel::delete(save_attribute::table.filter(save_attribute::game_save_uuid.eq_any(uuids)))
+            .execute(connection)?;
+        diesel::delete(game_save::table.filter(game_save::uuid.eq_any(uuids)))
+            .execute(connection)?;
+    }
+    let blob_ids: Vec<i32> = blobs.keys().copied().collect();
+    let mut still_referenced: HashSet<i32> = HashSet::new();
+    for blob_ids in blob_ids.chunks(SQLITE_MAX_VARIABLES) {
+        still_referenced.extend(
+            file_hash::table
+                .filter(file_hash::blob_id.eq_any(blob_ids))
+                .select(file_hash::blob_id)
+                .distinct()
+                .load::<i32>(connection)?,
+        );
+    }
+    blobs.retain(|id, _| !still_referenced.contains(id));
+    let released: Vec<i32> = blobs.keys().copied().collect();
+    let mut chunk_hashes: BTreeSet<String> = BTreeSet::new();
+    for blob_ids in released.chunks(SQLITE_MAX_VARIABLES) {
+        chunk_hashes.extend(
+            blob_chunk::table
+                .filter(blob_chunk::blob_id.eq_any(blob_ids))
+                .select(blob_chunk::chunk_hash)
+                .load::<String>(connection)?,
+        );
+        diesel::delete(blob_chunk::table.filter(blob_chunk::blob_id.eq_any(blob_ids)))
+            .execute(connection)?;
+        diesel::delete(blob::table.filter(blob::id.eq_any(blob_ids))).execute(connection)?;
+    }
+    // A chunk stays stored while another chunk list has it or a file is
+    // stored whole with the same content
+    let chunk_hashes: Vec<String> = chunk_hashes.into_iter().collect();
+    let mut chunks_in_use: HashSet<String> = HashSet::new();
+    for hashes in chunk_hashes.chunks(SQLITE_MAX_VARIABLES) {
+        chunks_in_use.extend(
+            blob_chunk::table
+                .filter(blob_chunk::chunk_hash.eq_any(hashes))
+                .select(blob_chunk::chunk_hash)
+                .distinct()
+                .load::<String>(connection)?,
+        );
+        chunks_in_use.extend(
+            file_hash::table
+                .inner_join(blob::table)
+                .filter(blob::algo.eq(HashAlgo::Sha256))
+                .filter(blob::hash.eq_any(hashes))
+                .select(blob::hash)
+                .distinct()
+                .load::<String>(connection)?,
+        );
+    }
 
-        let save_rows = game_save::table
-            .filter(game_save::path_id.eq(path_id))
-            .select(DbGameSave::as_select())
-            .load(connection)?;
+    let mut hashes: Vec<String> = blobs
+        .into_values()
+        .filter(|(algo, _)| *algo == HashAlgo::Sha256)
+        .map(|(_, hash)| hash)
+        .chain(
+            chunk_hashes
+                .into_iter()
+                .filter(|hash| !chunks_in_use.contains(hash)),
+        )
+        .collect();
+    hashes.sort();
+    hashes.dedup();
+    Ok(hashes)
+}
 
-        if save_rows.is_empty() {
-            return Ok(None);
-        }
+/// Deletes the game row with everything hanging off it, returns the uuids
+/// of the removed saves
+fn hard_delete_game_rows(
+    connection: &mut SqliteConnection,
+    game_id: i32,
+) -> Result<Vec<String>, DbError> {
+    let game_exists: i64 = game_metadata::table
+        .filter(game_metadata::id.eq(game_id))
+        .count()
+        .get_result(connection)?;
+    if game_exists == 0 {
+        return Err(DbError::NotFound);
+    }
 
-        let mut save_references: Vec<SaveReference> = Vec::with_capacity(save_rows.len());
-        for game_save in save_rows {
-            let files_hash_db =
-                DbFileHash::belonging_to(&game_save).load::<DbFileHash>(connection)?;
+    let path_ids = game_path::table
+        .filter(game_path::game_metadata_id.eq(game_id))
+        .select(game_path::id.assume_not_null());
+    let save_uuids: Vec<String> = game_save::table
+        .filter(game_save::path_id.eq_any(path_ids))
+        .select(game_save::uuid)
+        .load(connection)?;
 
-            save_references.push(SaveReference {
-                uuid: game_save.uuid.to_string(),
-                path_id: game_save.path_id,
-                time: game_save.time.assume_utc().unix_timestamp(),
-                files_hash: files_hash_db
-                    .iter()
-                    .map(|files_hash_db| FileHash {
-                        relative_path: files_hash_db.relative_path.clone(),
-                        hash: files_hash_db.hash.clone(),
-                    })
-                    .collect(),
-            })
+    delete_save_rows(connection, &save_uuids)?;
+    diesel::delete(game_path::table.filter(game_path::game_metadata_id.eq(game_id)))
+        .execute(connection)?;
+    diesel::delete(game_executable::table.filter(game_executable::game_metadata_id.eq(game_id)))
+        .execute(connection)?;
+    diesel::delete(game_alt_name::table.filter(game_alt_name::game_metadata_id.eq(game_id)))
+        .execute(connection)?;
+    diesel::delete(game_metadata::table.filter(game_metadata::id.eq(game_id)))
+        .execute(connection)?;
+
+    Ok(save_uuids)
+}
+
+/// Marks the game deleted, `NotFound` when it is missing or already deleted
+fn soft_delete_game_row(connection: &mut SqliteConnection, game_id: i32) -> Result<(), DbError> {
+    let updated = diesel::update(
+        game_metadata::table
+            .filter(game_metadata::id.eq(game_id))
+            .filter(game_metadata::is_deleted.eq(false)),
+    )
+    .set((
+        game_metadata::is_deleted.eq(true),
+        game_metadata::updated_at.eq(now_utc()),
+    ))
+    .execute(connection)?;
+    if updated == 0 {
+        return Err(DbError::NotFound);
+    }
+    Ok(())
+}
+
+/// Deletes the saves of `path_id` past the `keep` most recent unpinned ones
+fn prune_path_rows(
+    connection: &mut SqliteConnection,
+    path_id: i32,
+    keep: usize,
+) -> Result<PrunedSaves, DbError> {
+    let pruned_uuids: Vec<String> = game_save::table
+        .filter(game_save::path_id.eq(path_id))
+        .filter(game_save::pinned.eq(false))
+        .order((game_save::time.desc(), game_save::uuid.desc()))
+        .select(game_save::uuid)
+        .load::<String>(connection)?
+        .into_iter()
+        .skip(keep)
+        .collect();
+
+    let orphaned_hashes = delete_save_rows(connection, &pruned_uuids)?;
+
+    Ok(PrunedSaves {
+        uuids: pruned_uuids,
+        orphaned_hashes,
+    })
+}
+
+/// Path id, uuid and time of a save with its game's retention `keep` and
+/// `max_age_days`
+type RetentionRow = (
+    i32,
+    String,
+    time::PrimitiveDateTime,
+    Option<i32>,
+    Option<i32>,
+);
+
+/// Deletes the unpinned saves each path's game retention policy does not
+/// keep, `default_keep` applies to games without a stored `keep`. The
+/// newest unpinned save of a path is never removed for its age. Only the
+/// paths of `game_id` are pruned when it is given
+fn prune_rows_by_policy(
+    connection: &mut SqliteConnection,
+    game_id: Option<i32>,
+    default_keep: usize,
+) -> Result<PrunedSaves, DbError> {
+    let mut query = game_save::table
+        .inner_join(game_path::table.inner_join(game_metadata::table))
+        .filter(game_save::pinned.eq(false))
+        .into_boxed();
+    if let Some(game_id) = game_id {
+        query = query.filter(game_path::game_metadata_id.eq(game_id));
+    }
+    let save_rows: Vec<RetentionRow> = query
+        .order((
+            game_save::path_id.asc(),
+            game_save::time.desc(),
+            game_save::uuid.desc(),
+        ))
+        .select((
+            game_save::path_id,
+            game_save::uuid,
+            game_save::time,
+            game_metadata::retention_keep,
+            game_metadata::retention_max_age_days,
+        ))
+        .load(connection)?;
+
+    let now = now_utc();
+    let mut pruned_uuids = Vec::new();
+    let mut current_path_id = None;
+    let mut position = 0;
+    for (path_id, uuid, time, keep, max_age_days) in save_rows {
+        if current_path_id != Some(path_id) {
+            current_path_id = Some(path_id);
+            position = 0;
         }
+        let keep = keep.map_or(default_keep, |keep| keep.max(1) as usize);
+        let expired = position > 0
+            && max_age_days.is_some_and(|days| time < now - time::Duration::days(days.into()));
+        if position >= keep || expired {
+            pruned_uuids.push(uuid);
+        }
+        position += 1;
+    }
+
+    let orphaned_hashes = delete_save_rows(connection, &pruned_uuids)?;
+
+    Ok(PrunedSaves {
+        uuids: pruned_uuids,
+        orphaned_hashes,
+    })
+}
+
+/// Saves a caller may read: every save without `user_id`, otherwise only
+/// the ones that user uploaded
+type VisibleTo = diesel::dsl::Or<diesel::dsl::Is<game_save::user_id, Option<i32>>, bool, Bool>;
+
+fn visible_to(user_id: Option<i32>) -> VisibleTo {
+    game_save::user_id
+        .is(user_id)
+        .or::<_, Bool>(user_id.is_none())
+}
 
-        Ok(Some(save_references))
+/// `game_save.time` in unix seconds, the precision clients see and send
+/// back as a cursor, so pages are keyed and ordered on it
+fn save_unix_time() -> SqlLiteral<BigInt> {
+    sql::<BigInt>("CAST(strftime('%s', game_save.time) AS INTEGER)")
+}
+
+fn load_save_summaries(
+    connection: &mut SqliteConnection,
+    path_id: i32,
+    user_id: Option<i32>,
+    after: Option<(i64, Option<String>)>,
+    limit: Option<i64>,
+) -> QueryResult<Vec<SaveSummary>> {
+    let mut query = game_save::table
+        .left_join(file_hash::table)
+        .filter(game_save::path_id.eq(path_id))
+        .filter(visible_to(user_id))
+        .group_by((game_save::uuid, game_save::time))
+        .order((save_unix_time().asc(), game_save::uuid.asc()))
+        .select((
+            game_save::uuid,
+            game_save::time,
+            count(file_hash::relative_path.nullable()),
+        ))
+        .into_boxed();
+    match after {
+        Some((time, Some(uuid))) => {
+            query = query.filter(
+                save_unix_time()
+                    .gt(time)
+                    .or(save_unix_time().eq(time).and(game_save::uuid.gt(uuid))),
+            );
+        }
+        Some((time, None)) => query = query.filter(save_unix_time().gt(time)),
+        None => {}
+    }
+    if let Some(limit) = limit {
+        query = query.limit(limit);
+    }
+
+    Ok(query
+        .load::<(String, time::PrimitiveDateTime, i64)>(connection)?
+        .into_iter()
+        .map(|(uuid, time, file_count)| SaveSummary {
+            uuid,
+            time: time.assume_utc(),
+            file_count,
+        })
+        .collect())
+}
+
+/// Where `relative_path` goes under `root`, refusing anything that could
+/// escape it such as `..` or an absolute path
+fn restore_target(root: &Path, relative_path: &str) -> Result<PathBuf, DbError> {
+    Ok(root.join(sanitize_relative_path(relative_path).map_err(DbError::Invalid)?))
+}
+
+/// Streams `reader` into a temporary file beside `target` and renames it
+/// over `target`, so a read failing halfway leaves the old file in place
+fn replace_file(target: &Path, reader: &mut dyn Read) -> std::io::Result<()> {
+    let parent = target.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent)?;
+    let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = parent.join(format!(".{file_name}.{}.restore", Uuid::new_v4()));
+    let written = std::fs::File::create(&temporary).and_then(|mut file| {
+        std::io::copy(reader, &mut file)?;
+        file.sync_all()
+    });
+    let replaced = written.and_then(|()| std::fs::rename(&temporary, target));
+    if replaced.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+    replaced
+}
+
+/// Deletes the file at `relative_path` under `root` and then its parent
+/// directories up to `root` as long as they are left empty
+fn remove_restored_file(root: &Path, relative_path: &str) -> Result<(), DbError> {
+    let target = restore_target(root, relative_path)?;
+    match std::fs::remove_file(&target) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(DbError::Io(e)),
+    }
+    let mut parent = target.parent();
+    while let Some(directory) = parent.filter(|directory| *directory != root) {
+        if std::fs::remove_dir(directory).is_err() {
+            break;
+        }
+        parent = directory.parent();
+    }
+    Ok(())
+}
+
+fn validate_label(label: Option<&str>) -> Result<(), DbError> {
+    match label {
+        Some(label) => validate_save_label(label).map_err(DbError::Invalid),
+        None => Ok(()),
+    }
+}
+
+/// Rejects uploads recording a file outside the save directory, so the
+/// save can always be restored
+fn validate_files_hash(files_hash: &[FileHash]) -> Result<(), DbError> {
+    for file_hash in files_hash {
+        sanitize_relative_path(&file_hash.relative_path).map_err(DbError::Invalid)?;
+    }
+    Ok(())
+}
+
+fn is_busy(e: &DbError) -> bool {
+    match e {
+        DbError::Diesel(diesel::result::Error::DatabaseError(_, info)) => {
+            let message = info.message();
+            message.contains("database is locked") || message.contains("database table is locked")
+        }
+        _ => false,
+    }
+}
+
+fn primitive_from_unix(timestamp: i64) -> Result<time::PrimitiveDateTime, DbError> {
+    let time = time::OffsetDateTime::from_unix_timestamp(timestamp)
+        .map_err(|e| DbError::Invalid(e.to_string()))?;
+    Ok(time::PrimitiveDateTime::new(time.date(), time.time()))
+}
+
+fn load_save_reference(
+    connection: &mut SqliteConnection,
+    game_save: DbGameSave,
+) -> Result<SaveReference, DbError> {
+    let files_hash_db: Vec<(String, DbBlob)> = DbFileHash::belonging_to(&game_save)
+        .inner_join(blob::table)
+        .select((file_hash::relative_path, DbBlob::as_select()))
+        .load(connection)?;
+    let attributes_db =
+        DbSaveAttribute::belonging_to(&game_save).load::<DbSaveAttribute>(connection)?;
+    save_reference_from_rows(game_save, files_hash_db, attributes_db)
+}
+
+/// Same as `load_save_reference` for many saves at once, the file hashes
+/// and attributes of every save are read in one query per chunk of saves
+/// instead of two per save. References come back in the order of `game_saves`
+fn load_save_references(
+    connection: &mut SqliteConnection,
+    game_saves: Vec<DbGameSave>,
+) -> Result<Vec<SaveReference>, DbError> {
+    let mut references = Vec::with_capacity(game_saves.len());
+    let mut remaining = game_saves;
+    while !remaining.is_empty() {
+        let rest = remaining.split_off(remaining.len().min(SQLITE_MAX_VARIABLES));
+        let game_saves = std::mem::replace(&mut remaining, rest);
+        let files_hash_db = DbFileHash::belonging_to(&game_saves)
+            .inner_join(blob::table)
+            .select((DbFileHash::as_select(), DbBlob::as_select()))
+            .load::<(DbFileHash, DbBlob)>(connection)?
+            .grouped_by(&game_saves);
+        let attributes_db = DbSaveAttribute::belonging_to(&game_saves)
+            .load::<DbSaveAttribute>(connection)?
+            .grouped_by(&game_saves);
+        for ((game_save, files_hash_db), attributes_db) in
+            game_saves.into_iter().zip(files_hash_db).zip(attributes_db)
+        {
+            let files_hash_db = files_hash_db
+                .into_iter()
+                .map(|(file_hash, blob)| (file_hash.relative_path, blob))
+                .collect();
+            references.push(save_reference_from_rows(
+                game_save,
+                files_hash_db,
+                attributes_db,
+            )?);
+        }
+    }
+    Ok(references)
+}
+
+fn save_reference_from_rows(
+    game_save: DbGameSave,
+    files_hash_db: Vec<(String, DbBlob)>,
+    attributes_db: Vec<DbSaveAttribute>,
+) -> Result<SaveReference, DbError> {
+    let uuid = Uuid::parse_str(&game_save.uuid)
+        .map_err(|e| DbError::Corrupt(format!("game_save.uuid {:?}: {}", game_save.uuid, e)))?;
+    let files_hash: Vec<FileHash> = files_hash_db
+        .into_iter()
+        .map(|(relative_path, blob)| FileHash {
+            relative_path,
+            hash: blob.hash,
+            algo: blob.algo,
+            size: blob.size.map(|size| size.max(0) as u64),
+        })
+        .collect();
+
+    Ok(SaveReference {
+        uuid: uuid.to_string(),
+        path_id: game_save.path_id,
+        time: game_save.time.assume_utc(),
+        total_size_bytes: SaveReference::total_size(&files_hash),
+        generation: game_save.generation,
+        label: game_save.label,
+        pinned: game_save.pinned,
+        device_name: game_save.device_name,
+        files_hash,
+        attributes: attributes_db
+            .into_iter()
+            .map(|attribute| (attribute.key, attribute.value))
+            .collect(),
+    })
+}
+
+impl GameDatabase {
+    pub fn new(db_path: &str) -> Self {
+        Self::try_new(db_path).expect("Failed to open the database")
+    }
+
+    pub fn try_new(db_path: &str) -> Result<Self, DbError> {
+        Self::with_config(GameDatabaseConfig::new(db_path))
+    }
+
+    /// Every `:memory:` connection is its own empty database, so the pool
+    /// is pinned to a single connection that holds the migrated schema
+    pub fn new_in_memory() -> Result<Self, DbError> {
+        Self::with_config(GameDatabaseConfig {
+            max_size: 1,
+            min_idle: Some(1),
+            ..GameDatabaseConfig::new(":memory:")
+        })
+    }
+
+    /// Opens a file written by `backup_to`, pending migrations are applied so
+    /// a backup taken by an older build comes back with the current schema
+    pub fn restore_from(backup_path: &str) -> Result<Self, DbError> {
+        if !Path::new(backup_path).is_file() {
+            return Err(DbError::NotFound);
+        }
+        Self::try_new(backup_path)
+    }
+
+    pub fn with_config(config: GameDatabaseConfig) -> Result<Self, DbError> {
+        let database_url = if config.read_only {
+            read_only_uri(&config.db_path)
+        } else {
+            config.db_path.clone()
+        };
+        if config.db_path != ":memory:" {
+            check_database_file(&config.db_path, &database_url, config.read_only)?;
+        }
+        if config.query_timeout.is_some() {
+            // Before the pool opens its first connection
+            timeout::install_interrupt_handler();
+        }
+        let manager = ConnectionManager::<SqliteConnection>::new(database_url);
+        let pool_stats = Arc::new(PoolStats::default());
+        let pool = Pool::builder()
+            .max_size(config.max_size)
+            .min_idle(config.min_idle)
+            .connection_timeout(config.connection_timeout)
+            .connection_customizer(Box::new(SqlitePragmas {
+                busy_timeout_ms: config.busy_timeout_ms,
+                read_only: config.read_only,
+            }))
+            .event_handler(Box::new(PoolEvents {
+                stats: Arc::clone(&pool_stats),
+                slow_checkout_threshold: config.slow_checkout_threshold,
+                query_timeout: config.query_timeout,
+            }))
+            .build(manager)?;
+
+        let database = Self {
+            pool,
+            busy_retries: config.busy_retries,
+            save_created_callbacks: Arc::default(),
+            pool_stats,
+        };
+        if config.run_migrations {
+            database.run_migrations()?;
+        }
+        Ok(database)
+    }
+
+    /// Names of the embedded migrations not applied yet, oldest first
+    pub fn pending_migrations(&self) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("pending_migrations").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            Ok(connection
+                .pending_migrations(MIGRATIONS)
+                .map_err(|e| DbError::Migration(e.to_string()))?
+                .iter()
+                .map(|migration| migration.name().to_string())
+                .collect())
+        })
+    }
+
+    /// Version of the newest applied migration, the digits of its
+    /// timestamped name read as a number, so it only ever grows as the
+    /// schema does. 0 for a database no migration ran on
+    pub fn schema_version(&self) -> Result<i64, DbError> {
+        let _span = debug_span!("schema_version").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let applied = connection
+                .applied_migrations()
+                .map_err(|e| DbError::Migration(e.to_string()))?;
+            let mut latest = 0;
+            for version in applied {
+                let digits: String = version
+                    .to_string()
+                    .chars()
+                    .filter(char::is_ascii_digit)
+                    .collect();
+                let version = digits.parse::<i64>().map_err(|_| {
+                    DbError::Migration(format!("migration version {version} is not a number"))
+                })?;
+                latest = latest.max(version);
+            }
+            Ok(latest)
+        })
+    }
+
+    /// Applies the pending migrations and returns the names of those that
+    /// ran, then moves the file hashes the `blob` migration set aside
+    pub fn run_migrations(&self) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("run_migrations").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let pending: Vec<String> = connection
+                .pending_migrations(MIGRATIONS)
+                .map_err(|e| DbError::Migration(e.to_string()))?
+                .iter()
+                .map(|migration| migration.name().to_string())
+                .collect();
+            let applied = connection
+                .run_pending_migrations(MIGRATIONS)
+                .map_err(|e| DbError::Migration(e.to_string()))?;
+            let applied: Vec<String> = pending.into_iter().take(applied.len()).collect();
+
+            if applied
+                .iter()
+                .any(|name| name.ends_with("_unique_steam_appid"))
+            {
+                let conflicts: Vec<(i32, String)> = steam_appid_conflict::table
+                    .order(steam_appid_conflict::game_metadata_id.asc())
+                    .select((
+                        steam_appid_conflict::game_metadata_id,
+                        steam_appid_conflict::steam_appid,
+                    ))
+                    .load(connection)?;
+                for (game_id, steam_appid) in conflicts {
+                    warn!(
+                        game_id,
+                        steam_appid, "duplicate steam appid removed, kept in steam_appid_conflict"
+                    );
+                }
+            }
+            Ok(applied)
+        })
+    }
+
+    /// Waits at most the pool connection timeout for a connection, then
+    /// checks it answers queries and that the schema is up to date
+    pub fn health_check(&self) -> Result<HealthStatus, DbError> {
+        let _span = debug_span!("health_check").entered();
+        traced(|| {
+            let state = self.pool.state();
+            let connection = &mut self.pool.get()?;
+
+            connection.batch_execute("SELECT 1")?;
+            let pending_migrations = connection
+                .has_pending_migration(MIGRATIONS)
+                .map_err(|e| DbError::Migration(e.to_string()))?;
+
+            Ok(HealthStatus {
+                max_connections: self.pool.max_size(),
+                idle_connections: state.idle_connections,
+                active_connections: state.connections - state.idle_connections,
+                pending_migrations,
+            })
+        })
+    }
+
+    /// Every counter in one aggregate query so a scrape stays cheap
+    pub fn collect_metrics(&self) -> Result<DbMetrics, DbError> {
+        let _span = debug_span!("collect_metrics").entered();
+        traced(|| {
+            let state = self.pool.state();
+            let connection = &mut self.pool.get()?;
+
+            let (total_games, total_paths, total_saves, total_file_hashes, distinct_file_hashes) =
+                diesel::select((
+                    sql::<BigInt>("(SELECT COUNT(*) FROM game_metadata WHERE is_deleted = 0)"),
+                    sql::<BigInt>("(SELECT COUNT(*) FROM game_path)"),
+                    sql::<BigInt>("(SELECT COUNT(*) FROM game_save)"),
+                    sql::<BigInt>("(SELECT COUNT(*) FROM file_hash)"),
+                    sql::<BigInt>(
+                        "(SELECT COUNT(DISTINCT b.hash) FROM file_hash f \
+                         JOIN blob b ON b.id = f.blob_id)",
+                    ),
+                ))
+                .get_result::<(i64, i64, i64, i64, i64)>(connection)?;
+
+            Ok(DbMetrics {
+                total_games,
+                total_paths,
+                total_saves,
+                total_file_hashes,
+                distinct_file_hashes,
+                idle_connections: state.idle_connections,
+                active_connections: state.connections - state.idle_connections,
+                pool_checkouts: self.pool_stats.checkouts.load(Ordering::Relaxed),
+                pool_checkout_timeouts: self.pool_stats.timeouts.load(Ordering::Relaxed),
+                pool_max_checkout_wait_ms: self.pool_stats.max_wait_micros.load(Ordering::Relaxed)
+                    as f64
+                    / 1000.0,
+            })
+        })
+    }
+
+    /// Registers `callback` to run after each save insert commits, outside
+    /// the transaction so it never holds the write lock
+    pub fn on_save_created(&self, callback: Box<SaveCreatedCallback>) {
+        self.save_created_callbacks
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Arc::from(callback));
+    }
+
+    /// A panicking callback is logged and skipped, the others still run
+    fn fire_save_created(&self, save_reference: &SaveReference) {
+        let callbacks = self
+            .save_created_callbacks
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for callback in callbacks {
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| callback(save_reference)));
+            if result.is_err() {
+                tracing::error!(uuid = %save_reference.uuid, "save created callback panicked");
+            }
+        }
+    }
+
+    /// Runs `transaction` in an immediate transaction, retrying with
+    /// exponential backoff while sqlite reports the database busy or locked
+    pub(super) fn with_retry<T, F>(
+        &self,
+        connection: &mut SqliteConnection,
+        mut transaction: F,
+    ) -> Result<T, DbError>
+    where
+        F: FnMut(&mut SqliteConnection) -> Result<T, DbError>,
+    {
+        let mut backoff = Duration::from_millis(SQLITE_BUSY_INITIAL_BACKOFF_MS);
+        let mut attempt = 0;
+        loop {
+            match connection.immediate_transaction(&mut transaction) {
+                Err(e) if is_busy(&e) && attempt < self.busy_retries => {
+                    attempt += 1;
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Same as `with_retry` but the transaction is always rolled back, so a
+    /// preview runs the exact statements of the real operation and returns
+    /// what they would have done
+    fn with_rollback<T, F>(
+        &self,
+        connection: &mut SqliteConnection,
+        mut transaction: F,
+    ) -> Result<T, DbError>
+    where
+        F: FnMut(&mut SqliteConnection) -> Result<T, DbError>,
+    {
+        let mut outcome = None;
+        let result = self.with_retry(connection, |connection| {
+            outcome = Some(transaction(connection)?);
+            Err::<(), _>(DbError::Diesel(diesel::result::Error::RollbackTransaction))
+        });
+        match (result, outcome) {
+            (Err(DbError::Diesel(diesel::result::Error::RollbackTransaction)), Some(outcome)) => {
+                Ok(outcome)
+            }
+            (Err(e), _) => Err(e),
+            (Ok(()), _) => unreachable!("the previewed transaction never commits"),
+        }
+    }
+
+    pub fn add_game_metadata(
+        &self,
+        game_metadata: &GameMetadataCreate,
+    ) -> Result<GameMetadata, DbError> {
+        let _span = debug_span!("add_game_metadata").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            self.with_retry(connection, |connection| {
+                insert_game_metadata(connection, game_metadata)
+            })
+        })
+    }
+
+    /// Inserts the game with its paths and executables in one transaction,
+    /// so a failure leaves no part of it behind
+    pub fn add_complete_game(
+        &self,
+        game_metadata: &GameMetadataCreate,
+        paths: &[SavePathCreate],
+        executables: &[ExecutableCreate],
+    ) -> Result<GameBundle, DbError> {
+        let _span = debug_span!(
+            "add_complete_game",
+            paths = paths.len(),
+            executables = executables.len()
+        )
+        .entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            self.with_retry(connection, |connection| {
+                let metadata = insert_game_metadata(connection, game_metadata)?;
+                let game_id = metadata.id.ok_or(DbError::NotFound)?;
+                let updated_at = now_utc();
+                insert_game_paths(connection, game_id, paths, updated_at)?;
+                insert_game_executables(connection, game_id, executables, updated_at)?;
+
+                Ok(GameBundle {
+                    metadata,
+                    paths: load_paths(connection, game_id)?,
+                    executables: load_executables(connection, game_id)?,
+                })
+            })
+        })
+    }
+
+    pub fn update_game_metadata(
+        &self,
+        game_id: i32,
+        update: &GameMetadataCreate,
+    ) -> Result<GameMetadata, DbError> {
+        let _span = debug_span!("update_game_metadata", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            self.with_retry(connection, |connection| {
+                update_game_metadata_row(connection, game_id, update)
+            })
+        })
+    }
+
+    /// Sets a new default name, the previous one is kept as a known name so
+    /// searching or matching by it still finds the game
+    pub fn rename_game(
+        &self,
+        game_id: i32,
+        new_default_name: &str,
+    ) -> Result<GameMetadata, DbError> {
+        let _span = debug_span!("rename_game", game_id).entered();
+        traced(|| {
+            let new_default_name = new_default_name.trim();
+            if new_default_name.is_empty() {
+                return Err(DbError::Invalid("default name is empty".to_string()));
+            }
+            let connection = &mut self.pool.get()?;
+
+            self.with_retry(connection, |connection| {
+                let current: DbGameMetadata = game_metadata::table
+                    .filter(game_metadata::id.eq(game_id))
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .select(DbGameMetadata::as_select())
+                    .first(connection)?;
+                let mut known_name: Vec<String> = game_alt_name::table
+                    .filter(game_alt_name::game_metadata_id.eq(game_id))
+                    .select(game_alt_name::name)
+                    .load(connection)?;
+                known_name.push(current.default_name);
+
+                let update = GameMetadataCreate {
+                    known_name,
+                    steam_appid: current.steam_appid,
+                    default_name: new_default_name.to_string(),
+                };
+                update_game_metadata_row(connection, game_id, &update)
+            })
+        })
+    }
+
+    /// Sets the official Steam title as default name and adds the localized
+    /// ones as known names, the previous default name is kept as a known
+    /// name like `rename_game` does. A game without an appid, or one Steam
+    /// does not know, is returned unchanged. The store is queried before
+    /// the transaction starts
+    pub fn enrich_from_steam(
+        &self,
+        game_id: i32,
+        client: &dyn SteamClient,
+    ) -> Result<GameMetadata, DbError> {
+        let _span = debug_span!("enrich_from_steam", game_id).entered();
+        traced(|| {
+            // The connection goes back to the pool before the store is asked,
+            // a slow Steam answer must not hold it
+            let current: DbGameMetadata = {
+                let connection = &mut self.pool.get()?;
+                game_metadata::table
+                    .filter(game_metadata::id.eq(game_id))
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .select(DbGameMetadata::as_select())
+                    .first(connection)?
+            };
+            let details = match &current.steam_appid {
+                Some(appid) => client.app_details(appid)?,
+                None => None,
+            };
+            let connection = &mut self.pool.get()?;
+            let Some(details) = details else {
+                return load_games_metadata(connection, vec![current])?
+                    .pop()
+                    .ok_or(DbError::NotFound);
+            };
+
+            self.with_retry(connection, |connection| {
+                let current: DbGameMetadata = game_metadata::table
+                    .filter(game_metadata::id.eq(game_id))
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .select(DbGameMetadata::as_select())
+                    .first(connection)?;
+                let mut known_name: Vec<String> = game_alt_name::table
+                    .filter(game_alt_name::game_metadata_id.eq(game_id))
+                    .select(game_alt_name::name)
+                    .load(connection)?;
+                known_name.push(current.default_name);
+                known_name.extend(details.localized_names.iter().cloned());
+
+                let update = GameMetadataCreate {
+                    known_name,
+                    steam_appid: current.steam_appid,
+                    default_name: details.name.clone(),
+                };
+                update_game_metadata_row(connection, game_id, &update)
+            })
+        })
+    }
+
+    /// Games without an appid are always inserted
+    pub fn upsert_game_metadata_by_appid(
+        &self,
+        game_metadata: &GameMetadataCreate,
+    ) -> Result<GameMetadata, DbError> {
+        let _span = debug_span!("upsert_game_metadata_by_appid").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            self.with_retry(connection, |connection| {
+                let existing_id: Option<i32> = match &game_metadata.steam_appid {
+                    Some(appid) => game_metadata::table
+                        .filter(game_metadata::steam_appid.eq(appid))
+                        .filter(game_metadata::is_deleted.eq(false))
+                        .select(game_metadata::id)
+                        .first::<Option<i32>>(connection)
+                        .optional()?
+                        .flatten(),
+                    None => None,
+                };
+
+                match existing_id {
+                    Some(game_id) => update_game_metadata_row(connection, game_id, game_metadata),
+                    None => insert_game_metadata(connection, game_metadata),
+                }
+            })
+        })
+    }
+
+    /// Moves everything owned by `merge_id` under `keep_id`, the merged
+    /// default name is kept as a known name and its steam appid is adopted
+    /// when `keep_id` has none
+    pub fn merge_game_metadata(&self, keep_id: i32, merge_id: i32) -> Result<(), DbError> {
+        let _span = debug_span!("merge_game_metadata", keep_id, merge_id).entered();
+        traced(|| {
+            if keep_id == merge_id {
+                return Err(DbError::Invalid(
+                    "cannot merge a game into itself".to_string(),
+                ));
+            }
+
+            let connection = &mut self.pool.get()?;
+            let updated_at = now_utc();
+
+            self.with_retry(connection, |connection| {
+                let keep: DbGameMetadata = game_metadata::table
+                    .filter(game_metadata::id.eq(keep_id))
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .select(DbGameMetadata::as_select())
+                    .first(connection)?;
+                let merge: DbGameMetadata = game_metadata::table
+                    .filter(game_metadata::id.eq(merge_id))
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .select(DbGameMetadata::as_select())
+                    .first(connection)?;
+
+                let keep_paths: HashMap<(String, OS), Option<i32>> =
+                    load_paths(connection, keep_id)?
+                        .into_iter()
+                        .map(|path| ((path.path.path, path.path.operating_system), path.id))
+                        .collect();
+                for path in load_paths(connection, merge_id)? {
+                    let Some(path_id) = path.id else {
+                        continue;
+                    };
+                    match keep_paths.get(&(path.path.path, path.path.operating_system)) {
+                        Some(Some(keep_path_id)) => {
+                            move_saves_to_path(connection, path_id, *keep_path_id)?;
+                            diesel::delete(game_path::table.filter(game_path::id.eq(path_id)))
+                                .execute(connection)?;
+                        }
+                        _ => {
+                            diesel::update(game_path::table.filter(game_path::id.eq(path_id)))
+                                .set((
+                                    game_path::game_metadata_id.eq(keep_id),
+                                    game_path::updated_at.eq(updated_at),
+                                ))
+                                .execute(connection)?;
+                        }
+                    }
+                }
+
+                let keep_executables: HashSet<(String, OS)> =
+                    load_executables(connection, keep_id)?
+                        .into_iter()
+                        .map(|executable| {
+                            (
+                                executable.executable.executable,
+                                executable.executable.operating_system,
+                            )
+                        })
+                        .collect();
+                for executable in load_executables(connection, merge_id)? {
+                    let Some(executable_id) = executable.id else {
+                        continue;
+                    };
+                    let target =
+                        game_executable::table.filter(game_executable::id.eq(executable_id));
+                    if keep_executables.contains(&(
+                        executable.executable.executable,
+                        executable.executable.operating_system,
+                    )) {
+                        diesel::delete(target).execute(connection)?;
+                    } else {
+                        diesel::update(target)
+                            .set((
+                                game_executable::game_metadata_id.eq(keep_id),
+                                game_executable::updated_at.eq(updated_at),
+                            ))
+                            .execute(connection)?;
+                    }
+                }
+
+                let keep_names: HashSet<String> = game_alt_name::table
+                    .filter(game_alt_name::game_metadata_id.eq(keep_id))
+                    .select(game_alt_name::name)
+                    .load::<String>(connection)?
+                    .into_iter()
+                    .collect();
+                let mut merge_names: Vec<String> = game_alt_name::table
+                    .filter(game_alt_name::game_metadata_id.eq(merge_id))
+                    .select(game_alt_name::name)
+                    .load(connection)?;
+                merge_names.push(merge.default_name);
+                let added_names: Vec<DbGameName> = merge_names
+                    .into_iter()
+                    .filter(|name| *name != keep.default_name && !keep_names.contains(name))
+                    .collect::<HashSet<String>>()
+                    .into_iter()
+                    .map(|name| DbGameName {
+                        name,
+                        game_metadata_id: keep_id,
+                    })
+                    .collect();
+
+                diesel::delete(
+                    game_alt_name::table.filter(game_alt_name::game_metadata_id.eq(merge_id)),
+                )
+                .execute(connection)?;
+                diesel::delete(game_metadata::table.filter(game_metadata::id.eq(merge_id)))
+                    .execute(connection)?;
+
+                // A name differing from a kept one only by case is left out by
+                // the NOCASE index, the kept spelling wins
+                diesel::insert_or_ignore_into(game_alt_name::table)
+                    .values(added_names)
+                    .execute(connection)?;
+                diesel::update(game_metadata::table.filter(game_metadata::id.eq(keep_id)))
+                    .set(game_metadata::updated_at.eq(updated_at))
+                    .execute(connection)?;
+                if keep.steam_appid.is_none() && merge.steam_appid.is_some() {
+                    diesel::update(game_metadata::table.filter(game_metadata::id.eq(keep_id)))
+                        .set(game_metadata::steam_appid.eq(merge.steam_appid))
+                        .execute(connection)?;
+                }
+
+                Ok(())
+            })
+        })
+    }
+
+    /// Hides the game from every read until `restore_game_metadata`, its
+    /// paths and saves are left untouched
+    pub fn delete_game_metadata(&self, game_id: i32) -> Result<(), DbError> {
+        let _span = debug_span!("delete_game_metadata", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            soft_delete_game_row(connection, game_id)
+        })
+    }
+
+    /// Dry run of `delete_game_metadata`: fails the same way and returns the
+    /// game it would hide, nothing is written
+    pub fn delete_game_metadata_preview(&self, game_id: i32) -> Result<GameMetadata, DbError> {
+        let _span = debug_span!("delete_game_metadata_preview", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            self.with_rollback(connection, |connection| {
+                let game = load_games_by_ids(connection, &[game_id])?.pop();
+                soft_delete_game_row(connection, game_id)?;
+                game.ok_or(DbError::NotFound)
+            })
+        })
+    }
+
+    /// Fails with `UniqueViolation` when another game took the steam appid
+    /// while this one was deleted
+    pub fn restore_game_metadata(&self, game_id: i32) -> Result<(), DbError> {
+        let _span = debug_span!("restore_game_metadata", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let updated = diesel::update(
+                game_metadata::table
+                    .filter(game_metadata::id.eq(game_id))
+                    .filter(game_metadata::is_deleted.eq(true)),
+            )
+            .set((
+                game_metadata::is_deleted.eq(false),
+                game_metadata::updated_at.eq(now_utc()),
+            ))
+            .execute(connection)?;
+            if updated == 0 {
+                return Err(DbError::NotFound);
+            }
+            Ok(())
+        })
+    }
+
+    /// Removes the game with its names, paths, executables and saves whether
+    /// or not it was soft deleted, returns the uuids of the removed saves so
+    /// their files can be deleted
+    pub fn hard_delete_game_metadata(&self, game_id: i32) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("hard_delete_game_metadata", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            self.with_retry(connection, |connection| {
+                hard_delete_game_rows(connection, game_id)
+            })
+        })
+    }
+
+    /// Dry run of `hard_delete_game_metadata`, returns the uuids of the
+    /// saves it would remove without removing anything
+    pub fn hard_delete_game_metadata_preview(&self, game_id: i32) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("hard_delete_game_metadata_preview", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            self.with_rollback(connection, |connection| {
+                hard_delete_game_rows(connection, game_id)
+            })
+        })
+    }
+
+    pub fn get_game_metadata_by_name(
+        &self,
+        target_name: &str,
+    ) -> Result<Vec<GameMetadata>, DbError> {
+        let _span = debug_span!("get_game_metadata_by_name").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let db_games: Vec<DbGameMetadata> = game_metadata::table
+                .filter(game_metadata::default_name.eq(target_name))
+                .filter(game_metadata::is_deleted.eq(false))
+                .select(DbGameMetadata::as_select())
+                .load(connection)?;
+
+            Ok(load_games_metadata(connection, db_games)?)
+        })
+    }
+
+    /// The game a client means by `any_name`, from the first lookup that
+    /// finds one: the exact default name, an alternative name ignoring
+    /// ASCII case, the canonical name, then the best fuzzy match scoring at
+    /// least `DEFAULT_FUZZY_MATCH_THRESHOLD`. The oldest game wins a tie
+    /// of the exact lookups
+    pub fn resolve_game(&self, any_name: &str) -> Result<Option<ResolvedGame>, DbError> {
+        let _span = debug_span!("resolve_game", any_name).entered();
+        traced(|| {
+            let resolved = |game, matched_by| Some(ResolvedGame { game, matched_by });
+            {
+                let connection = &mut self.pool.get()?;
+                let by_default_name: Vec<DbGameMetadata> = game_metadata::table
+                    .filter(game_metadata::default_name.eq(any_name))
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .order(game_metadata::id.asc())
+                    .limit(1)
+                    .select(DbGameMetadata::as_select())
+                    .load(connection)?;
+                if let Some(game) = load_games_metadata(connection, by_default_name)?.pop() {
+                    return Ok(resolved(game, NameResolution::DefaultName));
+                }
+
+                // Same comparison as the NOCASE index on the alternative names
+                let by_alt_name: Vec<DbGameMetadata> = game_metadata::table
+                    .inner_join(game_alt_name::table)
+                    .filter(
+                        sql::<diesel::sql_types::Bool>("game_alt_name.name = ")
+                            .bind::<diesel::sql_types::Text, _>(any_name)
+                            .sql(" COLLATE NOCASE"),
+                    )
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .order(game_metadata::id.asc())
+                    .limit(1)
+                    .select(DbGameMetadata::as_select())
+                    .load(connection)?;
+                if let Some(game) = load_games_metadata(connection, by_alt_name)?.pop() {
+                    return Ok(resolved(game, NameResolution::AltName));
+                }
+            }
+
+            if let Some(game) = self.get_game_by_canonical_name(any_name)? {
+                return Ok(resolved(game, NameResolution::CanonicalName));
+            }
+            Ok(self
+                .match_game_by_fuzzy_name(any_name, DEFAULT_FUZZY_MATCH_THRESHOLD)?
+                .into_iter()
+                .next()
+                .and_then(|(game, _)| resolved(game, NameResolution::Fuzzy)))
+        })
+    }
+
+    /// Case-insensitive (ASCII only, like SQLite's `LIKE`) substring match on
+    /// the default and known names
+    pub fn search_game_metadata(&self, query: &str) -> Result<Vec<GameMetadata>, DbError> {
+        let _span = debug_span!("search_game_metadata").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            Ok(search_games_like(connection, query)?)
+        })
+    }
+
+    /// Games whose default or alternative name contains every word of
+    /// `query` as a prefix, most relevant (bm25) first. Falls back to the
+    /// unranked `LIKE` search when the FTS5 index is not available
+    pub fn search_games_ranked(
+        &self,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<GameMetadata>, DbError> {
+        let _span = debug_span!("search_games_ranked", limit).entered();
+        traced(|| {
+            let Some(fts_query) = fts_name_query(query) else {
+                return Ok(Vec::new());
+            };
+            let connection = &mut self.pool.get()?;
+
+            let ranked = diesel::sql_query(
+                "SELECT m.id AS game_metadata_id FROM ( \
+                     SELECT game_metadata_id, rank FROM game_name_fts WHERE game_name_fts MATCH ? \
+                 ) AS f JOIN game_metadata m ON m.id = f.game_metadata_id \
+                 WHERE m.is_deleted = 0 \
+                 GROUP BY m.id ORDER BY MIN(f.rank), m.id LIMIT ?",
+            )
+            .bind::<diesel::sql_types::Text, _>(fts_query)
+            .bind::<BigInt, _>(limit.max(0))
+            .load::<RankedGameId>(connection);
+            let ids: Vec<i32> = match ranked {
+                Ok(rows) => rows.into_iter().map(|row| row.game_metadata_id).collect(),
+                Err(e) if is_fts_unavailable(&e) => {
+                    warn!(error = %e, "full-text index unavailable, using LIKE search");
+                    let mut games = search_games_like(connection, query)?;
+                    games.truncate(limit.max(0) as usize);
+                    return Ok(games);
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let mut games: HashMap<i32, GameMetadata> = load_games_by_ids(connection, &ids)?
+                .into_iter()
+                .filter_map(|game| Some((game.id?, game)))
+                .collect();
+            Ok(ids.iter().filter_map(|id| games.remove(id)).collect())
+        })
+    }
+
+    /// Games whose default or alternative name contains `query` and that
+    /// have at least one save, on a path for `os` when it is given, most
+    /// recent save first. Filtering, counting and ordering are a single
+    /// aggregated query, the metadata is then loaded for the ids it returns
+    pub fn search_games_with_activity(
+        &self,
+        query: &str,
+        os: Option<OS>,
+        limit: i64,
+    ) -> Result<Vec<GameActivity>, DbError> {
+        let _span = debug_span!("search_games_with_activity", ?os, limit).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let rows = diesel::sql_query(
+                "SELECT m.id AS game_metadata_id, COUNT(*) AS save_count, \
+                 MAX(s.time) AS last_save_time FROM game_metadata m \
+                 JOIN game_path p ON p.game_metadata_id = m.id \
+                 AND (?2 IS NULL OR p.operating_system = ?2) \
+                 JOIN game_save s ON s.path_id = p.id \
+                 WHERE m.is_deleted = 0 AND (m.default_name LIKE ?1 ESCAPE '\\' \
+                 OR EXISTS (SELECT 1 FROM game_alt_name a WHERE a.game_metadata_id = m.id \
+                 AND a.name LIKE ?1 ESCAPE '\\')) \
+                 GROUP BY m.id ORDER BY last_save_time DESC, m.id LIMIT ?3",
+            )
+            .bind::<diesel::sql_types::Text, _>(like_pattern(query))
+            .bind::<Nullable<diesel::sql_types::Text>, _>(os)
+            .bind::<BigInt, _>(limit.max(0))
+            .load::<GameActivityRow>(connection)?;
+
+            let ids: Vec<i32> = rows.iter().map(|row| row.game_metadata_id).collect();
+            let mut games: HashMap<i32, GameMetadata> = load_games_by_ids(connection, &ids)?
+                .into_iter()
+                .filter_map(|game| Some((game.id?, game)))
+                .collect();
+            Ok(rows
+                .into_iter()
+                .filter_map(|row| {
+                    Some(GameActivity {
+                        game: games.remove(&row.game_metadata_id)?,
+                        save_count: row.save_count,
+                        last_save_time: row.last_save_time.assume_utc(),
+                    })
+                })
+                .collect())
+        })
+    }
+
+    /// Games whose default or alternative name scores at least `threshold`
+    /// against `raw_name`, best match first. Names are compared without
+    /// case, punctuation or edition words like "GOTY", so a client can map a
+    /// folder name to a game without an exact hit
+    pub fn match_game_by_fuzzy_name(
+        &self,
+        raw_name: &str,
+        threshold: f64,
+    ) -> Result<Vec<(GameMetadata, f64)>, DbError> {
+        let _span = debug_span!("match_game_by_fuzzy_name", raw_name, threshold).entered();
+        traced(|| {
+            if !(0.0..=1.0).contains(&threshold) {
+                return Err(DbError::Invalid(format!(
+                    "threshold must be between 0 and 1, got {threshold}"
+                )));
+            }
+            let wanted = normalize_game_name(raw_name);
+            if wanted.iter().all(String::is_empty) {
+                return Ok(Vec::new());
+            }
+            let connection = &mut self.pool.get()?;
+
+            let default_names: Vec<(Option<i32>, String)> = game_metadata::table
+                .filter(game_metadata::is_deleted.eq(false))
+                .select((game_metadata::id, game_metadata::default_name))
+                .load(connection)?;
+            let alt_names: Vec<(Option<i32>, String)> = game_alt_name::table
+                .inner_join(game_metadata::table)
+                .filter(game_metadata::is_deleted.eq(false))
+                .select((
+                    game_alt_name::game_metadata_id.nullable(),
+                    game_alt_name::name,
+                ))
+                .load(connection)?;
+
+            let mut scores: HashMap<i32, f64> = HashMap::new();
+            for (id, name) in default_names.into_iter().chain(alt_names) {
+                let Some(id) = id else { continue };
+                let score = game_name_similarity(&wanted, &normalize_game_name(&name));
+                if score >= threshold {
+                    let best = scores.entry(id).or_insert(score);
+                    *best = best.max(score);
+                }
+            }
+
+            let ids: Vec<i32> = scores.keys().copied().collect();
+            let mut matches: Vec<(GameMetadata, f64)> = load_games_by_ids(connection, &ids)?
+                .into_iter()
+                .filter_map(|game| {
+                    let score = *scores.get(&game.id?)?;
+                    Some((game, score))
+                })
+                .collect();
+            matches.sort_by(|(a, a_score), (b, b_score)| {
+                b_score
+                    .total_cmp(a_score)
+                    .then_with(|| a.metadata.default_name.cmp(&b.metadata.default_name))
+            });
+            Ok(matches)
+        })
+    }
+
+    /// If several games share the appid the oldest one is returned
+    pub fn get_game_metadata_by_appid(&self, appid: &str) -> Result<Option<GameMetadata>, DbError> {
+        let _span = debug_span!("get_game_metadata_by_appid", appid).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let db_games: Vec<DbGameMetadata> = game_metadata::table
+                .filter(game_metadata::steam_appid.eq(appid))
+                .filter(game_metadata::is_deleted.eq(false))
+                .order(game_metadata::id.asc())
+                .limit(1)
+                .select(DbGameMetadata::as_select())
+                .load(connection)?;
+
+            Ok(load_games_metadata(connection, db_games)?.pop())
+        })
+    }
+
+    /// Game whose default name normalizes to the same canonical name as
+    /// `raw_name`, an indexed equality lookup for names that only differ by
+    /// case, punctuation or edition words. The oldest one wins when several
+    /// match, alternative names are not looked at
+    pub fn get_game_by_canonical_name(
+        &self,
+        raw_name: &str,
+    ) -> Result<Option<GameMetadata>, DbError> {
+        let _span = debug_span!("get_game_by_canonical_name", raw_name).entered();
+        traced(|| {
+            let canonical = canonical_game_name(raw_name);
+            if canonical.is_empty() {
+                return Ok(None);
+            }
+            let connection = &mut self.pool.get()?;
+            let db_games: Vec<DbGameMetadata> = game_metadata::table
+                .filter(game_metadata::canonical_name.eq(canonical))
+                .filter(game_metadata::is_deleted.eq(false))
+                .order(game_metadata::id.asc())
+                .limit(1)
+                .select(DbGameMetadata::as_select())
+                .load(connection)?;
+
+            Ok(load_games_metadata(connection, db_games)?.pop())
+        })
+    }
+
+    pub fn get_game_metadata_by_id(
+        &self,
+        target_id: &i32,
+    ) -> Result<Option<GameMetadata>, DbError> {
+        let _span = debug_span!("get_game_metadata_by_id", game_id = *target_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            connection.transaction(|connection| {
+                let maybe_meta: Option<DbGameMetadata> = game_metadata::table
+                    .filter(game_metadata::id.eq(target_id))
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .select(DbGameMetadata::as_select())
+                    .first(connection)
+                    .optional()?;
+
+                let meta = match maybe_meta {
+                    Some(meta) => meta,
+                    None => return Ok(None),
+                };
+
+                let id = match meta.id {
+                    Some(id) => id,
+                    None => return Ok(None),
+                };
+
+                let name_rows: Vec<String> = game_alt_name::table
+                    .filter(game_alt_name::game_metadata_id.eq(id))
+                    .select(game_alt_name::name)
+                    .load(connection)?;
+
+                Ok(Some(GameMetadata {
+                    id: Some(id),
+                    metadata: GameMetadataCreate {
+                        known_name: name_rows,
+                        steam_appid: meta.steam_appid,
+                        default_name: meta.default_name,
+                    },
+                    created_at: Some(meta.created_at.assume_utc()),
+                    updated_at: Some(meta.updated_at.assume_utc()),
+                }))
+            })
+        })
+    }
+
+    /// The games among `ids` in the order they were asked for, unknown or
+    /// deleted ids are skipped and a repeated id is returned once
+    pub fn get_games_metadata_by_ids(&self, ids: &[i32]) -> Result<Vec<GameMetadata>, DbError> {
+        let _span = debug_span!("get_games_metadata_by_ids", ids = ids.len()).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let mut games: HashMap<i32, GameMetadata> = load_games_by_ids(connection, ids)?
+                .into_iter()
+                .filter_map(|game| Some((game.id?, game)))
+                .collect();
+            Ok(ids.iter().filter_map(|id| games.remove(id)).collect())
+        })
+    }
+
+    pub fn get_game_bundle(&self, game_id: i32) -> Result<Option<GameBundle>, DbError> {
+        let _span = debug_span!("get_game_bundle", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            connection.transaction(|connection| {
+                let maybe_meta: Option<DbGameMetadata> = game_metadata::table
+                    .filter(game_metadata::id.eq(game_id))
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .select(DbGameMetadata::as_select())
+                    .first(connection)
+                    .optional()?;
+
+                let Some(meta) = maybe_meta else {
+                    return Ok(None);
+                };
+                let Some(metadata) = load_games_metadata(connection, vec![meta])?.pop() else {
+                    return Ok(None);
+                };
+
+                Ok(Some(GameBundle {
+                    metadata,
+                    paths: load_paths(connection, game_id)?,
+                    executables: load_executables(connection, game_id)?,
+                }))
+            })
+        })
+    }
+
+    /// The save paths and executables of a game for `os`, read in one
+    /// transaction so both lists describe the same state
+    pub fn get_launch_profile(&self, game_id: i32, os: OS) -> Result<LaunchProfile, DbError> {
+        let _span = debug_span!("get_launch_profile", game_id, %os).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            connection.transaction(|connection| {
+                let exists: i64 = game_metadata::table
+                    .filter(game_metadata::id.eq(game_id))
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .count()
+                    .get_result(connection)?;
+                if exists == 0 {
+                    return Err(DbError::NotFound);
+                }
+
+                Ok(LaunchProfile {
+                    paths: game_path::table
+                        .filter(game_path::game_metadata_id.eq(game_id))
+                        .filter(game_path::operating_system.eq(&os))
+                        .order(game_path::id.asc())
+                        .select(game_path::path)
+                        .load(connection)?,
+                    executables: game_executable::table
+                        .filter(game_executable::game_metadata_id.eq(game_id))
+                        .filter(game_executable::operating_system.eq(os))
+                        .order(game_executable::id.asc())
+                        .select(game_executable::executable)
+                        .load(connection)?,
+                })
+            })
+        })
+    }
+
+    /// Every game with its paths and executables, without any save data.
+    /// Games are read a page at a time and written as they come, so a
+    /// game changed while the export runs may be seen before or after
+    pub fn export_metadata_to_json(&self) -> Result<String, DbError> {
+        let _span = debug_span!("export_metadata_to_json").entered();
+        traced(|| {
+            let mut json = vec![b'['];
+            self.for_each_game(|metadata| {
+                let game_id = metadata.id.unwrap_or_default();
+                let connection = &mut self.pool.get()?;
+                let bundle = connection.transaction(|connection| {
+                    Ok::<_, DbError>(GameBundle {
+                        metadata,
+                        paths: load_paths(connection, game_id)?,
+                        executables: load_executables(connection, game_id)?,
+                    })
+                })?;
+                if json.len() > 1 {
+                    json.push(b',');
+                }
+                serde_json::to_writer(&mut json, &bundle)
+                    .map_err(|e| DbError::Invalid(e.to_string()))
+            })?;
+            json.push(b']');
+
+            String::from_utf8(json).map_err(|e| DbError::Invalid(e.to_string()))
+        })
+    }
+
+    /// Reads a document written by `export_metadata_to_json`, ids in it are
+    /// ignored and games are matched on their steam appid
+    pub fn import_metadata_from_json(&self, json: &str) -> Result<ImportSummary, DbError> {
+        let _span = debug_span!("import_metadata_from_json").entered();
+        traced(|| {
+            let bundles: Vec<GameBundle> =
+                serde_json::from_str(json).map_err(|e| DbError::Invalid(e.to_string()))?;
+            let connection = &mut self.pool.get()?;
+            let updated_at = now_utc();
+
+            self.with_retry(connection, |connection| {
+                let mut summary = ImportSummary::default();
+                for bundle in &bundles {
+                    let existing_id: Option<i32> = match &bundle.metadata.metadata.steam_appid {
+                        Some(appid) => game_metadata::table
+                            .filter(game_metadata::steam_appid.eq(appid))
+                            .filter(game_metadata::is_deleted.eq(false))
+                            .select(game_metadata::id)
+                            .first::<Option<i32>>(connection)
+                            .optional()?
+                            .flatten(),
+                        None => None,
+                    };
+                    let game_id = match existing_id {
+                        Some(game_id) => {
+                            summary.skipped += 1;
+                            game_id
+                        }
+                        None => {
+                            summary.inserted += 1;
+                            insert_game_metadata(connection, &bundle.metadata.metadata)?
+                                .id
+                                .ok_or(DbError::NotFound)?
+                        }
+                    };
+
+                    let mut known_paths: HashSet<(String, OS)> = load_paths(connection, game_id)?
+                        .into_iter()
+                        .map(|path| (path.path.path, path.path.operating_system))
+                        .collect();
+                    let new_paths: Vec<SavePathCreate> = bundle
+                        .paths
+                        .iter()
+                        .filter(|path| {
+                            known_paths.insert((
+                                path.path.path.clone(),
+                                path.path.operating_system.clone(),
+                            ))
+                        })
+                        .map(|path| SavePathCreate {
+                            path: path.path.path.clone(),
+                            operating_system: path.path.operating_system.clone(),
+                        })
+                        .collect();
+                    insert_game_paths(connection, game_id, &new_paths, updated_at)?;
+
+                    let mut known_executables: HashSet<(String, OS)> =
+                        load_executables(connection, game_id)?
+                            .into_iter()
+                            .map(|executable| {
+                                (
+                                    executable.executable.executable,
+                                    executable.executable.operating_system,
+                                )
+                            })
+                            .collect();
+                    let new_executables: Vec<ExecutableCreate> = bundle
+                        .executables
+                        .iter()
+                        .filter(|executable| {
+                            known_executables.insert((
+                                executable.executable.executable.clone(),
+                                executable.executable.operating_system.clone(),
+                            ))
+                        })
+                        .map(|executable| ExecutableCreate {
+                            executable: executable.executable.executable.clone(),
+                            operating_system: executable.executable.operating_system.clone(),
+                        })
+                        .collect();
+                    insert_game_executables(connection, game_id, &new_executables, updated_at)?;
+                }
+                Ok(summary)
+            })
+        })
+    }
+
+    pub fn get_recently_added_games(&self, limit: i64) -> Result<Vec<GameMetadata>, DbError> {
+        let _span = debug_span!("get_recently_added_games", limit).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let db_games: Vec<DbGameMetadata> = game_metadata::table
+                .filter(game_metadata::is_deleted.eq(false))
+                .order((game_metadata::created_at.desc(), game_metadata::id.desc()))
+                .limit(limit)
+                .select(DbGameMetadata::as_select())
+                .load(connection)?;
+
+            Ok(load_games_metadata(connection, db_games)?)
+        })
+    }
+
+    /// Games with no save path, or none for `os` when it is given, by id
+    pub fn get_games_missing_paths(&self, os: Option<OS>) -> Result<Vec<GameMetadata>, DbError> {
+        let _span = debug_span!("get_games_missing_paths", ?os).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let mut games = connection.transaction(|connection| {
+                let ids = games_missing_children(connection, "game_path", os)?;
+                load_games_by_ids(connection, &ids)
+            })?;
+            games.sort_by_key(|game| game.id);
+            Ok(games)
+        })
+    }
+
+    /// Games with no executable, or none for `os` when it is given, by id
+    pub fn get_games_missing_executables(
+        &self,
+        os: Option<OS>,
+    ) -> Result<Vec<GameMetadata>, DbError> {
+        let _span = debug_span!("get_games_missing_executables", ?os).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let mut games = connection.transaction(|connection| {
+                let ids = games_missing_children(connection, "game_executable", os)?;
+                load_games_by_ids(connection, &ids)
+            })?;
+            games.sort_by_key(|game| game.id);
+            Ok(games)
+        })
+    }
+
+    pub fn get_games_metadata(&self) -> Result<Vec<GameMetadata>, DbError> {
+        let _span = debug_span!("get_games_metadata").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let db_games: Vec<DbGameMetadata> = game_metadata::table
+                .filter(game_metadata::is_deleted.eq(false))
+                .select(DbGameMetadata::as_select())
+                .load(connection)?;
+
+            Ok(load_games_metadata(connection, db_games)?)
+        })
+    }
+
+    pub fn get_games_metadata_paged(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<GameMetadata>, DbError> {
+        let _span = debug_span!("get_games_metadata_paged", offset, limit).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let db_games: Vec<DbGameMetadata> = game_metadata::table
+                .filter(game_metadata::is_deleted.eq(false))
+                .order(game_metadata::id.asc())
+                .limit(limit)
+                .offset(offset)
+                .select(DbGameMetadata::as_select())
+                .load(connection)?;
+
+            Ok(load_games_metadata(connection, db_games)?)
+        })
+    }
+
+    /// Calls `f` with every game that is not deleted, by ascending id. Games
+    /// are loaded a page at a time and the connection is released before
+    /// `f` runs, so memory stays bounded whatever the size of the catalog
+    /// and `f` may query the database itself. The first error stops it
+    pub fn for_each_game<F: FnMut(GameMetadata) -> Result<(), DbError>>(
+        &self,
+        mut f: F,
+    ) -> Result<(), DbError> {
+        let _span = debug_span!("for_each_game").entered();
+        let mut after_id = 0;
+        loop {
+            let page = traced(|| {
+                let connection = &mut self.pool.get()?;
+                let db_games: Vec<DbGameMetadata> = game_metadata::table
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .filter(game_metadata::id.gt(after_id))
+                    .order(game_metadata::id.asc())
+                    .limit(GAME_ITERATION_PAGE_SIZE)
+                    .select(DbGameMetadata::as_select())
+                    .load(connection)?;
+                Ok(load_games_metadata(connection, db_games)?)
+            })?;
+            let Some(last_id) = page.last().and_then(|game| game.id) else {
+                return Ok(());
+            };
+            after_id = last_id;
+            let full = page.len() as i64 == GAME_ITERATION_PAGE_SIZE;
+            for game in page {
+                f(game)?;
+            }
+            if !full {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn count_games(&self) -> Result<i64, DbError> {
+        let _span = debug_span!("count_games").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            Ok(game_metadata::table
+                .filter(game_metadata::is_deleted.eq(false))
+                .count()
+                .get_result(connection)?)
+        })
+    }
+
+    /// Steam appids of the games that are not deleted, sorted, without
+    /// loading anything else about them
+    pub fn get_all_appids(&self) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("get_all_appids").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            Ok(game_metadata::table
+                .filter(game_metadata::is_deleted.eq(false))
+                .filter(game_metadata::steam_appid.is_not_null())
+                .order(game_metadata::steam_appid.asc())
+                .select(game_metadata::steam_appid.assume_not_null())
+                .load(connection)?)
+        })
+    }
+
+    pub fn get_games_by_steam_appids(
+        &self,
+        appids: &[String],
+    ) -> Result<HashMap<String, GameMetadata>, DbError> {
+        let _span = debug_span!("get_games_by_steam_appids").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let mut db_games: Vec<DbGameMetadata> = Vec::new();
+            for appids in appids.chunks(SQLITE_MAX_VARIABLES) {
+                db_games.extend(
+                    game_metadata::table
+                        .filter(game_metadata::steam_appid.eq_any(appids))
+                        .filter(game_metadata::is_deleted.eq(false))
+                        .select(DbGameMetadata::as_select())
+                        .load(connection)?,
+                );
+            }
+
+            Ok(load_games_metadata(connection, db_games)?
+                .into_iter()
+                .filter_map(|game| Some((game.metadata.steam_appid.clone()?, game)))
+                .collect())
+        })
+    }
+
+    pub fn add_game_path(&self, game_id: i32, path: &SavePathCreate) -> Result<(), DbError> {
+        let _span = debug_span!("add_game_path", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            diesel::insert_into(game_path::table)
+                .values(DbGamePath {
+                    id: None,
+                    path: path.path.clone(),
+                    operating_system: path.operating_system.clone(),
+                    game_metadata_id: game_id,
+                    updated_at: now_utc(),
+                })
+                .execute(connection)?;
+            Ok(())
+        })
+    }
+
+    pub fn add_game_paths(&self, game_id: i32, paths: &[SavePathCreate]) -> Result<(), DbError> {
+        let _span = debug_span!("add_game_paths", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let updated_at = now_utc();
+
+            self.with_retry(connection, |connection| {
+                Ok(insert_game_paths(connection, game_id, paths, updated_at)?)
+            })
+        })
+    }
+
+    /// Makes `paths` the path set of the game in one transaction. Paths
+    /// already present keep their id and saves, the others are deleted with
+    /// their saves
+    pub fn replace_game_paths(
+        &self,
+        game_id: i32,
+        paths: &[SavePathCreate],
+    ) -> Result<(), DbError> {
+        self.replace_game_paths_with_saves(game_id, paths)?;
+        Ok(())
+    }
+
+    /// Same as `replace_game_paths`, returns the uuids of the saves deleted
+    /// with the dropped paths so their files can be removed
+    pub fn replace_game_paths_with_saves(
+        &self,
+        game_id: i32,
+        paths: &[SavePathCreate],
+    ) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("replace_game_paths", game_id, paths = paths.len()).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let updated_at = now_utc();
+
+            self.with_retry(connection, |connection| {
+                let game_exists: i64 = game_metadata::table
+                    .filter(game_metadata::id.eq(game_id))
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .count()
+                    .get_result(connection)?;
+                if game_exists == 0 {
+                    return Err(DbError::NotFound);
+                }
+
+                let mut existing: HashMap<(String, OS), Option<i32>> =
+                    load_paths(connection, game_id)?
+                        .into_iter()
+                        .map(|path| ((path.path.path, path.path.operating_system), path.id))
+                        .collect();
+                let mut wanted: HashSet<(&str, OS)> = HashSet::new();
+                let mut added: Vec<SavePathCreate> = Vec::new();
+                for path in paths {
+                    if !wanted.insert((&path.path, path.operating_system.clone())) {
+                        continue;
+                    }
+                    if existing
+                        .remove(&(path.path.clone(), path.operating_system.clone()))
+                        .is_none()
+                    {
+                        added.push(SavePathCreate {
+                            path: path.path.clone(),
+                            operating_system: path.operating_system.clone(),
+                        });
+                    }
+                }
+
+                let removed_ids: Vec<i32> = existing.into_values().flatten().collect();
+                let mut save_uuids: Vec<String> = Vec::new();
+                for removed_ids in removed_ids.chunks(SQLITE_MAX_VARIABLES) {
+                    save_uuids.extend(
+                        game_save::table
+                            .filter(game_save::path_id.eq_any(removed_ids))
+                            .select(game_save::uuid)
+                            .load::<String>(connection)?,
+                    );
+                }
+                delete_save_rows(connection, &save_uuids)?;
+                for removed_ids in removed_ids.chunks(SQLITE_MAX_VARIABLES) {
+                    diesel::delete(game_path::table.filter(game_path::id.eq_any(removed_ids)))
+                        .execute(connection)?;
+                }
+
+                insert_game_paths(connection, game_id, &added, updated_at)?;
+                Ok(save_uuids)
+            })
+        })
+    }
+
+    pub fn get_paths_by_game_id_and_os(
+        &self,
+        game_id: i32,
+        os: OS,
+    ) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("get_paths_by_game_id_and_os", game_id, %os).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let paths: Vec<String> = game_path::table
+                .filter(game_path::game_metadata_id.eq(game_id))
+                .filter(game_path::operating_system.eq(os))
+                .order(game_path::id.asc())
+                .select(game_path::path)
+                .load(connection)?;
+            Ok(paths)
+        })
+    }
+
+    pub fn get_paths_by_game_id(&self, game_id: i32) -> Result<Vec<SavePath>, DbError> {
+        let _span = debug_span!("get_paths_by_game_id", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            Ok(load_paths(connection, game_id)?)
+        })
+    }
+
+    /// Candidate templates for `target_os` guessed by translating the paths
+    /// the game has for the other OSes, in their stored order and without
+    /// the ones already stored for `target_os`. Nothing is persisted, a path
+    /// no known anchor maps is left out
+    pub fn suggest_paths_for_os(
+        &self,
+        game_id: i32,
+        target_os: OS,
+    ) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("suggest_paths_for_os", game_id, %target_os).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let paths = load_paths(connection, game_id)?;
+
+            let mut seen: HashSet<String> = paths
+                .iter()
+                .filter(|path| path.path.operating_system == target_os)
+                .map(|path| path.path.path.clone())
+                .collect();
+            Ok(paths
+                .iter()
+                .filter(|path| path.path.operating_system != target_os)
+                .filter_map(|path| {
+                    translate_save_path(&path.path.path, &path.path.operating_system, &target_os)
+                })
+                .filter(|candidate| seen.insert(candidate.clone()))
+                .collect())
+        })
+    }
+
+    /// User who uploaded the save, `None` when there is no such save and
+    /// `Some(None)` for a save uploaded with a shared token
+    pub fn get_save_owner(&self, uuid: Uuid) -> Result<Option<Option<i32>>, DbError> {
+        let _span = debug_span!("get_save_owner", %uuid).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            Ok(game_save::table
+                .find(uuid.to_string())
+                .select(game_save::user_id)
+                .first::<Option<i32>>(connection)
+                .optional()?)
+        })
+    }
+
+    /// Game the path belongs to, `None` when there is no such path
+    pub fn get_game_id_by_path_id(&self, path_id: i32) -> Result<Option<i32>, DbError> {
+        let _span = debug_span!("get_game_id_by_path_id", path_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            Ok(game_path::table
+                .filter(game_path::id.eq(path_id))
+                .select(game_path::game_metadata_id)
+                .first::<i32>(connection)
+                .optional()?)
+        })
+    }
+
+    /// Every path of `os` across the games that are not deleted, paired with
+    /// its game id and ordered by it
+    pub fn get_all_paths_for_os(&self, os: OS) -> Result<Vec<(i32, SavePath)>, DbError> {
+        let _span = debug_span!("get_all_paths_for_os", %os).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let path_rows: Vec<(i32, Option<i32>, String)> = game_path::table
+                .inner_join(game_metadata::table)
+                .filter(game_path::operating_system.eq(&os))
+                .filter(game_metadata::is_deleted.eq(false))
+                .order((game_path::game_metadata_id.asc(), game_path::id.asc()))
+                .select((game_path::game_metadata_id, game_path::id, game_path::path))
+                .load(connection)?;
+
+            Ok(path_rows
+                .into_iter()
+                .map(|(game_id, id, path)| {
+                    (
+                        game_id,
+                        SavePath {
+                            id,
+                            path: SavePathCreate {
+                                path,
+                                operating_system: os.clone(),
+                            },
+                        },
+                    )
+                })
+                .collect())
+        })
+    }
+
+    /// Deletes the path along with its saves, their file hashes and
+    /// attributes. Returns whether the path existed
+    pub fn delete_game_path(&self, path_id: i32) -> Result<bool, DbError> {
+        Ok(self.delete_game_path_with_saves(path_id)?.is_some())
+    }
+
+    /// Rewrites the template of a path in place, and its OS when `new_os`
+    /// is set, so the saves of `path_id` stay attached to it. Another path
+    /// of the game with the same template and OS is a `UniqueViolation`
+    pub fn update_game_path(
+        &self,
+        path_id: i32,
+        new_path: &str,
+        new_os: Option<OS>,
+    ) -> Result<SavePath, DbError> {
+        let _span = debug_span!("update_game_path", path_id).entered();
+        traced(|| {
+            if new_path.trim().is_empty() {
+                return Err(DbError::Invalid("the path cannot be empty".to_string()));
+            }
+            let connection = &mut self.pool.get()?;
+
+            self.with_retry(connection, |connection| {
+                let (game_id, os): (i32, OS) = game_path::table
+                    .filter(game_path::id.eq(path_id))
+                    .select((game_path::game_metadata_id, game_path::operating_system))
+                    .first(connection)
+                    .optional()?
+                    .ok_or(DbError::NotFound)?;
+                let os = new_os.clone().unwrap_or(os);
+
+                let duplicate: i64 = game_path::table
+                    .filter(game_path::game_metadata_id.eq(game_id))
+                    .filter(game_path::path.eq(new_path))
+                    .filter(game_path::operating_system.eq(&os))
+                    .filter(game_path::id.ne(path_id))
+                    .count()
+                    .get_result(connection)?;
+                if duplicate > 0 {
+                    return Err(DbError::UniqueViolation(format!(
+                        "game {game_id} already has the path {new_path} for {os}"
+                    )));
+                }
+
+                diesel::update(game_path::table.filter(game_path::id.eq(path_id)))
+                    .set((
+                        game_path::path.eq(new_path),
+                        game_path::operating_system.eq(&os),
+                        game_path::updated_at.eq(now_utc()),
+                    ))
+                    .execute(connection)?;
+                Ok(SavePath {
+                    id: Some(path_id),
+                    path: SavePathCreate {
+                        path: new_path.to_string(),
+                        operating_system: os,
+                    },
+                })
+            })
+        })
+    }
+
+    /// Same as `delete_game_path`, returns the uuids of the deleted saves so
+    /// their files can be removed, `None` when the path does not exist
+    pub fn delete_game_path_with_saves(
+        &self,
+        path_id: i32,
+    ) -> Result<Option<Vec<String>>, DbError> {
+        let _span = debug_span!("delete_game_path_with_saves", path_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            self.with_retry(connection, |connection| {
+                let save_uuids: Vec<String> = game_save::table
+                    .filter(game_save::path_id.eq(path_id))
+                    .select(game_save::uuid)
+                    .load(connection)?;
+                delete_save_rows(connection, &save_uuids)?;
+
+                let deleted = diesel::delete(game_path::table.filter(game_path::id.eq(path_id)))
+                    .execute(connection)?;
+                Ok((deleted > 0).then_some(save_uuids))
+            })
+        })
+    }
+
+    pub fn get_game_name_by_path_id(&self, path_id: i32) -> Result<Option<String>, DbError> {
+        let _span = debug_span!("get_game_name_by_path_id", path_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let name: Option<String> = game_path::table
+                .inner_join(game_metadata::table)
+                .filter(game_path::id.eq(path_id))
+                .select(game_metadata::default_name)
+                .first(connection)
+                .optional()?;
+            Ok(name)
+        })
+    }
+
+    pub fn add_game_executable(
+        &self,
+        game_id: i32,
+        executable: &ExecutableCreate,
+    ) -> Result<(), DbError> {
+        let _span = debug_span!("add_game_executable", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            diesel::insert_into(game_executable::table)
+                .values(DbGameExecutable {
+                    id: None,
+                    executable: executable.executable.clone(),
+                    operating_system: executable.operating_system.clone(),
+                    game_metadata_id: game_id,
+                    updated_at: now_utc(),
+                })
+                .execute(connection)?;
+            Ok(())
+        })
+    }
+
+    pub fn add_game_executables(
+        &self,
+        game_id: i32,
+        executables: &[ExecutableCreate],
+    ) -> Result<(), DbError> {
+        let _span = debug_span!("add_game_executables", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let updated_at = now_utc();
+
+            self.with_retry(connection, |connection| {
+                Ok(insert_game_executables(
+                    connection,
+                    game_id,
+                    executables,
+                    updated_at,
+                )?)
+            })
+        })
+    }
+
+    pub fn get_executable_by_game_id_and_os(
+        &self,
+        game_id: i32,
+        os: OS,
+    ) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("get_executable_by_game_id_and_os", game_id, %os).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let paths: Vec<String> = game_executable::table
+                .filter(game_executable::game_metadata_id.eq(game_id))
+                .filter(game_executable::operating_system.eq(os))
+                .order(game_executable::id.asc())
+                .select(game_executable::executable)
+                .load(connection)?;
+            Ok(paths)
+        })
+    }
+
+    pub fn get_executable_by_game_id(&self, game_id: i32) -> Result<Vec<Executable>, DbError> {
+        let _span = debug_span!("get_executable_by_game_id", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            Ok(load_executables(connection, game_id)?)
+        })
+    }
+
+    /// `user_id` is `None` for a save uploaded without a user token
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_reference_to_save(
+        &self,
+        uuid: Uuid,
+        path_id: i32,
+        user_id: Option<i32>,
+        files_hash: Vec<FileHash>,
+        label: Option<String>,
+        device_name: Option<String>,
+        idempotency_key: Option<&str>,
+    ) -> Result<SaveInsert<()>, DbError> {
+        let _span = debug_span!("add_reference_to_save", %uuid, path_id, ?user_id).entered();
+        traced(|| {
+            validate_files_hash(&files_hash)?;
+            validate_label(label.as_deref())?;
+            validate_device(device_name.as_deref())?;
+            validate_idempotency(idempotency_key)?;
+            let connection = &mut self.pool.get()?;
+
+            let game_save = self.with_retry(connection, |connection| {
+                if let Some(existing) =
+                    find_idempotent_save(connection, path_id, user_id, idempotency_key)?
+                {
+                    return Ok(SaveInsert::Replayed(existing));
+                }
+                Ok(SaveInsert::Created(insert_save_rows(
+                    connection,
+                    &uuid.to_string(),
+                    path_id,
+                    user_id,
+                    &files_hash,
+                    label.as_deref(),
+                    device_name.as_deref(),
+                    idempotency_key,
+                )?))
+            })?;
+            let game_save = match game_save {
+                SaveInsert::Created(game_save) => game_save,
+                SaveInsert::Replayed(existing) => return Ok(SaveInsert::Replayed(existing)),
+            };
+
+            self.fire_save_created(&SaveReference {
+                uuid: uuid.to_string(),
+                path_id,
+                time: game_save.time.assume_utc(),
+                total_size_bytes: SaveReference::total_size(&files_hash),
+                generation: game_save.generation,
+                label,
+                pinned: false,
+                device_name,
+                files_hash,
+                attributes: HashMap::new(),
+            });
+            Ok(SaveInsert::Created(()))
+        })
+    }
+
+    /// Inserts the save unless `user_id` already holds `max_saves_per_path`
+    /// saves on `path_id`, in which case `policy` either rejects it or
+    /// deletes their oldest ones to make room, the saves of other users are
+    /// neither counted nor pruned. Returns the pruned saves, or the save
+    /// already stored with `idempotency_key` without touching the quota
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_reference_to_save_with_quota(
+        &self,
+        uuid: Uuid,
+        path_id: i32,
+        user_id: Option<i32>,
+        files_hash: Vec<FileHash>,
+        label: Option<String>,
+        device_name: Option<String>,
+        max_saves_per_path: usize,
+        policy: QuotaPolicy,
+        idempotency_key: Option<&str>,
+    ) -> Result<SaveInsert<PrunedSaves>, DbError> {
+        let _span = debug_span!("add_reference_to_save_with_quota", %uuid, path_id, ?user_id, max_saves_per_path).entered();
+        traced(|| {
+            validate_files_hash(&files_hash)?;
+            validate_label(label.as_deref())?;
+            validate_device(device_name.as_deref())?;
+            validate_idempotency(idempotency_key)?;
+            if max_saves_per_path == 0 {
+                return Err(DbError::QuotaExceeded(0));
+            }
+            let connection = &mut self.pool.get()?;
+
+            // Counting inside the immediate transaction holds the write lock, so
+            // concurrent uploads cannot both see room for one more save
+            let inserted = self.with_retry(connection, |connection| {
+                if let Some(existing) =
+                    find_idempotent_save(connection, path_id, user_id, idempotency_key)?
+                {
+                    return Ok(SaveInsert::Replayed(existing));
+                }
+                let existing_uuids: Vec<String> = game_save::table
+                    .filter(game_save::path_id.eq(path_id))
+                    .filter(game_save::user_id.is(user_id))
+                    .filter(game_save::pinned.eq(false))
+                    .order((game_save::time.desc(), game_save::uuid.desc()))
+                    .select(game_save::uuid)
+                    .load(connection)?;
+
+                let pruned_uuids: Vec<String> = if existing_uuids.len() < max_saves_per_path {
+                    Vec::new()
+                } else if policy == QuotaPolicy::Reject {
+                    return Err(DbError::QuotaExceeded(max_saves_per_path));
+                } else {
+                    existing_uuids
+                        .into_iter()
+                        .skip(max_saves_per_path - 1)
+                        .collect()
+                };
+                let orphaned_hashes = delete_save_rows(connection, &pruned_uuids)?;
+
+                let game_save = insert_save_rows(
+                    connection,
+                    &uuid.to_string(),
+                    path_id,
+                    user_id,
+                    &files_hash,
+                    label.as_deref(),
+                    device_name.as_deref(),
+                    idempotency_key,
+                )?;
+                let pruned = PrunedSaves {
+                    uuids: pruned_uuids,
+                    orphaned_hashes,
+                };
+                Ok(SaveInsert::Created((pruned, game_save)))
+            })?;
+            let (pruned, game_save) = match inserted {
+                SaveInsert::Created(inserted) => inserted,
+                SaveInsert::Replayed(existing) => return Ok(SaveInsert::Replayed(existing)),
+            };
+
+            self.fire_save_created(&SaveReference {
+                uuid: uuid.to_string(),
+                path_id,
+                time: game_save.time.assume_utc(),
+                total_size_bytes: SaveReference::total_size(&files_hash),
+                generation: game_save.generation,
+                label,
+                pinned: false,
+                device_name,
+                files_hash,
+                attributes: HashMap::new(),
+            });
+            Ok(SaveInsert::Created(pruned))
+        })
+    }
+
+    /// Saves of `path_id` uploaded by `user_id`, oldest first
+    pub fn get_saves_for_user(
+        &self,
+        user_id: i32,
+        path_id: i32,
+    ) -> Result<Vec<SaveReference>, DbError> {
+        let _span = debug_span!("get_saves_for_user", ?user_id, path_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let save_rows = game_save::table
+                .filter(game_save::path_id.eq(path_id))
+                .filter(game_save::user_id.eq(user_id))
+                .order((game_save::time.asc(), game_save::uuid.asc()))
+                .select(DbGameSave::as_select())
+                .load(connection)?;
+
+            let save_references = load_save_references(connection, save_rows)?;
+            Ok(save_references)
+        })
+    }
+
+    /// `token_hash` is the SHA-256 hex digest, the token itself is never stored
+    pub fn add_user(&self, name: &str, token_hash: &str) -> Result<i32, DbError> {
+        let _span = debug_span!("add_user").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            diesel::insert_into(user::table)
+                .values(DbUser {
+                    id: None,
+                    name: name.to_string(),
+                    token_hash: token_hash.to_string(),
+                })
+                .returning(user::id)
+                .get_result::<Option<i32>>(connection)?
+                .ok_or(DbError::NotFound)
+        })
+    }
+
+    pub fn get_user_id_by_token_hash(&self, token_hash: &str) -> Result<Option<i32>, DbError> {
+        let _span = debug_span!("get_user_id_by_token_hash").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            Ok(user::table
+                .filter(user::token_hash.eq(token_hash))
+                .select(user::id)
+                .first::<Option<i32>>(connection)
+                .optional()?
+                .flatten())
+        })
+    }
+
+    pub fn count_users(&self) -> Result<i64, DbError> {
+        let _span = debug_span!("count_users").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            Ok(user::table.count().get_result(connection)?)
+        })
+    }
+
+    pub fn get_reference_to_save_by_path_id(
+        &self,
+        path_id: i32,
+    ) -> Result<Option<Vec<SaveReference>>, DbError> {
+        let _span = debug_span!("get_reference_to_save_by_path_id", path_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let save_rows = game_save::table
+                .filter(game_save::path_id.eq(path_id))
+                .select(DbGameSave::as_select())
+                .load(connection)?;
+
+            if save_rows.is_empty() {
+                return Ok(None);
+            }
+
+            let save_references = load_save_references(connection, save_rows)?;
+
+            Ok(Some(save_references))
+        })
+    }
+
+    pub fn get_reference_to_save_by_uuid(
+        &self,
+        uuid: Uuid,
+    ) -> Result<Option<SaveReference>, DbError> {
+        let _span = debug_span!("get_reference_to_save_by_uuid", %uuid).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let game_save = game_save::table
+                .find(uuid.to_string())
+                .select(DbGameSave::as_select())
+                .first(connection)
+                .optional()?;
+
+            match game_save {
+                Some(game_save) => Ok(Some(load_save_reference(connection, game_save)?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    /// The save as a `SaveManifest` document, names and the path are the
+    /// ones of this server so the importer can pick the matching path
+    pub fn export_save_manifest(&self, uuid: Uuid) -> Result<String, DbError> {
+        let _span = debug_span!("export_save_manifest", %uuid).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let manifest = connection.transaction(|connection| {
+                let game_save = game_save::table
+                    .find(uuid.to_string())
+                    .select(DbGameSave::as_select())
+                    .first(connection)
+                    .optional()?
+                    .ok_or(DbError::NotFound)?;
+                let (path, operating_system, game_name, steam_appid): (
+                    String,
+                    OS,
+                    String,
+                    Option<String>,
+                ) = game_path::table
+                    .inner_join(game_metadata::table)
+                    .filter(game_path::id.eq(game_save.path_id))
+                    .select((
+                        game_path::path,
+                        game_path::operating_system,
+                        game_metadata::default_name,
+                        game_metadata::steam_appid,
+                    ))
+                    .first(connection)?;
+                let save = load_save_reference(connection, game_save)?;
+
+                Ok::<_, DbError>(SaveManifest {
+                    uuid: save.uuid,
+                    time: save.time,
+                    steam_appid,
+                    game_name,
+                    path,
+                    operating_system,
+                    label: save.label,
+                    device_name: save.device_name,
+                    attributes: save.attributes,
+                    files_hash: save.files_hash,
+                })
+            })?;
+
+            serde_json::to_string(&manifest).map_err(|e| DbError::Invalid(e.to_string()))
+        })
+    }
+
+    /// Recreates the save of a document written by `export_save_manifest`
+    /// under `path_id`, with the uuid and time of the original. The blobs
+    /// are not part of the manifest and have to be uploaded separately
+    pub fn import_save_manifest(&self, json: &str, path_id: i32) -> Result<SaveReference, DbError> {
+        let _span = debug_span!("import_save_manifest", path_id).entered();
+        traced(|| {
+            let manifest: SaveManifest =
+                serde_json::from_str(json).map_err(|e| DbError::Invalid(e.to_string()))?;
+            let uuid =
+                Uuid::parse_str(&manifest.uuid).map_err(|e| DbError::Invalid(e.to_string()))?;
+            validate_save_fields(
+                &manifest.attributes,
+                manifest.label.as_deref(),
+                manifest.device_name.as_deref(),
+            )
+            .map_err(DbError::Invalid)?;
+            validate_files_hash(&manifest.files_hash)?;
+            let time = primitive_from_unix(manifest.time.unix_timestamp())?;
+            let connection = &mut self.pool.get()?;
+
+            let game_save = self.with_retry(connection, |connection| {
+                let path_exists: i64 = game_path::table
+                    .filter(game_path::id.eq(path_id))
+                    .count()
+                    .get_result(connection)?;
+                if path_exists == 0 {
+                    return Err(DbError::NotFound);
+                }
+
+                let mut game_save = insert_save_rows(
+                    connection,
+                    &uuid.to_string(),
+                    path_id,
+                    None,
+                    &manifest.files_hash,
+                    manifest.label.as_deref(),
+                    manifest.device_name.as_deref(),
+                    None,
+                )?;
+                diesel::update(game_save::table.find(&game_save.uuid))
+                    .set(game_save::time.eq(time))
+                    .execute(connection)?;
+                game_save.time = time;
+                let attributes: Vec<DbSaveAttribute> = manifest
+                    .attributes
+                    .iter()
+                    .map(|(key, value)| DbSaveAttribute {
+                        game_save_uuid: game_save.uuid.clone(),
+                        key: key.clone(),
+                        value: value.clone(),
+                    })
+                    .collect();
+                diesel::insert_into(save_attribute::table)
+                    .values(attributes)
+                    .execute(connection)?;
+                Ok(game_save)
+            })?;
+
+            let save = SaveReference {
+                uuid: game_save.uuid,
+                path_id,
+                time: game_save.time.assume_utc(),
+                total_size_bytes: SaveReference::total_size(&manifest.files_hash),
+                generation: game_save.generation,
+                label: game_save.label,
+                pinned: false,
+                device_name: game_save.device_name,
+                files_hash: manifest.files_hash,
+                attributes: manifest.attributes,
+            };
+            self.fire_save_created(&save);
+            Ok(save)
+        })
+    }
+
+    /// Saves of `path_id` stored between `start` and `end`, oldest first.
+    /// Only the saves of `user_id` when given
+    pub fn get_saves_in_range(
+        &self,
+        path_id: i32,
+        user_id: Option<i32>,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<SaveReference>, DbError> {
+        let _span = debug_span!("get_saves_in_range", path_id, ?user_id, start, end).entered();
+        traced(|| {
+            let start = primitive_from_unix(start)?;
+            let end = primitive_from_unix(end)?;
+            let connection = &mut self.pool.get()?;
+
+            let save_rows = game_save::table
+                .filter(game_save::path_id.eq(path_id))
+                .filter(game_save::time.between(start, end))
+                .filter(visible_to(user_id))
+                .order((game_save::time.asc(), game_save::uuid.asc()))
+                .select(DbGameSave::as_select())
+                .load(connection)?;
+
+            let save_references = load_save_references(connection, save_rows)?;
+            Ok(save_references)
+        })
+    }
+
+    /// Saves of `path_id` oldest first, with their file count instead of
+    /// the hashes themselves. Only the saves of `user_id` when given
+    pub fn list_save_summaries(
+        &self,
+        path_id: i32,
+        user_id: Option<i32>,
+    ) -> Result<Vec<SaveSummary>, DbError> {
+        let _span = debug_span!("list_save_summaries", path_id, ?user_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            Ok(load_save_summaries(
+                connection, path_id, user_id, None, None,
+            )?)
+        })
+    }
+
+    /// Keyset page of `list_save_summaries`, continuing after the
+    /// `(after_time, after_uuid)` of the last row of the previous page.
+    /// Without `after_uuid` the page starts after the whole second
+    pub fn list_saves_after(
+        &self,
+        path_id: i32,
+        user_id: Option<i32>,
+        after_time: Option<i64>,
+        after_uuid: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<SaveSummary>, DbError> {
+        let _span =
+            debug_span!("list_saves_after", path_id, after_time, ?after_uuid, limit).entered();
+        traced(|| {
+            let cursor = match (after_time, after_uuid) {
+                (Some(time), uuid) => Some((time, uuid.map(|uuid| uuid.to_string()))),
+                (None, None) => None,
+                (None, Some(_)) => {
+                    return Err(DbError::Invalid("after_uuid needs after_time".to_string()));
+                }
+            };
+            let connection = &mut self.pool.get()?;
+            Ok(load_save_summaries(
+                connection,
+                path_id,
+                user_id,
+                cursor,
+                Some(limit.max(0)),
+            )?)
+        })
+    }
+
+    /// Saves of `path_id` with a generation above `since`, oldest first, so a
+    /// client that synced up to `since` gets exactly what it is missing.
+    /// Only the saves of `user_id` when given
+    pub fn get_saves_since_generation(
+        &self,
+        path_id: i32,
+        user_id: Option<i32>,
+        since: i64,
+    ) -> Result<Vec<SaveReference>, DbError> {
+        let _span = debug_span!("get_saves_since_generation", path_id, ?user_id, since).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let save_rows = game_save::table
+                .filter(game_save::path_id.eq(path_id))
+                .filter(game_save::generation.gt(since))
+                .filter(visible_to(user_id))
+                .order(game_save::generation.asc())
+                .select(DbGameSave::as_select())
+                .load(connection)?;
+
+            let save_references = load_save_references(connection, save_rows)?;
+            Ok(save_references)
+        })
+    }
+
+    /// Replaces the label of a save, `None` clears it. Returns false when
+    /// the save does not exist
+    pub fn set_save_label(&self, uuid: Uuid, label: Option<String>) -> Result<bool, DbError> {
+        let _span = debug_span!("set_save_label", %uuid).entered();
+        traced(|| {
+            validate_label(label.as_deref())?;
+
+            let connection = &mut self.pool.get()?;
+            let updated =
+                diesel::update(game_save::table.filter(game_save::uuid.eq(uuid.to_string())))
+                    .set(game_save::label.eq(label))
+                    .execute(connection)?;
+            Ok(updated > 0)
+        })
+    }
+
+    /// Pins or unpins a save. Returns false when the save does not exist
+    pub fn set_save_pinned(&self, uuid: Uuid, pinned: bool) -> Result<bool, DbError> {
+        let _span = debug_span!("set_save_pinned", %uuid, pinned).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let updated =
+                diesel::update(game_save::table.filter(game_save::uuid.eq(uuid.to_string())))
+                    .set(game_save::pinned.eq(pinned))
+                    .execute(connection)?;
+            Ok(updated > 0)
+        })
+    }
+
+    /// Marks a save as current as of now without storing it again: its
+    /// time becomes now and it takes the next generation of its path, so
+    /// it is the latest save for conflict checks and generation based sync
+    pub fn touch_save(&self, uuid: Uuid) -> Result<SaveReference, DbError> {
+        let _span = debug_span!("touch_save", %uuid).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let uuid = uuid.to_string();
+
+            self.with_retry(connection, |connection| {
+                let path_id: i32 = game_save::table
+                    .filter(game_save::uuid.eq(&uuid))
+                    .select(game_save::path_id)
+                    .first(connection)
+                    .optional()?
+                    .ok_or(DbError::NotFound)?;
+                let generation = next_generation(connection, path_id)?;
+
+                let game_save = diesel::update(game_save::table.find(&uuid))
+                    .set((
+                        game_save::time.eq(now_utc()),
+                        game_save::generation.eq(generation),
+                    ))
+                    .returning(DbGameSave::as_returning())
+                    .get_result(connection)?;
+                load_save_reference(connection, game_save)
+            })
+        })
+    }
+
+    /// Moves a save to another path, for when a game's paths are
+    /// restructured and its saves belong under a different one. The save
+    /// takes the next generation of its new path, its time is kept. A path
+    /// that does not exist is refused by the foreign key and reported as
+    /// `DbError::NotFound`, like a missing save
+    pub fn reassign_save(&self, uuid: Uuid, new_path_id: i32) -> Result<SaveReference, DbError> {
+        let _span = debug_span!("reassign_save", %uuid, new_path_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let uuid = uuid.to_string();
+
+            self.with_retry(connection, |connection| {
+                let current: DbGameSave = game_save::table
+                    .find(&uuid)
+                    .select(DbGameSave::as_select())
+                    .first(connection)
+                    .optional()?
+                    .ok_or(DbError::NotFound)?;
+                if current.path_id == new_path_id {
+                    return load_save_reference(connection, current);
+                }
+                let generation = next_generation(connection, new_path_id)
+                    .optional()?
+                    .ok_or(DbError::NotFound)?;
+
+                let game_save = diesel::update(game_save::table.find(&uuid))
+                    .set((
+                        game_save::path_id.eq(new_path_id),
+                        game_save::generation.eq(generation),
+                    ))
+                    .returning(DbGameSave::as_returning())
+                    .get_result(connection)
+                    .map_err(|e| match DbError::from(e) {
+                        DbError::ForeignKeyViolation(_) => DbError::NotFound,
+                        e => e,
+                    })?;
+                load_save_reference(connection, game_save)
+            })
+        })
+    }
+
+    /// Saves of a path whose label contains `label`, ignoring ASCII case,
+    /// oldest first. Only the saves of `user_id` when given
+    pub fn find_saves_by_label(
+        &self,
+        path_id: i32,
+        user_id: Option<i32>,
+        label: &str,
+    ) -> Result<Vec<SaveReference>, DbError> {
+        let _span = debug_span!("find_saves_by_label", path_id, ?user_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let pattern = format!(
+                "%{}%",
+                label
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+            );
+
+            let save_rows = game_save::table
+                .filter(game_save::path_id.eq(path_id))
+                .filter(game_save::label.like(pattern).escape('\\'))
+                .filter(visible_to(user_id))
+                .order(game_save::time.asc())
+                .select(DbGameSave::as_select())
+                .load(connection)?;
+
+            let save_references = load_save_references(connection, save_rows)?;
+            Ok(save_references)
+        })
+    }
+
+    /// Only counts the saves of `user_id` when given
+    pub fn count_saves_for_path(&self, path_id: i32, user_id: Option<i32>) -> Result<i64, DbError> {
+        let _span = debug_span!("count_saves_for_path", path_id, ?user_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            Ok(game_save::table
+                .filter(game_save::path_id.eq(path_id))
+                .filter(visible_to(user_id))
+                .count()
+                .get_result(connection)?)
+        })
+    }
+
+    /// Newest save of `path_id`, of `user_id` when given
+    pub fn get_latest_save_reference_for_path(
+        &self,
+        path_id: i32,
+        user_id: Option<i32>,
+    ) -> Result<Option<SaveReference>, DbError> {
+        let _span = debug_span!("get_latest_save_reference_for_path", path_id, ?user_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let game_save = game_save::table
+                .filter(game_save::path_id.eq(path_id))
+                .filter(visible_to(user_id))
+                .order((game_save::time.desc(), game_save::uuid.desc()))
+                .select(DbGameSave::as_select())
+                .first(connection)
+                .optional()?;
+
+            match game_save {
+                Some(game_save) => Ok(Some(load_save_reference(connection, game_save)?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    /// Newest save of every path of the game, with their file hashes, for
+    /// a client syncing the whole game in one call. Paths without any save
+    /// are left out, the saves come ordered by path id. Three queries
+    /// whatever the number of paths. With `user_id` the newest of that
+    /// user's saves is taken on each path
+    pub fn get_latest_saves_for_game(
+        &self,
+        game_id: i32,
+        user_id: Option<i32>,
+    ) -> Result<Vec<SaveReference>, DbError> {
+        let _span = debug_span!("get_latest_saves_for_game", game_id, ?user_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            connection.transaction(|connection| {
+                let game_exists: i64 = game_metadata::table
+                    .filter(game_metadata::id.eq(game_id))
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .count()
+                    .get_result(connection)?;
+                if game_exists == 0 {
+                    return Err(DbError::NotFound);
+                }
+                // Same order as `get_latest_save_reference_for_path`, served
+                // by the (path_id, time) index
+                let same_owner = if user_id.is_some() {
+                    "AND latest.user_id = game_save.user_id "
+                } else {
+                    ""
+                };
+                let game_saves: Vec<DbGameSave> = game_save::table
+                    .inner_join(game_path::table)
+                    .filter(game_path::game_metadata_id.eq(game_id))
+                    .filter(visible_to(user_id))
+                    .filter(sql::<diesel::sql_types::Bool>(&format!(
+                        "game_save.uuid = (SELECT latest.uuid FROM game_save AS latest \
+                         WHERE latest.path_id = game_save.path_id {same_owner}\
+                         ORDER BY latest.time DESC, latest.uuid DESC LIMIT 1)"
+                    )))
+                    .order(game_save::path_id.asc())
+                    .select(DbGameSave::as_select())
+                    .load(connection)?;
+
+                load_save_references(connection, game_saves)
+            })
+        })
+    }
+
+    /// Weak validator of what `get_latest_save_reference_for_path` returns,
+    /// computed from the save row and its attributes without loading the
+    /// file hashes, which never change once a save is stored
+    pub fn latest_save_etag(
+        &self,
+        path_id: i32,
+        user_id: Option<i32>,
+    ) -> Result<Option<String>, DbError> {
+        let _span = debug_span!("latest_save_etag", path_id, ?user_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let Some(game_save) = game_save::table
+                .filter(game_save::path_id.eq(path_id))
+                .filter(visible_to(user_id))
+                .order((game_save::time.desc(), game_save::uuid.desc()))
+                .select(DbGameSave::as_select())
+                .first(connection)
+                .optional()?
+            else {
+                return Ok(None);
+            };
+            let attributes: Vec<(String, String)> = save_attribute::table
+                .filter(save_attribute::game_save_uuid.eq(&game_save.uuid))
+                .order(save_attribute::key)
+                .select((save_attribute::key, save_attribute::value))
+                .load(connection)?;
+
+            let mut hasher = Sha256::new();
+            hasher.update(game_save.label.as_deref().unwrap_or_default());
+            hasher.update([
+                u8::from(game_save.label.is_some()),
+                u8::from(game_save.pinned),
+            ]);
+            for (key, value) in &attributes {
+                hasher.update(key.len().to_le_bytes());
+                hasher.update(key);
+                hasher.update(value.len().to_le_bytes());
+                hasher.update(value);
+            }
+            let digest = format!("{:x}", hasher.finalize());
+            Ok(Some(format!(
+                "W/\"{}-{}-{}-{}\"",
+                game_save.uuid,
+                game_save.generation,
+                game_save.time.assume_utc().unix_timestamp(),
+                &digest[..16]
+            )))
+        })
+    }
+
+    /// Distinct device names the saves of `path_id` were uploaded from,
+    /// sorted, saves without one are left out. Only the saves of `user_id`
+    /// when given
+    pub fn list_devices_for_path(
+        &self,
+        path_id: i32,
+        user_id: Option<i32>,
+    ) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("list_devices_for_path", path_id, ?user_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            Ok(game_save::table
+                .filter(game_save::path_id.eq(path_id))
+                .filter(visible_to(user_id))
+                .filter(game_save::device_name.is_not_null())
+                .select(game_save::device_name.assume_not_null())
+                .distinct()
+                .order(game_save::device_name.asc())
+                .load(connection)?)
+        })
+    }
+
+    /// Conflict `resolve_upload` would see for an upload of `user_id`
+    /// based on `base_uuid`, so only that user's saves are compared
+    pub fn check_save_conflict(
+        &self,
+        path_id: i32,
+        user_id: Option<i32>,
+        base_uuid: Option<Uuid>,
+    ) -> Result<ConflictStatus, DbError> {
+        let _span = debug_span!("check_save_conflict", path_id, ?user_id, ?base_uuid).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let latest: Option<(String, Option<String>)> = game_save::table
+                .filter(game_save::path_id.eq(path_id))
+                .filter(game_save::user_id.is(user_id))
+                .order((game_save::time.desc(), game_save::uuid.desc()))
+                .select((game_save::uuid, game_save::device_name))
+                .first(connection)
+                .optional()?;
+
+            Ok(match latest {
+                None => ConflictStatus::NoBaseYet,
+                Some((latest_uuid, _))
+                    if base_uuid.is_some_and(|base| base.to_string() == latest_uuid) =>
+                {
+                    ConflictStatus::NoConflict
+                }
+                Some((latest_uuid, latest_device_name)) => ConflictStatus::BaseIsStale {
+                    latest_uuid,
+                    latest_device_name,
+                },
+            })
+        })
+    }
+
+    /// Stores `incoming` unless `strategy` says the server copy wins. Only
+    /// the saves `user_id` uploaded to the path take part, another user's
+    /// save is never the latest one nor replaced. The stored save is stamped
+    /// with the current time like any upload, its `time` is the client's
+    /// save time and only compared against the latest save for
+    /// `PreferNewest`. A save already stored with `idempotency_key` is
+    /// returned instead, before any conflict check. A pinned latest save is
+    /// kept beside the upload rather than replaced
+    pub fn resolve_upload(
+        &self,
+        path_id: i32,
+        incoming: SaveReference,
+        base_uuid: Option<Uuid>,
+        strategy: SyncStrategy,
+        user_id: Option<i32>,
+        idempotency_key: Option<&str>,
+    ) -> Result<SaveInsert<ResolveOutcome>, DbError> {
+        let _span =
+            debug_span!("resolve_upload", path_id, ?base_uuid, ?strategy, ?user_id).entered();
+        traced(|| {
+            Uuid::parse_str(&incoming.uuid).map_err(|e| DbError::Invalid(e.to_string()))?;
+            validate_save_fields(
+                &incoming.attributes,
+                incoming.label.as_deref(),
+                incoming.device_name.as_deref(),
+            )
+            .map_err(DbError::Invalid)?;
+            validate_files_hash(&incoming.files_hash)?;
+            validate_idempotency(idempotency_key)?;
+            let connection = &mut self.pool.get()?;
+
+            let resolved = self.with_retry(connection, |connection| {
+                if let Some(existing) =
+                    find_idempotent_save(connection, path_id, user_id, idempotency_key)?
+                {
+                    return Ok(SaveInsert::Replayed(existing));
+                }
+                let latest: Option<DbGameSave> = game_save::table
+                    .filter(game_save::path_id.eq(path_id))
+                    .filter(game_save::user_id.is(user_id))
+                    .order((game_save::time.desc(), game_save::uuid.desc()))
+                    .select(DbGameSave::as_select())
+                    .first(connection)
+                    .optional()?;
+                let stale = latest.filter(|latest| {
+                    base_uuid.is_none_or(|base_uuid| base_uuid.to_string() != latest.uuid)
+                });
+
+                let outcome = match (stale, strategy) {
+                    (None, _) => ResolveOutcome::Stored {
+                        uuid: incoming.uuid.clone(),
+                    },
+                    (Some(latest), SyncStrategy::PreferServer) => ResolveOutcome::Rejected {
+                        latest_uuid: latest.uuid,
+                    },
+                    (Some(latest), SyncStrategy::PreferNewest)
+                        if latest.time.assume_utc() >= incoming.time =>
+                    {
+                        ResolveOutcome::Rejected {
+                            latest_uuid: latest.uuid,
+                        }
+                    }
+                    // A pinned save is never deleted, the upload is stored beside it
+                    (Some(latest), SyncStrategy::PreferNewest | SyncStrategy::PreferClient)
+                        if latest.pinned =>
+                    {
+                        ResolveOutcome::KeptBoth {
+                            uuid: incoming.uuid.clone(),
+                            latest_uuid: latest.uuid,
+                        }
+                    }
+                    (Some(latest), SyncStrategy::PreferNewest | SyncStrategy::PreferClient) => {
+                        let orphaned_hashes =
+                            delete_save_rows(connection, std::slice::from_ref(&latest.uuid))?;
+                        ResolveOutcome::Replaced {
+                            uuid: incoming.uuid.clone(),
+                            replaced_uuid: latest.uuid,
+                            orphaned_hashes,
+                        }
+                    }
+                    (Some(latest), SyncStrategy::KeepBoth) => ResolveOutcome::KeptBoth {
+                        uuid: incoming.uuid.clone(),
+                        latest_uuid: latest.uuid,
+                    },
+                };
+                if matches!(outcome, ResolveOutcome::Rejected { .. }) {
+                    return Ok(SaveInsert::Created((outcome, None)));
+                }
+
+                let game_save = insert_save_rows(
+                    connection,
+                    &incoming.uuid,
+                    path_id,
+                    user_id,
+                    &incoming.files_hash,
+                    incoming.label.as_deref(),
+                    incoming.device_name.as_deref(),
+                    idempotency_key,
+                )?;
+                let attributes: Vec<DbSaveAttribute> = incoming
+                    .attributes
+                    .iter()
+                    .map(|(key, value)| DbSaveAttribute {
+                        game_save_uuid: incoming.uuid.clone(),
+                        key: key.clone(),
+                        value: value.clone(),
+                    })
+                    .collect();
+                diesel::insert_into(save_attribute::table)
+                    .values(attributes)
+                    .execute(connection)?;
+
+                Ok(SaveInsert::Created((outcome, Some(game_save))))
+            })?;
+            let (outcome, game_save) = match resolved {
+                SaveInsert::Created(resolved) => resolved,
+                SaveInsert::Replayed(existing) => return Ok(SaveInsert::Replayed(existing)),
+            };
+
+            if let Some(game_save) = game_save {
+                self.fire_save_created(&SaveReference {
+                    time: game_save.time.assume_utc(),
+                    total_size_bytes: SaveReference::total_size(&incoming.files_hash),
+                    generation: game_save.generation,
+                    ..incoming
+                });
+            }
+            Ok(SaveInsert::Created(outcome))
+        })
+    }
+
+    /// Returns `None` when the save does not exist, otherwise the blob
+    /// hashes the save was the last one to reference
+    pub fn delete_save_reference(&self, uuid: Uuid) -> Result<Option<Vec<String>>, DbError> {
+        let _span = debug_span!("delete_save_reference", %uuid).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let uuid = uuid.to_string();
+
+            self.with_retry(connection, |connection| {
+                let exists: i64 = game_save::table
+                    .filter(game_save::uuid.eq(&uuid))
+                    .count()
+                    .get_result(connection)?;
+                if exists == 0 {
+                    return Ok(None);
+                }
+
+                Ok(Some(delete_save_rows(
+                    connection,
+                    std::slice::from_ref(&uuid),
+                )?))
+            })
+        })
+    }
+
+    /// Deletes every named save that exists in a single transaction, the
+    /// others are skipped. Returns the uuids removed with the blob hashes
+    /// they were the last ones to reference
+    pub fn delete_save_references(&self, uuids: &[Uuid]) -> Result<PrunedSaves, DbError> {
+        let _span = debug_span!("delete_save_references", count = uuids.len()).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let uuids: Vec<String> = uuids.iter().map(Uuid::to_string).collect();
+
+            self.with_retry(connection, |connection| {
+                let mut existing = Vec::with_capacity(uuids.len());
+                for uuids in uuids.chunks(SQLITE_MAX_VARIABLES) {
+                    existing.extend(
+                        game_save::table
+                            .filter(game_save::uuid.eq_any(uuids))
+                            .select(game_save::uuid)
+                            .load::<String>(connection)?,
+                    );
+                }
+                existing.sort();
+                existing.dedup();
+
+                let orphaned_hashes = delete_save_rows(connection, &existing)?;
+                Ok(PrunedSaves {
+                    uuids: existing,
+                    orphaned_hashes,
+                })
+            })
+        })
+    }
+
+    /// How many file hash rows point at the `sha256` `hash`, the blob may
+    /// be deleted once this is zero
+    pub fn hash_reference_count(&self, hash: &str) -> Result<i64, DbError> {
+        let _span = debug_span!("hash_reference_count", hash).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            Ok(file_hash::table
+                .inner_join(blob::table)
+                .filter(blob::hash.eq(hash))
+                .filter(blob::algo.eq(HashAlgo::Sha256))
+                .count()
+                .get_result(connection)?)
+        })
+    }
+
+    /// Keeps the `keep` most recent saves of `path_id` and deletes the
+    /// others, pinned saves are kept on top of the `keep` recent ones
+    pub fn prune_save_references_for_path(
+        &self,
+        path_id: i32,
+        keep: usize,
+    ) -> Result<PrunedSaves, DbError> {
+        let _span = debug_span!("prune_save_references_for_path", path_id, keep).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            self.with_retry(connection, |connection| {
+                prune_path_rows(connection, path_id, keep)
+            })
+        })
+    }
+
+    /// Dry run of `prune_save_references_for_path`, returns the saves and
+    /// blob hashes it would delete without deleting anything
+    pub fn prune_save_references_for_path_preview(
+        &self,
+        path_id: i32,
+        keep: usize,
+    ) -> Result<PrunedSaves, DbError> {
+        let _span = debug_span!("prune_save_references_for_path_preview", path_id, keep).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            self.with_rollback(connection, |connection| {
+                prune_path_rows(connection, path_id, keep)
+            })
+        })
+    }
+
+    /// Prunes every path by its game's retention policy, keeping the `keep`
+    /// most recent saves of the games without one. Pinned saves are always
+    /// kept
+    pub fn prune_save_references(&self, keep: usize) -> Result<PrunedSaves, DbError> {
+        let _span = debug_span!("prune_save_references", keep).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            self.with_retry(connection, |connection| {
+                prune_rows_by_policy(connection, None, keep)
+            })
+        })
+    }
+
+    /// Dry run of `prune_save_references`, returns the saves and blob hashes
+    /// it would delete without deleting anything
+    pub fn prune_save_references_preview(&self, keep: usize) -> Result<PrunedSaves, DbError> {
+        let _span = debug_span!("prune_save_references_preview", keep).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            self.with_rollback(connection, |connection| {
+                prune_rows_by_policy(connection, None, keep)
+            })
+        })
+    }
+
+    /// Retention stored on the game, `None` when there is no such game
+    pub fn get_retention_policy(&self, game_id: i32) -> Result<Option<RetentionPolicy>, DbError> {
+        let _span = debug_span!("get_retention_policy", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let policy: Option<(Option<i32>, Option<i32>)> = game_metadata::table
+                .filter(game_metadata::id.eq(game_id))
+                .filter(game_metadata::is_deleted.eq(false))
+                .select((
+                    game_metadata::retention_keep,
+                    game_metadata::retention_max_age_days,
+                ))
+                .first(connection)
+                .optional()?;
+            Ok(policy.map(|(keep, max_age_days)| RetentionPolicy { keep, max_age_days }))
+        })
+    }
+
+    /// Replaces the game's retention, returns false when there is no such
+    /// game. It is only enforced by the next prune
+    pub fn set_retention_policy(
+        &self,
+        game_id: i32,
+        policy: RetentionPolicy,
+    ) -> Result<bool, DbError> {
+        let _span = debug_span!("set_retention_policy", game_id, ?policy).entered();
+        traced(|| {
+            validate_retention_policy(&policy).map_err(DbError::Invalid)?;
+            let connection = &mut self.pool.get()?;
+            let updated = diesel::update(
+                game_metadata::table
+                    .filter(game_metadata::id.eq(game_id))
+                    .filter(game_metadata::is_deleted.eq(false)),
+            )
+            .set((
+                game_metadata::retention_keep.eq(policy.keep),
+                game_metadata::retention_max_age_days.eq(policy.max_age_days),
+            ))
+            .execute(connection)?;
+            Ok(updated > 0)
+        })
+    }
+
+    /// Saves the retention of the path's game keeps, `default_keep` when the
+    /// game stores none or the path does not exist
+    pub fn retention_keep_for_path(
+        &self,
+        path_id: i32,
+        default_keep: usize,
+    ) -> Result<usize, DbError> {
+        let _span = debug_span!("retention_keep_for_path", path_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let keep: Option<i32> = game_path::table
+                .inner_join(game_metadata::table)
+                .filter(game_path::id.eq(path_id))
+                .select(game_metadata::retention_keep)
+                .first::<Option<i32>>(connection)
+                .optional()?
+                .flatten();
+            Ok(keep.map_or(default_keep, |keep| keep.max(1) as usize))
+        })
+    }
+
+    /// Prunes every path of the game by its stored retention, falling back
+    /// to `MAX_SAVES_PER_PATH` when it has no `keep`
+    pub fn apply_retention(&self, game_id: i32) -> Result<PrunedSaves, DbError> {
+        let _span = debug_span!("apply_retention", game_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            self.with_retry(connection, |connection| {
+                prune_rows_by_policy(connection, Some(game_id), MAX_SAVES_PER_PATH)
+            })
+        })
+    }
+
+    pub fn diff_save_references(&self, from: Uuid, to: Uuid) -> Result<SaveDiff, DbError> {
+        let _span = debug_span!("diff_save_references", %from, %to).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            type Files = BTreeMap<String, (HashAlgo, String)>;
+            let mut load_files = |uuid: Uuid| -> Result<Files, DbError> {
+                let uuid = uuid.to_string();
+                let save_exists: i64 = game_save::table
+                    .filter(game_save::uuid.eq(&uuid))
+                    .count()
+                    .get_result(connection)?;
+                if save_exists == 0 {
+                    return Err(DbError::NotFound);
+                }
+
+                Ok(file_hash::table
+                    .inner_join(blob::table)
+                    .filter(file_hash::game_save_uuid.eq(&uuid))
+                    .select((file_hash::relative_path, blob::algo, blob::hash))
+                    .load::<(String, HashAlgo, String)>(connection)?
+                    .into_iter()
+                    .map(|(relative_path, algo, hash)| (relative_path, (algo, hash)))
+                    .collect())
+            };
+            let from_files = load_files(from)?;
+            let mut to_files = load_files(to)?;
+
+            let mut diff = SaveDiff {
+                added: Vec::new(),
+                removed: Vec::new(),
+                changed: Vec::new(),
+                renamed: Vec::new(),
+            };
+            // Hashes from different algorithms can't be compared, count them as changed
+            for (relative_path, (from_algo, from_hash)) in from_files {
+                match to_files.remove(&relative_path) {
+                    Some((to_algo, to_hash)) if to_algo != from_algo || to_hash != from_hash => {
+                        diff.changed.push(ChangedFile {
+                            relative_path,
+                            from_hash,
+                            to_hash,
+                        })
+                    }
+                    Some(_) => {}
+                    None => diff.removed.push(FileHash {
+                        relative_path,
+                        hash: from_hash,
+                        algo: from_algo,
+                        size: None,
+                    }),
+                }
+            }
+
+            // Pair removed and added files by content, both sides in path order
+            let mut added_by_content: HashMap<(HashAlgo, String), Vec<String>> = HashMap::new();
+            for (relative_path, content) in to_files.iter().rev() {
+                added_by_content
+                    .entry(content.clone())
+                    .or_default()
+                    .push(relative_path.clone());
+            }
+            let removed = std::mem::take(&mut diff.removed);
+            for file in removed {
+                let renamed_to = added_by_content
+                    .get_mut(&(file.algo, file.hash.clone()))
+                    .and_then(|paths| paths.pop());
+                match renamed_to {
+                    Some(to) => {
+                        to_files.remove(&to);
+                        diff.renamed.push(RenamedFile {
+                            from: file.relative_path,
+                            to,
+                            hash: file.hash,
+                        });
+                    }
+                    None => diff.removed.push(file),
+                }
+            }
+
+            diff.added = to_files
+                .into_iter()
+                .map(|(relative_path, (algo, hash))| FileHash {
+                    relative_path,
+                    hash,
+                    algo,
+                    size: None,
+                })
+                .collect();
+
+            Ok(diff)
+        })
+    }
+
+    pub fn verify_save_integrity(
+        &self,
+        uuid: Uuid,
+        root: &Path,
+    ) -> Result<IntegrityReport, DbError> {
+        let _span = debug_span!("verify_save_integrity", %uuid).entered();
+        traced(|| {
+            let recorded: Vec<(String, HashAlgo, String)> = {
+                let connection = &mut self.pool.get()?;
+                let uuid = uuid.to_string();
+                let save_exists: i64 = game_save::table
+                    .filter(game_save::uuid.eq(&uuid))
+                    .count()
+                    .get_result(connection)?;
+                if save_exists == 0 {
+                    return Err(DbError::NotFound);
+                }
+
+                file_hash::table
+                    .inner_join(blob::table)
+                    .filter(file_hash::game_save_uuid.eq(&uuid))
+                    .order(file_hash::relative_path.asc())
+                    .select((file_hash::relative_path, blob::algo, blob::hash))
+                    .load(connection)?
+            };
+
+            let mut on_disk: BTreeMap<String, String> = hash_save_directory(root)?
+                .into_iter()
+                .map(|file| (file.relative_path, file.hash))
+                .collect();
+
+            let mut report = IntegrityReport::default();
+            for (relative_path, algo, hash) in recorded {
+                match on_disk.remove(&relative_path) {
+                    None => report.missing.push(relative_path),
+                    Some(_) if algo != HashAlgo::Sha256 => report.unverified.push(relative_path),
+                    Some(disk_hash) if disk_hash != hash => report.mismatched.push(relative_path),
+                    Some(_) => {}
+                }
+            }
+            report.extra = on_disk.into_keys().collect();
+
+            Ok(report)
+        })
+    }
+
+    /// Hashes every file under `root`, copies the ones `store` does not hold
+    /// yet into it and records them as a new save of `path_id`. Files at or
+    /// above the store's chunk threshold are stored as chunks. A file that
+    /// changes between hashing and copying fails the snapshot
+    pub fn snapshot_directory(
+        &self,
+        path_id: i32,
+        root: &Path,
+        store: &dyn BlobStore,
+    ) -> Result<SaveReference, DbError> {
+        let _span = debug_span!("snapshot_directory", path_id, root = %root.display()).entered();
+        traced(|| self.snapshot_with_label(path_id, root, store, None))
+    }
+
+    fn snapshot_with_label(
+        &self,
+        path_id: i32,
+        root: &Path,
+        store: &dyn BlobStore,
+        label: Option<String>,
+    ) -> Result<SaveReference, DbError> {
+        let files_hash = hash_save_directory(root)?;
+        for file_hash in &files_hash {
+            if store.exists(&file_hash.hash)? {
+                continue;
+            }
+            let mut file = std::fs::File::open(root.join(&file_hash.relative_path))?;
+            let stored_hash = if store.should_chunk(file_hash.size.unwrap_or_default()) {
+                if self.get_chunked_file(&file_hash.hash)?.is_some() {
+                    continue;
+                }
+                let chunked = store.put_chunked(&mut file)?;
+                if chunked.hash == file_hash.hash {
+                    let connection = &mut self.pool.get()?;
+                    self.with_retry(connection, |connection| {
+                        Ok(insert_chunk_list(connection, &chunked)?)
+                    })?;
+                }
+                chunked.hash
+            } else {
+                store.put_stream(&mut file)?
+            };
+            if stored_hash != file_hash.hash {
+                return Err(DbError::Invalid(format!(
+                    "{} changed while it was being snapshotted",
+                    file_hash.relative_path
+                )));
+            }
+        }
+
+        let uuid = Uuid::new_v4();
+        self.add_reference_to_save(uuid, path_id, None, files_hash, label, None, None)?;
+        self.get_reference_to_save_by_uuid(uuid)?
+            .ok_or(DbError::NotFound)
+    }
+
+    /// Snapshots `live_root` as a save of the path of `uuid`, labeled
+    /// `auto: before rollback`, then restores the save over it and deletes
+    /// the snapshotted files the save does not have, so `live_root` ends up
+    /// holding exactly the save. Returns the snapshot so the rollback can be
+    /// undone by restoring it. The snapshot is kept when the restore fails
+    pub fn rollback_to_save(
+        &self,
+        uuid: Uuid,
+        store: &dyn BlobStore,
+        live_root: &Path,
+    ) -> Result<SaveReference, DbError> {
+        let _span =
+            debug_span!("rollback_to_save", %uuid, live_root = %live_root.display()).entered();
+        traced(|| {
+            let target = self
+                .get_reference_to_save_by_uuid(uuid)?
+                .ok_or(DbError::NotFound)?;
+            let before = self.snapshot_with_label(
+                target.path_id,
+                live_root,
+                store,
+                Some(ROLLBACK_SNAPSHOT_LABEL.to_string()),
+            )?;
+            self.restore_save(uuid, store, live_root)?;
+
+            let restored: HashSet<&str> = target
+                .files_hash
+                .iter()
+                .map(|file_hash| file_hash.relative_path.as_str())
+                .collect();
+            for file_hash in &before.files_hash {
+                if !restored.contains(file_hash.relative_path.as_str()) {
+                    remove_restored_file(live_root, &file_hash.relative_path)?;
+                }
+            }
+            Ok(before)
+        })
+    }
+
+    /// Records one new save per `(path_id, files_hash)` entry in a single
+    /// transaction, so a failing entry leaves none of them behind. The
+    /// references come back in input order
+    pub fn snapshot_many(
+        &self,
+        snapshots: Vec<(i32, Vec<FileHash>)>,
+    ) -> Result<Vec<SaveReference>, DbError> {
+        let _span = debug_span!("snapshot_many", count = snapshots.len()).entered();
+        traced(|| {
+            for (_, files_hash) in &snapshots {
+                validate_files_hash(files_hash)?;
+            }
+            let snapshots: Vec<(Uuid, i32, Vec<FileHash>)> = snapshots
+                .into_iter()
+                .map(|(path_id, files_hash)| (Uuid::new_v4(), path_id, files_hash))
+                .collect();
+            let connection = &mut self.pool.get()?;
+
+            let game_saves = self.with_retry(connection, |connection| {
+                snapshots
+                    .iter()
+                    .map(|(uuid, path_id, files_hash)| {
+                        Ok(insert_save_rows(
+                            connection,
+                            &uuid.to_string(),
+                            *path_id,
+                            None,
+                            files_hash,
+                            None,
+                            None,
+                            None,
+                        )?)
+                    })
+                    .collect::<Result<Vec<DbGameSave>, DbError>>()
+            })?;
+
+            let references: Vec<SaveReference> = snapshots
+                .into_iter()
+                .zip(game_saves)
+                .map(|((uuid, path_id, files_hash), game_save)| SaveReference {
+                    uuid: uuid.to_string(),
+                    path_id,
+                    time: game_save.time.assume_utc(),
+                    total_size_bytes: SaveReference::total_size(&files_hash),
+                    generation: game_save.generation,
+                    label: None,
+                    pinned: false,
+                    device_name: None,
+                    files_hash,
+                    attributes: HashMap::new(),
+                })
+                .collect();
+            for reference in &references {
+                self.fire_save_created(reference);
+            }
+            Ok(references)
+        })
+    }
+
+    /// Writes every file of the save from `store` under `dest_root`, nothing
+    /// is written when a recorded path would land outside of it or a file
+    /// is not a `sha256` blob. Each file is replaced whole, a blob failing
+    /// to read leaves the file it was meant for untouched
+    pub fn restore_save(
+        &self,
+        uuid: Uuid,
+        store: &dyn BlobStore,
+        dest_root: &Path,
+    ) -> Result<(), DbError> {
+        let _span = debug_span!("restore_save", %uuid).entered();
+        traced(|| {
+            let recorded: Vec<(String, HashAlgo, String)> = {
+                let connection = &mut self.pool.get()?;
+                let uuid = uuid.to_string();
+                let save_exists: i64 = game_save::table
+                    .filter(game_save::uuid.eq(&uuid))
+                    .count()
+                    .get_result(connection)?;
+                if save_exists == 0 {
+                    return Err(DbError::NotFound);
+                }
+
+                file_hash::table
+                    .inner_join(blob::table)
+                    .filter(file_hash::game_save_uuid.eq(&uuid))
+                    .order(file_hash::relative_path.asc())
+                    .select((file_hash::relative_path, blob::algo, blob::hash))
+                    .load(connection)?
+            };
+
+            let mut files = Vec::with_capacity(recorded.len());
+            for (relative_path, algo, hash) in recorded {
+                if algo != HashAlgo::Sha256 {
+                    return Err(DbError::Invalid(format!(
+                        "{relative_path} is hashed with {algo:?}, only sha256 files are stored as blobs"
+                    )));
+                }
+                files.push((restore_target(dest_root, &relative_path)?, hash));
+            }
+
+            for (target, hash) in files {
+                let Some(mut reader) = self.open_content(&hash, store)? else {
+                    return Err(DbError::Io(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!("blob {hash} for {} is not stored", target.display()),
+                    )));
+                };
+                replace_file(&target, &mut reader)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Checks the chunks of `file` are stored with the sizes it lists and
+    /// that they add up to its hash, then records the chunk list so saves
+    /// can reference the file without it being stored whole. Recording a
+    /// file that already has a chunk list is a no-op
+    pub fn record_chunked_file(
+        &self,
+        file: &ChunkedFile,
+        store: &dyn BlobStore,
+    ) -> Result<(), DbError> {
+        let _span =
+            debug_span!("record_chunked_file", hash = %file.hash, chunks = file.chunks.len())
+                .entered();
+        traced(|| {
+            if !is_valid_hash(&file.hash) {
+                return Err(DbError::Invalid(format!(
+                    "invalid file hash: {}",
+                    file.hash
+                )));
+            }
+            if file.chunks.is_empty() {
+                return Err(DbError::Invalid("a chunked file needs chunks".to_string()));
+            }
+            let mut hasher = Sha256::new();
+            let mut size = 0;
+            for chunk in &file.chunks {
+                if !is_valid_hash(&chunk.hash) {
+                    return Err(DbError::Invalid(format!(
+                        "invalid chunk hash: {}",
+                        chunk.hash
+                    )));
+                }
+                let Some(mut reader) = store.get_stream(&chunk.hash)? else {
+                    return Err(DbError::Invalid(format!(
+                        "chunk {} is not stored",
+                        chunk.hash
+                    )));
+                };
+                let read = std::io::copy(&mut reader, &mut hasher)?;
+                if read != chunk.size {
+                    return Err(DbError::Invalid(format!(
+                        "chunk {} is {read} bytes, not {}",
+                        chunk.hash, chunk.size
+                    )));
+                }
+                size += read;
+            }
+            if size != file.size || format!("{:x}", hasher.finalize()) != file.hash {
+                return Err(DbError::Invalid(format!(
+                    "the chunks do not add up to {}",
+                    file.hash
+                )));
+            }
+
+            let connection = &mut self.pool.get()?;
+            self.with_retry(connection, |connection| {
+                Ok(insert_chunk_list(connection, file)?)
+            })
+        })
+    }
+
+    /// Chunk list of the `sha256` content `hash`, `None` when it is not
+    /// stored as chunks
+    pub fn get_chunked_file(&self, hash: &str) -> Result<Option<ChunkedFile>, DbError> {
+        let _span = debug_span!("get_chunked_file", %hash).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let rows: Vec<(Option<i64>, DbBlobChunk)> = blob::table
+                .inner_join(blob_chunk::table)
+                .filter(blob::hash.eq(hash))
+                .filter(blob::algo.eq(HashAlgo::Sha256))
+                .order(blob_chunk::position.asc())
+                .select((blob::size, DbBlobChunk::as_select()))
+                .load(connection)?;
+            let Some((size, _)) = rows.first() else {
+                return Ok(None);
+            };
+            let chunks: Vec<FileChunk> = rows
+                .iter()
+                .map(|(_, chunk)| FileChunk {
+                    hash: chunk.chunk_hash.clone(),
+                    size: chunk.chunk_size as u64,
+                })
+                .collect();
+            Ok(Some(ChunkedFile {
+                hash: hash.to_string(),
+                size: size.map_or_else(
+                    || chunks.iter().map(|chunk| chunk.size).sum(),
+                    |size| size as u64,
+                ),
+                chunks,
+            }))
+        })
+    }
+
+    /// Length of the `sha256` content `hash` as recorded with its blob row,
+    /// whether it is stored whole or as chunks. `None` when no row records
+    /// it, an upload that did not send sizes leaves it unknown
+    pub fn content_size(&self, hash: &str) -> Result<Option<u64>, DbError> {
+        let _span = debug_span!("content_size", %hash).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            Ok(blob::table
+                .filter(blob::hash.eq(hash))
+                .filter(blob::algo.eq(HashAlgo::Sha256))
+                .select(blob::size)
+                .first::<Option<i64>>(connection)
+                .optional()?
+                .flatten()
+                .map(|size| size.max(0) as u64))
+        })
+    }
+
+    /// Reads the `sha256` content `hash` from `store`, from its blob when it
+    /// is stored whole or else from the blobs of its chunk list. `None`
+    /// when it is neither
+    pub fn open_content<'a>(
+        &self,
+        hash: &str,
+        store: &'a dyn BlobStore,
+    ) -> Result<Option<Box<dyn Read + Send + 'a>>, DbError> {
+        if let Some(reader) = store.get_stream(hash)? {
+            return Ok(Some(reader));
+        }
+        Ok(self.get_chunked_file(hash)?.map(|file| {
+            store.get_chunks_stream(file.chunks.into_iter().map(|chunk| chunk.hash).collect())
+        }))
+    }
+
+    /// Removes blobs no `sha256` file hash or chunk list points to. Only blobs written
+    /// `BLOB_GC_GRACE_SECS` before the referenced hashes were read are
+    /// considered, so content uploaded ahead of its save reference survives.
+    /// Returns each removed hash with the bytes it freed
+    pub fn gc_unreferenced_blobs(
+        &self,
+        store: &dyn BlobStore,
+    ) -> Result<Vec<(String, u64)>, DbError> {
+        let _span = debug_span!("gc_unreferenced_blobs").entered();
+        traced(|| {
+            let mut removed = Vec::new();
+            for hash in self.unreferenced_blobs(store, &[])? {
+                let freed = store.delete(&hash)?;
+                removed.push((hash, freed));
+            }
+            Ok(removed)
+        })
+    }
+
+    /// Dry run of `gc_unreferenced_blobs`, returns the blobs it would delete
+    /// with their size on disk. `released` hashes are treated as no longer
+    /// referenced, pass the `orphaned_hashes` of a previewed prune to see
+    /// what a prune followed by a collection would remove
+    pub fn gc_unreferenced_blobs_preview(
+        &self,
+        store: &dyn BlobStore,
+        released: &[String],
+    ) -> Result<Vec<(String, u64)>, DbError> {
+        let _span = debug_span!("gc_unreferenced_blobs_preview").entered();
+        traced(|| {
+            let mut removable = Vec::new();
+            for hash in self.unreferenced_blobs(store, released)? {
+                let size = store.stored_size(&hash)?;
+                removable.push((hash, size));
+            }
+            Ok(removable)
+        })
+    }
+
+    /// Hashes of the stored blobs older than the grace period that no save
+    /// references and no chunk list has, counting `released` as
+    /// unreferenced
+    fn unreferenced_blobs(
+        &self,
+        store: &dyn BlobStore,
+        released: &[String],
+    ) -> Result<Vec<String>, DbError> {
+        let snapshot_time = SystemTime::now();
+        let mut referenced: HashSet<String> = {
+            let connection = &mut self.pool.get()?;
+            file_hash::table
+                .inner_join(blob::table)
+                .filter(blob::algo.eq(HashAlgo::Sha256))
+                .select(blob::hash)
+                .distinct()
+                .load::<String>(connection)?
+                .into_iter()
+                .chain(
+                    blob_chunk::table
+                        .select(blob_chunk::chunk_hash)
+                        .distinct()
+                        .load::<String>(connection)?,
+                )
+                .collect()
+        };
+        for hash in released {
+            referenced.remove(hash);
+        }
+        let cutoff = snapshot_time - Duration::from_secs(BLOB_GC_GRACE_SECS);
+
+        let mut unreferenced: Vec<String> = store
+            .list()?
+            .into_iter()
+            .filter(|(hash, modified)| *modified < cutoff && !referenced.contains(hash))
+            .map(|(hash, _)| hash)
+            .collect();
+        // A blob stored in both encodings is listed twice
+        unreferenced.dedup();
+        Ok(unreferenced)
+    }
+
+    /// Checks every distinct `sha256` file hash against `store`: it must be
+    /// stored and its content must hash back to it. Stored blobs no save
+    /// references are reported too, except those younger than
+    /// `BLOB_GC_GRACE_SECS` which may belong to an upload in progress.
+    /// Hashes are read a page at a time and compared with the sorted blob
+    /// listing, so neither side nor any content is held in memory at once,
+    /// and no connection is held while blobs are read. A file stored as
+    /// chunks is checked chunk by chunk and a bad chunk is reported under
+    /// its own hash. Files of saves uploaded as an archive are not in the
+    /// store and show up as missing
+    pub fn verify_store_consistency(
+        &self,
+        store: &dyn BlobStore,
+    ) -> Result<ConsistencyReport, DbError> {
+        let _span = debug_span!("verify_store_consistency").entered();
+        traced(|| {
+            let cutoff = SystemTime::now() - Duration::from_secs(BLOB_GC_GRACE_SECS);
+            let mut blobs = store.list()?;
+            // A blob stored in both encodings is listed twice
+            blobs.dedup_by(|a, b| a.0 == b.0);
+            let mut blobs = blobs.into_iter().peekable();
+            let mut report = ConsistencyReport::default();
+
+            let verify = |hash: &str, report: &mut ConsistencyReport| {
+                match store.rehash(hash) {
+                    Ok(Some(actual)) if actual == hash => {}
+                    Ok(Some(_)) => report.corrupt_blobs.push(hash.to_string()),
+                    Ok(None) => report.missing_blobs.push(hash.to_string()),
+                    // Removed by a concurrent gc between the listing and now
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        report.missing_blobs.push(hash.to_string())
+                    }
+                    Err(e) => {
+                        warn!(%hash, error = %e, "blob could not be read back");
+                        report.corrupt_blobs.push(hash.to_string())
+                    }
+                }
+            };
+
+            let mut after: Option<String> = None;
+            loop {
+                let page: Vec<String> = {
+                    let connection = &mut self.pool.get()?;
+                    let mut query = file_hash::table
+                        .inner_join(blob::table)
+                        .filter(blob::algo.eq(HashAlgo::Sha256))
+                        .select(blob::hash)
+                        .distinct()
+                        .order(blob::hash.asc())
+                        .limit(CONSISTENCY_PAGE_SIZE)
+                        .into_boxed();
+                    if let Some(after) = &after {
+                        query = query.filter(blob::hash.gt(after.clone()));
+                    }
+                    query.load(connection)?
+                };
+                let Some(last) = page.last().cloned() else {
+                    break;
+                };
+
+                for hash in &page {
+                    report.referenced += 1;
+                    while let Some((blob, modified)) = blobs.next_if(|(blob, _)| blob < hash) {
+                        if modified < cutoff {
+                            report.orphan_blobs.push(blob);
+                        }
+                    }
+                    // Not in the listing may still mean uploaded since, rehash
+                    // tells both cases apart
+                    blobs.next_if(|(blob, _)| blob == hash);
+                    if !store.exists(hash)?
+                        && let Some(file) = self.get_chunked_file(hash)?
+                    {
+                        for chunk in &file.chunks {
+                            verify(&chunk.hash, &mut report);
+                        }
+                        continue;
+                    }
+                    verify(hash, &mut report);
+                }
+                after = Some(last);
+            }
+            report.orphan_blobs.extend(
+                blobs
+                    .filter(|(_, modified)| *modified < cutoff)
+                    .map(|(blob, _)| blob),
+            );
+
+            // Chunks are listed among the blobs without being file hashes
+            let mut chunks: HashSet<String> = HashSet::new();
+            for hashes in report.orphan_blobs.chunks(SQLITE_MAX_VARIABLES) {
+                let connection = &mut self.pool.get()?;
+                chunks.extend(
+                    blob_chunk::table
+                        .filter(blob_chunk::chunk_hash.eq_any(hashes))
+                        .select(blob_chunk::chunk_hash)
+                        .distinct()
+                        .load::<String>(connection)?,
+                );
+            }
+            report.orphan_blobs.retain(|hash| !chunks.contains(hash));
+            Ok(report)
+        })
+    }
+
+    /// Writes a consistent snapshot to `dest_path` while other connections
+    /// keep writing. diesel does not expose the raw handle needed for the
+    /// sqlite3_backup API, so this uses `VACUUM INTO`, which copies from a
+    /// single read transaction and is safe under WAL unlike copying the file.
+    /// `dest_path` must not exist yet
+    pub fn backup_to(&self, dest_path: &str) -> Result<(), DbError> {
+        let _span = debug_span!("backup_to").entered();
+        traced(|| {
+            if Path::new(dest_path).exists() {
+                return Err(DbError::Invalid(format!("{dest_path} already exists")));
+            }
+            let connection = &mut self.pool.get()?;
+
+            diesel::sql_query("VACUUM INTO ?")
+                .bind::<diesel::sql_types::Text, _>(dest_path)
+                .execute(connection)?;
+            Ok(())
+        })
+    }
+
+    /// Refreshes the query planner statistics and rebuilds the file to give
+    /// back the pages freed by deleted saves. `VACUUM` needs every other
+    /// connection to be idle, so run this in a maintenance window; it
+    /// returns `DbError::Busy` instead of waiting on active writers
+    pub fn optimize(&self) -> Result<(), DbError> {
+        let _span = debug_span!("optimize").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            connection
+                .batch_execute("PRAGMA optimize; VACUUM;")
+                .map_err(|e| match DbError::from(e) {
+                    e if is_busy(&e) => DbError::Busy,
+                    e => e,
+                })
+        })
+    }
+
+    pub fn get_storage_stats(&self) -> Result<Vec<GameStorageStat>, DbError> {
+        let _span = debug_span!("get_storage_stats").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let rows: Vec<(Option<i32>, String, i64, i64)> = game_metadata::table
+                .inner_join(game_path::table.inner_join(
+                    game_save::table.left_join(file_hash::table.inner_join(blob::table)),
+                ))
+                .filter(game_metadata::is_deleted.eq(false))
+                .group_by((game_metadata::id, game_metadata::default_name))
+                .select((
+                    game_metadata::id,
+                    game_metadata::default_name,
+                    count(game_save::uuid).aggregate_distinct(),
+                    count(blob::hash.nullable()).aggregate_distinct(),
+                ))
+                .load(connection)?;
+
+            let distinct_files: Vec<(Option<i32>, String, Option<i64>)> = game_metadata::table
+                .inner_join(game_path::table.inner_join(
+                    game_save::table.inner_join(file_hash::table.inner_join(blob::table)),
+                ))
+                .filter(game_metadata::is_deleted.eq(false))
+                .select((game_metadata::id, blob::hash, blob::size))
+                .distinct()
+                .load(connection)?;
+            let mut stored_bytes: HashMap<Option<i32>, i64> = HashMap::new();
+            for (game_id, _, size) in distinct_files {
+                *stored_bytes.entry(game_id).or_default() += size.unwrap_or(0);
+            }
+
+            let mut stats: Vec<GameStorageStat> = rows
+                .into_iter()
+                .map(
+                    |(game_id, game_name, saves, distinct_hashes)| GameStorageStat {
+                        game_id,
+                        game_name,
+                        saves,
+                        distinct_hashes,
+                        stored_bytes: stored_bytes.get(&game_id).copied().unwrap_or(0),
+                    },
+                )
+                .collect();
+            stats.sort_by(|a, b| {
+                b.stored_bytes
+                    .cmp(&a.stored_bytes)
+                    .then(b.distinct_hashes.cmp(&a.distinct_hashes))
+            });
+            Ok(stats)
+        })
+    }
+
+    pub fn get_total_storage_stats(&self) -> Result<StorageTotals, DbError> {
+        let _span = debug_span!("get_total_storage_stats").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            Ok(StorageTotals {
+                games: game_metadata::table
+                    .filter(game_metadata::is_deleted.eq(false))
+                    .count()
+                    .get_result(connection)?,
+                saves: game_save::table.count().get_result(connection)?,
+                distinct_hashes: file_hash::table
+                    .inner_join(blob::table)
+                    .select(count(blob::hash).aggregate_distinct())
+                    .get_result(connection)?,
+                stored_bytes: diesel::select(sql::<Nullable<BigInt>>(
+                    "(SELECT SUM(size) FROM (SELECT DISTINCT b.hash, b.size FROM file_hash f \
+                     JOIN blob b ON b.id = f.blob_id))",
+                ))
+                .get_result::<Option<i64>>(connection)?
+                .unwrap_or(0),
+            })
+        })
+    }
+
+    pub fn get_most_recent_save(&self) -> Result<Option<SaveActivity>, DbError> {
+        let _span = debug_span!("get_most_recent_save").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let row: Option<(DbGameSave, String, OS, Option<i32>, String)> = game_save::table
+                .inner_join(game_path::table.inner_join(game_metadata::table))
+                .filter(game_metadata::is_deleted.eq(false))
+                .order((game_save::time.desc(), game_save::uuid.desc()))
+                .select((
+                    DbGameSave::as_select(),
+                    game_path::path,
+                    game_path::operating_system,
+                    game_metadata::id,
+                    game_metadata::default_name,
+                ))
+                .first(connection)
+                .optional()?;
+
+            let Some((game_save, path, operating_system, game_id, game_name)) = row else {
+                return Ok(None);
+            };
+
+            Ok(Some(SaveActivity {
+                game_id,
+                game_name,
+                path,
+                operating_system,
+                save: load_save_reference(connection, game_save)?,
+            }))
+        })
+    }
+
+    /// Every stored save, newest first, with its game, path and number of
+    /// files. Saves of deleted games are listed too since they still hold
+    /// storage
+    pub fn list_all_saves(&self, offset: i64, limit: i64) -> Result<Vec<AdminSaveRow>, DbError> {
+        let _span = debug_span!("list_all_saves", offset, limit).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let rows: Vec<AdminSaveRowData> = game_save::table
+                .inner_join(game_path::table.inner_join(game_metadata::table))
+                .order((game_save::time.desc(), game_save::uuid.desc()))
+                .limit(limit.max(0))
+                .offset(offset.max(0))
+                .select((
+                    game_save::uuid,
+                    game_metadata::default_name,
+                    game_path::operating_system,
+                    game_path::path,
+                    game_save::time,
+                    file_hash::table
+                        .filter(file_hash::game_save_uuid.eq(game_save::uuid))
+                        .count()
+                        .single_value(),
+                ))
+                .load(connection)?;
+
+            Ok(rows
+                .into_iter()
+                .map(
+                    |(uuid, game_name, os, path, time, file_count)| AdminSaveRow {
+                        uuid,
+                        game_name,
+                        os,
+                        path,
+                        time: time.assume_utc(),
+                        file_count: file_count.unwrap_or(0),
+                    },
+                )
+                .collect())
+        })
+    }
+
+    /// Consecutive saves of `path_id` sharing no file, among the saves of
+    /// `user_id` when given
+    pub fn detect_full_rewrites(
+        &self,
+        path_id: i32,
+        user_id: Option<i32>,
+    ) -> Result<Vec<FullRewrite>, DbError> {
+        let _span = debug_span!("detect_full_rewrites", path_id, ?user_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let save_rows = game_save::table
+                .filter(game_save::path_id.eq(path_id))
+                .filter(visible_to(user_id))
+                .order((game_save::time.asc(), game_save::uuid.asc()))
+                .select(DbGameSave::as_select())
+                .load(connection)?;
+
+            let files_hash_db = DbFileHash::belonging_to(&save_rows)
+                .inner_join(blob::table)
+                .select((DbFileHash::as_select(), DbBlob::as_select()))
+                .load::<(DbFileHash, DbBlob)>(connection)?
+                .grouped_by(&save_rows);
+
+            let hash_sets: Vec<HashSet<(HashAlgo, String)>> = files_hash_db
+                .into_iter()
+                .map(|files_hash| {
+                    files_hash
+                        .into_iter()
+                        .map(|(_, blob)| (blob.algo, blob.hash))
+                        .collect()
+                })
+                .collect();
+
+            let mut rewrites = Vec::new();
+            for index in 1..save_rows.len() {
+                let previous = &hash_sets[index - 1];
+                let current = &hash_sets[index];
+                if previous.is_empty() || current.is_empty() || !previous.is_disjoint(current) {
+                    continue;
+                }
+                rewrites.push(FullRewrite {
+                    previous_uuid: save_rows[index - 1].uuid.clone(),
+                    uuid: save_rows[index].uuid.clone(),
+                    time: save_rows[index].time.assume_utc(),
+                });
+            }
+
+            Ok(rewrites)
+        })
+    }
+
+    pub fn set_save_attribute(&self, uuid: Uuid, key: &str, value: &str) -> Result<bool, DbError> {
+        let _span = debug_span!("set_save_attribute", %uuid).entered();
+        traced(|| {
+            validate_save_attribute(key, value).map_err(DbError::Invalid)?;
+
+            let connection = &mut self.pool.get()?;
+            let uuid = uuid.to_string();
+
+            self.with_retry(connection, |connection| {
+                let save_exists: i64 = game_save::table
+                    .filter(game_save::uuid.eq(&uuid))
+                    .count()
+                    .get_result(connection)?;
+                if save_exists == 0 {
+                    return Ok(false);
+                }
+
+                let key_exists: i64 = save_attribute::table
+                    .filter(save_attribute::game_save_uuid.eq(&uuid))
+                    .filter(save_attribute::key.eq(key))
+                    .count()
+                    .get_result(connection)?;
+                if key_exists == 0 {
+                    let attribute_count: i64 = save_attribute::table
+                        .filter(save_attribute::game_save_uuid.eq(&uuid))
+                        .count()
+                        .get_result(connection)?;
+                    if attribute_count >= MAX_SAVE_ATTRIBUTES_PER_SAVE {
+                        return Err(DbError::Invalid(format!(
+                            "a save can hold at most {MAX_SAVE_ATTRIBUTES_PER_SAVE} attributes"
+                        )));
+                    }
+                }
+
+                diesel::insert_into(save_attribute::table)
+                    .values(DbSaveAttribute {
+                        game_save_uuid: uuid.clone(),
+                        key: key.to_string(),
+                        value: value.to_string(),
+                    })
+                    .on_conflict((save_attribute::game_save_uuid, save_attribute::key))
+                    .do_update()
+                    .set(save_attribute::value.eq(value))
+                    .execute(connection)?;
+
+                Ok(true)
+            })
+        })
+    }
+
+    pub fn get_save_attributes(&self, uuid: Uuid) -> Result<HashMap<String, String>, DbError> {
+        let _span = debug_span!("get_save_attributes", %uuid).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let attributes: Vec<(String, String)> = save_attribute::table
+                .filter(save_attribute::game_save_uuid.eq(uuid.to_string()))
+                .select((save_attribute::key, save_attribute::value))
+                .load(connection)?;
+            Ok(attributes.into_iter().collect())
+        })
+    }
+
+    /// What `prune_save_references(keep_latest)` would remove, computed by
+    /// its dry run so each game's retention policy applies the same way.
+    /// `bytes` sums the known sizes of the blobs nothing would reference
+    pub fn retention_impact(&self, keep_latest: usize) -> Result<RetentionImpact, DbError> {
+        let _span = debug_span!("retention_impact", keep_latest).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let pruned = self.with_rollback(connection, |connection| {
+                prune_rows_by_policy(connection, None, keep_latest)
+            })?;
+            let mut bytes = 0;
+            for hashes in pruned.orphaned_hashes.chunks(SQLITE_MAX_VARIABLES) {
+                let sizes: Vec<Option<i64>> = blob::table
+                    .filter(blob::algo.eq(HashAlgo::Sha256))
+                    .filter(blob::hash.eq_any(hashes))
+                    .select(blob::size)
+                    .load(connection)?;
+                bytes += sizes.into_iter().flatten().sum::<i64>() as u64;
+            }
+
+            Ok(RetentionImpact {
+                keep_latest,
+                saves: pruned.uuids.len() as u64,
+                blobs: pruned.orphaned_hashes.len() as u64,
+                bytes,
+            })
+        })
+    }
+
+    pub fn get_child_changes_since(&self, revision: i64) -> Result<CatalogChildChanges, DbError> {
+        let _span = debug_span!("get_child_changes_since", revision).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let since = time::OffsetDateTime::from_unix_timestamp(revision)
+                .map_err(|e| DbError::Invalid(e.to_string()))?;
+            let since = time::PrimitiveDateTime::new(since.date(), since.time());
+
+            connection.transaction(|connection| {
+                let path_rows: Vec<(Option<i32>, String, OS, i32, time::PrimitiveDateTime)> =
+                    game_path::table
+                        .filter(game_path::updated_at.gt(since))
+                        .order(game_path::id.asc())
+                        .select((
+                            game_path::id,
+                            game_path::path,
+                            game_path::operating_system,
+                            game_path::game_metadata_id,
+                            game_path::updated_at,
+                        ))
+                        .load(connection)?;
+
+                let executable_rows: Vec<(Option<i32>, String, OS, i32, time::PrimitiveDateTime)> =
+                    game_executable::table
+                        .filter(game_executable::updated_at.gt(since))
+                        .order(game_executable::id.asc())
+                        .select((
+                            game_executable::id,
+                            game_executable::executable,
+                            game_executable::operating_system,
+                            game_executable::game_metadata_id,
+                            game_executable::updated_at,
+                        ))
+                        .load(connection)?;
+
+                let mut latest = revision;
+                let mut games: BTreeMap<i32, GameChildChanges> = BTreeMap::new();
+                for (id, path, os, game_id, updated_at) in path_rows {
+                    latest = latest.max(updated_at.assume_utc().unix_timestamp());
+                    games
+                        .entry(game_id)
+                        .or_insert_with(|| GameChildChanges::new(game_id))
+                        .paths
+                        .push(SavePath {
+                            id,
+                            path: SavePathCreate {
+                                path,
+                                operating_system: os,
+                            },
+                        });
+                }
+                for (id, executable, os, game_id, updated_at) in executable_rows {
+                    latest = latest.max(updated_at.assume_utc().unix_timestamp());
+                    games
+                        .entry(game_id)
+                        .or_insert_with(|| GameChildChanges::new(game_id))
+                        .executables
+                        .push(Executable {
+                            id,
+                            executable: ExecutableCreate {
+                                executable,
+                                operating_system: os,
+                            },
+                        });
+                }
+
+                Ok(CatalogChildChanges {
+                    revision: latest,
+                    games: games.into_values().collect(),
+                })
+            })
+        })
+    }
+
+    /// Every distinct `(hash, unix time)` the file at `relative_path` had
+    /// across the saves of `path_id`, oldest first. Consecutive entries with
+    /// different hashes mark a change of the file. Only the saves of
+    /// `user_id` when given
+    pub fn get_hash_history_for_path(
+        &self,
+        path_id: i32,
+        user_id: Option<i32>,
+        relative_path: &str,
+    ) -> Result<Vec<(String, i64)>, DbError> {
+        let _span = debug_span!("get_hash_history_for_path", path_id, ?user_id).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            Ok(file_hash::table
+                .inner_join(game_save::table)
+                .inner_join(blob::table)
+                .filter(game_save::path_id.eq(path_id))
+                .filter(file_hash::relative_path.eq(relative_path))
+                .filter(visible_to(user_id))
+                .select((blob::hash, save_unix_time()))
+                .distinct()
+                .order((save_unix_time().asc(), blob::hash.asc()))
+                .load(connection)?)
+        })
+    }
+
+    pub fn get_saves_containing_hash(
+        &self,
+        hash: &str,
+        limit: i64,
+    ) -> Result<Vec<SaveActivity>, DbError> {
+        let _span = debug_span!("get_saves_containing_hash", limit).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let rows: Vec<(DbGameSave, String, OS, Option<i32>, String)> = file_hash::table
+                .inner_join(blob::table)
+                .inner_join(
+                    game_save::table.inner_join(game_path::table.inner_join(game_metadata::table)),
+                )
+                .filter(blob::hash.eq(hash))
+                .filter(game_metadata::is_deleted.eq(false))
+                .order((game_save::time.desc(), game_save::uuid.desc()))
+                .select((
+                    DbGameSave::as_select(),
+                    game_path::path,
+                    game_path::operating_system,
+                    game_metadata::id,
+                    game_metadata::default_name,
+                ))
+                .distinct()
+                .limit(limit)
+                .load(connection)?;
+
+            let (save_rows, places): (Vec<_>, Vec<_>) = rows
+                .into_iter()
+                .map(|(game_save, path, operating_system, game_id, game_name)| {
+                    (game_save, (path, operating_system, game_id, game_name))
+                })
+                .unzip();
+            let saves = load_save_references(connection, save_rows)?
+                .into_iter()
+                .zip(places)
+                .map(
+                    |(save, (path, operating_system, game_id, game_name))| SaveActivity {
+                        game_id,
+                        game_name,
+                        path,
+                        operating_system,
+                        save,
+                    },
+                )
+                .collect();
+            Ok(saves)
+        })
+    }
+
+    /// The candidates no stored save references, in the order given and
+    /// without duplicates. The chunks of a recorded chunk list and the
+    /// files it describes count as present, so a client can hash a large
+    /// file with `hash_file_chunked` and only upload the chunks it lacks
+    pub fn missing_hashes(&self, candidate_hashes: &[String]) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("missing_hashes").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let mut seen: HashSet<&String> = HashSet::new();
+            let candidates: Vec<&String> = candidate_hashes
+                .iter()
+                .filter(|hash| seen.insert(*hash))
+                .collect();
+
+            let mut present: HashSet<String> = HashSet::new();
+            for hashes in candidates.chunks(SQLITE_MAX_VARIABLES) {
+                let found: Vec<String> = file_hash::table
+                    .inner_join(blob::table)
+                    .filter(blob::hash.eq_any(hashes))
+                    .select(blob::hash)
+                    .distinct()
+                    .load(connection)?;
+                present.extend(found);
+                present.extend(
+                    blob_chunk::table
+                        .filter(blob_chunk::chunk_hash.eq_any(hashes))
+                        .select(blob_chunk::chunk_hash)
+                        .distinct()
+                        .load::<String>(connection)?,
+                );
+                present.extend(
+                    blob::table
+                        .inner_join(blob_chunk::table)
+                        .filter(blob::hash.eq_any(hashes))
+                        .select(blob::hash)
+                        .distinct()
+                        .load::<String>(connection)?,
+                );
+            }
+
+            Ok(candidates
+                .into_iter()
+                .filter(|hash| !present.contains(*hash))
+                .cloned()
+                .collect())
+        })
+    }
+
+    pub fn find_saves_containing_hash(&self, hash: &str) -> Result<Vec<String>, DbError> {
+        let _span = debug_span!("find_saves_containing_hash").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            Ok(file_hash::table
+                .inner_join(blob::table)
+                .filter(blob::hash.eq(hash))
+                .select(file_hash::game_save_uuid)
+                .distinct()
+                .order(file_hash::game_save_uuid.asc())
+                .load(connection)?)
+        })
+    }
+
+    /// Groups the games that are likely the same one: a shared steam appid,
+    /// or default or alternative names that normalize to the same words.
+    /// Nothing is merged, the groups are for an operator to review
+    pub fn find_duplicate_games(&self) -> Result<Vec<DuplicateGroup>, DbError> {
+        let _span = debug_span!("find_duplicate_games").entered();
+        traced(|| {
+            let mut by_appid: BTreeMap<String, BTreeSet<i32>> = BTreeMap::new();
+            // Normalized name to the games using it, and whether any of them
+            // only has it as an alternative
+            let mut by_name: BTreeMap<String, (BTreeSet<i32>, bool)> = BTreeMap::new();
+            self.for_each_game(|game| {
+                let Some(id) = game.id else { return Ok(()) };
+                let metadata = game.metadata;
+                if let Some(appid) = metadata
+                    .steam_appid
+                    .filter(|appid| !appid.trim().is_empty())
+                {
+                    by_appid.entry(appid).or_default().insert(id);
+                }
+                let key = canonical_game_name(&metadata.default_name);
+                if !key.is_empty() {
+                    by_name.entry(key).or_default().0.insert(id);
+                }
+                // The default name of a game is seen before its alternatives,
+                // an alternative only counts when it brings the game in
+                for name in &metadata.known_name {
+                    let key = canonical_game_name(name);
+                    if key.is_empty() {
+                        continue;
+                    }
+                    let (ids, from_alt) = by_name.entry(key).or_default();
+                    *from_alt |= ids.insert(id);
+                }
+                Ok(())
+            })?;
+
+            let mut matches: Vec<DuplicateMatch> = by_appid
+                .into_iter()
+                .map(|(key, ids)| (DuplicateReason::SteamAppid, key, ids))
+                .chain(by_name.into_iter().map(|(key, (ids, from_alt))| {
+                    let reason = if from_alt {
+                        DuplicateReason::AltName
+                    } else {
+                        DuplicateReason::DefaultName
+                    };
+                    (reason, key, ids)
+                }))
+                .filter(|(_, _, ids)| ids.len() > 1)
+                .map(|(reason, key, ids)| DuplicateMatch {
+                    reason,
+                    key,
+                    game_ids: ids.into_iter().collect(),
+                })
+                .collect();
+
+            // Union-find over the matches so games linked through a third
+            // one end up in the same group, the lowest id is the root
+            fn root(parent: &mut HashMap<i32, i32>, id: i32) -> i32 {
+                let mut current = id;
+                loop {
+                    let next = *parent.entry(current).or_insert(current);
+                    if next == current {
+                        break;
+                    }
+                    current = next;
+                }
+                parent.insert(id, current);
+                current
+            }
+            let mut parent: HashMap<i32, i32> = HashMap::new();
+            for duplicate in &matches {
+                let mut first = root(&mut parent, duplicate.game_ids[0]);
+                for &id in &duplicate.game_ids[1..] {
+                    let other = root(&mut parent, id);
+                    if other != first {
+                        parent.insert(first.max(other), first.min(other));
+                        first = first.min(other);
+                    }
+                }
+            }
+
+            let mut groups: BTreeMap<i32, DuplicateGroup> = BTreeMap::new();
+            for id in parent.keys().copied().collect::<Vec<_>>() {
+                let group_root = root(&mut parent, id);
+                groups
+                    .entry(group_root)
+                    .or_insert_with(|| DuplicateGroup {
+                        game_ids: Vec::new(),
+                        matches: Vec::new(),
+                    })
+                    .game_ids
+                    .push(id);
+            }
+            for duplicate in matches.drain(..) {
+                let group_root = root(&mut parent, duplicate.game_ids[0]);
+                if let Some(group) = groups.get_mut(&group_root) {
+                    group.matches.push(duplicate);
+                }
+            }
+
+            Ok(groups
+                .into_values()
+                .map(|mut group| {
+                    group.game_ids.sort_unstable();
+                    group
+                })
+                .collect())
+        })
+    }
+
+    pub fn validate_catalog(&self, rules: &CatalogRules) -> Result<Vec<ValidationIssue>, DbError> {
+        let _span = debug_span!("validate_catalog").entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+
+            let db_games: Vec<DbGameMetadata> = game_metadata::table
+                .filter(game_metadata::is_deleted.eq(false))
+                .order(game_metadata::id.asc())
+                .select(DbGameMetadata::as_select())
+                .load(connection)?;
+            let path_rows: Vec<(i32, OS)> = game_path::table
+                .select((game_path::game_metadata_id, game_path::operating_system))
+                .load(connection)?;
+            let executable_rows: Vec<(i32, OS)> = game_executable::table
+                .select((
+                    game_executable::game_metadata_id,
+                    game_executable::operating_system,
+                ))
+                .load(connection)?;
+
+            let mut path_os: HashMap<i32, Vec<OS>> = HashMap::new();
+            for (game_id, os) in path_rows {
+                path_os.entry(game_id).or_default().push(os);
+            }
+            let mut executable_os: HashMap<i32, Vec<OS>> = HashMap::new();
+            for (game_id, os) in executable_rows {
+                executable_os.entry(game_id).or_default().push(os);
+            }
+
+            let mut issues = Vec::new();
+            for db_game in db_games {
+                let mut kinds = Vec::new();
+                if db_game.default_name.trim().is_empty() {
+                    kinds.push(ValidationIssueKind::EmptyDefaultName);
+                }
+
+                let paths = db_game.id.and_then(|id| path_os.get(&id));
+                match paths {
+                    None => kinds.push(ValidationIssueKind::NoPath),
+                    Some(paths) if paths.iter().any(|os| matches!(os, OS::Unknown(_))) => {
+                        kinds.push(ValidationIssueKind::UnknownPathOs)
+                    }
+                    Some(_) => {}
+                }
+                if db_game
+                    .id
+                    .and_then(|id| executable_os.get(&id))
+                    .is_some_and(|executables| {
+                        executables.iter().any(|os| matches!(os, OS::Unknown(_)))
+                    })
+                {
+                    kinds.push(ValidationIssueKind::UnknownExecutableOs);
+                }
+
+                if rules.require_steam_appid
+                    && db_game
+                        .steam_appid
+                        .as_deref()
+                        .is_none_or(|appid| appid.trim().is_empty())
+                {
+                    kinds.push(ValidationIssueKind::MissingSteamAppid);
+                }
+
+                issues.extend(kinds.into_iter().map(|kind| ValidationIssue {
+                    game_id: db_game.id,
+                    game_name: db_game.default_name.clone(),
+                    kind,
+                }));
+            }
+
+            Ok(issues)
+        })
     }
 }
@@ -0,0 +1,175 @@
+use crate::blob_store::BlobStore;
+use crate::const_var::{MAX_SAVES_PER_PATH, REBUILD_DERIVED_BATCH_SIZE, SAVE_DIR};
+use crate::database::error::DbError;
+use crate::database::interface::{GameDatabase, canonical_game_name_sql};
+use crate::database::schema::game_metadata;
+use crate::database::timeout::Unbounded;
+use crate::database::trace::traced;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use serde::Serialize;
+use tracing::{debug_span, info};
+
+/// Which housekeeping jobs `run_maintenance` runs, everything by default
+#[derive(Clone, Copy, Debug)]
+pub struct MaintenanceOpts {
+    /// Saves kept per path of the games without a retention policy, `None`
+    /// skips pruning
+    pub keep_latest: Option<usize>,
+    pub gc_blobs: bool,
+    /// Run `PRAGMA optimize` and `VACUUM` last so the pages freed by the
+    /// other jobs are given back
+    pub optimize: bool,
+    /// Report what pruning and collection would remove without removing
+    /// it, the database is not optimized either
+    pub dry_run: bool,
+}
+
+impl Default for MaintenanceOpts {
+    fn default() -> Self {
+        Self {
+            keep_latest: Some(MAX_SAVES_PER_PATH),
+            gc_blobs: true,
+            optimize: true,
+            dry_run: false,
+        }
+    }
+}
+
+/// Column computed from others that `rebuild_derived` recomputes
+#[derive(Clone, Copy, Debug)]
+pub enum DerivedKind {
+    /// `game_metadata.canonical_name`, from the default name
+    CanonicalName,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MaintenanceReport {
+    /// Nothing was deleted, the other fields say what would have been
+    pub dry_run: bool,
+    /// Uuids of the saves deleted by the retention pass
+    pub saves_pruned: Vec<String>,
+    /// Hashes of the unreferenced blobs removed
+    pub blobs_removed: Vec<String>,
+    /// Bytes freed by deleting save files and blobs
+    pub bytes_reclaimed: u64,
+    /// Bytes the database shrank by, `None` when it was not vacuumed
+    pub database_bytes_reclaimed: Option<u64>,
+}
+
+impl GameDatabase {
+    /// Prunes saves beyond the retention, collects unreferenced blobs and
+    /// vacuums the database, in that order. A failing job stops the run,
+    /// the jobs before it stay applied. It runs without the query timeout
+    pub fn run_maintenance(
+        &self,
+        store: &dyn BlobStore,
+        opts: MaintenanceOpts,
+    ) -> Result<MaintenanceReport, DbError> {
+        let _span = debug_span!("run_maintenance", ?opts).entered();
+        let _unbounded = Unbounded::enter();
+        traced(|| {
+            let mut report = MaintenanceReport {
+                dry_run: opts.dry_run,
+                ..MaintenanceReport::default()
+            };
+
+            let mut released_hashes = Vec::new();
+            if let Some(keep_latest) = opts.keep_latest {
+                let pruned = if opts.dry_run {
+                    self.prune_save_references_preview(keep_latest)?
+                } else {
+                    self.prune_save_references(keep_latest)?
+                };
+                report.saves_pruned = pruned.uuids;
+                released_hashes = pruned.orphaned_hashes;
+                for uuid in &report.saves_pruned {
+                    let save_path = format!("{}/{}.sav", SAVE_DIR, uuid);
+                    if let Ok(metadata) = std::fs::metadata(&save_path)
+                        && (opts.dry_run || std::fs::remove_file(&save_path).is_ok())
+                    {
+                        report.bytes_reclaimed += metadata.len();
+                    }
+                }
+            }
+
+            if opts.gc_blobs {
+                let blobs = if opts.dry_run {
+                    self.gc_unreferenced_blobs_preview(store, &released_hashes)?
+                } else {
+                    self.gc_unreferenced_blobs(store)?
+                };
+                for (hash, freed) in blobs {
+                    report.bytes_reclaimed += freed;
+                    report.blobs_removed.push(hash);
+                }
+            }
+
+            if opts.optimize && !opts.dry_run {
+                let before = self.database_size()?;
+                self.optimize()?;
+                let after = self.database_size()?;
+                report.database_bytes_reclaimed = Some(before.saturating_sub(after));
+            }
+
+            Ok(report)
+        })
+    }
+
+    /// Recomputes `which` on every row, soft deleted ones included, in
+    /// batches of `REBUILD_DERIVED_BATCH_SIZE` rows each committed on its
+    /// own. Only rows whose value differs are written, so an interrupted run
+    /// started again goes quickly over the batches it already did. Returns
+    /// how many rows changed
+    pub fn rebuild_derived(&self, which: DerivedKind) -> Result<usize, DbError> {
+        let _span = debug_span!("rebuild_derived", ?which).entered();
+        traced(|| {
+            let connection = &mut self.pool.get()?;
+            let mut after = 0;
+            let mut rebuilt = 0;
+            loop {
+                let ids: Vec<Option<i32>> = game_metadata::table
+                    .filter(game_metadata::id.gt(after))
+                    .order(game_metadata::id.asc())
+                    .select(game_metadata::id)
+                    .limit(REBUILD_DERIVED_BATCH_SIZE)
+                    .load(connection)?;
+                let Some(last) = ids.last().copied().flatten() else {
+                    return Ok(rebuilt);
+                };
+                rebuilt += self.with_retry(connection, |connection| match which {
+                    DerivedKind::CanonicalName => {
+                        let canonical = canonical_game_name_sql(game_metadata::default_name);
+                        Ok(diesel::update(
+                            game_metadata::table
+                                .filter(game_metadata::id.gt(after))
+                                .filter(game_metadata::id.le(last))
+                                .filter(game_metadata::canonical_name.is_not(canonical.nullable())),
+                        )
+                        .set(game_metadata::canonical_name.eq(canonical.nullable()))
+                        .execute(connection)?)
+                    }
+                })?;
+                after = last;
+                info!(
+                    ?which,
+                    up_to_id = last,
+                    rebuilt,
+                    "derived column batch rebuilt"
+                );
+            }
+        })
+    }
+
+    /// Size of the main database file in bytes, from its page count
+    fn database_size(&self) -> Result<u64, DbError> {
+        let connection = &mut self.pool.get()?;
+
+        let size: i64 = diesel::select(sql::<BigInt>(
+            "(SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size())",
+        ))
+        .get_result(connection)?;
+        Ok(size.max(0) as u64)
+    }
+}
@@ -1,3 +1,12 @@
+pub mod async_interface;
+pub mod bulk;
 mod datatype;
+pub mod error;
 pub mod interface;
+pub mod maintenance;
+pub mod read_only;
 mod schema;
+#[cfg(test)]
+mod tests;
+mod timeout;
+pub mod trace;
@@ -0,0 +1,214 @@
+use crate::database::error::DbError;
+use crate::database::interface::{GameDatabase, GameDatabaseConfig, MIGRATIONS};
+use crate::database::trace::traced;
+use crate::datatype_endpoint::{
+    CatalogChildChanges, Executable, GameBundle, GameMetadata, LaunchProfile, OS, ResolvedGame,
+    SavePath,
+};
+use diesel::migration::MigrationSource;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel::sqlite::Sqlite;
+use std::collections::{HashMap, HashSet};
+use tracing::debug_span;
+
+/// Catalog reads over a database file opened with SQLite's read-only flag.
+/// The wrapped `GameDatabase` is private and only reading methods are
+/// forwarded, so a write cannot even be expressed through this type
+#[derive(Clone)]
+pub struct ReadOnlyGameDatabase {
+    database: GameDatabase,
+}
+
+impl GameDatabase {
+    /// Opens `db_path` for reading only, migrations are never applied and
+    /// a file with pending ones is refused since its schema is not the one
+    /// the queries expect
+    pub fn open_read_only(db_path: &str) -> Result<ReadOnlyGameDatabase, DbError> {
+        let database = Self::with_config(GameDatabaseConfig {
+            run_migrations: false,
+            read_only: true,
+            ..GameDatabaseConfig::new(db_path)
+        })?;
+
+        let pending = pending_migrations_read_only(&database)?;
+        if !pending.is_empty() {
+            return Err(DbError::Migration(format!(
+                "{} migration(s) pending: {}",
+                pending.len(),
+                pending.join(", ")
+            )));
+        }
+        Ok(ReadOnlyGameDatabase { database })
+    }
+}
+
+#[derive(QueryableByName)]
+struct AppliedMigration {
+    #[diesel(sql_type = Text)]
+    version: String,
+}
+
+/// Same as `GameDatabase::pending_migrations` without the bookkeeping
+/// table creation the migration harness does first, which is a write
+fn pending_migrations_read_only(database: &GameDatabase) -> Result<Vec<String>, DbError> {
+    let _span = debug_span!("pending_migrations_read_only").entered();
+    traced(|| {
+        let connection = &mut database.pool.get()?;
+
+        let applied: HashSet<String> =
+            diesel::sql_query("SELECT version FROM __diesel_schema_migrations")
+                .load::<AppliedMigration>(connection)?
+                .into_iter()
+                .map(|migration| migration.version)
+                .collect();
+        let migrations = MigrationSource::<Sqlite>::migrations(&MIGRATIONS)
+            .map_err(|e| DbError::Migration(e.to_string()))?;
+        Ok(migrations
+            .iter()
+            .filter(|migration| !applied.contains(&migration.name().version().to_string()))
+            .map(|migration| migration.name().to_string())
+            .collect())
+    })
+}
+
+impl ReadOnlyGameDatabase {
+    pub fn get_game_metadata_by_name(
+        &self,
+        target_name: &str,
+    ) -> Result<Vec<GameMetadata>, DbError> {
+        self.database.get_game_metadata_by_name(target_name)
+    }
+
+    pub fn search_game_metadata(&self, query: &str) -> Result<Vec<GameMetadata>, DbError> {
+        self.database.search_game_metadata(query)
+    }
+
+    pub fn search_games_ranked(
+        &self,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<GameMetadata>, DbError> {
+        self.database.search_games_ranked(query, limit)
+    }
+
+    pub fn match_game_by_fuzzy_name(
+        &self,
+        raw_name: &str,
+        threshold: f64,
+    ) -> Result<Vec<(GameMetadata, f64)>, DbError> {
+        self.database.match_game_by_fuzzy_name(raw_name, threshold)
+    }
+
+    pub fn resolve_game(&self, any_name: &str) -> Result<Option<ResolvedGame>, DbError> {
+        self.database.resolve_game(any_name)
+    }
+
+    pub fn get_game_by_canonical_name(
+        &self,
+        raw_name: &str,
+    ) -> Result<Option<GameMetadata>, DbError> {
+        self.database.get_game_by_canonical_name(raw_name)
+    }
+
+    pub fn get_game_metadata_by_appid(&self, appid: &str) -> Result<Option<GameMetadata>, DbError> {
+        self.database.get_game_metadata_by_appid(appid)
+    }
+
+    pub fn get_game_metadata_by_id(
+        &self,
+        target_id: &i32,
+    ) -> Result<Option<GameMetadata>, DbError> {
+        self.database.get_game_metadata_by_id(target_id)
+    }
+
+    pub fn get_games_metadata_by_ids(&self, ids: &[i32]) -> Result<Vec<GameMetadata>, DbError> {
+        self.database.get_games_metadata_by_ids(ids)
+    }
+
+    pub fn get_game_bundle(&self, game_id: i32) -> Result<Option<GameBundle>, DbError> {
+        self.database.get_game_bundle(game_id)
+    }
+
+    pub fn get_launch_profile(&self, game_id: i32, os: OS) -> Result<LaunchProfile, DbError> {
+        self.database.get_launch_profile(game_id, os)
+    }
+
+    pub fn get_recently_added_games(&self, limit: i64) -> Result<Vec<GameMetadata>, DbError> {
+        self.database.get_recently_added_games(limit)
+    }
+
+    pub fn get_games_metadata(&self) -> Result<Vec<GameMetadata>, DbError> {
+        self.database.get_games_metadata()
+    }
+
+    pub fn for_each_game<F: FnMut(GameMetadata) -> Result<(), DbError>>(
+        &self,
+        f: F,
+    ) -> Result<(), DbError> {
+        self.database.for_each_game(f)
+    }
+
+    pub fn get_games_metadata_paged(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<GameMetadata>, DbError> {
+        self.database.get_games_metadata_paged(offset, limit)
+    }
+
+    pub fn count_games(&self) -> Result<i64, DbError> {
+        self.database.count_games()
+    }
+
+    pub fn get_all_appids(&self) -> Result<Vec<String>, DbError> {
+        self.database.get_all_appids()
+    }
+
+    pub fn get_games_by_steam_appids(
+        &self,
+        appids: &[String],
+    ) -> Result<HashMap<String, GameMetadata>, DbError> {
+        self.database.get_games_by_steam_appids(appids)
+    }
+
+    pub fn get_paths_by_game_id_and_os(
+        &self,
+        game_id: i32,
+        os: OS,
+    ) -> Result<Vec<String>, DbError> {
+        self.database.get_paths_by_game_id_and_os(game_id, os)
+    }
+
+    pub fn get_paths_by_game_id(&self, game_id: i32) -> Result<Vec<SavePath>, DbError> {
+        self.database.get_paths_by_game_id(game_id)
+    }
+
+    pub fn suggest_paths_for_os(
+        &self,
+        game_id: i32,
+        target_os: OS,
+    ) -> Result<Vec<String>, DbError> {
+        self.database.suggest_paths_for_os(game_id, target_os)
+    }
+
+    pub fn get_all_paths_for_os(&self, os: OS) -> Result<Vec<(i32, SavePath)>, DbError> {
+        self.database.get_all_paths_for_os(os)
+    }
+
+    pub fn get_executable_by_game_id_and_os(
+        &self,
+        game_id: i32,
+        os: OS,
+    ) -> Result<Vec<String>, DbError> {
+        self.database.get_executable_by_game_id_and_os(game_id, os)
+    }
+
+    pub fn get_executable_by_game_id(&self, game_id: i32) -> Result<Vec<Executable>, DbError> {
+        self.database.get_executable_by_game_id(game_id)
+    }
+
+    pub fn get_child_changes_since(&self, revision: i64) -> Result<CatalogChildChanges, DbError> {
+        self.database.get_child_changes_since(revision)
+    }
+}
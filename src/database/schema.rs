@@ -1,10 +1,28 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    blob (id) {
+        id -> Integer,
+        hash -> Text,
+        algo -> Text,
+        size -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    blob_chunk (blob_id, position) {
+        blob_id -> Integer,
+        position -> Integer,
+        chunk_hash -> Text,
+        chunk_size -> BigInt,
+    }
+}
+
 diesel::table! {
     file_hash (relative_path, game_save_uuid) {
         relative_path -> Text,
-        hash -> Text,
         game_save_uuid -> Text,
+        blob_id -> Integer,
     }
 }
 
@@ -21,6 +39,7 @@ diesel::table! {
         executable -> Text,
         operating_system -> Text,
         game_metadata_id -> Integer,
+        updated_at -> Timestamp,
     }
 }
 
@@ -29,6 +48,12 @@ diesel::table! {
         id -> Nullable<Integer>,
         default_name -> Text,
         steam_appid -> Nullable<Text>,
+        is_deleted -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        retention_keep -> Nullable<Integer>,
+        retention_max_age_days -> Nullable<Integer>,
+        canonical_name -> Nullable<Text>,
     }
 }
 
@@ -38,6 +63,8 @@ diesel::table! {
         path -> Text,
         operating_system -> Text,
         game_metadata_id -> Integer,
+        updated_at -> Timestamp,
+        last_generation -> BigInt,
     }
 }
 
@@ -46,20 +73,58 @@ diesel::table! {
         uuid -> Text,
         path_id -> Integer,
         time -> Timestamp,
+        user_id -> Nullable<Integer>,
+        generation -> BigInt,
+        label -> Nullable<Text>,
+        pinned -> Bool,
+        idempotency_key -> Nullable<Text>,
+        device_name -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    save_attribute (game_save_uuid, key) {
+        game_save_uuid -> Text,
+        key -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    steam_appid_conflict (game_metadata_id) {
+        game_metadata_id -> Integer,
+        steam_appid -> Text,
+    }
+}
+
+diesel::table! {
+    user (id) {
+        id -> Nullable<Integer>,
+        name -> Text,
+        token_hash -> Text,
     }
 }
 
+diesel::joinable!(blob_chunk -> blob (blob_id));
+diesel::joinable!(file_hash -> blob (blob_id));
 diesel::joinable!(file_hash -> game_save (game_save_uuid));
 diesel::joinable!(game_alt_name -> game_metadata (game_metadata_id));
 diesel::joinable!(game_executable -> game_metadata (game_metadata_id));
 diesel::joinable!(game_path -> game_metadata (game_metadata_id));
 diesel::joinable!(game_save -> game_path (path_id));
+diesel::joinable!(game_save -> user (user_id));
+diesel::joinable!(save_attribute -> game_save (game_save_uuid));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    blob,
+    blob_chunk,
     file_hash,
     game_alt_name,
     game_executable,
     game_metadata,
     game_path,
     game_save,
+    save_attribute,
+    steam_appid_conflict,
+    user,
 );
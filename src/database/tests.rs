@@ -0,0 +1,1261 @@
+use crate::blob_store::{BlobStore, FsBlobStore};
+use crate::const_var::ROLLBACK_SNAPSHOT_LABEL;
+use crate::database::error::{DbError, OpenFailure};
+use crate::database::interface::{
+    GameDatabase, GameDatabaseConfig, MIGRATIONS, QuotaPolicy, SaveInsert, register_sql_functions,
+};
+use crate::database::maintenance::DerivedKind;
+use crate::datatype_endpoint::{
+    ExecutableCreate, FileHash, GameMetadataCreate, HashAlgo, OS, ResolveOutcome, RetentionPolicy,
+    SavePathCreate, SaveReference, SyncStrategy,
+};
+use crate::file_system::hash_save_directory;
+use crate::steam::{SteamAppDetails, SteamClient};
+use diesel::connection::SimpleConnection;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+use diesel_migrations::MigrationHarness;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Fresh directory under the system temp dir for tests that need real files
+fn temp_dir() -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("gss-test-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Raw connection with the migrations before the one named `until` applied
+fn migrated_until(until: &str) -> SqliteConnection {
+    let mut connection = SqliteConnection::establish(":memory:").unwrap();
+    register_sql_functions(&mut connection).unwrap();
+    for migration in connection.pending_migrations(MIGRATIONS).unwrap() {
+        if migration.name().to_string().ends_with(until) {
+            break;
+        }
+        connection.run_migration(&migration).unwrap();
+    }
+    connection
+}
+
+fn add_game(database: &GameDatabase, name: &str) -> i32 {
+    database
+        .add_game_metadata(&GameMetadataCreate {
+            known_name: Vec::new(),
+            steam_appid: None,
+            default_name: name.to_string(),
+        })
+        .unwrap()
+        .id
+        .unwrap()
+}
+
+fn add_path(database: &GameDatabase, game_id: i32, path: &str) -> i32 {
+    database
+        .add_game_path(
+            game_id,
+            &SavePathCreate {
+                path: path.to_string(),
+                operating_system: OS::Windows,
+            },
+        )
+        .unwrap();
+    database
+        .get_paths_by_game_id(game_id)
+        .unwrap()
+        .into_iter()
+        .find(|saved| saved.path.path == path)
+        .and_then(|saved| saved.id)
+        .unwrap()
+}
+
+fn file_hash(content: &str) -> Vec<FileHash> {
+    vec![FileHash {
+        relative_path: "save.dat".to_string(),
+        hash: format!("{:0>64}", content),
+        algo: HashAlgo::default(),
+        size: Some(1),
+    }]
+}
+
+fn add_save(database: &GameDatabase, path_id: i32, idempotency_key: Option<&str>) -> Uuid {
+    add_save_of(database, path_id, "a", idempotency_key)
+}
+
+fn add_save_of(
+    database: &GameDatabase,
+    path_id: i32,
+    content: &str,
+    idempotency_key: Option<&str>,
+) -> Uuid {
+    let uuid = Uuid::new_v4();
+    database
+        .add_reference_to_save(
+            uuid,
+            path_id,
+            None,
+            file_hash(content),
+            None,
+            None,
+            idempotency_key,
+        )
+        .unwrap();
+    uuid
+}
+
+#[test]
+fn merge_renumbers_saves_on_a_shared_path() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let keep_id = add_game(&database, "Hollow Knight");
+    let merge_id = add_game(&database, "Hollow Knight GOTY");
+    let keep_path = add_path(&database, keep_id, "%APPDATA%/Hollow");
+    let merge_path = add_path(&database, merge_id, "%APPDATA%/Hollow");
+    let kept = add_save(&database, keep_path, Some("upload-1"));
+    add_save(&database, keep_path, None);
+    add_save(&database, merge_path, Some("upload-1"));
+    add_save(&database, merge_path, Some("upload-2"));
+
+    database.merge_game_metadata(keep_id, merge_id).unwrap();
+
+    let mut generations: Vec<i64> = database
+        .get_reference_to_save_by_path_id(keep_path)
+        .unwrap()
+        .unwrap()
+        .into_iter()
+        .map(|save| save.generation)
+        .collect();
+    generations.sort();
+    assert_eq!(generations, vec![1, 2, 3, 4]);
+
+    // The clashing key stays with the save already on the kept path
+    match database
+        .add_reference_to_save(
+            Uuid::new_v4(),
+            keep_path,
+            None,
+            file_hash("b"),
+            None,
+            None,
+            Some("upload-1"),
+        )
+        .unwrap()
+    {
+        SaveInsert::Replayed(save) => assert_eq!(save.uuid, kept.to_string()),
+        SaveInsert::Created(()) => panic!("upload-1 was not kept"),
+    }
+    assert!(matches!(
+        database.add_reference_to_save(
+            Uuid::new_v4(),
+            keep_path,
+            None,
+            file_hash("c"),
+            None,
+            None,
+            Some("upload-2"),
+        ),
+        Ok(SaveInsert::Replayed(_))
+    ));
+    assert!(
+        database
+            .get_game_metadata_by_id(&merge_id)
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn unknown_operating_system_keeps_the_row_and_its_value() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Celeste");
+    add_path(&database, game_id, "%APPDATA%/Celeste");
+    database
+        .pool
+        .get()
+        .unwrap()
+        .batch_execute(&format!(
+            "INSERT INTO game_path (path, operating_system, game_metadata_id, updated_at) \
+             VALUES ('~/celeste', 'freebsd', {game_id}, '2026-01-01 00:00:00')"
+        ))
+        .unwrap();
+
+    let paths = database.get_paths_by_game_id(game_id).unwrap();
+    assert_eq!(paths.len(), 2);
+    assert!(
+        paths
+            .iter()
+            .any(|path| path.path.operating_system == OS::Windows)
+    );
+    let unknown = paths
+        .iter()
+        .find(|path| path.path.operating_system == OS::Unknown("freebsd".to_string()))
+        .unwrap();
+
+    // Rewriting the row stores the raw value back
+    let path_id = unknown.id.unwrap();
+    database
+        .update_game_path(path_id, "~/Celeste", None)
+        .unwrap();
+    assert!(
+        database
+            .get_paths_by_game_id(game_id)
+            .unwrap()
+            .iter()
+            .any(|path| path.path.operating_system.as_str() == "freebsd")
+    );
+}
+
+#[test]
+fn retention_impact_follows_each_game_policy() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let strict_id = add_game(&database, "Hades");
+    let strict_path = add_path(&database, strict_id, "%APPDATA%/Hades");
+    database
+        .set_retention_policy(
+            strict_id,
+            RetentionPolicy {
+                keep: Some(1),
+                max_age_days: None,
+            },
+        )
+        .unwrap();
+    for content in ["a", "b", "c"] {
+        add_save_of(&database, strict_path, content, None);
+    }
+    let default_id = add_game(&database, "Tunic");
+    let default_path = add_path(&database, default_id, "%APPDATA%/Tunic");
+    for content in ["d", "e"] {
+        add_save_of(&database, default_path, content, None);
+    }
+
+    let impact = database.retention_impact(5).unwrap();
+    assert_eq!(impact.saves, 2);
+    assert_eq!(impact.blobs, 2);
+    assert_eq!(impact.bytes, 2);
+
+    let pruned = database.prune_save_references(5).unwrap();
+    assert_eq!(pruned.uuids.len() as u64, impact.saves);
+    assert_eq!(pruned.orphaned_hashes.len() as u64, impact.blobs);
+}
+
+#[test]
+fn steam_appid_lookup_goes_past_the_bind_variable_limit() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    database
+        .add_game_metadata(&GameMetadataCreate {
+            known_name: Vec::new(),
+            steam_appid: Some("367520".to_string()),
+            default_name: "Hollow Knight".to_string(),
+        })
+        .unwrap();
+    // Past the 250000 variables some distributions build sqlite with
+    let mut appids: Vec<String> = (1..=300_000).map(|appid| appid.to_string()).collect();
+    appids.push("367520".to_string());
+
+    let games = database.get_games_by_steam_appids(&appids).unwrap();
+    assert_eq!(games.len(), 1);
+    assert_eq!(games["367520"].metadata.default_name, "Hollow Knight");
+}
+
+#[test]
+fn blob_migration_moves_the_file_hashes_itself() {
+    let mut connection = migrated_until("_blob");
+    connection
+        .batch_execute(
+            "INSERT INTO game_metadata (id, default_name) VALUES (1, 'Celeste');
+             INSERT INTO game_path (id, path, operating_system, game_metadata_id)
+                 VALUES (1, '~/celeste', 'linux', 1);
+             INSERT INTO game_save (uuid, path_id, time, generation)
+                 VALUES ('s1', 1, '2026-01-01 00:00:00', 1), ('s2', 1, '2026-01-02 00:00:00', 2);
+             INSERT INTO file_hash (relative_path, hash, game_save_uuid, size)
+                 VALUES ('a.sav', 'aa', 's1', NULL), ('a.sav', 'aa', 's2', 10),
+                        ('b.sav', 'bb', 's2', 3);",
+        )
+        .unwrap();
+    connection.run_pending_migrations(MIGRATIONS).unwrap();
+
+    let sizes: Vec<(String, Option<i64>)> =
+        diesel::sql_query("SELECT hash, size FROM blob ORDER BY hash")
+            .load::<BlobRow>(&mut connection)
+            .unwrap()
+            .into_iter()
+            .map(|row| (row.hash, row.size))
+            .collect();
+    assert_eq!(
+        sizes,
+        vec![("aa".to_string(), Some(10)), ("bb".to_string(), Some(3))]
+    );
+    let file_hashes: i64 = diesel::select(sql::<BigInt>("(SELECT COUNT(*) FROM file_hash)"))
+        .get_result(&mut connection)
+        .unwrap();
+    assert_eq!(file_hashes, 3);
+    let leftover: i64 = diesel::select(sql::<BigInt>(
+        "(SELECT COUNT(*) FROM sqlite_master WHERE name = 'file_hash_uncompacted')",
+    ))
+    .get_result(&mut connection)
+    .unwrap();
+    assert_eq!(leftover, 0);
+}
+
+#[derive(QueryableByName)]
+struct BlobRow {
+    #[diesel(sql_type = Text)]
+    hash: String,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    size: Option<i64>,
+}
+
+#[test]
+fn canonical_name_migration_fills_existing_games() {
+    let mut connection = migrated_until("_game_canonical_name");
+    connection
+        .batch_execute(
+            "INSERT INTO game_metadata (default_name) VALUES ('The Witcher III: Wild Hunt');",
+        )
+        .unwrap();
+    connection.run_pending_migrations(MIGRATIONS).unwrap();
+
+    let canonical: Option<String> = diesel::select(sql::<Nullable<Text>>(
+        "(SELECT canonical_name FROM game_metadata)",
+    ))
+    .get_result(&mut connection)
+    .unwrap();
+    assert_eq!(canonical.as_deref(), Some("witcher 3 wild hunt"));
+}
+
+#[test]
+fn unique_appid_migration_keeps_the_dropped_appids() {
+    let mut connection = migrated_until("_unique_steam_appid");
+    connection
+        .batch_execute(
+            "INSERT INTO game_metadata (id, default_name, steam_appid)
+                 VALUES (1, 'Portal', '400'), (2, 'Portal (copy)', '400'), (3, 'Portal 2', '620');",
+        )
+        .unwrap();
+    connection.run_pending_migrations(MIGRATIONS).unwrap();
+
+    let conflicts: Vec<(i32, String)> =
+        diesel::sql_query("SELECT game_metadata_id, steam_appid FROM steam_appid_conflict")
+            .load::<AppidConflictRow>(&mut connection)
+            .unwrap()
+            .into_iter()
+            .map(|row| (row.game_metadata_id, row.steam_appid))
+            .collect();
+    assert_eq!(conflicts, vec![(2, "400".to_string())]);
+    let remaining: Option<String> = diesel::select(sql::<Nullable<Text>>(
+        "(SELECT steam_appid FROM game_metadata WHERE id = 2)",
+    ))
+    .get_result(&mut connection)
+    .unwrap();
+    assert_eq!(remaining, None);
+}
+
+#[derive(QueryableByName)]
+struct AppidConflictRow {
+    #[diesel(sql_type = Integer)]
+    game_metadata_id: i32,
+    #[diesel(sql_type = Text)]
+    steam_appid: String,
+}
+
+#[test]
+fn save_added_for_a_user_is_listed_for_that_user_only() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Stardew Valley");
+    let path_id = add_path(&database, game_id, "%APPDATA%/StardewValley");
+    let user_id = database.add_user("alice", "token-hash").unwrap();
+    let uuid = Uuid::new_v4();
+    database
+        .add_reference_to_save(
+            uuid,
+            path_id,
+            Some(user_id),
+            file_hash("a"),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    add_save(&database, path_id, None);
+
+    let saves = database.get_saves_for_user(user_id, path_id).unwrap();
+    assert_eq!(saves.len(), 1);
+    assert_eq!(saves[0].uuid, uuid.to_string());
+}
+
+#[test]
+fn duplicate_path_and_executable_are_unique_violations() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Terraria");
+    let path = SavePathCreate {
+        path: "%USERPROFILE%/Documents/My Games/Terraria".to_string(),
+        operating_system: OS::Windows,
+    };
+    database.add_game_path(game_id, &path).unwrap();
+    assert!(matches!(
+        database.add_game_path(game_id, &path),
+        Err(DbError::UniqueViolation(_))
+    ));
+
+    let executable = ExecutableCreate {
+        executable: "Terraria.exe".to_string(),
+        operating_system: OS::Windows,
+    };
+    database.add_game_executable(game_id, &executable).unwrap();
+    assert!(matches!(
+        database.add_game_executable(game_id, &executable),
+        Err(DbError::UniqueViolation(_))
+    ));
+}
+
+#[test]
+fn path_without_its_game_is_a_foreign_key_violation() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let result = database.add_game_path(
+        404,
+        &SavePathCreate {
+            path: "~/orphan".to_string(),
+            operating_system: OS::Linux,
+        },
+    );
+    assert!(matches!(result, Err(DbError::ForeignKeyViolation(_))));
+    assert!(database.get_paths_by_game_id(404).unwrap().is_empty());
+}
+
+#[test]
+fn known_names_are_trimmed_and_deduplicated_ignoring_case() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game = database
+        .add_game_metadata(&GameMetadataCreate {
+            known_name: vec![
+                "  Hollow Knight ".to_string(),
+                "HK".to_string(),
+                "hk".to_string(),
+                "   ".to_string(),
+                "Hollow Knight: Voidheart Edition".to_string(),
+                " hollow knight: voidheart edition".to_string(),
+                "".to_string(),
+            ],
+            steam_appid: None,
+            default_name: "Hollow Knight".to_string(),
+        })
+        .unwrap();
+
+    let mut stored = database
+        .get_game_metadata_by_id(&game.id.unwrap())
+        .unwrap()
+        .unwrap()
+        .metadata
+        .known_name;
+    stored.sort();
+    assert_eq!(stored, vec!["HK", "Hollow Knight: Voidheart Edition"]);
+}
+
+#[test]
+fn pinned_save_survives_pruning_down_to_one() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Celeste");
+    let path_id = add_path(&database, game_id, "%APPDATA%/Celeste");
+    let pinned = add_save_of(&database, path_id, "a", None);
+    for content in ["b", "c"] {
+        add_save_of(&database, path_id, content, None);
+    }
+    assert!(database.set_save_pinned(pinned, true).unwrap());
+
+    let pruned = database.prune_save_references(1).unwrap();
+    assert_eq!(pruned.uuids.len(), 1);
+    assert!(!pruned.uuids.contains(&pinned.to_string()));
+
+    let remaining: Vec<String> = database
+        .get_reference_to_save_by_path_id(path_id)
+        .unwrap()
+        .unwrap()
+        .into_iter()
+        .map(|save| save.uuid)
+        .collect();
+    assert_eq!(remaining.len(), 2);
+    assert!(remaining.contains(&pinned.to_string()));
+
+    // Nor does it go to a conflict resolution preferring the upload
+    let latest = add_save_of(&database, path_id, "d", None);
+    assert!(database.set_save_pinned(latest, true).unwrap());
+    for strategy in [SyncStrategy::PreferClient, SyncStrategy::PreferNewest] {
+        let outcome = resolve(
+            &database,
+            incoming_save(path_id, "e", -3600),
+            strategy,
+            None,
+        );
+        assert!(matches!(outcome, ResolveOutcome::KeptBoth { .. }));
+        let latest_uuid = database
+            .get_reference_to_save_by_path_id(path_id)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .max_by_key(|save| save.generation)
+            .unwrap()
+            .uuid;
+        assert!(
+            database
+                .set_save_pinned(Uuid::parse_str(&latest_uuid).unwrap(), true)
+                .unwrap()
+        );
+    }
+    let remaining = database
+        .get_reference_to_save_by_path_id(path_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(remaining.len(), 5);
+    assert!(remaining.iter().any(|save| save.uuid == latest.to_string()));
+}
+
+#[test]
+fn opening_a_directory_or_a_text_file_says_why() {
+    let dir = temp_dir();
+    match GameDatabase::try_new(dir.to_str().unwrap()) {
+        Err(DbError::OpenFailed {
+            reason: OpenFailure::IsDirectory,
+            ..
+        }) => {}
+        other => panic!("expected IsDirectory, got {:?}", other.map(|_| ())),
+    }
+
+    let text_file = dir.join("notes.txt");
+    std::fs::write(
+        &text_file,
+        "these are not the saves you are looking for\n".repeat(20),
+    )
+    .unwrap();
+    match GameDatabase::try_new(text_file.to_str().unwrap()) {
+        Err(DbError::OpenFailed {
+            reason: OpenFailure::NotADatabase,
+            ..
+        }) => {}
+        other => panic!("expected NotADatabase, got {:?}", other.map(|_| ())),
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn opening_read_only_says_why_like_opening_for_writes() {
+    let dir = temp_dir();
+    let missing = dir.join("missing.sqlite");
+    match GameDatabase::open_read_only(missing.to_str().unwrap()) {
+        Err(DbError::OpenFailed {
+            reason: OpenFailure::MissingFile,
+            ..
+        }) => {}
+        other => panic!("expected MissingFile, got {:?}", other.map(|_| ())),
+    }
+    assert!(!missing.exists());
+    match GameDatabase::open_read_only(dir.to_str().unwrap()) {
+        Err(DbError::OpenFailed {
+            reason: OpenFailure::IsDirectory,
+            ..
+        }) => {}
+        other => panic!("expected IsDirectory, got {:?}", other.map(|_| ())),
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn alt_name_differing_only_by_case_is_rejected() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game = database
+        .add_game_metadata(&GameMetadataCreate {
+            known_name: vec!["Portal".to_string()],
+            steam_appid: None,
+            default_name: "Portal: Still Alive".to_string(),
+        })
+        .unwrap();
+    let game_id = game.id.unwrap();
+
+    let insert = database.pool.get().unwrap().batch_execute(&format!(
+        "INSERT INTO game_alt_name (name, game_metadata_id) VALUES ('PORTAL', {game_id});"
+    ));
+    assert!(matches!(
+        insert.map_err(DbError::from),
+        Err(DbError::UniqueViolation(_))
+    ));
+
+    // Through the API the other spelling is dropped, the stored one is kept
+    let merged = database
+        .add_game_metadata(&GameMetadataCreate {
+            known_name: vec!["portal".to_string()],
+            steam_appid: None,
+            default_name: "Portal (2007)".to_string(),
+        })
+        .unwrap();
+    database
+        .merge_game_metadata(game_id, merged.id.unwrap())
+        .unwrap();
+    let stored = database
+        .get_game_metadata_by_id(&game_id)
+        .unwrap()
+        .unwrap()
+        .metadata
+        .known_name;
+    assert_eq!(
+        stored
+            .iter()
+            .filter(|name| name.eq_ignore_ascii_case("portal"))
+            .collect::<Vec<_>>(),
+        vec!["Portal"]
+    );
+}
+
+#[test]
+fn paths_and_executables_list_in_insertion_order() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Stardew Valley");
+    // Added within the same second and against the alphabetical order the
+    // unique indexes would give
+    let names = ["zeta", "mid", "alpha", "beta"];
+    for name in names {
+        add_path(&database, game_id, name);
+        database
+            .add_game_executable(
+                game_id,
+                &ExecutableCreate {
+                    executable: format!("{name}.exe"),
+                    operating_system: OS::Windows,
+                },
+            )
+            .unwrap();
+    }
+
+    for _ in 0..3 {
+        let paths: Vec<String> = database
+            .get_paths_by_game_id(game_id)
+            .unwrap()
+            .into_iter()
+            .map(|saved| saved.path.path)
+            .collect();
+        assert_eq!(paths, names);
+        let executables: Vec<String> = database
+            .get_executable_by_game_id(game_id)
+            .unwrap()
+            .into_iter()
+            .map(|saved| saved.executable.executable)
+            .collect();
+        assert_eq!(executables, names.map(|name| format!("{name}.exe")));
+    }
+}
+
+#[test]
+fn quota_only_counts_and_prunes_the_uploaders_saves() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Dead Cells");
+    let path_id = add_path(&database, game_id, "%APPDATA%/DeadCells");
+    let alice = database.add_user("alice", "alice-hash").unwrap();
+    let bob = database.add_user("bob", "bob-hash").unwrap();
+    let upload = |user_id, content| match database
+        .add_reference_to_save_with_quota(
+            Uuid::new_v4(),
+            path_id,
+            Some(user_id),
+            file_hash(content),
+            None,
+            None,
+            2,
+            QuotaPolicy::PruneOldest,
+            None,
+        )
+        .unwrap()
+    {
+        SaveInsert::Created(pruned) => pruned,
+        SaveInsert::Replayed(_) => panic!("no idempotency key was sent"),
+    };
+    upload(alice, "a");
+    upload(alice, "b");
+    assert!(upload(bob, "c").uuids.is_empty());
+    assert!(upload(bob, "d").uuids.is_empty());
+
+    let pruned = upload(bob, "e");
+    assert_eq!(pruned.uuids.len(), 1);
+    assert_eq!(pruned.orphaned_hashes.len(), 1);
+    assert_eq!(
+        database.get_saves_for_user(alice, path_id).unwrap().len(),
+        2
+    );
+    assert_eq!(database.get_saves_for_user(bob, path_id).unwrap().len(), 2);
+}
+
+#[test]
+fn generation_is_not_reused_after_deleting_the_latest_save() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Hades");
+    let path_id = add_path(&database, game_id, "%APPDATA%/Hades");
+    let generation_of = |uuid: Uuid| {
+        database
+            .get_reference_to_save_by_path_id(path_id)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .find(|save| save.uuid == uuid.to_string())
+            .unwrap()
+            .generation
+    };
+    add_save_of(&database, path_id, "a", None);
+    let latest = add_save_of(&database, path_id, "b", None);
+    assert_eq!(generation_of(latest), 2);
+
+    database.delete_save_reference(latest).unwrap();
+    let next = add_save_of(&database, path_id, "c", None);
+    assert_eq!(generation_of(next), 3);
+    assert_eq!(
+        database
+            .get_saves_since_generation(path_id, None, 2)
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // Not even an emptied path starts over
+    for save in database
+        .get_reference_to_save_by_path_id(path_id)
+        .unwrap()
+        .unwrap()
+    {
+        database
+            .delete_save_reference(Uuid::parse_str(&save.uuid).unwrap())
+            .unwrap();
+    }
+    let after_empty = add_save_of(&database, path_id, "d", None);
+    assert_eq!(generation_of(after_empty), 4);
+}
+
+#[test]
+fn generation_migration_starts_the_counter_at_the_stored_maximum() {
+    let mut connection = migrated_until("game_path_last_generation");
+    connection
+        .batch_execute(
+            "INSERT INTO game_metadata (id, default_name, canonical_name) VALUES (1, 'Hades', 'hades');
+             INSERT INTO game_path (id, path, operating_system, game_metadata_id) VALUES
+                 (1, 'a', 'windows', 1), (2, 'b', 'windows', 1);
+             INSERT INTO game_save (uuid, path_id, time, generation) VALUES
+                 ('00000000-0000-0000-0000-000000000001', 1, '2026-01-01 00:00:00', 1),
+                 ('00000000-0000-0000-0000-000000000002', 1, '2026-01-02 00:00:00', 5);",
+        )
+        .unwrap();
+    connection.run_pending_migrations(MIGRATIONS).unwrap();
+
+    let counters: Vec<i64> = sql::<BigInt>("SELECT last_generation FROM game_path ORDER BY id")
+        .load(&mut connection)
+        .unwrap();
+    assert_eq!(counters, vec![5, 0]);
+}
+
+/// Upload of `content` to `path_id` the client wrote `seconds_ago`
+fn incoming_save(path_id: i32, content: &str, seconds_ago: i64) -> SaveReference {
+    SaveReference {
+        uuid: Uuid::new_v4().to_string(),
+        path_id,
+        time: time::OffsetDateTime::now_utc() - time::Duration::seconds(seconds_ago),
+        files_hash: file_hash(content),
+        attributes: HashMap::new(),
+        total_size_bytes: 1,
+        generation: 0,
+        label: None,
+        pinned: false,
+        device_name: None,
+    }
+}
+
+fn resolve(
+    database: &GameDatabase,
+    incoming: SaveReference,
+    strategy: SyncStrategy,
+    user_id: Option<i32>,
+) -> ResolveOutcome {
+    let path_id = incoming.path_id;
+    match database
+        .resolve_upload(path_id, incoming, None, strategy, user_id, None)
+        .unwrap()
+    {
+        SaveInsert::Created(outcome) => outcome,
+        SaveInsert::Replayed(_) => panic!("no idempotency key was sent"),
+    }
+}
+
+#[test]
+fn prefer_newest_compares_the_clients_save_time() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Hades");
+    let path_id = add_path(&database, game_id, "%APPDATA%/Hades");
+    let latest = add_save_of(&database, path_id, "a", None);
+
+    let older = resolve(
+        &database,
+        incoming_save(path_id, "b", 3600),
+        SyncStrategy::PreferNewest,
+        None,
+    );
+    assert_eq!(
+        older,
+        ResolveOutcome::Rejected {
+            latest_uuid: latest.to_string()
+        }
+    );
+
+    let newer = incoming_save(path_id, "c", -3600);
+    let newer_uuid = newer.uuid.clone();
+    match resolve(&database, newer, SyncStrategy::PreferNewest, None) {
+        ResolveOutcome::Replaced {
+            uuid,
+            replaced_uuid,
+            orphaned_hashes,
+        } => {
+            assert_eq!(uuid, newer_uuid);
+            assert_eq!(replaced_uuid, latest.to_string());
+            assert_eq!(orphaned_hashes, vec![format!("{:0>64}", "a")]);
+        }
+        other => panic!("expected Replaced, got {other:?}"),
+    }
+}
+
+#[test]
+fn prefer_client_leaves_other_users_saves_alone() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Hades");
+    let path_id = add_path(&database, game_id, "%APPDATA%/Hades");
+    let alice = database.add_user("alice", "alice-hash").unwrap();
+    let bob = database.add_user("bob", "bob-hash").unwrap();
+    let alices = Uuid::new_v4();
+    database
+        .add_reference_to_save(
+            alices,
+            path_id,
+            Some(alice),
+            file_hash("a"),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+    let outcome = resolve(
+        &database,
+        incoming_save(path_id, "b", 0),
+        SyncStrategy::PreferClient,
+        Some(bob),
+    );
+    assert!(matches!(outcome, ResolveOutcome::Stored { .. }));
+    assert_eq!(
+        database.get_saves_for_user(alice, path_id).unwrap()[0].uuid,
+        alices.to_string()
+    );
+}
+
+/// Relative path and hash of every file of a save or directory, in order
+fn listing(files_hash: &[FileHash]) -> Vec<(String, String)> {
+    let mut listing: Vec<(String, String)> = files_hash
+        .iter()
+        .map(|file| (file.relative_path.clone(), file.hash.clone()))
+        .collect();
+    listing.sort();
+    listing
+}
+
+#[test]
+fn rollback_leaves_exactly_the_target_save() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Celeste");
+    let path_id = add_path(&database, game_id, "%APPDATA%/Celeste");
+    let root = temp_dir();
+    let store = FsBlobStore::new(root.join("blobs"));
+    let live = root.join("live");
+    std::fs::create_dir_all(live.join("slots")).unwrap();
+    std::fs::write(live.join("settings.ini"), b"volume=5").unwrap();
+    std::fs::write(live.join("slots/0.sav"), b"chapter 1").unwrap();
+    std::fs::write(live.join("slots/1.sav"), b"chapter 2").unwrap();
+    let target = database.snapshot_directory(path_id, &live, &store).unwrap();
+
+    std::fs::write(live.join("settings.ini"), b"volume=9").unwrap();
+    std::fs::remove_file(live.join("slots/1.sav")).unwrap();
+    std::fs::write(live.join("slots/2.sav"), b"chapter 7").unwrap();
+    std::fs::create_dir_all(live.join("mods/skins")).unwrap();
+    std::fs::write(live.join("mods/skins/madeline.png"), b"png").unwrap();
+
+    let before = database
+        .rollback_to_save(Uuid::parse_str(&target.uuid).unwrap(), &store, &live)
+        .unwrap();
+    assert_eq!(before.label.as_deref(), Some(ROLLBACK_SNAPSHOT_LABEL));
+    assert_eq!(before.files_hash.len(), 4);
+    assert_eq!(
+        listing(&hash_save_directory(&live).unwrap()),
+        listing(&target.files_hash)
+    );
+    assert!(!live.join("mods").exists());
+    assert_eq!(
+        std::fs::read(live.join("settings.ini")).unwrap(),
+        b"volume=5"
+    );
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn user_listings_and_idempotency_keys_stay_with_their_user() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Stardew Valley");
+    let path_id = add_path(&database, game_id, "%APPDATA%/StardewValley");
+    let alice = database.add_user("alice", "alice-hash").unwrap();
+    let bob = database.add_user("bob", "bob-hash").unwrap();
+    let upload = |user_id, content| {
+        database
+            .add_reference_to_save(
+                Uuid::new_v4(),
+                path_id,
+                Some(user_id),
+                file_hash(content),
+                None,
+                None,
+                Some("upload-1"),
+            )
+            .unwrap()
+    };
+    assert!(matches!(upload(alice, "a"), SaveInsert::Created(())));
+    // Another user's key is not a replay
+    assert!(matches!(upload(bob, "b"), SaveInsert::Created(())));
+    let alices = match upload(alice, "c") {
+        SaveInsert::Replayed(save) => save.uuid,
+        SaveInsert::Created(()) => panic!("alice's retry was stored again"),
+    };
+
+    let summaries = database.list_save_summaries(path_id, Some(alice)).unwrap();
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].uuid, alices);
+    assert_eq!(
+        database.list_save_summaries(path_id, None).unwrap().len(),
+        2
+    );
+    assert_eq!(
+        database.count_saves_for_path(path_id, Some(bob)).unwrap(),
+        1
+    );
+    let bobs = database
+        .get_latest_save_reference_for_path(path_id, Some(bob))
+        .unwrap()
+        .unwrap()
+        .uuid;
+    assert_ne!(bobs, alices);
+    assert_eq!(
+        database
+            .get_saves_since_generation(path_id, Some(alice), 0)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        database
+            .get_save_owner(Uuid::parse_str(&bobs).unwrap())
+            .unwrap(),
+        Some(Some(bob))
+    );
+    assert_eq!(database.get_save_owner(Uuid::new_v4()).unwrap(), None);
+}
+
+#[test]
+fn chunked_file_round_trips_through_the_store() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Factorio");
+    let path_id = add_path(&database, game_id, "%APPDATA%/Factorio/saves");
+    let root = temp_dir();
+    let store = FsBlobStore::new(root.join("blobs")).with_chunk_threshold(Some(1));
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let content: Vec<u8> = (0..3 * 1024 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 56) as u8
+        })
+        .collect();
+
+    let file = store.put_chunked(&mut content.as_slice()).unwrap();
+    assert!(file.chunks.len() > 1);
+    assert!(!store.exists(&file.hash).unwrap());
+    database.record_chunked_file(&file, &store).unwrap();
+    let recorded = database.get_chunked_file(&file.hash).unwrap().unwrap();
+    let chunk_hashes: Vec<String> = recorded
+        .chunks
+        .into_iter()
+        .map(|chunk| chunk.hash)
+        .collect();
+    assert_eq!(
+        chunk_hashes,
+        file.chunks
+            .iter()
+            .map(|chunk| chunk.hash.clone())
+            .collect::<Vec<_>>()
+    );
+    let mut streamed = Vec::new();
+    std::io::Read::read_to_end(
+        &mut (&store as &dyn BlobStore).get_chunks_stream(chunk_hashes),
+        &mut streamed,
+    )
+    .unwrap();
+    assert_eq!(streamed, content);
+
+    let uuid = Uuid::new_v4();
+    database
+        .add_reference_to_save(
+            uuid,
+            path_id,
+            None,
+            vec![FileHash {
+                relative_path: "world.zip".to_string(),
+                hash: file.hash.clone(),
+                algo: HashAlgo::Sha256,
+                size: Some(file.size),
+            }],
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    let live = root.join("live");
+    database.restore_save(uuid, &store, &live).unwrap();
+    assert_eq!(std::fs::read(live.join("world.zip")).unwrap(), content);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// Canned Steam store, each answer checks the database can still be queried
+/// so the single connection of an in-memory pool was handed back first
+struct FakeSteam<'a> {
+    database: &'a GameDatabase,
+    details: Option<SteamAppDetails>,
+    asked: Cell<usize>,
+}
+
+impl SteamClient for FakeSteam<'_> {
+    fn app_details(&self, _appid: &str) -> std::io::Result<Option<SteamAppDetails>> {
+        self.asked.set(self.asked.get() + 1);
+        self.database.get_games_metadata().unwrap();
+        Ok(self.details.clone())
+    }
+}
+
+#[test]
+fn steam_enrichment_renames_and_keeps_the_old_name() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = database
+        .add_game_metadata(&GameMetadataCreate {
+            known_name: vec!["Portal Two".to_string()],
+            steam_appid: Some("620".to_string()),
+            default_name: "portal 2 rough".to_string(),
+        })
+        .unwrap()
+        .id
+        .unwrap();
+    let steam = FakeSteam {
+        database: &database,
+        details: Some(SteamAppDetails {
+            name: "Portal 2".to_string(),
+            localized_names: vec![
+                "Portal 2".to_string(),
+                "Портал 2".to_string(),
+                "portal two".to_string(),
+                "Портал 2".to_string(),
+            ],
+        }),
+        asked: Cell::new(0),
+    };
+
+    let enriched = database.enrich_from_steam(game_id, &steam).unwrap();
+    assert_eq!(steam.asked.get(), 1);
+    assert_eq!(enriched.metadata.default_name, "Portal 2");
+    let mut known_name = enriched.metadata.known_name;
+    known_name.sort();
+    assert_eq!(known_name, ["Portal Two", "portal 2 rough", "Портал 2"]);
+    assert_eq!(
+        database
+            .get_game_metadata_by_id(&game_id)
+            .unwrap()
+            .unwrap()
+            .metadata
+            .default_name,
+        "Portal 2"
+    );
+}
+
+#[test]
+fn steam_enrichment_skips_games_without_an_appid() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Untitled Goose Game");
+    let steam = FakeSteam {
+        database: &database,
+        details: Some(SteamAppDetails {
+            name: "Wrong Game".to_string(),
+            localized_names: Vec::new(),
+        }),
+        asked: Cell::new(0),
+    };
+
+    let unchanged = database.enrich_from_steam(game_id, &steam).unwrap();
+    assert_eq!(steam.asked.get(), 0);
+    assert_eq!(unchanged.metadata.default_name, "Untitled Goose Game");
+    assert!(unchanged.metadata.known_name.is_empty());
+}
+
+#[test]
+fn rebuild_derived_only_rewrites_stale_canonical_names() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let celeste = add_game(&database, "Celeste: Deluxe Edition");
+    let hades = add_game(&database, "HADES");
+    let knight = add_game(&database, "Hollow Knight");
+    database
+        .pool
+        .get()
+        .unwrap()
+        .batch_execute(&format!(
+            "UPDATE game_metadata SET canonical_name = NULL WHERE id = {celeste};
+             UPDATE game_metadata SET canonical_name = 'stale' WHERE id = {hades};"
+        ))
+        .unwrap();
+
+    assert_eq!(
+        database
+            .rebuild_derived(DerivedKind::CanonicalName)
+            .unwrap(),
+        2
+    );
+    let canonical = |id: i32| -> Option<String> {
+        diesel::select(sql::<Nullable<Text>>(&format!(
+            "(SELECT canonical_name FROM game_metadata WHERE id = {id})"
+        )))
+        .get_result(&mut database.pool.get().unwrap())
+        .unwrap()
+    };
+    assert_eq!(canonical(celeste).as_deref(), Some("celeste"));
+    assert_eq!(canonical(hades).as_deref(), Some("hades"));
+    assert_eq!(canonical(knight).as_deref(), Some("hollow knight"));
+    // Nothing is stale anymore, running it again changes nothing
+    assert_eq!(
+        database
+            .rebuild_derived(DerivedKind::CanonicalName)
+            .unwrap(),
+        0
+    );
+}
+
+#[test]
+fn batched_listings_keep_each_saves_own_files() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Terraria");
+    let path_id = add_path(
+        &database,
+        game_id,
+        "%USERPROFILE%/Documents/My Games/Terraria",
+    );
+    let shared = || FileHash {
+        relative_path: "config.json".to_string(),
+        ..file_hash("c").remove(0)
+    };
+    let mut uuids = Vec::new();
+    for content in ["a", "b"] {
+        let uuid = Uuid::new_v4();
+        let mut files = file_hash(content);
+        files.push(shared());
+        database
+            .add_reference_to_save(uuid, path_id, None, files, None, None, None)
+            .unwrap();
+        uuids.push(uuid.to_string());
+    }
+
+    let since = database
+        .get_saves_since_generation(path_id, None, 0)
+        .unwrap();
+    assert_eq!(
+        since
+            .iter()
+            .map(|save| save.uuid.clone())
+            .collect::<Vec<_>>(),
+        uuids
+    );
+    for (save, content) in since.iter().zip(["a", "b"]) {
+        assert_eq!(
+            listing(&save.files_hash),
+            listing(&[shared(), file_hash(content).remove(0)])
+        );
+    }
+
+    let containing = database
+        .get_saves_containing_hash(&shared().hash, 10)
+        .unwrap();
+    assert_eq!(containing.len(), 2);
+    for activity in &containing {
+        assert_eq!(activity.path, "%USERPROFILE%/Documents/My Games/Terraria");
+        assert_eq!(activity.game_id, Some(game_id));
+        assert_eq!(activity.save.files_hash.len(), 2);
+    }
+}
+
+#[test]
+fn bulk_import_runs_past_the_query_timeout() {
+    let database = GameDatabase::with_config(GameDatabaseConfig {
+        max_size: 1,
+        min_idle: Some(1),
+        query_timeout: Some(Duration::from_millis(50)),
+        ..GameDatabaseConfig::new(":memory:")
+    })
+    .unwrap();
+    let started = Instant::now();
+    let imported = database
+        .bulk_import(|import| {
+            (0..1000)
+                .map(|i| {
+                    import.add_complete_game(
+                        &GameMetadataCreate {
+                            known_name: Vec::new(),
+                            steam_appid: None,
+                            default_name: format!("Imported Game {i}"),
+                        },
+                        &[],
+                        &[],
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .unwrap();
+    assert_eq!(imported.len(), 1000);
+    // Only meaningful when the import outlasted the timeout it is exempt from
+    assert!(started.elapsed() > Duration::from_millis(50));
+    assert_eq!(database.get_games_metadata().unwrap().len(), 1000);
+}
+
+#[test]
+fn full_rewrite_is_reported_at_the_save_that_shares_nothing() {
+    let database = GameDatabase::new_in_memory().unwrap();
+    let game_id = add_game(&database, "Baldur's Gate 3");
+    let path_id = add_path(&database, game_id, "%LOCALAPPDATA%/Larian Studios");
+    let mut uuids = Vec::new();
+    for contents in [["a", "b"], ["c", "d"], ["d", "e"]] {
+        let mut save = incoming_save(path_id, "", 0);
+        save.files_hash = contents
+            .iter()
+            .map(|content| FileHash {
+                relative_path: format!("{content}.lsv"),
+                ..file_hash(content).remove(0)
+            })
+            .collect();
+        uuids.push(save.uuid.clone());
+        resolve(&database, save, SyncStrategy::KeepBoth, None);
+    }
+    // The server stamps the time a save is stored with
+    let rewriting = database
+        .get_saves_since_generation(path_id, None, 1)
+        .unwrap()
+        .remove(0);
+
+    let rewrites = database.detect_full_rewrites(path_id, None).unwrap();
+    assert_eq!(rewrites.len(), 1);
+    assert_eq!(rewrites[0].previous_uuid, uuids[0]);
+    assert_eq!(rewrites[0].uuid, uuids[1]);
+    assert_eq!(rewriting.uuid, uuids[1]);
+    assert_eq!(rewrites[0].time, rewriting.time);
+    let json = serde_json::to_value(&rewrites[0]).unwrap();
+    assert_eq!(json["time"], rewriting.time.unix_timestamp());
+}
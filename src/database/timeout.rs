@@ -0,0 +1,162 @@
+use libsqlite3_sys as ffi;
+use std::cell::Cell;
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::Once;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// SQLite virtual machine instructions run between two deadline checks
+const PROGRESS_HANDLER_OPS: c_int = 1000;
+
+thread_local! {
+    /// Connections the thread has checked out with the deadline of the
+    /// first one, a nested checkout keeps the deadline it started under
+    static DEADLINE: Cell<(u32, Option<Instant>)> = const { Cell::new((0, None)) };
+}
+
+static INSTALL: Once = Once::new();
+
+/// Makes every connection opened from now on check the deadline of its
+/// thread while a statement runs, and abort it once the deadline passed.
+/// SQLite has no statement timeout and diesel does not expose the
+/// connection handle, so the progress handler is set by an auto extension,
+/// which SQLite runs on each connection it opens
+pub(crate) fn install_interrupt_handler() {
+    INSTALL.call_once(|| {
+        // SAFETY: the entry point has the signature SQLite calls it with
+        let rc = unsafe { ffi::sqlite3_auto_extension(Some(register_progress_handler)) };
+        if rc != ffi::SQLITE_OK {
+            warn!(
+                rc,
+                "could not install the query timeout, queries run unbounded"
+            );
+        }
+    });
+}
+
+unsafe extern "C" fn register_progress_handler(
+    db: *mut ffi::sqlite3,
+    _error_message: *mut *mut c_char,
+    _api: *const ffi::sqlite3_api_routines,
+) -> c_int {
+    // SAFETY: SQLite passes the connection it is opening, still valid here
+    unsafe {
+        ffi::sqlite3_progress_handler(
+            db,
+            PROGRESS_HANDLER_OPS,
+            Some(check_deadline),
+            std::ptr::null_mut(),
+        )
+    };
+    ffi::SQLITE_OK
+}
+
+/// Non-zero makes SQLite abort the running statement as `interrupted`
+unsafe extern "C" fn check_deadline(_context: *mut c_void) -> c_int {
+    let (_, deadline) = DEADLINE.get();
+    c_int::from(deadline.is_some_and(|deadline| Instant::now() >= deadline))
+}
+
+/// Starts the deadline of the operation running on this thread when it
+/// checks out its first connection
+pub(crate) fn enter(timeout: Duration) {
+    let (depth, deadline) = DEADLINE.get();
+    let deadline = if depth == 0 {
+        Some(Instant::now() + timeout)
+    } else {
+        deadline
+    };
+    DEADLINE.set((depth + 1, deadline));
+}
+
+/// Ends it once the last connection the thread checked out is back
+pub(crate) fn leave() {
+    let (depth, deadline) = DEADLINE.get();
+    let depth = depth.saturating_sub(1);
+    DEADLINE.set((depth, if depth == 0 { None } else { deadline }));
+}
+
+/// Lifts the deadline on this thread until it is dropped, for jobs such as
+/// maintenance that are expected to run long. Connections checked out
+/// meanwhile run unbounded, the deadline in place before comes back after
+pub(crate) struct Unbounded {
+    previous: (u32, Option<Instant>),
+}
+
+impl Unbounded {
+    pub(crate) fn enter() -> Self {
+        let previous = DEADLINE.get();
+        DEADLINE.set((previous.0 + 1, None));
+        Self { previous }
+    }
+}
+
+impl Drop for Unbounded {
+    fn drop(&mut self) {
+        DEADLINE.set(self.previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::error::DbError;
+    use crate::database::interface::{GameDatabase, GameDatabaseConfig};
+    use diesel::connection::SimpleConnection;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn slow_statement_is_aborted_as_a_timeout() {
+        let database = GameDatabase::with_config(GameDatabaseConfig {
+            max_size: 1,
+            min_idle: Some(1),
+            query_timeout: Some(Duration::from_millis(50)),
+            ..GameDatabaseConfig::new(":memory:")
+        })
+        .unwrap();
+        // Counts far past what runs in 50ms, the limit only keeps a broken
+        // handler from hanging the test
+        let slow = "WITH RECURSIVE counter(x) AS (
+                SELECT 1 UNION ALL SELECT x + 1 FROM counter LIMIT 1000000000
+            ) SELECT count(*) FROM counter;";
+
+        let started = Instant::now();
+        let result = database.pool.get().unwrap().batch_execute(slow);
+        assert!(matches!(
+            result.map_err(DbError::from),
+            Err(DbError::Timeout)
+        ));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // The next checkout starts a fresh deadline
+        database
+            .pool
+            .get()
+            .unwrap()
+            .batch_execute("SELECT 1;")
+            .unwrap();
+    }
+
+    #[test]
+    fn unbounded_job_outlives_the_deadline() {
+        let database = GameDatabase::with_config(GameDatabaseConfig {
+            max_size: 1,
+            min_idle: Some(1),
+            query_timeout: Some(Duration::from_millis(50)),
+            ..GameDatabaseConfig::new(":memory:")
+        })
+        .unwrap();
+        let slow = "WITH RECURSIVE counter(x) AS (
+                SELECT 1 UNION ALL SELECT x + 1 FROM counter LIMIT 3000000
+            ) SELECT count(*) FROM counter;";
+
+        {
+            let _unbounded = super::Unbounded::enter();
+            database.pool.get().unwrap().batch_execute(slow).unwrap();
+        }
+        let result = database.pool.get().unwrap().batch_execute(slow);
+        assert!(matches!(
+            result.map_err(DbError::from),
+            Err(DbError::Timeout)
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::database::error::DbError;
+use crate::database::interface::{PrunedSaves, SaveInsert};
+use crate::datatype_endpoint::{
+    CatalogChildChanges, ConflictStatus, GameBundle, GameMetadata, HealthStatus, ImportSummary,
+    IntegrityReport, ResolveOutcome, RetentionImpact, SaveDiff, SaveReference, StorageTotals,
+};
+use std::collections::HashMap;
+use tracing::{debug, error, warn};
+
+/// Rows a query returned or touched, reported on the success event
+pub(crate) trait RowCount {
+    fn row_count(&self) -> usize;
+}
+
+impl<T> RowCount for Vec<T> {
+    fn row_count(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: RowCount> RowCount for Option<T> {
+    fn row_count(&self) -> usize {
+        self.as_ref().map_or(0, RowCount::row_count)
+    }
+}
+
+impl<K, V> RowCount for HashMap<K, V> {
+    fn row_count(&self) -> usize {
+        self.len()
+    }
+}
+
+impl RowCount for PrunedSaves {
+    fn row_count(&self) -> usize {
+        self.uuids.len()
+    }
+}
+
+impl<T: RowCount> RowCount for SaveInsert<T> {
+    fn row_count(&self) -> usize {
+        match self {
+            SaveInsert::Created(created) => created.row_count(),
+            SaveInsert::Replayed(_) => 1,
+        }
+    }
+}
+
+impl RowCount for () {
+    fn row_count(&self) -> usize {
+        0
+    }
+}
+
+impl RowCount for bool {
+    fn row_count(&self) -> usize {
+        usize::from(*self)
+    }
+}
+
+/// One row per chunk of the list
+impl RowCount for crate::datatype_endpoint::ChunkedFile {
+    fn row_count(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+macro_rules! single_row {
+    ($($ty:ty),* $(,)?) => {
+        $(impl RowCount for $ty {
+            fn row_count(&self) -> usize {
+                1
+            }
+        })*
+    };
+}
+
+single_row!(
+    i32,
+    i64,
+    u64,
+    usize,
+    String,
+    GameMetadata,
+    GameBundle,
+    SaveReference,
+    crate::datatype_endpoint::SavePath,
+    ConflictStatus,
+    ResolveOutcome,
+    crate::datatype_endpoint::ResolvedGame,
+    SaveDiff,
+    IntegrityReport,
+    crate::datatype_endpoint::ConsistencyReport,
+    StorageTotals,
+    RetentionImpact,
+    crate::datatype_endpoint::RetentionPolicy,
+    CatalogChildChanges,
+    ImportSummary,
+    HealthStatus,
+    crate::datatype_endpoint::DbMetrics,
+    crate::datatype_endpoint::LaunchProfile,
+    crate::datatype_endpoint::SaveActivity,
+    crate::database::maintenance::MaintenanceReport,
+);
+
+/// Runs `query` and emits an event with its outcome, meant to be called
+/// inside the span of the calling method
+pub(crate) fn traced<T: RowCount>(
+    query: impl FnOnce() -> Result<T, DbError>,
+) -> Result<T, DbError> {
+    let result = query();
+    match &result {
+        Ok(value) => debug!(rows = value.row_count(), "query succeeded"),
+        Err(DbError::NotFound) => debug!("record not found"),
+        Err(
+            e @ (DbError::UniqueViolation(_)
+            | DbError::ForeignKeyViolation(_)
+            | DbError::Invalid(_)
+            | DbError::QuotaExceeded(_)
+            | DbError::Busy),
+        ) => warn!(error = %e, "query rejected"),
+        Err(e) => error!(error = %e, "query failed"),
+    }
+    result
+}
@@ -1,9 +1,18 @@
+use crate::const_var::{
+    MAX_DEVICE_NAME_LEN, MAX_IDEMPOTENCY_KEY_LEN, MAX_SAVE_ATTRIBUTE_KEY_LEN,
+    MAX_SAVE_ATTRIBUTE_VALUE_LEN, MAX_SAVE_ATTRIBUTES_PER_SAVE, MAX_SAVE_LABEL_LEN,
+};
+use const_format::concatcp;
 use diesel::backend::Backend;
 use diesel::deserialize::FromSql;
 use diesel::serialize::{Output, ToSql};
 use diesel::sql_types::Text;
 use diesel::{AsExpression, FromSqlRow, deserialize, serialize};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
 use utoipa::{IntoParams, ToSchema};
 
 #[derive(ToSchema)]
@@ -13,6 +22,16 @@ pub struct UploadedSave {
     pub file: Vec<u8>,
     #[schema(value_type = String, example = json!([{"relative_path": "file.txt", "hash": "abc123"}]))]
     pub file_hash: Vec<FileHash>,
+    /// Latest save the client synced from, only read with `strategy`
+    #[schema(value_type = Option<String>)]
+    pub base_uuid: Option<String>,
+    /// Without it the upload is always stored
+    pub strategy: Option<SyncStrategy>,
+    pub label: Option<String>,
+    /// Hostname or any name telling the client's machines apart
+    pub device_name: Option<String>,
+    /// Unix time the client wrote the save at, required by `prefer_newest`
+    pub save_time: Option<i64>,
 }
 
 #[derive(ToSchema)]
@@ -22,15 +41,159 @@ pub struct UploadedFileYaml {
     pub file: Vec<u8>,
 }
 
-#[derive(
-    Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow,
-)]
+/// Whether a client basing its upload on `base_uuid` would overwrite a
+/// save it has not seen
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ConflictStatus {
+    NoConflict,
+    /// Another client uploaded after the base, or the client has no base
+    /// while the path already holds saves
+    BaseIsStale {
+        latest_uuid: String,
+        /// Where the latest save was uploaded from, when the client said
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        latest_device_name: Option<String>,
+    },
+    /// The path has no save yet
+    NoBaseYet,
+}
+
+/// What an upload does when its base is no longer the latest save
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStrategy {
+    /// The incoming save replaces the latest one only if it is newer
+    PreferNewest,
+    /// The upload is rejected
+    PreferServer,
+    /// The incoming save replaces the latest one
+    PreferClient,
+    /// The incoming save is stored next to the latest one
+    KeepBoth,
+}
+
+impl FromStr for SyncStrategy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "prefer_newest" => Ok(SyncStrategy::PreferNewest),
+            "prefer_server" => Ok(SyncStrategy::PreferServer),
+            "prefer_client" => Ok(SyncStrategy::PreferClient),
+            "keep_both" => Ok(SyncStrategy::KeepBoth),
+            _ => Err(format!("unknown sync strategy: {value}")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, Debug)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum ResolveOutcome {
+    /// There was no conflict
+    Stored {
+        uuid: String,
+    },
+    /// The latest save was deleted in favour of the incoming one
+    Replaced {
+        uuid: String,
+        replaced_uuid: String,
+        /// Blobs only the replaced save referenced, for the caller to remove
+        #[serde(skip)]
+        orphaned_hashes: Vec<String>,
+    },
+    KeptBoth {
+        uuid: String,
+        latest_uuid: String,
+    },
+    /// Nothing was stored
+    Rejected {
+        latest_uuid: String,
+    },
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ConflictQuery {
+    /// Latest save the client synced from
+    pub base_uuid: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Default)]
+pub struct ImportSummary {
+    pub inserted: usize,
+    /// Games already present by steam appid, their missing paths and
+    /// executables are still added
+    pub skipped: usize,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq, Hash, AsExpression, FromSqlRow)]
 #[diesel(sql_type = Text)]
 #[serde(rename_all = "lowercase")]
 pub enum OS {
     Windows,
     Linux,
+    Mac,
+    /// SteamOS, a Linux host running Windows games through Proton
+    SteamDeck,
     Undefined,
+    /// Value written by a newer version of the server, the raw text is kept
+    /// so the row still loads and a rewrite stores it back unchanged
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl OS {
+    /// Values a client may send, `Unknown` only ever comes from the database
+    pub const ACCEPTED: [OS; 5] = [
+        OS::Windows,
+        OS::Linux,
+        OS::Mac,
+        OS::SteamDeck,
+        OS::Undefined,
+    ];
+
+    /// Stored text representation, new variants only ever add values
+    pub fn as_str(&self) -> &str {
+        match self {
+            OS::Windows => "windows",
+            OS::Linux => "linux",
+            OS::Mac => "mac",
+            OS::SteamDeck => "steamdeck",
+            OS::Undefined => "undefined",
+            OS::Unknown(raw) => raw,
+        }
+    }
+}
+
+impl fmt::Display for OS {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Case-insensitive and limited to `OS::ACCEPTED`, the error lists the valid values
+impl FromStr for OS {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OS::ACCEPTED
+            .into_iter()
+            .find(|os| os.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let valid: Vec<&str> = OS::ACCEPTED.iter().map(OS::as_str).collect();
+                format!(
+                    "unknown operating system: {s}, expected one of {}",
+                    valid.join(", ")
+                )
+            })
+    }
+}
+
+impl<'de> Deserialize<'de> for OS {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
 }
 
 impl<DB> ToSql<Text, DB> for OS
@@ -39,11 +202,7 @@ where
     str: ToSql<Text, DB>,
 {
     fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
-        match self {
-            OS::Windows => <str as ToSql<Text, DB>>::to_sql("windows", out),
-            OS::Linux => <str as ToSql<Text, DB>>::to_sql("linux", out),
-            OS::Undefined => <str as ToSql<Text, DB>>::to_sql("undefined", out),
-        }
+        <str as ToSql<Text, DB>>::to_sql(self.as_str(), out)
     }
 }
 
@@ -54,11 +213,12 @@ where
 {
     fn from_sql(bytes: <DB as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
         let s = <String as FromSql<Text, DB>>::from_sql(bytes)?;
-        match s.as_str() {
-            "windows" => Ok(OS::Windows),
-            "linux" => Ok(OS::Linux),
-            "undefined" => Ok(OS::Undefined),
-            other => Err(format!("invalid OS value in the database: {other}").into()),
+        match OS::ACCEPTED.into_iter().find(|os| os.as_str() == s) {
+            Some(os) => Ok(os),
+            None => {
+                tracing::warn!(value = %s, "unknown operating system in the database");
+                Ok(OS::Unknown(s))
+            }
         }
     }
 }
@@ -70,11 +230,28 @@ pub struct SavePathCreate {
 }
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct SavePath {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<i32>,
     #[serde(flatten)]
     pub path: SavePathCreate,
 }
 
+/// Fix for the template of a stored path, the saves of the path keep it
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SavePathUpdate {
+    pub path: String,
+    /// Keeps the current operating system when absent
+    #[serde(default)]
+    pub operating_system: Option<OS>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GameSavePath {
+    pub game_id: i32,
+    #[serde(flatten)]
+    pub path: SavePath,
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct ExecutableCreate {
     pub executable: String,
@@ -82,6 +259,7 @@ pub struct ExecutableCreate {
 }
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct Executable {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<i32>,
     #[serde(flatten)]
     pub executable: ExecutableCreate,
@@ -95,23 +273,1003 @@ pub struct GameMetadataCreate {
     pub steam_appid: Option<String>,
     pub default_name: String,
 }
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GameRename {
+    pub default_name: String,
+}
+
 #[derive(Serialize, Deserialize, ToSchema, IntoParams)]
 pub struct GameMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<i32>,
     #[serde(flatten)]
     pub metadata: GameMetadataCreate,
+    /// UTC, sent as a unix timestamp and ignored on import
+    #[serde(default, with = "time::serde::timestamp::option")]
+    #[schema(value_type = Option<i64>)]
+    #[param(value_type = Option<i64>)]
+    pub created_at: Option<time::OffsetDateTime>,
+    /// UTC, sent as a unix timestamp and ignored on import
+    #[serde(default, with = "time::serde::timestamp::option")]
+    #[schema(value_type = Option<i64>)]
+    #[param(value_type = Option<i64>)]
+    pub updated_at: Option<time::OffsetDateTime>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GameBundle {
+    pub metadata: GameMetadata,
+    pub paths: Vec<SavePath>,
+    pub executables: Vec<Executable>,
+}
+
+/// What a launcher on one operating system needs for a game
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct LaunchProfile {
+    pub paths: Vec<String>,
+    pub executables: Vec<String>,
+}
+
+#[derive(
+    Serialize,
+    Deserialize,
+    ToSchema,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    AsExpression,
+    FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgo {
+    #[default]
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgo {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgo::Sha256 => "sha256",
+            HashAlgo::Blake3 => "blake3",
+        }
+    }
+}
+
+impl<DB> ToSql<Text, DB> for HashAlgo
+where
+    DB: Backend,
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        <str as ToSql<Text, DB>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl<DB> FromSql<Text, DB> for HashAlgo
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: <DB as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        let s = <String as FromSql<Text, DB>>::from_sql(bytes)?;
+        match s.as_str() {
+            "sha256" => Ok(HashAlgo::Sha256),
+            "blake3" => Ok(HashAlgo::Blake3),
+            other => Err(format!("unknown hash algorithm: {other}").into()),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct FileHash {
     pub relative_path: String,
     pub hash: String,
+    /// Defaults to `sha256` when the client does not send it
+    #[serde(default)]
+    pub algo: HashAlgo,
+    /// Bytes of the file, unknown for saves uploaded before sizes were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// One content-defined chunk of a `ChunkedFile`, stored as its own blob
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct FileChunk {
+    /// sha256 of the chunk
+    pub hash: String,
+    pub size: u64,
+}
+
+/// A large file stored as the blobs of its chunks instead of one blob, a
+/// change to the file only stores the chunks it touched again
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct ChunkedFile {
+    /// sha256 of the whole file, what the save's `files_hash` refers to
+    pub hash: String,
+    pub size: u64,
+    /// In file order, their concatenation is the file
+    pub chunks: Vec<FileChunk>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct SaveReference {
     pub uuid: String,
     pub path_id: i32,
-    pub time: i64,
+    /// UTC, sent as a unix timestamp
+    #[serde(with = "time::serde::timestamp")]
+    #[schema(value_type = i64)]
+    pub time: time::OffsetDateTime,
+    pub files_hash: Vec<FileHash>,
+    pub attributes: HashMap<String, String>,
+    /// Sum of the known file sizes, computed by the server
+    #[serde(default)]
+    pub total_size_bytes: u64,
+    /// Position of the save in its path's history, assigned by the server
+    #[serde(default)]
+    pub generation: i64,
+    /// Free text like "before boss fight"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Kept by retention whatever its age, set through the pin endpoint
+    #[serde(default)]
+    pub pinned: bool,
+    /// Machine the save was uploaded from, as the client names it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_name: Option<String>,
+}
+
+/// One save with what identifies its game and path, carried alongside the
+/// blobs to recreate the save on another server
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SaveManifest {
+    pub uuid: String,
+    /// UTC, sent as a unix timestamp, kept as is on import
+    #[serde(with = "time::serde::timestamp")]
+    #[schema(value_type = i64)]
+    pub time: time::OffsetDateTime,
+    pub steam_appid: Option<String>,
+    pub game_name: String,
+    pub path: String,
+    pub operating_system: OS,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_name: Option<String>,
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
     pub files_hash: Vec<FileHash>,
 }
+
+/// A save without its file hashes, for listing a path's history
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SaveSummary {
+    pub uuid: String,
+    /// UTC, sent as a unix timestamp
+    #[serde(with = "time::serde::timestamp")]
+    #[schema(value_type = i64)]
+    pub time: time::OffsetDateTime,
+    pub file_count: i64,
+}
+
+impl SaveReference {
+    pub fn unix_timestamp(&self) -> i64 {
+        self.time.unix_timestamp()
+    }
+
+    /// Sum of the sizes of `files_hash`, files without a size count as empty
+    pub fn total_size(files_hash: &[FileHash]) -> u64 {
+        files_hash.iter().filter_map(|file| file.size).sum()
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SaveActivity {
+    pub game_id: Option<i32>,
+    pub game_name: String,
+    pub path: String,
+    pub operating_system: OS,
+    #[serde(flatten)]
+    pub save: SaveReference,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct FileVersion {
+    pub hash: String,
+    /// Unix time of the save holding this content
+    pub time: i64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SaveAttribute {
+    /// Namespaced key, e.g. `game.playtime`
+    pub key: String,
+    pub value: String,
+}
+
+pub fn validate_save_attribute(key: &str, value: &str) -> Result<(), String> {
+    if key.is_empty() || key.len() > MAX_SAVE_ATTRIBUTE_KEY_LEN {
+        return Err(format!(
+            "save attribute key must be between 1 and {MAX_SAVE_ATTRIBUTE_KEY_LEN} bytes"
+        ));
+    }
+    if value.len() > MAX_SAVE_ATTRIBUTE_VALUE_LEN {
+        return Err(format!(
+            "save attribute value must be at most {MAX_SAVE_ATTRIBUTE_VALUE_LEN} bytes"
+        ));
+    }
+    Ok(())
+}
+
+/// Attributes, label and device name of a save a client sends whole, as an
+/// upload or an imported manifest
+pub fn validate_save_fields(
+    attributes: &HashMap<String, String>,
+    label: Option<&str>,
+    device_name: Option<&str>,
+) -> Result<(), String> {
+    if attributes.len() > MAX_SAVE_ATTRIBUTES_PER_SAVE as usize {
+        return Err(format!(
+            "a save can hold at most {MAX_SAVE_ATTRIBUTES_PER_SAVE} attributes"
+        ));
+    }
+    for (key, value) in attributes {
+        validate_save_attribute(key, value)?;
+    }
+    if let Some(label) = label {
+        validate_save_label(label)?;
+    }
+    if let Some(device_name) = device_name {
+        validate_device_name(device_name)?;
+    }
+    Ok(())
+}
+
+pub fn validate_save_label(label: &str) -> Result<(), String> {
+    if label.trim().is_empty() || label.len() > MAX_SAVE_LABEL_LEN {
+        return Err(format!(
+            "save label must be between 1 and {MAX_SAVE_LABEL_LEN} bytes and not blank"
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_device_name(device_name: &str) -> Result<(), String> {
+    if device_name.trim().is_empty() || device_name.len() > MAX_DEVICE_NAME_LEN {
+        return Err(format!(
+            "device name must be between 1 and {MAX_DEVICE_NAME_LEN} bytes and not blank"
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_idempotency_key(key: &str) -> Result<(), String> {
+    if key.trim().is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(format!(
+            "idempotency key must be between 1 and {MAX_IDEMPOTENCY_KEY_LEN} bytes and not blank"
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_retention_policy(policy: &RetentionPolicy) -> Result<(), String> {
+    if policy.keep.is_some_and(|keep| keep < 1) {
+        return Err("retention keep must be at least 1".to_string());
+    }
+    if policy.max_age_days.is_some_and(|days| days < 1) {
+        return Err("retention max age must be at least 1 day".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SaveLabel {
+    /// `null` removes the label
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SavePinned {
+    pub pinned: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SaveReassign {
+    /// Path the save is moved to
+    pub path_id: i32,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct FullRewrite {
+    pub previous_uuid: String,
+    pub uuid: String,
+    /// UTC time of the rewriting save, sent as a unix timestamp
+    #[serde(with = "time::serde::timestamp")]
+    #[schema(value_type = i64)]
+    pub time: time::OffsetDateTime,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ChangedFile {
+    pub relative_path: String,
+    pub from_hash: String,
+    pub to_hash: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SaveDiff {
+    pub added: Vec<FileHash>,
+    pub removed: Vec<FileHash>,
+    pub changed: Vec<ChangedFile>,
+    /// A removed and an added file with the same content
+    pub renamed: Vec<RenamedFile>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct RenamedFile {
+    pub from: String,
+    pub to: String,
+    pub hash: String,
+}
+
+/// Result of checking the blob store against every `sha256` file hash
+#[derive(Serialize, Deserialize, ToSchema, Default)]
+pub struct ConsistencyReport {
+    /// Distinct hashes the saves reference
+    pub referenced: u64,
+    /// Referenced but not stored
+    pub missing_blobs: Vec<String>,
+    /// Stored but the content read back hashes to something else or does
+    /// not read back at all
+    pub corrupt_blobs: Vec<String>,
+    /// Stored, older than the gc grace period and referenced by no save
+    pub orphan_blobs: Vec<String>,
+}
+
+impl ConsistencyReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing_blobs.is_empty()
+            && self.corrupt_blobs.is_empty()
+            && self.orphan_blobs.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Default)]
+pub struct IntegrityReport {
+    pub missing: Vec<String>,
+    pub mismatched: Vec<String>,
+    /// Files on disk the save does not contain
+    pub extra: Vec<String>,
+    /// Files recorded with an algorithm the server can't compute
+    pub unverified: Vec<String>,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty()
+            && self.mismatched.is_empty()
+            && self.extra.is_empty()
+            && self.unverified.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SaveCreatedEvent {
+    pub uuid: String,
+    pub path_id: i32,
+    pub game_name: Option<String>,
+    pub time: i64,
+    pub size: u64,
+}
+
+/// Pushed to the save event subscribers of the path, the file hashes are
+/// left out, the client fetches them when it decides to download
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct SaveNotification {
+    pub uuid: String,
+    pub path_id: i32,
+    /// UTC, sent as a unix timestamp
+    #[serde(with = "time::serde::timestamp")]
+    #[schema(value_type = i64)]
+    pub time: time::OffsetDateTime,
+    pub generation: i64,
+    pub total_size_bytes: u64,
+    pub file_count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl From<&SaveReference> for SaveNotification {
+    fn from(save: &SaveReference) -> Self {
+        Self {
+            uuid: save.uuid.clone(),
+            path_id: save.path_id,
+            time: save.time,
+            generation: save.generation,
+            total_size_bytes: save.total_size_bytes,
+            file_count: save.files_hash.len(),
+            label: save.label.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SaveEventsQuery {
+    /// Only saves of this path, exclusive with `game_id`
+    pub path_id: Option<i32>,
+    /// Saves of any path of this game, exclusive with `path_id`
+    pub game_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GameStorageStat {
+    pub game_id: Option<i32>,
+    pub game_name: String,
+    pub saves: i64,
+    /// Distinct file hashes across every save, i.e. deduplicated files
+    pub distinct_hashes: i64,
+    /// Bytes of those distinct files, files uploaded without a size count
+    /// as empty
+    pub stored_bytes: i64,
+}
+
+/// How many saves of each of a game's paths are kept, stored on the game so
+/// every client prunes the same way. Pinned saves are always kept
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug, Default, PartialEq)]
+pub struct RetentionPolicy {
+    /// Saves kept per path, the server default when `null`
+    pub keep: Option<i32>,
+    /// Saves older than this are pruned too, except the newest of a path
+    pub max_age_days: Option<i32>,
+}
+
+/// A game that has saves, with how many and when the newest was made
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GameActivity {
+    pub game: GameMetadata,
+    pub save_count: i64,
+    /// UTC, sent as a unix timestamp
+    #[serde(with = "time::serde::timestamp")]
+    #[schema(value_type = i64)]
+    pub last_save_time: time::OffsetDateTime,
+}
+
+/// A stored save with where it belongs, for a server-wide audit
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct AdminSaveRow {
+    pub uuid: String,
+    pub game_name: String,
+    pub os: OS,
+    pub path: String,
+    /// UTC, sent as a unix timestamp
+    #[serde(with = "time::serde::timestamp")]
+    #[schema(value_type = i64)]
+    pub time: time::OffsetDateTime,
+    pub file_count: i64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct StorageTotals {
+    pub games: i64,
+    pub saves: i64,
+    pub distinct_hashes: i64,
+    pub stored_bytes: i64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct HealthStatus {
+    pub max_connections: u32,
+    pub idle_connections: u32,
+    pub active_connections: u32,
+    pub pending_migrations: bool,
+}
+
+impl HealthStatus {
+    pub fn is_ready(&self) -> bool {
+        !self.pending_migrations
+    }
+}
+
+/// What a client checks before syncing, it should refuse a server whose
+/// schema is older than the one it was built against
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ServerVersion {
+    /// Version of the server binary
+    pub crate_version: String,
+    /// Newest migration applied to the database, only ever grows
+    pub schema_version: i64,
+}
+
+/// Database counters for an exporter, `total_games` leaves out deleted games
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DbMetrics {
+    pub total_games: i64,
+    pub total_paths: i64,
+    pub total_saves: i64,
+    pub total_file_hashes: i64,
+    pub distinct_file_hashes: i64,
+    pub idle_connections: u32,
+    pub active_connections: u32,
+    /// Connection checkouts since startup
+    pub pool_checkouts: u64,
+    /// Checkouts that gave up after the connection timeout
+    pub pool_checkout_timeouts: u64,
+    /// Longest a checkout waited for a connection since startup
+    pub pool_max_checkout_wait_ms: f64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct RetentionImpact {
+    pub keep_latest: usize,
+    /// Save references that would be removed
+    pub saves: u64,
+    /// Blobs and chunks that would no longer be referenced
+    pub blobs: u64,
+    /// Known size of those blobs
+    pub bytes: u64,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct RetentionQuery {
+    /// Saves kept per path for the games without a retention policy
+    pub keep_latest: usize,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GameChildChanges {
+    pub game_id: i32,
+    pub paths: Vec<SavePath>,
+    pub executables: Vec<Executable>,
+}
+
+impl GameChildChanges {
+    pub fn new(game_id: i32) -> Self {
+        Self {
+            game_id,
+            paths: Vec::new(),
+            executables: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CatalogChildChanges {
+    /// Revision to send back as `since` on the next sync
+    pub revision: i64,
+    pub games: Vec<GameChildChanges>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ChangesQuery {
+    /// Unix timestamp of the last known revision
+    pub since: i64,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SearchQuery {
+    pub q: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct RankedSearchQuery {
+    pub q: String,
+    /// Defaults to 50
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ActivitySearchQuery {
+    pub q: String,
+    /// Only saves of paths for this OS count, any OS when left out
+    pub os: Option<OS>,
+    /// Defaults to 50
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct FuzzyNameQuery {
+    pub name: String,
+    /// Lowest similarity between 0 and 1 to return, defaults to 0.85
+    pub threshold: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GameNameMatch {
+    #[serde(flatten)]
+    pub game: GameMetadata,
+    /// Similarity between 0 and 1 of the best matching name
+    pub score: f64,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ResolveNameQuery {
+    /// Folder name, display string or any other name the game is known by
+    pub name: String,
+}
+
+/// Lookup of `resolve_game` that found the game, in the order they are tried
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NameResolution {
+    /// The name is the default name as is
+    DefaultName,
+    /// The name is an alternative name, ignoring ASCII case
+    AltName,
+    /// The default name equals it once both are normalized
+    CanonicalName,
+    /// Best fuzzy match of the default and alternative names
+    Fuzzy,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ResolvedGame {
+    #[serde(flatten)]
+    pub game: GameMetadata,
+    pub matched_by: NameResolution,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct PageQuery {
+    /// Every game is returned when no limit is given
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct TimeRangeQuery {
+    /// Inclusive unix timestamp
+    pub start: i64,
+    /// Inclusive unix timestamp
+    pub end: i64,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SaveCursorQuery {
+    /// `time` of the last save of the previous page
+    pub after_time: Option<i64>,
+    /// `uuid` of the last save of the previous page
+    pub after_uuid: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct GenerationQuery {
+    /// Only saves with a higher generation are returned
+    pub since: i64,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct RelativePathQuery {
+    /// Path of the file inside the save, as sent in `file_hash`
+    pub relative_path: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct OsFilterQuery {
+    /// Only rows for this OS count, any OS when left out
+    pub os: Option<OS>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct LabelQuery {
+    /// Matched anywhere in the label, ignoring ASCII case
+    pub label: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct BlobUploadQuery {
+    /// `false` stores the blob as is, for content that is already compressed
+    pub compress: Option<bool>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SavePageQuery {
+    pub offset: Option<i64>,
+    /// Defaults to 50
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct LimitQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Default)]
+pub struct CatalogRules {
+    pub require_steam_appid: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationIssueKind {
+    EmptyDefaultName,
+    NoPath,
+    UnknownPathOs,
+    UnknownExecutableOs,
+    MissingSteamAppid,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ValidationIssue {
+    pub game_id: Option<i32>,
+    pub game_name: String,
+    pub kind: ValidationIssueKind,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateReason {
+    SteamAppid,
+    /// Default names equal once normalized like the fuzzy match does
+    DefaultName,
+    /// An alternative name equal to a default or alternative name of
+    /// another game once normalized
+    AltName,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DuplicateMatch {
+    pub reason: DuplicateReason,
+    /// Shared steam appid or normalized name
+    pub key: String,
+    pub game_ids: Vec<i32>,
+}
+
+/// Games linked by at least one match, directly or through another game
+/// of the group
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DuplicateGroup {
+    /// Ascending, so the oldest game comes first
+    pub game_ids: Vec<i32>,
+    pub matches: Vec<DuplicateMatch>,
+}
+
+fn expand_windows_variables(path: &str, missing: &mut Vec<String>) -> String {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(start) = rest.find('%') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('%') else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = &after[..end];
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => expanded.push_str(&value),
+            _ if name.is_empty() => expanded.push('%'),
+            _ => {
+                missing.push(name.to_string());
+                expanded.push_str(&rest[start..start + end + 2]);
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+fn expand_unix_variables(path: &str, missing: &mut Vec<String>) -> String {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, consumed) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        if name.is_empty() {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        match std::env::var(name) {
+            Ok(value) => expanded.push_str(&value),
+            Err(_) => {
+                missing.push(name.to_string());
+                expanded.push_str(&rest[start..start + 1 + consumed]);
+            }
+        }
+        rest = &after[consumed..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Resolves `%VAR%` (Windows) and `$VAR`/`${VAR}` (Unix) tokens of a stored
+/// save path against the process environment, the error lists every
+/// variable that is not set
+pub fn expand_save_path(path: &str, os: &OS) -> Result<PathBuf, String> {
+    let mut missing = Vec::new();
+    let expanded = match os {
+        OS::Windows => expand_windows_variables(path, &mut missing),
+        OS::Linux | OS::Mac | OS::SteamDeck => expand_unix_variables(path, &mut missing),
+        OS::Undefined | OS::Unknown(_) => {
+            let expanded = expand_windows_variables(path, &mut missing);
+            expand_unix_variables(&expanded, &mut missing)
+        }
+    };
+
+    if missing.is_empty() {
+        Ok(PathBuf::from(expanded))
+    } else {
+        Err(format!(
+            "unresolved environment variables: {}",
+            missing.join(", ")
+        ))
+    }
+}
+
+const PROTON_USER_DIR: &str = "$STEAM_COMPAT_DATA_PATH/pfx/drive_c/users/steamuser";
+
+/// Equivalent locations across operating systems, used in both directions
+const SAVE_PATH_ANCHORS: &[(OS, &str, OS, &str)] = &[
+    (
+        OS::Windows,
+        "%APPDATA%",
+        OS::SteamDeck,
+        concatcp!(PROTON_USER_DIR, "/AppData/Roaming"),
+    ),
+    (
+        OS::Windows,
+        "%LOCALAPPDATA%",
+        OS::SteamDeck,
+        concatcp!(PROTON_USER_DIR, "/AppData/Local"),
+    ),
+    (
+        OS::Windows,
+        "%USERPROFILE%\\Documents",
+        OS::SteamDeck,
+        concatcp!(PROTON_USER_DIR, "/Documents"),
+    ),
+    (OS::Windows, "%USERPROFILE%", OS::SteamDeck, PROTON_USER_DIR),
+    (OS::Windows, "%USERPROFILE%", OS::Linux, "$HOME"),
+    (OS::Windows, "%USERPROFILE%", OS::Mac, "$HOME"),
+    (OS::Linux, "$HOME", OS::Mac, "$HOME"),
+    (OS::Linux, "$HOME", OS::SteamDeck, "$HOME"),
+    (OS::Mac, "$HOME", OS::SteamDeck, "$HOME"),
+];
+
+fn is_unix(os: &OS) -> bool {
+    matches!(os, OS::Linux | OS::Mac | OS::SteamDeck)
+}
+
+/// Rewrites the anchor of a stored save path (`%USERPROFILE%`, `$HOME`, the
+/// Proton prefix...) for another operating system, `None` when no anchor of
+/// the table applies
+pub fn translate_save_path(path: &str, from: &OS, to: &OS) -> Option<String> {
+    if from == to {
+        return Some(path.to_string());
+    }
+
+    let candidates = SAVE_PATH_ANCHORS
+        .iter()
+        .filter_map(|(os_a, anchor_a, os_b, anchor_b)| {
+            if (os_a, os_b) == (from, to) {
+                Some((*anchor_a, *anchor_b))
+            } else if (os_b, os_a) == (from, to) {
+                Some((*anchor_b, *anchor_a))
+            } else {
+                None
+            }
+        });
+    let (from_anchor, to_anchor) = candidates
+        .filter(|(from_anchor, _)| {
+            path.strip_prefix(from_anchor)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '\\']))
+        })
+        .max_by_key(|(from_anchor, _)| from_anchor.len())?;
+
+    let rest = &path[from_anchor.len()..];
+    let rest = match (is_unix(from), is_unix(to)) {
+        (false, true) => rest.replace('\\', "/"),
+        (true, false) => rest.replace('/', "\\"),
+        _ => rest.to_string(),
+    };
+    Some(format!("{to_anchor}{rest}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+
+    /// JSON of `value` after a trip through its own deserializer, which has
+    /// to give the same JSON back
+    fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> serde_json::Value {
+        let json = serde_json::to_value(value).unwrap();
+        let parsed: T = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+        json
+    }
+
+    fn game(id: Option<i32>) -> GameMetadata {
+        GameMetadata {
+            id,
+            metadata: GameMetadataCreate {
+                known_name: vec!["HK".to_string()],
+                steam_appid: Some("367520".to_string()),
+                default_name: "Hollow Knight".to_string(),
+            },
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn game_metadata_round_trips_and_omits_an_unset_id() {
+        assert_eq!(round_trip(&game(Some(7)))["id"], 7);
+        assert!(round_trip(&game(None)).get("id").is_none());
+    }
+
+    #[test]
+    fn save_path_round_trips_and_omits_an_unset_id() {
+        let path = |id| SavePath {
+            id,
+            path: SavePathCreate {
+                path: "%APPDATA%/Hollow".to_string(),
+                operating_system: OS::Windows,
+            },
+        };
+        let json = round_trip(&path(Some(3)));
+        assert_eq!(json["id"], 3);
+        assert_eq!(json["operating_system"], "windows");
+        assert!(round_trip(&path(None)).get("id").is_none());
+    }
+
+    #[test]
+    fn executable_round_trips_and_omits_an_unset_id() {
+        let executable = |id| Executable {
+            id,
+            executable: ExecutableCreate {
+                executable: "hollow_knight.exe".to_string(),
+                operating_system: OS::SteamDeck,
+            },
+        };
+        let json = round_trip(&executable(Some(4)));
+        assert_eq!(json["id"], 4);
+        assert_eq!(json["operating_system"], "steamdeck");
+        assert!(round_trip(&executable(None)).get("id").is_none());
+    }
+
+    #[test]
+    fn game_name_match_round_trips() {
+        let json = round_trip(&GameNameMatch {
+            game: game(Some(7)),
+            score: 0.75,
+        });
+        assert_eq!(json["score"], 0.75);
+        assert_eq!(json["default_name"], "Hollow Knight");
+    }
+
+    #[test]
+    fn save_fields_are_checked_together() {
+        let attributes: HashMap<String, String> =
+            HashMap::from([("game.playtime".to_string(), "12h".to_string())]);
+        assert!(validate_save_fields(&attributes, Some("before boss"), Some("deck")).is_ok());
+        assert!(validate_save_fields(&attributes, None, None).is_ok());
+        assert!(validate_save_fields(&attributes, Some("  "), None).is_err());
+        assert!(validate_save_fields(&attributes, None, Some("")).is_err());
+
+        let too_many: HashMap<String, String> = (0..=MAX_SAVE_ATTRIBUTES_PER_SAVE)
+            .map(|index| (format!("key.{index}"), String::new()))
+            .collect();
+        assert!(validate_save_fields(&too_many, None, None).is_err());
+        let empty_key = HashMap::from([(String::new(), "value".to_string())]);
+        assert!(validate_save_fields(&empty_key, None, None).is_err());
+    }
+}
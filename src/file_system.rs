@@ -1,7 +1,13 @@
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::fs::{self, File};
 use tokio::io::AsyncWriteExt;
 
-use crate::const_var::{DATA_DIR, TMP_DIR};
+use crate::chunking::{ChunkingOptions, chunk_hashing};
+use crate::const_var::{BLOB_DIR, DATA_DIR, DEFAULT_HASH_CHUNK_SIZE, TMP_DIR};
+use crate::datatype_endpoint::{ChunkedFile, FileHash, HashAlgo};
 
 pub async fn write_bytes_to_data_file(
     tmp_path: &str,
@@ -25,6 +31,278 @@ pub async fn write_bytes_to_tmp_file(
 pub async fn create_fs_structure() -> Result<(), Box<dyn std::error::Error>> {
     fs::create_dir_all(DATA_DIR).await?;
     fs::create_dir_all(TMP_DIR).await?;
+    fs::create_dir_all(BLOB_DIR).await?;
     fs::create_dir_all(format!("{}/saves", DATA_DIR)).await?;
     Ok(())
 }
+
+/// How files are read while they are hashed, the best chunk size depends on
+/// the disk so it is left to the caller
+#[derive(Clone, Copy, Debug)]
+pub struct HashOptions {
+    /// Bytes read per call, the digest does not depend on it. Never 0, a
+    /// zero sized read would look like the end of the file
+    pub(crate) chunk_size: usize,
+}
+
+impl Default for HashOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_HASH_CHUNK_SIZE,
+        }
+    }
+}
+
+impl HashOptions {
+    pub fn with_chunk_size(chunk_size: usize) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+        }
+    }
+}
+
+/// SHA-256 and length of the file, both taken from the same read
+fn hash_file(path: &Path, options: HashOptions) -> std::io::Result<(String, u64)> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; options.chunk_size];
+    let mut size = 0;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
+/// Content-defined chunks of the file with their hashes, what has to be
+/// stored for it to be kept as a `ChunkedFile`
+pub fn hash_file_chunked(path: &Path, options: ChunkingOptions) -> std::io::Result<ChunkedFile> {
+    let file = std::fs::File::open(path)?;
+    chunk_hashing(file, options, |_, _| Ok(()))
+}
+
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<(String, PathBuf)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        if file_type.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if file_type.is_file() {
+            let relative_path = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((relative_path, path));
+        }
+    }
+    Ok(())
+}
+
+/// Turns a client supplied relative path into one that stays under the
+/// directory it is joined to. `\` is accepted as a separator and `.`
+/// components are dropped, while absolute paths, drive or UNC prefixes and
+/// `..` components are rejected
+pub fn sanitize_relative_path(raw: &str) -> Result<PathBuf, String> {
+    let normalized = raw.replace('\\', "/");
+    let bytes = normalized.as_bytes();
+    if normalized.starts_with('/') {
+        return Err(format!("relative path is absolute: {raw}"));
+    }
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        return Err(format!("relative path has a drive prefix: {raw}"));
+    }
+    if normalized.contains('\0') {
+        return Err(format!("relative path contains a nul byte: {raw}"));
+    }
+
+    let mut path = PathBuf::new();
+    for component in normalized.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => return Err(format!("relative path escapes its root: {raw}")),
+            component => path.push(component),
+        }
+    }
+    if path.as_os_str().is_empty() {
+        return Err(format!("relative path names no file: {raw}"));
+    }
+    Ok(path)
+}
+
+/// Index in the sorted file list, hash and size of each file one worker hashed
+type HashedFiles = Vec<(usize, String, u64)>;
+
+/// SHA-256 of every regular file under `root`, symlinks are skipped and the
+/// `/` separated relative paths are sorted. Files are hashed on one thread
+/// per core, each streamed through a fixed size buffer
+pub fn hash_save_directory(root: &Path) -> std::io::Result<Vec<FileHash>> {
+    hash_save_directory_with(root, HashOptions::default())
+}
+
+/// Same as `hash_save_directory` reading files in `options.chunk_size` chunks
+pub fn hash_save_directory_with(
+    root: &Path,
+    options: HashOptions,
+) -> std::io::Result<Vec<FileHash>> {
+    let mut files = Vec::new();
+    collect_files(root, root, &mut files)?;
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(files.len())
+        .max(1);
+    // Every worker takes the next file nobody hashed yet, so a large file
+    // only holds up the thread reading it. The first error stops them all
+    let next = AtomicUsize::new(0);
+    let hash_next = || -> std::io::Result<HashedFiles> {
+        let mut hashed = Vec::new();
+        loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some((_, path)) = files.get(index) else {
+                return Ok(hashed);
+            };
+            match hash_file(path, options) {
+                Ok((hash, size)) => hashed.push((index, hash, size)),
+                Err(e) => {
+                    next.store(files.len(), Ordering::Relaxed);
+                    return Err(e);
+                }
+            }
+        }
+    };
+    let results: Vec<std::thread::Result<std::io::Result<HashedFiles>>> =
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers).map(|_| scope.spawn(hash_next)).collect();
+            handles.into_iter().map(|handle| handle.join()).collect()
+        });
+
+    let mut hashes: Vec<Option<(String, u64)>> = vec![None; files.len()];
+    for result in results {
+        let hashed = result.map_err(|_| std::io::Error::other("hashing thread panicked"))??;
+        for (index, hash, size) in hashed {
+            hashes[index] = Some((hash, size));
+        }
+    }
+    Ok(files
+        .into_iter()
+        .zip(hashes)
+        .filter_map(|((relative_path, _), hashed)| {
+            let (hash, size) = hashed?;
+            Some(FileHash {
+                relative_path,
+                hash,
+                algo: HashAlgo::Sha256,
+                size: Some(size),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty directory under the system temp dir, removed by the caller
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gss-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn directory_hashes_come_back_sorted_with_their_sizes() {
+        let root = temp_dir();
+        std::fs::create_dir_all(root.join("slot1")).unwrap();
+        for index in 0..40 {
+            std::fs::write(root.join(format!("slot1/{index:02}.dat")), b"abc").unwrap();
+        }
+        std::fs::write(root.join("config.ini"), b"").unwrap();
+
+        let files_hash = hash_save_directory(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(files_hash.len(), 41);
+        assert_eq!(files_hash[0].relative_path, "config.ini");
+        assert_eq!(
+            files_hash[0].hash,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(files_hash[1].relative_path, "slot1/00.dat");
+        assert!(files_hash[1..].iter().all(|file| file.size == Some(3)
+            && file.hash == "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"));
+        assert!(
+            files_hash
+                .windows(2)
+                .all(|pair| pair[0].relative_path < pair[1].relative_path)
+        );
+    }
+
+    #[test]
+    fn escaping_and_absolute_relative_paths_are_rejected() {
+        for raw in [
+            "../../etc/passwd",
+            "saves/../../outside",
+            "C:\\Windows\\System32",
+            "/etc/passwd",
+            "\\\\server\\share",
+            "",
+            "./.",
+        ] {
+            assert!(sanitize_relative_path(raw).is_err(), "{raw} was accepted");
+        }
+    }
+
+    #[test]
+    fn relative_path_separators_are_normalized() {
+        assert_eq!(
+            sanitize_relative_path("a\\b/./c").unwrap(),
+            PathBuf::from("a").join("b").join("c")
+        );
+    }
+
+    #[test]
+    fn missing_directory_is_an_io_error() {
+        let root = temp_dir().join("missing");
+        assert!(hash_save_directory(&root).is_err());
+    }
+
+    #[test]
+    fn chunk_size_does_not_change_the_digests() {
+        let root = temp_dir();
+        let large: Vec<u8> = (0..DEFAULT_HASH_CHUNK_SIZE * 2 + 13)
+            .map(|index| (index % 251) as u8)
+            .collect();
+        std::fs::write(root.join("large.sav"), &large).unwrap();
+        std::fs::write(root.join("small.sav"), b"hollow").unwrap();
+        std::fs::write(root.join("empty.sav"), b"").unwrap();
+
+        let digests = |options| -> Vec<(String, String, Option<u64>)> {
+            hash_save_directory_with(&root, options)
+                .unwrap()
+                .into_iter()
+                .map(|file| (file.relative_path, file.hash, file.size))
+                .collect()
+        };
+        let expected = digests(HashOptions::default());
+        for chunk_size in [0, 1, 7, 4096] {
+            assert_eq!(digests(HashOptions::with_chunk_size(chunk_size)), expected);
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(expected.len(), 3);
+        assert_eq!(expected[1].2, Some(large.len() as u64));
+    }
+}
@@ -1,87 +1,72 @@
+use std::collections::HashSet;
 use tokio::{fs, io::AsyncReadExt};
+use tracing::debug_span;
 
 use crate::{
     DATABASE,
-    datatype_endpoint::{ExecutableCreate, OS, SavePathCreate},
-    ludusavi_datatype::{Game, GameIndex, Os, Tag},
+    database::{bulk::BulkImport, error::DbError, interface::GameDatabase, trace::traced},
+    datatype_endpoint::{ExecutableCreate, GameMetadataCreate, ImportSummary, OS, SavePathCreate},
+    ludusavi_datatype::{FileConstraint, Game, GameIndex, Os, Tag},
 };
 
-fn import_game_into_game_metadata_db(
-    (name, game): &(String, Game),
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    if DATABASE.get_game_metadata_by_name(name)?.is_empty() {
-        DATABASE.add_game_metadata(&crate::datatype_endpoint::GameMetadataCreate {
-            known_name: Vec::new(),
-            steam_appid: match game.steam {
-                Some(info) => info.id.map(|id| id.to_string()),
-                None => None,
-            },
-            default_name: name.to_string(),
-        })?;
+/// A file entry without any `when` applies to every OS
+fn file_operating_systems(when: Option<&Vec<FileConstraint>>) -> Vec<OS> {
+    match when {
+        Some(constraints) if !constraints.is_empty() => constraints
+            .iter()
+            .filter_map(|file_constraint| match file_constraint.os {
+                Some(Os::Linux) => Some(OS::Linux),
+                Some(Os::Windows) => Some(OS::Windows),
+                Some(Os::Mac) => Some(OS::Mac),
+                None => Some(OS::Undefined),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![OS::Undefined],
     }
-    Ok(())
 }
 
-fn import_path_into_game_path_db(
-    (name, game): &(String, Game),
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let id = DATABASE
-        .get_game_metadata_by_name(name)?
-        .first()
-        .and_then(|game_metadata| game_metadata.id)
-        .ok_or("Id not found: import path")?;
+/// Path templates such as `<home>` or `<winAppData>` are stored untouched,
+/// registry keys are only meaningful on Windows. Paths in `known_paths`
+/// are left out
+fn game_paths(game: &Game, mut known_paths: HashSet<(String, OS)>) -> Vec<SavePathCreate> {
+    let mut new_paths: Vec<SavePathCreate> = Vec::new();
 
-    let files = match &game.files {
-        Some(files) => files,
-        None => return Ok(()),
+    let mut push_path = |path: &String, os: OS| {
+        if known_paths.insert((path.clone(), os.clone())) {
+            new_paths.push(SavePathCreate {
+                path: path.clone(),
+                operating_system: os,
+            });
+        }
     };
 
-    for (path, file) in files {
+    for (path, file) in game.files.iter().flatten() {
         if !file.tags.iter().any(|tags| tags.contains(&Tag::Save)) {
             continue;
         }
-        let os_iter = file
-            .when
-            .iter()
-            .flat_map(|file_constraints| file_constraints.iter())
-            .filter_map(|file_constraint| match file_constraint.os {
-                Some(Os::Linux) => Some(OS::Linux),
-                Some(Os::Windows) => Some(OS::Windows),
-                None => Some(OS::Undefined),
-                _ => None,
-            });
-        for os in os_iter {
-            let exists = DATABASE
-                .get_paths_by_game_id_and_os(id, os)?
-                .iter()
-                .any(|db_path| db_path == path);
-            if !exists {
-                DATABASE.add_game_path(
-                    id,
-                    &SavePathCreate {
-                        path: path.clone(),
-                        operating_system: os,
-                    },
-                )?;
-            }
+        for os in file_operating_systems(file.when.as_ref()) {
+            push_path(path, os);
         }
     }
-    Ok(())
+    for (key, registry) in game.registry.iter().flatten() {
+        if registry.tags.iter().any(|tags| tags.contains(&Tag::Save)) {
+            push_path(key, OS::Windows);
+        }
+    }
+    new_paths
 }
 
-pub fn import_executable_into_game_executable_db(
-    (name, game): &(String, Game),
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let id = DATABASE
-        .get_game_metadata_by_name(name)?
-        .first()
-        .and_then(|game_metadata| game_metadata.id)
-        .ok_or("Id not found: import path")?;
-
+/// Executables in `known_executables` are left out
+fn game_executables(
+    game: &Game,
+    mut known_executables: HashSet<(String, OS)>,
+) -> Vec<ExecutableCreate> {
     let launch = match &game.launch {
         Some(launch) => launch,
-        None => return Ok(()),
+        None => return Vec::new(),
     };
+    let mut new_executables: Vec<ExecutableCreate> = Vec::new();
 
     for (executable_path, launch_entries) in launch {
         let os_iter = launch_entries
@@ -91,42 +76,92 @@ pub fn import_executable_into_game_executable_db(
             .filter_map(|launch_constraint| match launch_constraint.os {
                 Some(Os::Linux) => Some(OS::Linux),
                 Some(Os::Windows) => Some(OS::Windows),
+                Some(Os::Mac) => Some(OS::Mac),
                 None => Some(OS::Undefined),
                 _ => None,
             });
 
         for os in os_iter {
-            let exists = DATABASE
-                .get_executable_by_game_id_and_os(id, os)?
-                .iter()
-                .any(|db_exec_path| db_exec_path == executable_path);
-            if !exists {
-                DATABASE.add_game_executable(
-                    id,
-                    &ExecutableCreate {
-                        executable: executable_path.clone(),
-                        operating_system: os,
-                    },
-                )?;
+            if known_executables.insert((executable_path.clone(), os.clone())) {
+                new_executables.push(ExecutableCreate {
+                    executable: executable_path.clone(),
+                    operating_system: os,
+                });
             }
         }
     }
+    new_executables
+}
+
+/// Adds the paths and executables of `game` the existing game `id` lacks
+fn complete_existing_game(import: &mut BulkImport, id: i32, game: &Game) -> Result<(), DbError> {
+    let known_paths: HashSet<(String, OS)> = import
+        .get_paths_by_game_id(id)?
+        .into_iter()
+        .map(|db_path| (db_path.path.path, db_path.path.operating_system))
+        .collect();
+    import.add_game_paths(id, &game_paths(game, known_paths))?;
 
+    let known_executables: HashSet<(String, OS)> = import
+        .get_executable_by_game_id(id)?
+        .into_iter()
+        .map(|db_executable| {
+            (
+                db_executable.executable.executable,
+                db_executable.executable.operating_system,
+            )
+        })
+        .collect();
+    import.add_game_executables(id, &game_executables(game, known_executables))?;
     Ok(())
 }
 
-pub async fn yaml_import(yaml_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+impl GameDatabase {
+    /// Games already present by name or steam appid are counted as skipped,
+    /// only their missing paths and executables are added. A new game is
+    /// inserted with its paths and executables at once. The whole manifest
+    /// is one `bulk_import`, a failure leaves the catalog as it was
+    pub fn import_ludusavi_manifest(&self, yaml: &str) -> Result<ImportSummary, DbError> {
+        let _span = debug_span!("import_ludusavi_manifest").entered();
+        traced(|| {
+            let games: GameIndex =
+                serde_yaml::from_str(yaml).map_err(|e| DbError::Invalid(e.to_string()))?;
+
+            self.bulk_import(|import| {
+                let mut summary = ImportSummary::default();
+                for (name, game) in &games {
+                    let steam_appid = game.steam.and_then(|info| info.id).map(|id| id.to_string());
+                    match import.find_game_id(name, steam_appid.as_deref())? {
+                        Some(id) => {
+                            summary.skipped += 1;
+                            complete_existing_game(import, id, game)?;
+                        }
+                        None => {
+                            summary.inserted += 1;
+                            import.add_complete_game(
+                                &GameMetadataCreate {
+                                    known_name: Vec::new(),
+                                    steam_appid: steam_appid.clone(),
+                                    default_name: name.to_string(),
+                                },
+                                &game_paths(game, HashSet::new()),
+                                &game_executables(game, HashSet::new()),
+                            )?;
+                        }
+                    }
+                }
+                Ok(summary)
+            })
+        })
+    }
+}
+
+pub async fn yaml_import(
+    yaml_path: &str,
+) -> Result<ImportSummary, Box<dyn std::error::Error + Send + Sync>> {
     let mut file = fs::File::open(yaml_path).await?;
     let mut yaml_str = String::new();
     file.read_to_string(&mut yaml_str).await?;
 
-    let games: GameIndex = serde_yaml::from_str(&yaml_str)?;
-
-    for game in games {
-        import_game_into_game_metadata_db(&game)?;
-        import_path_into_game_path_db(&game)?;
-        import_executable_into_game_executable_db(&game)?;
-    }
-
-    Ok(())
+    Ok(DATABASE.import_ludusavi_manifest(&yaml_str)?)
 }
@@ -1,3 +1,7 @@
+mod auth;
+mod blob_store;
+mod chunking;
+mod cli;
 mod const_var;
 mod database;
 mod datatype_endpoint;
@@ -5,55 +9,310 @@ mod file_system;
 mod ludusavi;
 mod ludusavi_datatype;
 mod openapi;
+mod route_blobs;
 mod route_executable;
 mod route_games;
+mod route_health;
 mod route_paths;
 mod route_saves;
 mod route_yaml_import;
+mod scheduler;
+mod steam;
+mod webhook;
+mod ws;
 
-use crate::const_var::{DATA_DIR, MAX_BODY_SIZE, ROOT_API_PATH};
-use crate::database::interface::GameDatabase;
+use crate::auth::{API_TOKENS, require_bearer_token};
+use crate::blob_store::FsBlobStore;
+use crate::const_var::{
+    BLOB_DIR, BLOB_SHARD_DEPTH_ENV, CHUNK_THRESHOLD_ENV, DATA_DIR, DEFAULT_BLOB_SHARD_DEPTH,
+    DEFAULT_QUERY_TIMEOUT_MS, MAINTENANCE_INTERVAL_SECS_ENV, MAX_BODY_SIZE, QUERY_TIMEOUT_MS_ENV,
+    ROOT_API_PATH, SKIP_MIGRATIONS_ENV,
+};
+use crate::database::async_interface::AsyncGameDatabase;
+use crate::database::error::DbError;
+use crate::database::interface::{GameDatabase, GameDatabaseConfig};
 use crate::file_system::create_fs_structure;
 use crate::openapi::ApiDoc;
+use crate::route_blobs::{get_blob, get_blob_chunks, post_blob, post_chunked_file};
 use crate::route_executable::{
     get_game_executables, get_game_executables_by_os, post_game_executable,
 };
-use crate::route_games::{get_game_metadata, get_games_metadata, post_game_metadata};
-use crate::route_paths::{get_game_paths, get_game_paths_by_os, post_game_path};
+use crate::route_games::{
+    delete_game_metadata, delete_game_metadata_permanently, get_duplicate_games, get_game_bundle,
+    get_game_launch_profile, get_game_metadata, get_game_metadata_by_steam_appid, get_games_appids,
+    get_games_child_changes, get_games_count, get_games_export, get_games_metadata,
+    get_games_missing_executables, get_games_missing_paths, get_games_storage_stats,
+    get_recently_added_games, get_total_storage_stats, match_games_by_name, post_game_metadata,
+    post_games_import, post_games_metadata_by_ids, post_games_metadata_by_steam_appids,
+    post_merge_game_metadata, post_rename_game, post_restore_game_metadata, put_game_metadata,
+    put_game_metadata_by_steam_appid, resolve_game_by_name, search_games_metadata,
+    search_games_metadata_ranked, search_games_with_activity,
+};
+use crate::route_health::{get_health, get_metrics, get_version};
+use crate::route_paths::{
+    delete_game_path, get_all_paths_by_os, get_game_paths, get_game_paths_by_os,
+    get_suggested_game_paths_by_os, post_game_path, put_game_path, put_game_paths,
+};
 use crate::route_saves::{
-    get_game_save_by_uuid, get_game_saves_reference_by_path_id, post_game_save_by_path_id,
+    delete_game_save_by_uuid, get_all_game_saves, get_file_hash_history, get_game_retention_policy,
+    get_game_save_attributes, get_game_save_by_uuid, get_game_save_conflict, get_game_save_diff,
+    get_game_save_manifest, get_game_save_reference_by_uuid, get_game_save_summaries_by_path_id,
+    get_game_saves_by_label, get_game_saves_containing_hash, get_game_saves_count_by_path_id,
+    get_game_saves_full_rewrites_by_path_id, get_game_saves_in_range_by_path_id,
+    get_game_saves_page_by_path_id, get_game_saves_reference_by_path_id,
+    get_game_saves_since_generation, get_latest_game_save_reference_by_path_id,
+    get_latest_game_saves_by_game_id, get_most_recent_game_save, get_path_devices,
+    get_retention_impact, post_apply_game_retention, post_delete_game_saves,
+    post_game_save_attribute, post_game_save_by_path_id, post_game_save_manifest,
+    post_missing_hashes, post_touch_game_save, put_game_retention_policy, put_game_save_label,
+    put_game_save_path, put_game_save_pinned,
 };
 use crate::route_yaml_import::post_ludusavi_yaml;
+use crate::ws::{get_save_events, register_save_events};
 use axum::extract::DefaultBodyLimit;
-use axum::{Router, routing::get, routing::post};
+use axum::middleware;
+use axum::{Router, routing::delete, routing::get, routing::post, routing::put};
 use const_format::concatcp;
 use once_cell::sync::Lazy;
+use std::time::Duration;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 pub static DATABASE: Lazy<GameDatabase> = Lazy::new(|| {
     let db_path = concatcp!(DATA_DIR, "/database.sqlite");
-    GameDatabase::new(db_path)
+    let config = GameDatabaseConfig {
+        run_migrations: std::env::var_os(SKIP_MIGRATIONS_ENV).is_none(),
+        query_timeout: query_timeout(),
+        ..GameDatabaseConfig::new(db_path)
+    };
+    GameDatabase::with_config(config).unwrap_or_else(|e| {
+        match e {
+            DbError::OpenFailed { .. } => eprintln!("{}", e),
+            e => eprintln!("Error opening database at {}: {}", db_path, e),
+        }
+        std::process::exit(1);
+    })
+});
+
+pub static ASYNC_DATABASE: Lazy<AsyncGameDatabase> =
+    Lazy::new(|| AsyncGameDatabase::new(&DATABASE));
+
+pub static BLOB_STORE: Lazy<FsBlobStore> = Lazy::new(|| {
+    FsBlobStore::new(BLOB_DIR)
+        .with_shard_depth(blob_shard_depth())
+        .with_chunk_threshold(chunk_threshold())
 });
 
+/// A depth not matching the store on disk would hide every existing blob,
+/// so an unusable value stops the server instead of falling back
+fn blob_shard_depth() -> usize {
+    let Ok(value) = std::env::var(BLOB_SHARD_DEPTH_ENV) else {
+        return DEFAULT_BLOB_SHARD_DEPTH;
+    };
+    match value.trim().parse::<usize>() {
+        Ok(depth) if depth <= 2 => depth,
+        _ => {
+            eprintln!(
+                "{} must be 0, 1 or 2, got {:?}",
+                BLOB_SHARD_DEPTH_ENV, value
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Chunked storage is opt-in, without the variable every file is stored
+/// whole
+fn chunk_threshold() -> Option<u64> {
+    let value = std::env::var(CHUNK_THRESHOLD_ENV).ok()?;
+    match value.trim().parse::<u64>() {
+        Ok(threshold) => Some(threshold),
+        Err(_) => {
+            eprintln!(
+                "{} must be a size in bytes, got {:?}",
+                CHUNK_THRESHOLD_ENV, value
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Only bounds the queries of the server, admin commands such as VACUUM
+/// or maintenance are expected to take long and run without one. The
+/// server uses the default unless the variable is set, 0 turns it off
+fn query_timeout() -> Option<Duration> {
+    if std::env::args().len() > 1 {
+        return None;
+    }
+    let Ok(value) = std::env::var(QUERY_TIMEOUT_MS_ENV) else {
+        return Some(Duration::from_millis(DEFAULT_QUERY_TIMEOUT_MS));
+    };
+    match value.trim().parse::<u64>() {
+        Ok(0) => None,
+        Ok(ms) => Some(Duration::from_millis(ms)),
+        Err(_) => {
+            eprintln!(
+                "{} must be a number of milliseconds, got {:?}",
+                QUERY_TIMEOUT_MS_ENV, value
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Scheduled maintenance is opt-in, without the variable or with 0 it only
+/// runs through the `maintenance` command
+fn maintenance_interval() -> Option<Duration> {
+    let value = std::env::var(MAINTENANCE_INTERVAL_SECS_ENV).ok()?;
+    match value.trim().parse::<u64>() {
+        Ok(0) => None,
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => {
+            eprintln!(
+                "{} must be a number of seconds, got {:?}",
+                MAINTENANCE_INTERVAL_SECS_ENV, value
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     create_fs_structure().await.unwrap();
     Lazy::force(&DATABASE);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run_command(&args) {
+        std::process::exit(code);
+    }
+
     tracing_subscriber::fmt::init();
+    DATABASE.on_save_created(Box::new(|save| {
+        tracing::info!(uuid = %save.uuid, path_id = save.path_id, "save created");
+    }));
+    register_save_events();
+    if let Some(interval) = maintenance_interval() {
+        scheduler::spawn_maintenance(interval);
+    }
+    match DATABASE.pending_migrations() {
+        Ok(pending) if !pending.is_empty() => {
+            eprintln!(
+                "{} migration(s) pending, run the migrate command: {}",
+                pending.len(),
+                pending.join(", ")
+            );
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Error listing pending migrations: {}", e),
+    }
+    if API_TOKENS.is_empty() && DATABASE.count_users().is_ok_and(|users| users == 0) {
+        eprintln!(
+            "API_TOKENS is not set and no user exists, the API accepts unauthenticated requests"
+        );
+    }
 
     let api_router = Router::new()
         .route("/games", post(post_game_metadata))
         .route("/games", get(get_games_metadata))
+        .route("/games/batch", post(post_games_metadata_by_ids))
+        .route("/games/changes", get(get_games_child_changes))
+        .route("/games/count", get(get_games_count))
+        .route("/games/appids", get(get_games_appids))
+        .route("/games/duplicates", get(get_duplicate_games))
+        .route("/games/export", get(get_games_export))
+        .route("/games/import", post(post_games_import))
+        .route("/games/recent", get(get_recently_added_games))
+        .route("/games/match", get(match_games_by_name))
+        .route("/games/resolve", get(resolve_game_by_name))
+        .route("/games/missing/paths", get(get_games_missing_paths))
+        .route(
+            "/games/missing/executables",
+            get(get_games_missing_executables),
+        )
+        .route("/games/search", get(search_games_metadata))
+        .route("/games/search/ranked", get(search_games_metadata_ranked))
+        .route("/games/search/activity", get(search_games_with_activity))
+        .route("/games/storage", get(get_games_storage_stats))
+        .route("/games/storage/total", get(get_total_storage_stats))
+        .route("/games/steam", post(post_games_metadata_by_steam_appids))
+        .route("/games/steam", put(put_game_metadata_by_steam_appid))
+        .route(
+            "/games/steam/{Appid}",
+            get(get_game_metadata_by_steam_appid),
+        )
         .route("/games/{Id}", get(get_game_metadata))
+        .route("/games/{Id}", put(put_game_metadata))
+        .route("/games/{Id}", delete(delete_game_metadata))
+        .route(
+            "/games/{Id}/permanent",
+            delete(delete_game_metadata_permanently),
+        )
+        .route("/games/{Id}/restore", post(post_restore_game_metadata))
+        .route("/games/{Id}/rename", post(post_rename_game))
+        .route("/games/{Id}/bundle", get(get_game_bundle))
+        .route("/games/{Id}/launch/{OS}", get(get_game_launch_profile))
+        .route(
+            "/games/{Id}/merge/{MergeId}",
+            post(post_merge_game_metadata),
+        )
+        .route(
+            "/games/{Id}/saves/latest",
+            get(get_latest_game_saves_by_game_id),
+        )
         .route("/games/{Id}/paths", get(get_game_paths))
         .route("/games/{Id}/paths", post(post_game_path))
+        .route("/games/{Id}/paths", put(put_game_paths))
         .route("/games/{Id}/paths/{OS}", get(get_game_paths_by_os))
+        .route(
+            "/games/{Id}/paths/suggested/{OS}",
+            get(get_suggested_game_paths_by_os),
+        )
+        .route("/paths/os/{OS}", get(get_all_paths_by_os))
+        .route("/paths/{Id}", put(put_game_path))
+        .route("/paths/{Id}", delete(delete_game_path))
         .route(
             "/paths/{Id}/saves",
             get(get_game_saves_reference_by_path_id),
         )
+        .route(
+            "/paths/{Id}/saves/summary",
+            get(get_game_save_summaries_by_path_id),
+        )
+        .route(
+            "/paths/{Id}/saves/page",
+            get(get_game_saves_page_by_path_id),
+        )
+        .route(
+            "/paths/{Id}/saves/since",
+            get(get_game_saves_since_generation),
+        )
+        .route("/paths/{Id}/saves/labeled", get(get_game_saves_by_label))
+        .route("/paths/{Id}/files/history", get(get_file_hash_history))
+        .route(
+            "/paths/{Id}/saves/count",
+            get(get_game_saves_count_by_path_id),
+        )
+        .route(
+            "/paths/{Id}/saves/range",
+            get(get_game_saves_in_range_by_path_id),
+        )
+        .route("/paths/{Id}/saves/conflict", get(get_game_save_conflict))
+        .route("/paths/{Id}/devices", get(get_path_devices))
+        .route(
+            "/paths/{Id}/saves/latest",
+            get(get_latest_game_save_reference_by_path_id),
+        )
+        .route(
+            "/paths/{Id}/saves/rewrites",
+            get(get_game_saves_full_rewrites_by_path_id),
+        )
         .route("/paths/{Id}/saves/upload", post(post_game_save_by_path_id))
+        .route("/paths/{Id}/saves/manifest", post(post_game_save_manifest))
+        .route("/blobs", post(post_blob))
+        .route("/blobs/chunked", post(post_chunked_file))
+        .route("/blobs/{Hash}", get(get_blob))
+        .route("/blobs/{Hash}/chunks", get(get_blob_chunks))
         .layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
         .route("/games/{Id}/executables", get(get_game_executables))
         .route("/games/{Id}/executables", post(post_game_executable))
@@ -61,9 +320,39 @@ async fn main() {
             "/games/{Id}/executables/{OS}",
             get(get_game_executables_by_os),
         )
+        .route("/saves", get(get_all_game_saves))
+        .route("/saves/events", get(get_save_events))
+        .route("/saves/latest", get(get_most_recent_game_save))
+        .route("/saves/retention", get(get_retention_impact))
+        .route("/games/{Id}/retention", get(get_game_retention_policy))
+        .route("/games/{Id}/retention", put(put_game_retention_policy))
+        .route(
+            "/games/{Id}/retention/apply",
+            post(post_apply_game_retention),
+        )
         .route("/saves/{Uuid}", get(get_game_save_by_uuid))
+        .route("/saves/delete", post(post_delete_game_saves))
+        .route("/saves/{Uuid}", delete(delete_game_save_by_uuid))
+        .route(
+            "/saves/{Uuid}/reference",
+            get(get_game_save_reference_by_uuid),
+        )
+        .route("/saves/{Uuid}/manifest", get(get_game_save_manifest))
+        .route("/saves/{Uuid}/diff/{OtherUuid}", get(get_game_save_diff))
+        .route("/saves/{Uuid}/attributes", get(get_game_save_attributes))
+        .route("/saves/{Uuid}/attributes", post(post_game_save_attribute))
+        .route("/saves/{Uuid}/label", put(put_game_save_label))
+        .route("/saves/{Uuid}/pinned", put(put_game_save_pinned))
+        .route("/saves/{Uuid}/touch", post(post_touch_game_save))
+        .route("/saves/{Uuid}/path", put(put_game_save_path))
+        .route("/hashes/missing", post(post_missing_hashes))
+        .route("/hashes/{Hash}/saves", get(get_game_saves_containing_hash))
         .route("/yaml/ludusavi", post(post_ludusavi_yaml))
-        .layer(DefaultBodyLimit::max(MAX_BODY_SIZE));
+        .route("/metrics", get(get_metrics))
+        .route("/version", get(get_version))
+        .layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
+        .layer(middleware::from_fn(require_bearer_token))
+        .route("/health", get(get_health));
 
     let swagger_router =
         SwaggerUi::new("/swagger-ui").url("/api-doc/openapi.json", ApiDoc::openapi());
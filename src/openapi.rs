@@ -1,51 +1,206 @@
 use crate::datatype_endpoint::{
-    Executable, ExecutableCreate, FileHash, GameMetadata, GameMetadataCreate, OS, SavePath,
-    SavePathCreate, SaveReference, UploadedFileYaml, UploadedSave,
+    AdminSaveRow, CatalogChildChanges, ChangedFile, ChunkedFile, ConflictStatus, DbMetrics,
+    DuplicateGroup, DuplicateMatch, DuplicateReason, Executable, ExecutableCreate, FileChunk,
+    FileHash, FileVersion, FullRewrite, GameActivity, GameBundle, GameChildChanges, GameMetadata,
+    GameMetadataCreate, GameNameMatch, GameRename, GameSavePath, GameStorageStat, HashAlgo,
+    HealthStatus, ImportSummary, LaunchProfile, NameResolution, OS, RenamedFile, ResolveOutcome,
+    ResolvedGame, RetentionImpact, RetentionPolicy, SaveActivity, SaveAttribute, SaveCreatedEvent,
+    SaveDiff, SaveLabel, SaveManifest, SaveNotification, SavePath, SavePathCreate, SavePathUpdate,
+    SavePinned, SaveReassign, SaveReference, SaveSummary, ServerVersion, StorageTotals,
+    SyncStrategy, UploadedFileYaml, UploadedSave,
+};
+use crate::route_blobs::{
+    __path_get_blob, __path_get_blob_chunks, __path_post_blob, __path_post_chunked_file,
 };
 use crate::route_executable::{
     __path_get_game_executables, __path_get_game_executables_by_os, __path_post_game_executable,
 };
 use crate::route_games::{
-    __path_get_game_metadata, __path_get_games_metadata, __path_post_game_metadata,
+    __path_delete_game_metadata, __path_delete_game_metadata_permanently,
+    __path_get_duplicate_games, __path_get_game_bundle, __path_get_game_launch_profile,
+    __path_get_game_metadata, __path_get_game_metadata_by_steam_appid, __path_get_games_appids,
+    __path_get_games_child_changes, __path_get_games_count, __path_get_games_export,
+    __path_get_games_metadata, __path_get_games_missing_executables,
+    __path_get_games_missing_paths, __path_get_games_storage_stats,
+    __path_get_recently_added_games, __path_get_total_storage_stats, __path_match_games_by_name,
+    __path_post_game_metadata, __path_post_games_import, __path_post_games_metadata_by_ids,
+    __path_post_games_metadata_by_steam_appids, __path_post_merge_game_metadata,
+    __path_post_rename_game, __path_post_restore_game_metadata, __path_put_game_metadata,
+    __path_put_game_metadata_by_steam_appid, __path_resolve_game_by_name,
+    __path_search_games_metadata, __path_search_games_metadata_ranked,
+    __path_search_games_with_activity,
 };
+use crate::route_health::{__path_get_health, __path_get_metrics, __path_get_version};
 use crate::route_paths::{
-    __path_get_game_paths, __path_get_game_paths_by_os, __path_post_game_path,
+    __path_delete_game_path, __path_get_all_paths_by_os, __path_get_game_paths,
+    __path_get_game_paths_by_os, __path_get_suggested_game_paths_by_os, __path_post_game_path,
+    __path_put_game_path, __path_put_game_paths,
 };
 use crate::route_saves::{
-    __path_get_game_save_by_uuid, __path_get_game_saves_reference_by_path_id,
-    __path_post_game_save_by_path_id,
+    __path_delete_game_save_by_uuid, __path_get_all_game_saves, __path_get_file_hash_history,
+    __path_get_game_retention_policy, __path_get_game_save_attributes,
+    __path_get_game_save_by_uuid, __path_get_game_save_conflict, __path_get_game_save_diff,
+    __path_get_game_save_manifest, __path_get_game_save_reference_by_uuid,
+    __path_get_game_save_summaries_by_path_id, __path_get_game_saves_by_label,
+    __path_get_game_saves_containing_hash, __path_get_game_saves_count_by_path_id,
+    __path_get_game_saves_full_rewrites_by_path_id, __path_get_game_saves_in_range_by_path_id,
+    __path_get_game_saves_page_by_path_id, __path_get_game_saves_reference_by_path_id,
+    __path_get_game_saves_since_generation, __path_get_latest_game_save_reference_by_path_id,
+    __path_get_latest_game_saves_by_game_id, __path_get_most_recent_game_save,
+    __path_get_path_devices, __path_get_retention_impact, __path_post_apply_game_retention,
+    __path_post_delete_game_saves, __path_post_game_save_attribute,
+    __path_post_game_save_by_path_id, __path_post_game_save_manifest, __path_post_missing_hashes,
+    __path_post_touch_game_save, __path_put_game_retention_policy, __path_put_game_save_label,
+    __path_put_game_save_path, __path_put_game_save_pinned,
 };
 use crate::route_yaml_import::__path_post_ludusavi_yaml;
+use crate::ws::__path_get_save_events;
 use utoipa::OpenApi;
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
+        get_blob,
+        get_blob_chunks,
+        post_blob,
+        post_chunked_file,
+        get_health,
+        get_version,
+        get_metrics,
         get_game_executables,
         get_game_executables_by_os,
         get_game_metadata,
+        get_game_bundle,
+        get_game_launch_profile,
+        post_merge_game_metadata,
+        search_games_metadata,
+        search_games_metadata_ranked,
+        search_games_with_activity,
+        match_games_by_name,
+        resolve_game_by_name,
+        post_games_metadata_by_ids,
+        get_games_count,
+        get_games_appids,
+        get_duplicate_games,
+        get_recently_added_games,
+        delete_game_metadata,
+        post_restore_game_metadata,
+        post_rename_game,
+        delete_game_metadata_permanently,
+        get_games_export,
+        post_games_import,
+        get_games_storage_stats,
+        get_total_storage_stats,
+        get_game_metadata_by_steam_appid,
+        put_game_metadata_by_steam_appid,
         get_game_paths,
         get_game_paths_by_os,
+        get_suggested_game_paths_by_os,
+        get_all_paths_by_os,
+        delete_game_path,
+        put_game_path,
+        get_game_save_attributes,
         get_game_save_by_uuid,
+        get_game_saves_containing_hash,
+        get_game_save_reference_by_uuid,
+        delete_game_save_by_uuid,
+        post_delete_game_saves,
+        get_game_save_diff,
+        get_game_saves_full_rewrites_by_path_id,
+        get_latest_game_save_reference_by_path_id,
+        get_game_saves_in_range_by_path_id,
+        get_game_save_summaries_by_path_id,
+        get_game_saves_page_by_path_id,
+        get_game_saves_since_generation,
+        get_game_saves_by_label,
+        get_file_hash_history,
+        get_game_saves_count_by_path_id,
+        get_game_save_conflict,
+        get_path_devices,
+        post_missing_hashes,
         get_game_saves_reference_by_path_id,
+        get_games_child_changes,
         get_games_metadata,
+        get_all_game_saves,
+        get_most_recent_game_save,
+        get_retention_impact,
+        get_game_retention_policy,
+        put_game_retention_policy,
+        post_apply_game_retention,
         post_game_executable,
         post_game_metadata,
         post_game_path,
+        put_game_paths,
+        post_game_save_attribute,
+        put_game_save_label,
+        put_game_save_pinned,
+        post_touch_game_save,
+        get_latest_game_saves_by_game_id,
+        put_game_save_path,
         post_game_save_by_path_id,
+        post_games_metadata_by_steam_appids,
         post_ludusavi_yaml,
+        put_game_metadata,
+        get_save_events,
+        get_games_missing_paths,
+        get_games_missing_executables,
+        get_game_save_manifest,
+        post_game_save_manifest,
     ),
     components(schemas(
         FileHash,
+        ChunkedFile,
+        FileChunk,
+        HashAlgo,
         UploadedSave,
         UploadedFileYaml,
         SavePathCreate,
+        SavePathUpdate,
         SavePath,
         ExecutableCreate,
         Executable,
         GameMetadataCreate,
         GameMetadata,
+        GameRename,
+        GameNameMatch,
+        NameResolution,
+        ResolvedGame,
+        DuplicateGroup,
+        DuplicateMatch,
+        DuplicateReason,
+        LaunchProfile,
+        DbMetrics,
         SaveReference,
+        SaveSummary,
+        SaveAttribute,
+        SaveLabel,
+        FileVersion,
+        SavePinned,
+        SaveReassign,
+        SaveActivity,
+        GameBundle,
+        GameSavePath,
+        ImportSummary,
+        ConflictStatus,
+        SyncStrategy,
+        ResolveOutcome,
+        GameStorageStat,
+        StorageTotals,
+        RetentionPolicy,
+        GameActivity,
+        AdminSaveRow,
+        HealthStatus,
+        ServerVersion,
+        SaveDiff,
+        ChangedFile,
+        RenamedFile,
+        FullRewrite,
+        SaveCreatedEvent,
+        SaveNotification,
+        SaveManifest,
+        RetentionImpact,
+        GameChildChanges,
+        CatalogChildChanges,
         OS,
     ))
 )]
@@ -0,0 +1,372 @@
+use crate::blob_store::{BlobEncoding, BlobStore, StoredBlob};
+use crate::const_var::{BLOB_STREAM_CHUNK_SIZE, ROOT_API_PATH};
+use crate::database::error::DbError;
+use crate::datatype_endpoint::{BlobUploadQuery, ChunkedFile};
+use crate::{ASYNC_DATABASE, BLOB_STORE, DATABASE};
+use axum::Json;
+use axum::body::Body;
+use axum::extract::{Path, Query};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use const_format::concatcp;
+use futures_util::TryStreamExt;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use tokio_util::io::{ReaderStream, StreamReader, SyncIoBridge};
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/blobs"),
+    params(BlobUploadQuery),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    responses(
+        (status = 201, description = "blob stored, its sha256 is returned", body = String)
+    )
+)]
+pub async fn post_blob(
+    Query(query): Query<BlobUploadQuery>,
+    body: Body,
+) -> Result<(StatusCode, String), StatusCode> {
+    let reader = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+    let mut reader = SyncIoBridge::new(reader);
+    let compress = query.compress.unwrap_or(true);
+    let stored = tokio::task::spawn_blocking(move || {
+        if compress {
+            BLOB_STORE.put_stream(&mut reader)
+        } else {
+            BLOB_STORE.put_raw_stream(&mut reader)
+        }
+    })
+    .await
+    .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+    match stored {
+        Ok(hash) => Ok((StatusCode::CREATED, hash)),
+        Err(e) => {
+            eprintln!("Error storing blob: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/blobs/{Hash}"),
+    params(
+        ("Hash" = String, Path, description = "sha256 of the blob")
+    ),
+    responses(
+        (status = 200, description = "blob content returned, a file stored as chunks is put back together. A blob stored compressed is sent as is with `Content-Encoding: gzip` when the client accepts it", content_type = "application/octet-stream"),
+        (status = 206, description = "the single byte range asked by `Range`, of the gzip bytes when they are sent", content_type = "application/octet-stream"),
+        (status = 400, description = "invalid hash"),
+        (status = 404, description = "blob not found"),
+        (status = 416, description = "the range starts past the end")
+    )
+)]
+pub async fn get_blob(
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let read_error = |e: DbError| match e {
+        DbError::Io(e) if e.kind() == ErrorKind::InvalidInput => StatusCode::BAD_REQUEST,
+        e => {
+            eprintln!("Error reading blob: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+
+    // The gzip bytes on disk are the representation sent, ranges and the
+    // length are of them and nothing is compressed twice
+    let stored = BLOB_STORE
+        .open_stored(&hash)
+        .map_err(|e| read_error(e.into()))?;
+    if let Some(stored) = stored
+        && (stored.encoding == BlobEncoding::Identity || accepts_gzip(&headers))
+    {
+        return stored_response(stored, &headers).map_err(|e| read_error(e.into()));
+    }
+
+    let content = tokio::task::spawn_blocking(move || {
+        let Some(reader) = DATABASE.open_content(&hash, &*BLOB_STORE)? else {
+            return Ok(None);
+        };
+        Ok(Some((reader, DATABASE.content_size(&hash)?)))
+    })
+    .await
+    .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+    let Some((reader, len)) = content.map_err(read_error)? else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    // Decoded on the fly, a range is only known to be right with the
+    // length recorded for the content and is reached by skipping to it
+    let range = match len.map(|len| requested_range(&headers, len)) {
+        Some(Ok(range)) => range,
+        Some(Err(())) => return Ok(range_not_satisfiable(len.unwrap_or_default())),
+        None => None,
+    };
+    let skip = range.map_or(0, |(start, _)| start);
+    Ok(blob_response(reader, skip, len, range, false))
+}
+
+/// Sends `stored` in the encoding it is kept in, the range asked and the
+/// length given are of the stored bytes
+fn stored_response(mut stored: StoredBlob, headers: &HeaderMap) -> std::io::Result<Response> {
+    let range = match requested_range(headers, stored.len) {
+        Ok(range) => range,
+        Err(()) => return Ok(range_not_satisfiable(stored.len)),
+    };
+    if let Some((start, _)) = range {
+        stored.reader.seek(SeekFrom::Start(start))?;
+    }
+    let gzip = stored.encoding == BlobEncoding::Gzip;
+    Ok(blob_response(
+        stored.reader,
+        0,
+        Some(stored.len),
+        range,
+        gzip,
+    ))
+}
+
+/// Streams `reader` from a blocking task after discarding `skip` bytes of
+/// it, the whole content or the inclusive `range` of `len` bytes
+fn blob_response(
+    mut reader: Box<dyn Read + Send + 'static>,
+    skip: u64,
+    len: Option<u64>,
+    range: Option<(u64, u64)>,
+    gzip: bool,
+) -> Response {
+    let sent = match (range, len) {
+        (Some((start, end)), _) => Some(end - start + 1),
+        (None, len) => len,
+    };
+    let (read_half, write_half) = tokio::io::duplex(BLOB_STREAM_CHUNK_SIZE);
+    tokio::task::spawn_blocking(move || {
+        let mut writer = SyncIoBridge::new(write_half);
+        let copied =
+            std::io::copy(&mut (&mut reader).take(skip), &mut std::io::sink()).and_then(|_| {
+                match sent {
+                    Some(sent) => std::io::copy(&mut reader.take(sent), &mut writer),
+                    None => std::io::copy(&mut reader, &mut writer),
+                }
+            });
+        if let Err(e) = copied {
+            eprintln!("Error streaming blob: {}", e);
+        }
+    });
+
+    let mut response = Body::from_stream(ReaderStream::new(read_half)).into_response();
+    let response_headers = response.headers_mut();
+    let header = |value: String| HeaderValue::from_str(&value).expect("ascii header value");
+    response_headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/octet-stream"),
+    );
+    response_headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    if gzip {
+        response_headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    }
+    if let Some(sent) = sent {
+        response_headers.insert(header::CONTENT_LENGTH, header(sent.to_string()));
+    }
+    if let Some(len) = len {
+        response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        if let Some((start, end)) = range {
+            response_headers.insert(
+                header::CONTENT_RANGE,
+                header(format!("bytes {start}-{end}/{len}")),
+            );
+            *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+        }
+    }
+    response
+}
+
+fn range_not_satisfiable(len: u64) -> Response {
+    (
+        StatusCode::RANGE_NOT_SATISFIABLE,
+        [(header::CONTENT_RANGE, format!("bytes */{len}"))],
+    )
+        .into_response()
+}
+
+/// Whether `Accept-Encoding` lists gzip, or `*`, without a zero weight
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';');
+            let name = parts.next().unwrap_or_default().trim();
+            let weight = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (name.eq_ignore_ascii_case("gzip") || name == "*") && weight > 0.0
+        })
+}
+
+/// Inclusive byte range a single range `Range: bytes=` header asks of
+/// content of `len` bytes. `None` when there is no such header, several
+/// ranges or one that does not parse are answered whole as well. `Err`
+/// when no byte of the range exists
+fn requested_range(headers: &HeaderMap, len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(spec) = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().strip_prefix("bytes="))
+        .filter(|spec| !spec.contains(','))
+    else {
+        return Ok(None);
+    };
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return Ok(None);
+    };
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return Err(()),
+            Ok(suffix) => (len.saturating_sub(suffix), u64::MAX),
+            Err(_) => return Ok(None),
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(start) => (start, u64::MAX),
+            Err(_) => return Ok(None),
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end),
+            _ => return Ok(None),
+        },
+    };
+    if start >= len {
+        return Err(());
+    }
+    Ok(Some((start, end.min(len - 1))))
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/blobs/chunked"),
+    request_body = ChunkedFile,
+    responses(
+        (status = 201, description = "chunk list recorded, saves can reference the file by its hash"),
+        (status = 400, description = "a chunk is not stored or the chunks do not add up to the file")
+    )
+)]
+pub async fn post_chunked_file(Json(file): Json<ChunkedFile>) -> StatusCode {
+    let recorded =
+        tokio::task::spawn_blocking(move || DATABASE.record_chunked_file(&file, &*BLOB_STORE))
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+    match recorded {
+        Ok(()) => StatusCode::CREATED,
+        Err(DbError::Invalid(_)) => StatusCode::BAD_REQUEST,
+        Err(e) => {
+            eprintln!("Error recording chunked file: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/blobs/{Hash}/chunks"),
+    params(
+        ("Hash" = String, Path, description = "sha256 of the whole file")
+    ),
+    responses(
+        (status = 200, description = "chunk list returned, a client only downloads the chunks it lacks", body = ChunkedFile),
+        (status = 404, description = "file not stored as chunks")
+    )
+)]
+pub async fn get_blob_chunks(Path(hash): Path<String>) -> Result<Json<ChunkedFile>, StatusCode> {
+    match ASYNC_DATABASE.get_chunked_file(hash).await {
+        Ok(Some(file)) => Ok(Json(file)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error reading chunk list: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blob_store::FsBlobStore;
+    use uuid::Uuid;
+
+    fn headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn range(value: &str, len: u64) -> Result<Option<(u64, u64)>, ()> {
+        requested_range(&headers(&[(header::RANGE, value)]), len)
+    }
+
+    #[test]
+    fn range_header_forms() {
+        assert_eq!(requested_range(&HeaderMap::new(), 100), Ok(None));
+        assert_eq!(range("bytes=10-19", 100), Ok(Some((10, 19))));
+        assert_eq!(range("bytes=90-200", 100), Ok(Some((90, 99))));
+        // Suffix and open-ended ranges
+        assert_eq!(range("bytes=-30", 100), Ok(Some((70, 99))));
+        assert_eq!(range("bytes=-300", 100), Ok(Some((0, 99))));
+        assert_eq!(range("bytes=40-", 100), Ok(Some((40, 99))));
+        // Nothing of the range exists
+        assert_eq!(range("bytes=100-", 100), Err(()));
+        assert_eq!(range("bytes=150-160", 100), Err(()));
+        assert_eq!(range("bytes=-0", 100), Err(()));
+        // Answered whole
+        assert_eq!(range("bytes=0-9,20-29", 100), Ok(None));
+        assert_eq!(range("bytes=20-10", 100), Ok(None));
+        assert_eq!(range("items=0-9", 100), Ok(None));
+    }
+
+    #[test]
+    fn gzip_needs_a_non_zero_weight() {
+        let accepts = |value| accepts_gzip(&headers(&[(header::ACCEPT_ENCODING, value)]));
+        assert!(!accepts_gzip(&HeaderMap::new()));
+        assert!(accepts("gzip"));
+        assert!(accepts("br, GZIP;q=0.5"));
+        assert!(accepts("*"));
+        assert!(!accepts("gzip;q=0"));
+        assert!(!accepts("gzip; q=0.0, br"));
+        assert!(!accepts("*;q=0"));
+        assert!(!accepts("identity, br"));
+    }
+
+    #[tokio::test]
+    async fn gzip_blob_ranges_are_of_the_stored_bytes() {
+        let root = std::env::temp_dir().join(format!("gss-test-{}", Uuid::new_v4()));
+        let store = FsBlobStore::new(&root);
+        let content = b"compressible save data ".repeat(200);
+        let hash = store.put(&content).unwrap();
+        let (gzipped, encoding) = store.get_raw(&hash).unwrap().unwrap();
+        assert_eq!(encoding, BlobEncoding::Gzip);
+        let stored = store.open_stored(&hash).unwrap().unwrap();
+        let request = headers(&[
+            (header::ACCEPT_ENCODING, "gzip"),
+            (header::RANGE, "bytes=4-11"),
+        ]);
+
+        let response = stored_response(stored, &request).unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let sent = response.headers();
+        assert_eq!(sent[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(
+            sent[header::CONTENT_RANGE],
+            format!("bytes 4-11/{}", gzipped.len()).as_str()
+        );
+        assert_eq!(sent[header::CONTENT_LENGTH], "8");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], &gzipped[4..12]);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
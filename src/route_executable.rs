@@ -1,6 +1,9 @@
 use crate::DATABASE;
 use crate::const_var::ROOT_API_PATH;
+use crate::database::error::DbError;
 use crate::datatype_endpoint::{Executable, ExecutableCreate, OS};
+use axum::extract::rejection::JsonRejection;
+use axum::response::{IntoResponse, Response};
 use axum::{Json, extract::Path, http::StatusCode};
 use const_format::concatcp;
 
@@ -60,17 +63,27 @@ pub async fn get_game_executables_by_os(
     request_body = ExecutableCreate,
     responses(
         (status = 201, description = "game executable created"),
+        (status = 400, description = "invalid body, e.g. an unknown operating system", body = String),
+        (status = 404, description = "game not found"),
+        (status = 409, description = "game executable already exists"),
     )
 )]
 pub async fn post_game_executable(
     Path(id): Path<i32>,
-    Json(payload): Json<ExecutableCreate>,
-) -> StatusCode {
+    payload: Result<Json<ExecutableCreate>, JsonRejection>,
+) -> Response {
+    let Json(payload) = match payload {
+        Ok(payload) => payload,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.body_text()).into_response(),
+    };
     match DATABASE.add_game_executable(id, &payload) {
         Ok(()) => StatusCode::CREATED,
+        Err(DbError::UniqueViolation(_)) => StatusCode::CONFLICT,
+        Err(DbError::ForeignKeyViolation(_)) => StatusCode::NOT_FOUND,
         Err(e) => {
             eprintln!("Error adding game path: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
         }
     }
+    .into_response()
 }
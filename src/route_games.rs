@@ -1,8 +1,19 @@
-use crate::DATABASE;
-use crate::const_var::ROOT_API_PATH;
-use crate::datatype_endpoint::{GameMetadata, GameMetadataCreate};
-use axum::{Json, extract::Path, http::StatusCode};
+use crate::const_var::{
+    DEFAULT_FUZZY_MATCH_THRESHOLD, DEFAULT_QUERY_LIMIT, ROOT_API_PATH, SAVE_DIR,
+};
+use crate::database::error::DbError;
+use crate::datatype_endpoint::{
+    ActivitySearchQuery, CatalogChildChanges, ChangesQuery, DuplicateGroup, FuzzyNameQuery,
+    GameActivity, GameBundle, GameMetadata, GameMetadataCreate, GameNameMatch, GameRename,
+    GameStorageStat, ImportSummary, LaunchProfile, LimitQuery, OS, OsFilterQuery, PageQuery,
+    RankedSearchQuery, ResolveNameQuery, ResolvedGame, SearchQuery, StorageTotals,
+};
+use crate::{ASYNC_DATABASE, DATABASE};
+use axum::extract::Query;
+use axum::response::IntoResponse;
+use axum::{Json, extract::Path, http::StatusCode, http::header};
 use const_format::concatcp;
+use std::collections::HashMap;
 
 #[utoipa::path(
     post,
@@ -10,15 +21,19 @@ use const_format::concatcp;
     params(),
     request_body = GameMetadataCreate,
     responses(
-        (status = 201, description = "game metadata created")
+        (status = 201, description = "game metadata created", body = GameMetadata),
+        (status = 409, description = "game metadata already exists")
     )
 )]
-pub async fn post_game_metadata(Json(payload): Json<GameMetadataCreate>) -> StatusCode {
+pub async fn post_game_metadata(
+    Json(payload): Json<GameMetadataCreate>,
+) -> Result<(StatusCode, Json<GameMetadata>), StatusCode> {
     match DATABASE.add_game_metadata(&payload) {
-        Ok(()) => StatusCode::CREATED,
+        Ok(data) => Ok((StatusCode::CREATED, Json(data))),
+        Err(DbError::UniqueViolation(_)) => Err(StatusCode::CONFLICT),
         Err(e) => {
             eprintln!("Error adding game metadata: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
@@ -26,13 +41,23 @@ pub async fn post_game_metadata(Json(payload): Json<GameMetadataCreate>) -> Stat
 #[utoipa::path(
     get,
     path = concatcp!(ROOT_API_PATH, "/games"),
-    params(),
+    params(PageQuery),
     responses(
         (status = 200, description = "get all games metadata", body = [GameMetadata])
     )
 )]
-pub async fn get_games_metadata() -> Result<Json<Vec<GameMetadata>>, StatusCode> {
-    match DATABASE.get_games_metadata() {
+pub async fn get_games_metadata(
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Vec<GameMetadata>>, StatusCode> {
+    let games = match page.limit {
+        Some(limit) => {
+            ASYNC_DATABASE
+                .get_games_metadata_paged(page.offset.unwrap_or(0), limit)
+                .await
+        }
+        None => ASYNC_DATABASE.get_games_metadata().await,
+    };
+    match games {
         Ok(data) => Ok(Json(data)),
         Err(e) => {
             eprintln!("Error retrieving game metadata: {}", e);
@@ -41,6 +66,246 @@ pub async fn get_games_metadata() -> Result<Json<Vec<GameMetadata>>, StatusCode>
     }
 }
 
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/count"),
+    params(),
+    responses(
+        (status = 200, description = "number of games returned", body = i64)
+    )
+)]
+pub async fn get_games_count() -> Result<Json<i64>, StatusCode> {
+    match DATABASE.count_games() {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error counting games: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/appids"),
+    params(),
+    responses(
+        (status = 200, description = "sorted steam appids of every game that has one", body = [String])
+    )
+)]
+pub async fn get_games_appids() -> Result<Json<Vec<String>>, StatusCode> {
+    match DATABASE.get_all_appids() {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error getting game appids: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/duplicates"),
+    responses(
+        (status = 200, description = "groups of games that are likely the same, to review before merging", body = [DuplicateGroup])
+    )
+)]
+pub async fn get_duplicate_games() -> Result<Json<Vec<DuplicateGroup>>, StatusCode> {
+    match DATABASE.find_duplicate_games() {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error finding duplicate games: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/missing/paths"),
+    params(OsFilterQuery),
+    responses(
+        (status = 200, description = "games without any save path for the OS, ordered by id", body = [GameMetadata])
+    )
+)]
+pub async fn get_games_missing_paths(
+    Query(query): Query<OsFilterQuery>,
+) -> Result<Json<Vec<GameMetadata>>, StatusCode> {
+    match DATABASE.get_games_missing_paths(query.os) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error listing games missing paths: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/missing/executables"),
+    params(OsFilterQuery),
+    responses(
+        (status = 200, description = "games without any executable for the OS, ordered by id", body = [GameMetadata])
+    )
+)]
+pub async fn get_games_missing_executables(
+    Query(query): Query<OsFilterQuery>,
+) -> Result<Json<Vec<GameMetadata>>, StatusCode> {
+    match DATABASE.get_games_missing_executables(query.os) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error listing games missing executables: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/storage"),
+    params(),
+    responses(
+        (status = 200, description = "storage used by each game with saves, largest first", body = [GameStorageStat])
+    )
+)]
+pub async fn get_games_storage_stats() -> Result<Json<Vec<GameStorageStat>>, StatusCode> {
+    match DATABASE.get_storage_stats() {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error getting storage stats: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/storage/total"),
+    params(),
+    responses(
+        (status = 200, description = "storage used by the whole server", body = StorageTotals)
+    )
+)]
+pub async fn get_total_storage_stats() -> Result<Json<StorageTotals>, StatusCode> {
+    match DATABASE.get_total_storage_stats() {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error getting total storage stats: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/search"),
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "games whose name contains the query", body = [GameMetadata])
+    )
+)]
+pub async fn search_games_metadata(
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Vec<GameMetadata>>, StatusCode> {
+    match DATABASE.search_game_metadata(&query.q) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error searching game metadata: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/search/activity"),
+    params(ActivitySearchQuery),
+    responses(
+        (status = 200, description = "games whose name contains the query and that have saves, most recent save first", body = [GameActivity])
+    )
+)]
+pub async fn search_games_with_activity(
+    Query(query): Query<ActivitySearchQuery>,
+) -> Result<Json<Vec<GameActivity>>, StatusCode> {
+    let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+    match DATABASE.search_games_with_activity(&query.q, query.os, limit) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error searching games with activity: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/search/ranked"),
+    params(RankedSearchQuery),
+    responses(
+        (status = 200, description = "games matching every word of the query, most relevant first", body = [GameMetadata])
+    )
+)]
+pub async fn search_games_metadata_ranked(
+    Query(query): Query<RankedSearchQuery>,
+) -> Result<Json<Vec<GameMetadata>>, StatusCode> {
+    let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+    match DATABASE.search_games_ranked(&query.q, limit) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error searching game metadata: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/match"),
+    params(FuzzyNameQuery),
+    responses(
+        (status = 200, description = "games with a similar name, best match first", body = [GameNameMatch]),
+        (status = 400, description = "threshold outside 0 to 1")
+    )
+)]
+pub async fn match_games_by_name(
+    Query(query): Query<FuzzyNameQuery>,
+) -> Result<Json<Vec<GameNameMatch>>, StatusCode> {
+    let threshold = query.threshold.unwrap_or(DEFAULT_FUZZY_MATCH_THRESHOLD);
+    match DATABASE.match_game_by_fuzzy_name(&query.name, threshold) {
+        Ok(data) => Ok(Json(
+            data.into_iter()
+                .map(|(game, score)| GameNameMatch { game, score })
+                .collect(),
+        )),
+        Err(DbError::Invalid(_)) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            eprintln!("Error matching game names: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/resolve"),
+    params(ResolveNameQuery),
+    responses(
+        (status = 200, description = "game the name refers to and which lookup found it", body = ResolvedGame),
+        (status = 404, description = "no game is known by that name")
+    )
+)]
+pub async fn resolve_game_by_name(
+    Query(query): Query<ResolveNameQuery>,
+) -> Result<Json<ResolvedGame>, StatusCode> {
+    match DATABASE.resolve_game(&query.name) {
+        Ok(Some(data)) => Ok(Json(data)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error resolving game name: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 #[utoipa::path(
     get,
     path = concatcp!(ROOT_API_PATH, "/games/{Id}"),
@@ -62,3 +327,373 @@ pub async fn get_game_metadata(Path(id): Path<i32>) -> Result<Json<GameMetadata>
         }
     }
 }
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/merge/{MergeId}"),
+    params(
+        ("Id" = String, Path, description = "Id of the game to keep"),
+        ("MergeId" = String, Path, description = "Id of the game merged into it and removed")
+    ),
+    responses(
+        (status = 204, description = "games merged"),
+        (status = 400, description = "cannot merge a game into itself"),
+        (status = 404, description = "game not found")
+    )
+)]
+pub async fn post_merge_game_metadata(Path((keep_id, merge_id)): Path<(i32, i32)>) -> StatusCode {
+    match DATABASE.merge_game_metadata(keep_id, merge_id) {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(DbError::Invalid(_)) => StatusCode::BAD_REQUEST,
+        Err(DbError::NotFound) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            eprintln!("Error merging game metadata: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/bundle"),
+    params(
+        ("Id" = String, Path, description = "Id of the game")
+    ),
+    responses(
+        (status = 200, description = "game metadata, paths and executables returned", body = GameBundle),
+        (status = 404, description = "game not found")
+    )
+)]
+pub async fn get_game_bundle(Path(id): Path<i32>) -> Result<Json<GameBundle>, StatusCode> {
+    match ASYNC_DATABASE.get_game_bundle(id).await {
+        Ok(Some(data)) => Ok(Json(data)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error getting game bundle: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/launch/{OS}"),
+    params(
+        ("Id" = String, Path, description = "Id of the game"),
+        ("OS" = OS, Path, description = "Operating system [OS]")
+    ),
+    responses(
+        (status = 200, description = "save paths and executables of the game for the operating system", body = LaunchProfile),
+        (status = 400, description = "invalid operating system"),
+        (status = 404, description = "game not found")
+    )
+)]
+pub async fn get_game_launch_profile(
+    Path((id, os)): Path<(i32, OS)>,
+) -> Result<Json<LaunchProfile>, StatusCode> {
+    match ASYNC_DATABASE.get_launch_profile(id, os).await {
+        Ok(data) => Ok(Json(data)),
+        Err(DbError::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error getting game launch profile: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/games/batch"),
+    params(),
+    request_body = [i32],
+    responses(
+        (status = 200, description = "games metadata in the order of the ids, unknown ids skipped", body = [GameMetadata])
+    )
+)]
+pub async fn post_games_metadata_by_ids(
+    Json(ids): Json<Vec<i32>>,
+) -> Result<Json<Vec<GameMetadata>>, StatusCode> {
+    match DATABASE.get_games_metadata_by_ids(&ids) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error retrieving games metadata by id: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/games/steam"),
+    params(),
+    request_body = [String],
+    responses(
+        (status = 200, description = "games metadata keyed by steam appid", body = HashMap<String, GameMetadata>)
+    )
+)]
+pub async fn post_games_metadata_by_steam_appids(
+    Json(appids): Json<Vec<String>>,
+) -> Result<Json<HashMap<String, GameMetadata>>, StatusCode> {
+    match DATABASE.get_games_by_steam_appids(&appids) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error retrieving games metadata by steam appid: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = concatcp!(ROOT_API_PATH, "/games/steam"),
+    params(),
+    request_body = GameMetadataCreate,
+    responses(
+        (status = 200, description = "game metadata created or updated by steam appid", body = GameMetadata)
+    )
+)]
+pub async fn put_game_metadata_by_steam_appid(
+    Json(payload): Json<GameMetadataCreate>,
+) -> Result<Json<GameMetadata>, StatusCode> {
+    match DATABASE.upsert_game_metadata_by_appid(&payload) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error upserting game metadata: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/steam/{Appid}"),
+    params(
+        ("Appid" = String, Path, description = "Steam appid of the game")
+    ),
+    responses(
+        (status = 200, description = "game metadata returned", body = GameMetadata),
+        (status = 404, description = "game not found")
+    )
+)]
+pub async fn get_game_metadata_by_steam_appid(
+    Path(appid): Path<String>,
+) -> Result<Json<GameMetadata>, StatusCode> {
+    match DATABASE.get_game_metadata_by_appid(&appid) {
+        Ok(Some(data)) => Ok(Json(data)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error getting game metadata by steam appid: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/changes"),
+    params(ChangesQuery),
+    responses(
+        (status = 200, description = "paths and executables changed since the revision, grouped by game", body = CatalogChildChanges)
+    )
+)]
+pub async fn get_games_child_changes(
+    Query(query): Query<ChangesQuery>,
+) -> Result<Json<CatalogChildChanges>, StatusCode> {
+    match DATABASE.get_child_changes_since(query.since) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error retrieving catalog changes: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}"),
+    params(
+        ("Id" = String, Path, description = "Id of the game")
+    ),
+    request_body = GameMetadataCreate,
+    responses(
+        (status = 200, description = "game metadata updated", body = GameMetadata),
+        (status = 404, description = "game not found"),
+        (status = 409, description = "conflicts with another game")
+    )
+)]
+pub async fn put_game_metadata(
+    Path(id): Path<i32>,
+    Json(payload): Json<GameMetadataCreate>,
+) -> Result<Json<GameMetadata>, StatusCode> {
+    match DATABASE.update_game_metadata(id, &payload) {
+        Ok(data) => Ok(Json(data)),
+        Err(DbError::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(DbError::UniqueViolation(_)) => Err(StatusCode::CONFLICT),
+        Err(e) => {
+            eprintln!("Error updating game metadata: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/export"),
+    responses(
+        (status = 200, description = "every game with its paths and executables", body = [GameBundle])
+    )
+)]
+pub async fn get_games_export() -> Result<impl IntoResponse, StatusCode> {
+    match DATABASE.export_metadata_to_json() {
+        Ok(json) => Ok(([(header::CONTENT_TYPE, "application/json")], json)),
+        Err(e) => {
+            eprintln!("Error exporting game metadata: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/games/import"),
+    request_body = [GameBundle],
+    responses(
+        (status = 200, description = "games imported, existing steam appids skipped", body = ImportSummary),
+        (status = 400, description = "invalid document")
+    )
+)]
+pub async fn post_games_import(body: String) -> Result<Json<ImportSummary>, StatusCode> {
+    match DATABASE.import_metadata_from_json(&body) {
+        Ok(summary) => Ok(Json(summary)),
+        Err(DbError::Invalid(_)) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            eprintln!("Error importing game metadata: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}"),
+    params(
+        ("Id" = String, Path, description = "Id of the game")
+    ),
+    responses(
+        (status = 204, description = "game moved to the trash, it can be restored"),
+        (status = 404, description = "game not found")
+    )
+)]
+pub async fn delete_game_metadata(Path(id): Path<i32>) -> StatusCode {
+    match DATABASE.delete_game_metadata(id) {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(DbError::NotFound) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            eprintln!("Error deleting game metadata: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/rename"),
+    params(
+        ("Id" = String, Path, description = "Id of the game")
+    ),
+    request_body = GameRename,
+    responses(
+        (status = 200, description = "game renamed, the previous name is now a known name", body = GameMetadata),
+        (status = 400, description = "empty name"),
+        (status = 404, description = "game not found")
+    )
+)]
+pub async fn post_rename_game(
+    Path(id): Path<i32>,
+    Json(payload): Json<GameRename>,
+) -> Result<Json<GameMetadata>, StatusCode> {
+    match DATABASE.rename_game(id, &payload.default_name) {
+        Ok(data) => Ok(Json(data)),
+        Err(DbError::Invalid(_)) => Err(StatusCode::BAD_REQUEST),
+        Err(DbError::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error renaming game: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/restore"),
+    params(
+        ("Id" = String, Path, description = "Id of the deleted game")
+    ),
+    responses(
+        (status = 204, description = "game restored"),
+        (status = 404, description = "no deleted game with this id"),
+        (status = 409, description = "another game now uses the steam appid")
+    )
+)]
+pub async fn post_restore_game_metadata(Path(id): Path<i32>) -> StatusCode {
+    match DATABASE.restore_game_metadata(id) {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(DbError::NotFound) => StatusCode::NOT_FOUND,
+        Err(DbError::UniqueViolation(_)) => StatusCode::CONFLICT,
+        Err(e) => {
+            eprintln!("Error restoring game metadata: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/permanent"),
+    params(
+        ("Id" = String, Path, description = "Id of the game")
+    ),
+    responses(
+        (status = 204, description = "game and all of its saves permanently deleted"),
+        (status = 404, description = "game not found")
+    )
+)]
+pub async fn delete_game_metadata_permanently(Path(id): Path<i32>) -> StatusCode {
+    match DATABASE.hard_delete_game_metadata(id) {
+        Ok(uuids) => {
+            for uuid in uuids {
+                let file_path = format!("{}/{}.sav", SAVE_DIR, uuid);
+                if let Err(e) = std::fs::remove_file(&file_path) {
+                    eprintln!("Error removing game save file {}: {}", file_path, e);
+                }
+            }
+            StatusCode::NO_CONTENT
+        }
+        Err(DbError::NotFound) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            eprintln!("Error permanently deleting game metadata: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/recent"),
+    params(LimitQuery),
+    responses(
+        (status = 200, description = "most recently added games first", body = [GameMetadata])
+    )
+)]
+pub async fn get_recently_added_games(
+    Query(query): Query<LimitQuery>,
+) -> Result<Json<Vec<GameMetadata>>, StatusCode> {
+    let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+    match DATABASE.get_recently_added_games(limit) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error getting recently added games: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
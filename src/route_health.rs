@@ -0,0 +1,61 @@
+use crate::ASYNC_DATABASE;
+use crate::const_var::ROOT_API_PATH;
+use crate::datatype_endpoint::{DbMetrics, HealthStatus, ServerVersion};
+use axum::{Json, http::StatusCode};
+use const_format::concatcp;
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/health"),
+    responses(
+        (status = 200, description = "database answers and the schema is up to date", body = HealthStatus),
+        (status = 503, description = "migrations are pending, or the database is unreachable", body = HealthStatus)
+    )
+)]
+pub async fn get_health() -> Result<(StatusCode, Json<HealthStatus>), StatusCode> {
+    match ASYNC_DATABASE.health_check().await {
+        Ok(status) if status.is_ready() => Ok((StatusCode::OK, Json(status))),
+        Ok(status) => Ok((StatusCode::SERVICE_UNAVAILABLE, Json(status))),
+        Err(e) => {
+            eprintln!("Error checking database health: {}", e);
+            Err(StatusCode::SERVICE_UNAVAILABLE)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/version"),
+    responses(
+        (status = 200, description = "server and database schema versions", body = ServerVersion)
+    )
+)]
+pub async fn get_version() -> Result<Json<ServerVersion>, StatusCode> {
+    match ASYNC_DATABASE.schema_version().await {
+        Ok(schema_version) => Ok(Json(ServerVersion {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version,
+        })),
+        Err(e) => {
+            eprintln!("Error reading schema version: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/metrics"),
+    responses(
+        (status = 200, description = "database counters and connection pool usage", body = DbMetrics)
+    )
+)]
+pub async fn get_metrics() -> Result<Json<DbMetrics>, StatusCode> {
+    match ASYNC_DATABASE.collect_metrics().await {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error collecting database metrics: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -1,8 +1,12 @@
 use crate::DATABASE;
-use crate::const_var::ROOT_API_PATH;
-use crate::datatype_endpoint::{OS, SavePath, SavePathCreate};
+use crate::const_var::{ROOT_API_PATH, SAVE_DIR};
+use crate::database::error::DbError;
+use crate::datatype_endpoint::{GameSavePath, OS, SavePath, SavePathCreate, SavePathUpdate};
+use axum::extract::rejection::JsonRejection;
+use axum::response::{IntoResponse, Response};
 use axum::{Json, extract::Path, http::StatusCode};
 use const_format::concatcp;
+use std::fs;
 
 #[utoipa::path(
     get,
@@ -49,6 +53,57 @@ pub async fn get_game_paths_by_os(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/paths/suggested/{OS}"),
+    params(
+        ("Id" = String, Path, description = "Id of the game"),
+        ("OS" = OS, Path, description = "Operating system [OS]")
+    ),
+    responses(
+        (status = 200, description = "paths translated from the game's other operating systems, not stored", body = [String]),
+        (status = 400, description = "invalid operating system")
+    )
+)]
+pub async fn get_suggested_game_paths_by_os(
+    Path((id, os)): Path<(i32, OS)>,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    match DATABASE.suggest_paths_for_os(id, os) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error suggesting game paths: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/os/{OS}"),
+    params(
+        ("OS" = OS, Path, description = "Operating system [OS]")
+    ),
+    responses(
+        (status = 200, description = "paths of every game for the operating system, ordered by game id", body = [GameSavePath]),
+        (status = 400, description = "invalid operating system")
+    )
+)]
+pub async fn get_all_paths_by_os(
+    Path(os): Path<OS>,
+) -> Result<Json<Vec<GameSavePath>>, StatusCode> {
+    match DATABASE.get_all_paths_for_os(os) {
+        Ok(data) => Ok(Json(
+            data.into_iter()
+                .map(|(game_id, path)| GameSavePath { game_id, path })
+                .collect(),
+        )),
+        Err(e) => {
+            eprintln!("Error getting paths for operating system: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 #[utoipa::path(
     post,
     path = concatcp!(ROOT_API_PATH, "/games/{Id}/paths"),
@@ -58,17 +113,156 @@ pub async fn get_game_paths_by_os(
     request_body = SavePathCreate,
     responses(
         (status = 201, description = "game path created"),
+        (status = 400, description = "invalid body, e.g. an unknown operating system", body = String),
+        (status = 404, description = "game not found"),
+        (status = 409, description = "game path already exists"),
     )
 )]
 pub async fn post_game_path(
     Path(id): Path<i32>,
-    Json(payload): Json<SavePathCreate>,
-) -> StatusCode {
+    payload: Result<Json<SavePathCreate>, JsonRejection>,
+) -> Response {
+    let Json(payload) = match payload {
+        Ok(payload) => payload,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.body_text()).into_response(),
+    };
     match DATABASE.add_game_path(id, &payload) {
         Ok(()) => StatusCode::CREATED,
+        Err(DbError::UniqueViolation(_)) => StatusCode::CONFLICT,
+        Err(DbError::ForeignKeyViolation(_)) => StatusCode::NOT_FOUND,
         Err(e) => {
             eprintln!("Error adding game path: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
         }
     }
+    .into_response()
+}
+
+#[utoipa::path(
+    put,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/paths"),
+    params(
+        ("Id" = String, Path, description = "Id of the game"),
+    ),
+    request_body = [SavePathCreate],
+    responses(
+        (status = 204, description = "game paths replaced, unchanged paths keep their id and saves"),
+        (status = 400, description = "invalid body, e.g. an unknown operating system", body = String),
+        (status = 404, description = "game not found"),
+    )
+)]
+pub async fn put_game_paths(
+    Path(id): Path<i32>,
+    payload: Result<Json<Vec<SavePathCreate>>, JsonRejection>,
+) -> Response {
+    let Json(payload) = match payload {
+        Ok(payload) => payload,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.body_text()).into_response(),
+    };
+    match DATABASE.replace_game_paths_with_saves(id, &payload) {
+        Ok(save_uuids) => {
+            for uuid in save_uuids {
+                let file_path = format!("{}/{}.sav", SAVE_DIR, uuid);
+                if let Err(e) = fs::remove_file(&file_path) {
+                    eprintln!("Error removing game save file {}: {}", file_path, e);
+                }
+            }
+            StatusCode::NO_CONTENT
+        }
+        Err(DbError::NotFound) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            eprintln!("Error replacing game paths: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+    .into_response()
+}
+
+#[utoipa::path(
+    put,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}"),
+    params(
+        ("Id" = String, Path, description = "Id of the path")
+    ),
+    request_body = SavePathUpdate,
+    responses(
+        (status = 200, description = "path updated in place, its saves stay attached", body = SavePath),
+        (status = 400, description = "invalid body, e.g. an empty path", body = String),
+        (status = 404, description = "path not found"),
+        (status = 409, description = "the game already has this path for the operating system")
+    )
+)]
+pub async fn put_game_path(
+    Path(path_id): Path<i32>,
+    payload: Result<Json<SavePathUpdate>, JsonRejection>,
+) -> Response {
+    let Json(payload) = match payload {
+        Ok(payload) => payload,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.body_text()).into_response(),
+    };
+    match DATABASE.update_game_path(path_id, &payload.path, payload.operating_system) {
+        Ok(path) => Json(path).into_response(),
+        Err(DbError::Invalid(message)) => (StatusCode::BAD_REQUEST, message).into_response(),
+        Err(DbError::NotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(DbError::UniqueViolation(_)) => StatusCode::CONFLICT.into_response(),
+        Err(e) => {
+            eprintln!("Error updating game path: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}"),
+    params(
+        ("Id" = String, Path, description = "Id of the path")
+    ),
+    responses(
+        (status = 204, description = "path and its saves deleted"),
+        (status = 404, description = "path not found")
+    )
+)]
+pub async fn delete_game_path(Path(path_id): Path<i32>) -> StatusCode {
+    match DATABASE.delete_game_path_with_saves(path_id) {
+        Ok(Some(save_uuids)) => {
+            for uuid in save_uuids {
+                let file_path = format!("{}/{}.sav", SAVE_DIR, uuid);
+                if let Err(e) = fs::remove_file(&file_path) {
+                    eprintln!("Error removing game save file {}: {}", file_path, e);
+                }
+            }
+            StatusCode::NO_CONTENT
+        }
+        Ok(None) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            eprintln!("Error deleting game path: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::extract::{FromRequest, Request};
+    use axum::http::header;
+
+    #[tokio::test]
+    async fn unknown_operating_system_lists_the_valid_values() {
+        let request = Request::post("/v1/games/1/paths")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"path":"~/saves","operating_system":"bsd"}"#))
+            .unwrap();
+        let payload = Json::<SavePathCreate>::from_request(request, &()).await;
+
+        let response = post_game_path(Path(1), payload).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        for os in OS::ACCEPTED {
+            assert!(body.contains(os.as_str()), "{body} does not list {os}");
+        }
+    }
 }
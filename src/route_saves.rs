@@ -1,12 +1,29 @@
-use crate::DATABASE;
-use crate::const_var::{ROOT_API_PATH, SAVE_DIR, TMP_DIR};
-use crate::datatype_endpoint::{SaveReference, UploadedSave};
-use crate::file_system::write_bytes_to_data_file;
+use crate::auth::Caller;
+use crate::blob_store::BlobStore;
+use crate::const_var::{
+    DEFAULT_QUERY_LIMIT, IDEMPOTENCY_KEY_HEADER, MAX_SAVES_PER_PATH, ROOT_API_PATH, SAVE_DIR,
+    TMP_DIR,
+};
+use crate::database::error::DbError;
+use crate::database::interface::{QuotaPolicy, SaveInsert};
+use crate::datatype_endpoint::{
+    AdminSaveRow, ConflictQuery, ConflictStatus, FileVersion, FullRewrite, GenerationQuery,
+    LabelQuery, LimitQuery, RelativePathQuery, ResolveOutcome, RetentionImpact, RetentionPolicy,
+    RetentionQuery, SaveActivity, SaveAttribute, SaveCreatedEvent, SaveCursorQuery, SaveDiff,
+    SaveLabel, SaveManifest, SavePageQuery, SavePinned, SaveReassign, SaveReference, SaveSummary,
+    SyncStrategy, TimeRangeQuery, UploadedSave, validate_device_name, validate_idempotency_key,
+    validate_save_label,
+};
+use crate::file_system::{sanitize_relative_path, write_bytes_to_data_file};
+use crate::webhook::notify_save_created;
+use crate::{ASYNC_DATABASE, BLOB_STORE, DATABASE};
 use axum::body::Body;
-use axum::extract::Multipart;
+use axum::extract::{Extension, Multipart, Query};
+use axum::http::{HeaderMap, header};
 use axum::response::{IntoResponse, Response};
 use axum::{Json, extract::Path, http::StatusCode};
 use const_format::concatcp;
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use tokio::fs::File;
@@ -27,8 +44,21 @@ use uuid::Uuid;
 )]
 pub async fn get_game_saves_reference_by_path_id(
     Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
 ) -> Result<Json<Vec<SaveReference>>, StatusCode> {
-    match DATABASE.get_reference_to_save_by_path_id(path_id) {
+    if let Some(user_id) = caller.user_id() {
+        return match DATABASE.get_saves_for_user(user_id, path_id) {
+            Ok(data) => Ok(Json(data)),
+            Err(e) => {
+                eprintln!("Error getting game saves reference: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        };
+    }
+    match ASYNC_DATABASE
+        .get_reference_to_save_by_path_id(path_id)
+        .await
+    {
         Ok(Some(data)) => Ok(Json(data)),
         Ok(None) => Err(StatusCode::NOT_FOUND),
         Err(e) => {
@@ -38,11 +68,359 @@ pub async fn get_game_saves_reference_by_path_id(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/saves/summary"),
+    params(
+        ("Id" = String, Path, description = "Id of the path")
+    ),
+    responses(
+        (status = 200, description = "game saves without their file hashes, oldest first", body = [SaveSummary])
+    )
+)]
+pub async fn get_game_save_summaries_by_path_id(
+    Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<SaveSummary>>, StatusCode> {
+    match DATABASE.list_save_summaries(path_id, caller.user_id()) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error listing game save summaries: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/saves/page"),
+    params(
+        ("Id" = String, Path, description = "Id of the path"),
+        SaveCursorQuery
+    ),
+    responses(
+        (status = 200, description = "next page of game saves ordered by (time, uuid), the last row is the next cursor", body = [SaveSummary]),
+        (status = 400, description = "invalid cursor")
+    )
+)]
+pub async fn get_game_saves_page_by_path_id(
+    Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<SaveCursorQuery>,
+) -> Result<Json<Vec<SaveSummary>>, StatusCode> {
+    let after_uuid = match query.after_uuid.as_deref().map(Uuid::parse_str) {
+        Some(Ok(uuid)) => Some(uuid),
+        Some(Err(_)) => return Err(StatusCode::BAD_REQUEST),
+        None => None,
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+    match DATABASE.list_saves_after(
+        path_id,
+        caller.user_id(),
+        query.after_time,
+        after_uuid,
+        limit,
+    ) {
+        Ok(data) => Ok(Json(data)),
+        Err(DbError::Invalid(_)) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            eprintln!("Error paging game saves: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/saves/since"),
+    params(
+        ("Id" = String, Path, description = "Id of the path"),
+        GenerationQuery
+    ),
+    responses(
+        (status = 200, description = "game saves newer than the generation, oldest first", body = [SaveReference])
+    )
+)]
+pub async fn get_game_saves_since_generation(
+    Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<GenerationQuery>,
+) -> Result<Json<Vec<SaveReference>>, StatusCode> {
+    match DATABASE.get_saves_since_generation(path_id, caller.user_id(), query.since) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error getting game saves since generation: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/saves/labeled"),
+    params(
+        ("Id" = String, Path, description = "Id of the path"),
+        LabelQuery
+    ),
+    responses(
+        (status = 200, description = "game saves whose label matches, oldest first", body = [SaveReference])
+    )
+)]
+pub async fn get_game_saves_by_label(
+    Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<LabelQuery>,
+) -> Result<Json<Vec<SaveReference>>, StatusCode> {
+    match DATABASE.find_saves_by_label(path_id, caller.user_id(), &query.label) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error finding game saves by label: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/files/history"),
+    params(
+        ("Id" = String, Path, description = "Id of the path"),
+        RelativePathQuery
+    ),
+    responses(
+        (status = 200, description = "content hashes the file had across the saves, oldest first", body = [FileVersion])
+    )
+)]
+pub async fn get_file_hash_history(
+    Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<RelativePathQuery>,
+) -> Result<Json<Vec<FileVersion>>, StatusCode> {
+    match DATABASE.get_hash_history_for_path(path_id, caller.user_id(), &query.relative_path) {
+        Ok(data) => Ok(Json(
+            data.into_iter()
+                .map(|(hash, time)| FileVersion { hash, time })
+                .collect(),
+        )),
+        Err(e) => {
+            eprintln!("Error getting file hash history: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/saves/count"),
+    params(
+        ("Id" = String, Path, description = "Id of the path")
+    ),
+    responses(
+        (status = 200, description = "number of game saves for the path", body = i64)
+    )
+)]
+pub async fn get_game_saves_count_by_path_id(
+    Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<i64>, StatusCode> {
+    match DATABASE.count_saves_for_path(path_id, caller.user_id()) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error counting game saves: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/saves/range"),
+    params(
+        ("Id" = String, Path, description = "Id of the path"),
+        TimeRangeQuery
+    ),
+    responses(
+        (status = 200, description = "game saves created in the range, oldest first", body = [SaveReference]),
+        (status = 400, description = "invalid timestamp")
+    )
+)]
+pub async fn get_game_saves_in_range_by_path_id(
+    Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+    Query(range): Query<TimeRangeQuery>,
+) -> Result<Json<Vec<SaveReference>>, StatusCode> {
+    match DATABASE.get_saves_in_range(path_id, caller.user_id(), range.start, range.end) {
+        Ok(data) => Ok(Json(data)),
+        Err(DbError::Invalid(_)) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            eprintln!("Error getting game saves in range: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/saves/latest"),
+    params(
+        ("Id" = String, Path, description = "Id of the path")
+    ),
+    responses(
+        (status = 200, description = "newest game save returned, with a weak ETag", body = SaveReference),
+        (status = 304, description = "the If-None-Match tag is still the newest save's"),
+        (status = 404, description = "no save stored for this path")
+    )
+)]
+pub async fn get_latest_game_save_reference_by_path_id(
+    Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let etag = match DATABASE.latest_save_etag(path_id, caller.user_id()) {
+        Ok(Some(etag)) => etag,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error getting latest game save etag: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    // Clients may keep the payload but have to check back every time
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ];
+    if etag_matches(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+
+    match DATABASE.get_latest_save_reference_for_path(path_id, caller.user_id()) {
+        Ok(Some(data)) => Ok((cache_headers, Json(data)).into_response()),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error getting latest game save reference: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/saves/latest"),
+    params(
+        ("Id" = String, Path, description = "Id of the game")
+    ),
+    responses(
+        (status = 200, description = "newest save of each path of the game that has one, by path id", body = [SaveReference]),
+        (status = 404, description = "game not found")
+    )
+)]
+pub async fn get_latest_game_saves_by_game_id(
+    Path((game_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<SaveReference>>, StatusCode> {
+    match DATABASE.get_latest_saves_for_game(game_id, caller.user_id()) {
+        Ok(data) => Ok(Json(data)),
+        Err(DbError::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error getting latest game saves of game: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Weak comparison of `etag` against every tag of `If-None-Match`
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/saves/rewrites"),
+    params(
+        ("Id" = String, Path, description = "Id of the path")
+    ),
+    responses(
+        (status = 200, description = "saves sharing no file with the previous save", body = [FullRewrite]),
+    )
+)]
+pub async fn get_game_saves_full_rewrites_by_path_id(
+    Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<FullRewrite>>, StatusCode> {
+    match DATABASE.detect_full_rewrites(path_id, caller.user_id()) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error detecting game saves full rewrites: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/saves/conflict"),
+    params(
+        ("Id" = String, Path, description = "Id of the path"),
+        ConflictQuery
+    ),
+    responses(
+        (status = 200, description = "whether the base is still the latest save", body = ConflictStatus),
+        (status = 400, description = "invalid uuid")
+    )
+)]
+pub async fn get_game_save_conflict(
+    Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<ConflictQuery>,
+) -> Result<Json<ConflictStatus>, StatusCode> {
+    let base_uuid = match query.base_uuid {
+        Some(base_uuid) => Some(Uuid::parse_str(&base_uuid).map_err(|_| StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    match DATABASE.check_save_conflict(path_id, caller.user_id(), base_uuid) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error checking game save conflict: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/devices"),
+    params(
+        ("Id" = String, Path, description = "Id of the path")
+    ),
+    responses(
+        (status = 200, description = "device names the path's saves were uploaded from", body = [String])
+    )
+)]
+pub async fn get_path_devices(
+    Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    match DATABASE.list_devices_for_path(path_id, caller.user_id()) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error listing path devices: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 #[utoipa::path(
     post,
     path = concatcp!(ROOT_API_PATH, "/paths/{Id}/saves/upload"),
     params(
-        ("Id" = String, Path, description = "Id of the path")
+        ("Id" = String, Path, description = "Id of the path"),
+        ("Idempotency-Key" = Option<String>, Header, description = "retrying with the key of an earlier upload of the path returns that save instead of storing a new one")
     ),
     request_body(
         content = UploadedSave,
@@ -51,20 +429,40 @@ pub async fn get_game_saves_reference_by_path_id(
     ),
     responses(
         (status = 201, description = "game save created", body = String),
-        (status = 404, description = "path not found")
+        (status = 200, description = "an earlier upload used the same idempotency key, body is its uuid", body = String),
+        (status = 400, description = "invalid base uuid, strategy, label, device name, save time, file path or idempotency key, or prefer_newest without a save time"),
+        (status = 404, description = "path not found"),
+        (status = 409, description = "strategy rejected the upload, body is the latest save uuid", body = String)
     )
 )]
 pub async fn post_game_save_by_path_id(
     Path((path_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
     mut multipart: Multipart,
 ) -> Result<(StatusCode, String), StatusCode> {
     let uuid = Uuid::new_v4();
     let tmp_path = format!("{}/{}.sav", TMP_DIR, uuid);
     let save_path = format!("{}/{}.sav", SAVE_DIR, uuid);
 
-    let result: Result<(), Box<dyn std::error::Error + Send + Sync>> = async {
+    let idempotency_key = match headers.get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) => {
+            let key = value.to_str().map_err(|_| StatusCode::BAD_REQUEST)?;
+            validate_idempotency_key(key).map_err(|_| StatusCode::BAD_REQUEST)?;
+            Some(key.to_string())
+        }
+        None => None,
+    };
+
+    let mut bad_request = false;
+    let result: Result<(StatusCode, String), Box<dyn std::error::Error + Send + Sync>> = async {
         let mut file_hash: Vec<crate::datatype_endpoint::FileHash> = Vec::new();
         let mut file_bytes: Vec<u8> = Vec::new();
+        let mut base_uuid: Option<Uuid> = None;
+        let mut strategy: Option<SyncStrategy> = None;
+        let mut label: Option<String> = None;
+        let mut device_name: Option<String> = None;
+        let mut save_time: Option<time::OffsetDateTime> = None;
 
         while let Some(field) = multipart.next_field().await? {
             match field.name() {
@@ -72,6 +470,43 @@ pub async fn post_game_save_by_path_id(
                     let bytes = field.bytes().await?;
                     let json_str = String::from_utf8(bytes.to_vec())?;
                     file_hash = serde_json::from_str(&json_str)?;
+                    bad_request |= file_hash
+                        .iter()
+                        .any(|file| sanitize_relative_path(&file.relative_path).is_err());
+                }
+                Some("base_uuid") => {
+                    let parsed = Uuid::parse_str(&field.text().await?);
+                    bad_request |= parsed.is_err();
+                    base_uuid = parsed.ok();
+                }
+                Some("strategy") => {
+                    let parsed = field.text().await?.parse::<SyncStrategy>();
+                    bad_request |= parsed.is_err();
+                    strategy = parsed.ok();
+                }
+                Some("label") => {
+                    let text = field.text().await?;
+                    bad_request |= validate_save_label(&text).is_err();
+                    label = Some(text);
+                }
+                Some("device_name") => {
+                    let text = field.text().await?;
+                    bad_request |= validate_device_name(&text).is_err();
+                    device_name = Some(text);
+                }
+                Some("save_time") => {
+                    let parsed =
+                        field
+                            .text()
+                            .await?
+                            .trim()
+                            .parse::<i64>()
+                            .ok()
+                            .and_then(|seconds| {
+                                time::OffsetDateTime::from_unix_timestamp(seconds).ok()
+                            });
+                    bad_request |= parsed.is_none();
+                    save_time = parsed;
                 }
                 _ => {
                     let data = field.bytes().await?;
@@ -80,31 +515,193 @@ pub async fn post_game_save_by_path_id(
             }
         }
 
-        write_bytes_to_data_file(&tmp_path, &save_path, &file_bytes).await?;
+        // Without the client's time there is nothing to tell which side is newer
+        bad_request |= strategy == Some(SyncStrategy::PreferNewest) && save_time.is_none();
+        if bad_request {
+            return Err(
+                "invalid base_uuid, strategy, label, device_name, save_time or file_hash field"
+                    .into(),
+            );
+        }
 
-        DATABASE.add_reference_to_save(uuid, path_id, file_hash)?;
+        write_bytes_to_data_file(&tmp_path, &save_path, &file_bytes).await?;
 
-        if let Some(mut saves_ref) = DATABASE.get_reference_to_save_by_path_id(path_id)? {
-            saves_ref.sort_by_key(|save_ref| save_ref.time);
-            while saves_ref.len() > 5 {
-                let old = saves_ref.remove(0);
-                let old_path = format!("{}/{}.sav", SAVE_DIR, old.uuid);
-                let _ = std::fs::remove_file(&old_path);
+        let keep = DATABASE.retention_keep_for_path(path_id, MAX_SAVES_PER_PATH)?;
+        let pruned_uuids = match strategy {
+            None => match DATABASE.add_reference_to_save_with_quota(
+                uuid,
+                path_id,
+                caller.user_id(),
+                file_hash,
+                label,
+                device_name,
+                keep,
+                QuotaPolicy::PruneOldest,
+                idempotency_key.as_deref(),
+            )? {
+                SaveInsert::Created(pruned) => {
+                    remove_orphaned_blobs(&pruned.orphaned_hashes);
+                    pruned.uuids
+                }
+                SaveInsert::Replayed(existing) => return Ok(replayed_upload(&save_path, existing)),
+            },
+            Some(strategy) => {
+                let incoming = SaveReference {
+                    uuid: uuid.to_string(),
+                    path_id,
+                    time: save_time.unwrap_or_else(time::OffsetDateTime::now_utc),
+                    total_size_bytes: SaveReference::total_size(&file_hash),
+                    generation: 0,
+                    label,
+                    pinned: false,
+                    device_name,
+                    files_hash: file_hash,
+                    attributes: HashMap::new(),
+                };
+                match DATABASE.resolve_upload(
+                    path_id,
+                    incoming,
+                    base_uuid,
+                    strategy,
+                    caller.user_id(),
+                    idempotency_key.as_deref(),
+                )? {
+                    SaveInsert::Replayed(existing) => {
+                        return Ok(replayed_upload(&save_path, existing));
+                    }
+                    SaveInsert::Created(ResolveOutcome::Rejected { latest_uuid }) => {
+                        let _ = fs::remove_file(&save_path);
+                        return Ok((StatusCode::CONFLICT, latest_uuid));
+                    }
+                    SaveInsert::Created(ResolveOutcome::Replaced {
+                        replaced_uuid,
+                        orphaned_hashes,
+                        ..
+                    }) => {
+                        remove_orphaned_blobs(&orphaned_hashes);
+                        let replaced_path = format!("{}/{}.sav", SAVE_DIR, replaced_uuid);
+                        let _ = std::fs::remove_file(&replaced_path);
+                    }
+                    SaveInsert::Created(
+                        ResolveOutcome::Stored { .. } | ResolveOutcome::KeptBoth { .. },
+                    ) => {}
+                }
+                let pruned = DATABASE.prune_save_references_for_path(path_id, keep)?;
+                remove_orphaned_blobs(&pruned.orphaned_hashes);
+                pruned.uuids
             }
+        };
+
+        notify_save_created(SaveCreatedEvent {
+            uuid: uuid.to_string(),
+            path_id,
+            game_name: DATABASE.get_game_name_by_path_id(path_id).unwrap_or(None),
+            time: time::OffsetDateTime::now_utc().unix_timestamp(),
+            size: file_bytes.len() as u64,
+        });
+
+        for old_uuid in pruned_uuids {
+            let old_path = format!("{}/{}.sav", SAVE_DIR, old_uuid);
+            let _ = std::fs::remove_file(&old_path);
         }
 
-        Ok(())
+        Ok((StatusCode::CREATED, uuid.to_string()))
     }
     .await;
 
-    if let Err(e) = result {
-        eprintln!("Error uploading game save: {}", e);
-        //Try to clean up
-        let _ = fs::remove_file(&tmp_path);
-        let _ = fs::remove_file(&save_path);
-        Err(StatusCode::INTERNAL_SERVER_ERROR)
-    } else {
-        Ok((StatusCode::CREATED, uuid.to_string()))
+    match result {
+        Ok(response) => Ok(response),
+        Err(e) => {
+            eprintln!("Error uploading game save: {}", e);
+            //Try to clean up
+            let _ = fs::remove_file(&tmp_path);
+            let _ = fs::remove_file(&save_path);
+            if bad_request {
+                Err(StatusCode::BAD_REQUEST)
+            } else {
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+/// 403 when a user calls on a save they did not upload, a shared token
+/// reaches every save. A missing save is left for the route to report
+fn check_save_owner(caller: Caller, uuid: Uuid) -> Result<(), StatusCode> {
+    let Some(user_id) = caller.user_id() else {
+        return Ok(());
+    };
+    match DATABASE.get_save_owner(uuid) {
+        Ok(Some(owner)) if owner != Some(user_id) => Err(StatusCode::FORBIDDEN),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            eprintln!("Error looking up the owner of save {}: {}", uuid, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// 403 for user tokens on the routes spanning every user's saves
+fn require_shared(caller: Caller) -> Result<(), StatusCode> {
+    match caller {
+        Caller::Shared => Ok(()),
+        Caller::User(_) => Err(StatusCode::FORBIDDEN),
+    }
+}
+
+/// Drops the file written for a retried upload, the save its earlier
+/// attempt stored is already on disk
+fn replayed_upload(save_path: &str, existing: SaveReference) -> (StatusCode, String) {
+    let _ = fs::remove_file(save_path);
+    (StatusCode::OK, existing.uuid)
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/saves"),
+    params(SavePageQuery),
+    responses(
+        (status = 200, description = "every stored save, newest first", body = [AdminSaveRow]),
+        (status = 403, description = "only shared tokens reach the saves of every user")
+    )
+)]
+pub async fn get_all_game_saves(
+    Query(page): Query<SavePageQuery>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<AdminSaveRow>>, StatusCode> {
+    require_shared(caller)?;
+    match DATABASE.list_all_saves(
+        page.offset.unwrap_or(0),
+        page.limit.unwrap_or(DEFAULT_QUERY_LIMIT),
+    ) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error listing all game saves: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/saves/latest"),
+    responses(
+        (status = 200, description = "most recent game save returned", body = SaveActivity),
+        (status = 404, description = "no save stored"),
+        (status = 403, description = "only shared tokens reach the saves of every user")
+    )
+)]
+pub async fn get_most_recent_game_save(
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<SaveActivity>, StatusCode> {
+    require_shared(caller)?;
+    match DATABASE.get_most_recent_save() {
+        Ok(Some(data)) => Ok(Json(data)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error getting most recent game save: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
     }
 }
 
@@ -116,10 +713,20 @@ pub async fn post_game_save_by_path_id(
     ),
     responses(
         (status = 200, description = "game save file returned", content_type = "application/octet-stream"),
-        (status = 404, description = "save not found")
+        (status = 404, description = "save not found"),
+        (status = 403, description = "the save belongs to another user")
     )
 )]
-pub async fn get_game_save_by_uuid(Path((uuid,)): Path<(String,)>) -> impl IntoResponse {
+pub async fn get_game_save_by_uuid(
+    Path((uuid,)): Path<(String,)>,
+    Extension(caller): Extension<Caller>,
+) -> Response {
+    let Ok(parsed) = Uuid::parse_str(&uuid) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if let Err(status) = check_save_owner(caller, parsed) {
+        return status.into_response();
+    }
     let file_path = format!("{}/{}.sav", SAVE_DIR, uuid);
     let path_buf = PathBuf::from(&file_path);
 
@@ -153,3 +760,579 @@ pub async fn get_game_save_by_uuid(Path((uuid,)): Path<(String,)>) -> impl IntoR
             .unwrap(),
     }
 }
+
+#[utoipa::path(
+    delete,
+    path = concatcp!(ROOT_API_PATH, "/saves/{uuid}"),
+    params(
+        ("uuid" = String, Path, description = "UUID of the game save")
+    ),
+    responses(
+        (status = 204, description = "game save deleted"),
+        (status = 400, description = "invalid uuid"),
+        (status = 404, description = "save not found"),
+        (status = 403, description = "the save belongs to another user")
+    )
+)]
+pub async fn delete_game_save_by_uuid(
+    Path((uuid,)): Path<(String,)>,
+    Extension(caller): Extension<Caller>,
+) -> StatusCode {
+    let Ok(uuid) = Uuid::parse_str(&uuid) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    if let Err(status) = check_save_owner(caller, uuid) {
+        return status;
+    }
+    match DATABASE.delete_save_reference(uuid) {
+        Ok(Some(orphaned_hashes)) => {
+            let file_path = format!("{}/{}.sav", SAVE_DIR, uuid);
+            if let Err(e) = fs::remove_file(&file_path) {
+                eprintln!("Error removing game save file {}: {}", file_path, e);
+            }
+            remove_orphaned_blobs(&orphaned_hashes);
+            StatusCode::NO_CONTENT
+        }
+        Ok(None) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            eprintln!("Error deleting game save: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/saves/delete"),
+    request_body = Vec<String>,
+    responses(
+        (status = 200, description = "uuids of the saves deleted, unknown ones are skipped", body = [String]),
+        (status = 400, description = "invalid uuid"),
+        (status = 403, description = "the save belongs to another user")
+    )
+)]
+pub async fn post_delete_game_saves(
+    Extension(caller): Extension<Caller>,
+    Json(uuids): Json<Vec<String>>,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    let uuids = uuids
+        .iter()
+        .map(|uuid| Uuid::parse_str(uuid))
+        .collect::<Result<Vec<Uuid>, _>>()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    for uuid in &uuids {
+        check_save_owner(caller, *uuid)?;
+    }
+    match DATABASE.delete_save_references(&uuids) {
+        Ok(deleted) => {
+            for uuid in &deleted.uuids {
+                let _ = fs::remove_file(format!("{}/{}.sav", SAVE_DIR, uuid));
+            }
+            remove_orphaned_blobs(&deleted.orphaned_hashes);
+            Ok(Json(deleted.uuids))
+        }
+        Err(e) => {
+            eprintln!("Error deleting game saves: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/saves/{uuid}/reference"),
+    params(
+        ("uuid" = String, Path, description = "UUID of the game save")
+    ),
+    responses(
+        (status = 200, description = "game save reference returned", body = SaveReference),
+        (status = 400, description = "invalid uuid"),
+        (status = 404, description = "save not found"),
+        (status = 403, description = "the save belongs to another user")
+    )
+)]
+pub async fn get_game_save_reference_by_uuid(
+    Path((uuid,)): Path<(String,)>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<SaveReference>, StatusCode> {
+    let uuid = Uuid::parse_str(&uuid).map_err(|_| StatusCode::BAD_REQUEST)?;
+    check_save_owner(caller, uuid)?;
+    match DATABASE.get_reference_to_save_by_uuid(uuid) {
+        Ok(Some(data)) => Ok(Json(data)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error getting game save reference: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/saves/{uuid}/manifest"),
+    params(
+        ("uuid" = String, Path, description = "UUID of the game save")
+    ),
+    responses(
+        (status = 200, description = "save manifest returned, the blobs are not included", body = SaveManifest),
+        (status = 400, description = "invalid uuid"),
+        (status = 404, description = "save not found"),
+        (status = 403, description = "the save belongs to another user")
+    )
+)]
+pub async fn get_game_save_manifest(
+    Path((uuid,)): Path<(String,)>,
+    Extension(caller): Extension<Caller>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let uuid = Uuid::parse_str(&uuid).map_err(|_| StatusCode::BAD_REQUEST)?;
+    check_save_owner(caller, uuid)?;
+    match DATABASE.export_save_manifest(uuid) {
+        Ok(json) => Ok(([(header::CONTENT_TYPE, "application/json")], json)),
+        Err(DbError::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error exporting save manifest: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/paths/{Id}/saves/manifest"),
+    params(
+        ("Id" = String, Path, description = "Id of the path the save is recreated under")
+    ),
+    request_body = SaveManifest,
+    responses(
+        (status = 201, description = "save recreated with its original uuid and time", body = SaveReference),
+        (status = 400, description = "invalid manifest"),
+        (status = 404, description = "path not found"),
+        (status = 409, description = "a save with this uuid already exists")
+    )
+)]
+pub async fn post_game_save_manifest(
+    Path((path_id,)): Path<(i32,)>,
+    body: String,
+) -> Result<(StatusCode, Json<SaveReference>), StatusCode> {
+    match DATABASE.import_save_manifest(&body, path_id) {
+        Ok(save) => Ok((StatusCode::CREATED, Json(save))),
+        Err(DbError::Invalid(_)) => Err(StatusCode::BAD_REQUEST),
+        Err(DbError::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(DbError::UniqueViolation(_)) => Err(StatusCode::CONFLICT),
+        Err(e) => {
+            eprintln!("Error importing save manifest: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/saves/{uuid}/diff/{other_uuid}"),
+    params(
+        ("uuid" = String, Path, description = "UUID of the game save to compare from"),
+        ("other_uuid" = String, Path, description = "UUID of the game save to compare to")
+    ),
+    responses(
+        (status = 200, description = "file differences between the two saves", body = SaveDiff),
+        (status = 400, description = "invalid uuid"),
+        (status = 404, description = "save not found"),
+        (status = 403, description = "the save belongs to another user")
+    )
+)]
+pub async fn get_game_save_diff(
+    Path((from, to)): Path<(String, String)>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<SaveDiff>, StatusCode> {
+    let from = Uuid::parse_str(&from).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let to = Uuid::parse_str(&to).map_err(|_| StatusCode::BAD_REQUEST)?;
+    check_save_owner(caller, from)?;
+    check_save_owner(caller, to)?;
+    match DATABASE.diff_save_references(from, to) {
+        Ok(data) => Ok(Json(data)),
+        Err(DbError::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error diffing game saves: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/saves/{uuid}/attributes"),
+    params(
+        ("uuid" = String, Path, description = "UUID of the game save")
+    ),
+    responses(
+        (status = 200, description = "game save attributes returned", body = HashMap<String, String>),
+        (status = 400, description = "invalid uuid"),
+        (status = 403, description = "the save belongs to another user")
+    )
+)]
+pub async fn get_game_save_attributes(
+    Path((uuid,)): Path<(String,)>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<HashMap<String, String>>, StatusCode> {
+    let uuid = Uuid::parse_str(&uuid).map_err(|_| StatusCode::BAD_REQUEST)?;
+    check_save_owner(caller, uuid)?;
+    match DATABASE.get_save_attributes(uuid) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error getting game save attributes: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/saves/{uuid}/attributes"),
+    params(
+        ("uuid" = String, Path, description = "UUID of the game save")
+    ),
+    request_body = SaveAttribute,
+    responses(
+        (status = 201, description = "game save attribute set"),
+        (status = 400, description = "invalid uuid or attribute"),
+        (status = 404, description = "save not found"),
+        (status = 403, description = "the save belongs to another user")
+    )
+)]
+pub async fn post_game_save_attribute(
+    Path((uuid,)): Path<(String,)>,
+    Extension(caller): Extension<Caller>,
+    Json(payload): Json<SaveAttribute>,
+) -> StatusCode {
+    let Ok(uuid) = Uuid::parse_str(&uuid) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    if let Err(status) = check_save_owner(caller, uuid) {
+        return status;
+    }
+    match DATABASE.set_save_attribute(uuid, &payload.key, &payload.value) {
+        Ok(true) => StatusCode::CREATED,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(DbError::Invalid(e)) => {
+            eprintln!("Error setting game save attribute: {}", e);
+            StatusCode::BAD_REQUEST
+        }
+        Err(e) => {
+            eprintln!("Error setting game save attribute: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = concatcp!(ROOT_API_PATH, "/saves/{uuid}/label"),
+    params(
+        ("uuid" = String, Path, description = "UUID of the game save")
+    ),
+    request_body = SaveLabel,
+    responses(
+        (status = 204, description = "game save label set"),
+        (status = 400, description = "invalid uuid or label"),
+        (status = 404, description = "save not found"),
+        (status = 403, description = "the save belongs to another user")
+    )
+)]
+pub async fn put_game_save_label(
+    Path((uuid,)): Path<(String,)>,
+    Extension(caller): Extension<Caller>,
+    Json(payload): Json<SaveLabel>,
+) -> StatusCode {
+    let Ok(uuid) = Uuid::parse_str(&uuid) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    if let Err(status) = check_save_owner(caller, uuid) {
+        return status;
+    }
+    match DATABASE.set_save_label(uuid, payload.label) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(DbError::Invalid(e)) => {
+            eprintln!("Error setting game save label: {}", e);
+            StatusCode::BAD_REQUEST
+        }
+        Err(e) => {
+            eprintln!("Error setting game save label: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = concatcp!(ROOT_API_PATH, "/saves/{uuid}/pinned"),
+    params(
+        ("uuid" = String, Path, description = "UUID of the game save")
+    ),
+    request_body = SavePinned,
+    responses(
+        (status = 204, description = "game save pinned or unpinned"),
+        (status = 400, description = "invalid uuid"),
+        (status = 404, description = "save not found"),
+        (status = 403, description = "the save belongs to another user")
+    )
+)]
+pub async fn put_game_save_pinned(
+    Path((uuid,)): Path<(String,)>,
+    Extension(caller): Extension<Caller>,
+    Json(payload): Json<SavePinned>,
+) -> StatusCode {
+    let Ok(uuid) = Uuid::parse_str(&uuid) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    if let Err(status) = check_save_owner(caller, uuid) {
+        return status;
+    }
+    match DATABASE.set_save_pinned(uuid, payload.pinned) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            eprintln!("Error pinning game save: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/saves/{uuid}/touch"),
+    params(
+        ("uuid" = String, Path, description = "UUID of the game save")
+    ),
+    responses(
+        (status = 200, description = "save re-timestamped as the latest of its path, nothing is stored again", body = SaveReference),
+        (status = 400, description = "invalid uuid"),
+        (status = 404, description = "save not found"),
+        (status = 403, description = "the save belongs to another user")
+    )
+)]
+pub async fn post_touch_game_save(
+    Path((uuid,)): Path<(String,)>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<SaveReference>, StatusCode> {
+    let Ok(uuid) = Uuid::parse_str(&uuid) else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    check_save_owner(caller, uuid)?;
+    match DATABASE.touch_save(uuid) {
+        Ok(reference) => Ok(Json(reference)),
+        Err(DbError::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error touching game save: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = concatcp!(ROOT_API_PATH, "/saves/{uuid}/path"),
+    params(
+        ("uuid" = String, Path, description = "UUID of the game save")
+    ),
+    request_body = SaveReassign,
+    responses(
+        (status = 200, description = "save moved to the path as its newest generation", body = SaveReference),
+        (status = 400, description = "invalid uuid"),
+        (status = 404, description = "save or path not found"),
+        (status = 409, description = "the path already has a save with the same idempotency key"),
+        (status = 403, description = "the save belongs to another user")
+    )
+)]
+pub async fn put_game_save_path(
+    Path((uuid,)): Path<(String,)>,
+    Extension(caller): Extension<Caller>,
+    Json(payload): Json<SaveReassign>,
+) -> Result<Json<SaveReference>, StatusCode> {
+    let Ok(uuid) = Uuid::parse_str(&uuid) else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    check_save_owner(caller, uuid)?;
+    match DATABASE.reassign_save(uuid, payload.path_id) {
+        Ok(reference) => Ok(Json(reference)),
+        Err(DbError::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(DbError::UniqueViolation(_)) => Err(StatusCode::CONFLICT),
+        Err(e) => {
+            eprintln!("Error reassigning game save: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/saves/retention"),
+    params(RetentionQuery),
+    responses(
+        (status = 200, description = "what pruning by every game's retention policy would remove, nothing is deleted", body = RetentionImpact),
+        (status = 403, description = "only shared tokens reach the saves of every user")
+    )
+)]
+pub async fn get_retention_impact(
+    Query(query): Query<RetentionQuery>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<RetentionImpact>, StatusCode> {
+    require_shared(caller)?;
+    match DATABASE.retention_impact(query.keep_latest) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error computing retention impact: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/retention"),
+    params(
+        ("Id" = String, Path, description = "Id of the game")
+    ),
+    responses(
+        (status = 200, description = "retention stored on the game", body = RetentionPolicy),
+        (status = 404, description = "game not found")
+    )
+)]
+pub async fn get_game_retention_policy(
+    Path((game_id,)): Path<(i32,)>,
+) -> Result<Json<RetentionPolicy>, StatusCode> {
+    match DATABASE.get_retention_policy(game_id) {
+        Ok(Some(data)) => Ok(Json(data)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error getting game retention policy: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/retention"),
+    params(
+        ("Id" = String, Path, description = "Id of the game")
+    ),
+    request_body = RetentionPolicy,
+    responses(
+        (status = 204, description = "retention policy stored, enforced from the next prune"),
+        (status = 400, description = "invalid policy"),
+        (status = 404, description = "game not found")
+    )
+)]
+pub async fn put_game_retention_policy(
+    Path((game_id,)): Path<(i32,)>,
+    Json(payload): Json<RetentionPolicy>,
+) -> StatusCode {
+    match DATABASE.set_retention_policy(game_id, payload) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(DbError::Invalid(e)) => {
+            eprintln!("Error setting game retention policy: {}", e);
+            StatusCode::BAD_REQUEST
+        }
+        Err(e) => {
+            eprintln!("Error setting game retention policy: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/games/{Id}/retention/apply"),
+    params(
+        ("Id" = String, Path, description = "Id of the game")
+    ),
+    responses(
+        (status = 200, description = "uuids of the saves the stored retention pruned", body = [String]),
+        (status = 404, description = "game not found"),
+        (status = 403, description = "only shared tokens reach the saves of every user")
+    )
+)]
+pub async fn post_apply_game_retention(
+    Path((game_id,)): Path<(i32,)>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    require_shared(caller)?;
+    let result = DATABASE.get_retention_policy(game_id).and_then(|policy| {
+        policy
+            .map(|_| DATABASE.apply_retention(game_id))
+            .transpose()
+    });
+    match result {
+        Ok(Some(pruned)) => {
+            for uuid in &pruned.uuids {
+                let _ = fs::remove_file(format!("{}/{}.sav", SAVE_DIR, uuid));
+            }
+            remove_orphaned_blobs(&pruned.orphaned_hashes);
+            Ok(Json(pruned.uuids))
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error applying game retention: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/hashes/{hash}/saves"),
+    params(
+        ("hash" = String, Path, description = "Hash of the file content"),
+        LimitQuery
+    ),
+    responses(
+        (status = 200, description = "game saves containing the file returned", body = [SaveActivity]),
+        (status = 403, description = "only shared tokens reach the saves of every user")
+    )
+)]
+pub async fn get_game_saves_containing_hash(
+    Path((hash,)): Path<(String,)>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<LimitQuery>,
+) -> Result<Json<Vec<SaveActivity>>, StatusCode> {
+    require_shared(caller)?;
+    let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+    match DATABASE.get_saves_containing_hash(&hash, limit) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error getting game saves containing hash: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = concatcp!(ROOT_API_PATH, "/hashes/missing"),
+    request_body = [String],
+    responses(
+        (status = 200, description = "hashes no stored save contains yet", body = [String]),
+    )
+)]
+pub async fn post_missing_hashes(
+    Json(hashes): Json<Vec<String>>,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    match DATABASE.missing_hashes(&hashes) {
+        Ok(data) => Ok(Json(data)),
+        Err(e) => {
+            eprintln!("Error checking missing hashes: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Deletes the blobs a delete or prune left without any reference
+fn remove_orphaned_blobs(hashes: &[String]) {
+    for hash in hashes {
+        if let Err(e) = BLOB_STORE.delete(hash) {
+            eprintln!("Error removing orphaned blob {}: {}", hash, e);
+        }
+    }
+}
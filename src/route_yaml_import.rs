@@ -1,10 +1,10 @@
-use axum::{extract::Multipart, http::StatusCode};
+use axum::{Json, extract::Multipart, http::StatusCode};
 use const_format::concatcp;
 use tokio::fs;
 
 use crate::{
     const_var::{ROOT_API_PATH, TMP_DIR},
-    datatype_endpoint::UploadedFileYaml,
+    datatype_endpoint::{ImportSummary, UploadedFileYaml},
     file_system::write_bytes_to_tmp_file,
     ludusavi::yaml_import,
 };
@@ -18,12 +18,15 @@ use crate::{
         description = "Ludusavi manifest"
     ),
     responses(
-        (status = 200, description = "Ludusavi manifest imported", body = String),
+        (status = 200, description = "Ludusavi manifest imported", body = ImportSummary),
     )
 )]
-pub async fn post_ludusavi_yaml(mut multipart: Multipart) -> StatusCode {
+pub async fn post_ludusavi_yaml(
+    mut multipart: Multipart,
+) -> Result<Json<ImportSummary>, StatusCode> {
     let tmp_path = format!("{}/{}", TMP_DIR, "ludusavi.yaml");
     let mut err: Option<String> = None;
+    let mut summary = ImportSummary::default();
 
     let mut file_bytes: Vec<u8> = Vec::new();
     while let Some(field) = multipart.next_field().await.ok().flatten() {
@@ -34,16 +37,19 @@ pub async fn post_ludusavi_yaml(mut multipart: Multipart) -> StatusCode {
 
     if let Err(e) = write_bytes_to_tmp_file(&tmp_path, &file_bytes).await {
         err = Some(format!("write file failed: {}", e));
-    } else if let Err(e) = yaml_import(&tmp_path).await {
-        err = Some(format!("yaml import failed: {}", e));
+    } else {
+        match yaml_import(&tmp_path).await {
+            Ok(imported) => summary = imported,
+            Err(e) => err = Some(format!("yaml import failed: {}", e)),
+        }
     }
 
     // Whatever happened, clean up
     let _ = fs::remove_file(&tmp_path).await;
     if let Some(e) = err {
         eprintln!("Error importing ludusavi manifest: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
+        Err(StatusCode::INTERNAL_SERVER_ERROR)
     } else {
-        StatusCode::OK
+        Ok(Json(summary))
     }
 }
@@ -0,0 +1,127 @@
+use crate::const_var::MAINTENANCE_BUSY_BACKOFF_SECS;
+use crate::database::error::DbError;
+use crate::database::maintenance::{MaintenanceOpts, MaintenanceReport};
+use crate::{BLOB_STORE, DATABASE};
+use std::time::Duration;
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{info, warn};
+
+/// Runs `run_maintenance` with its default jobs every `interval`, the first
+/// run waits a whole interval so startup is not slowed down. A run finding
+/// the database busy is retried after a doubling backoff, and left to the
+/// next tick once that backoff would reach the interval
+pub fn spawn_maintenance(interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval_at(Instant::now() + interval, interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let backoff = Duration::from_secs(MAINTENANCE_BUSY_BACKOFF_SECS);
+            match retry_while_busy(run_maintenance, backoff, interval).await {
+                Ok(report) => info!(
+                    saves_pruned = report.saves_pruned.len(),
+                    blobs_removed = report.blobs_removed.len(),
+                    bytes_reclaimed = report.bytes_reclaimed,
+                    database_bytes_reclaimed = ?report.database_bytes_reclaimed,
+                    "scheduled maintenance done"
+                ),
+                Err(DbError::Busy) => {
+                    warn!("database still busy, scheduled maintenance skipped until the next run")
+                }
+                Err(e) => eprintln!("Error running scheduled maintenance: {}", e),
+            }
+        }
+    });
+}
+
+async fn run_maintenance() -> Result<MaintenanceReport, DbError> {
+    tokio::task::spawn_blocking(|| {
+        DATABASE.run_maintenance(&*BLOB_STORE, MaintenanceOpts::default())
+    })
+    .await
+    .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+/// Runs `job` again while it fails with `Busy`, sleeping `backoff` and then
+/// twice as long each time. The `Busy` is returned once the next sleep would
+/// be `limit` or longer
+async fn retry_while_busy<T, F, Fut>(
+    mut job: F,
+    mut backoff: Duration,
+    limit: Duration,
+) -> Result<T, DbError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, DbError>>,
+{
+    loop {
+        match job().await {
+            Err(DbError::Busy) if backoff < limit => {
+                warn!(
+                    backoff_ms = backoff.as_millis() as u64,
+                    "database busy, maintenance retried after a backoff"
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Job failing with each of `failures` in turn before it succeeds,
+    /// `attempts` counts the runs
+    async fn attempt(attempts: &Cell<usize>, failures: &[fn() -> DbError]) -> Result<(), DbError> {
+        let attempt = attempts.get();
+        attempts.set(attempt + 1);
+        match failures.get(attempt) {
+            Some(failure) => Err(failure()),
+            None => Ok(()),
+        }
+    }
+
+    #[tokio::test]
+    async fn busy_runs_are_retried_until_they_go_through() {
+        let attempts = Cell::new(0);
+        let failures: [fn() -> DbError; 2] = [|| DbError::Busy, || DbError::Busy];
+        let result = retry_while_busy(
+            || attempt(&attempts, &failures),
+            Duration::from_millis(1),
+            Duration::from_secs(1),
+        )
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn backoff_stops_before_the_limit_and_other_errors_are_not_retried() {
+        let attempts = Cell::new(0);
+        let failures: [fn() -> DbError; 8] = [|| DbError::Busy; 8];
+        let result = retry_while_busy(
+            || attempt(&attempts, &failures),
+            Duration::from_millis(1),
+            Duration::from_millis(4),
+        )
+        .await;
+        assert!(matches!(result, Err(DbError::Busy)));
+        // Slept 1ms and 2ms, the next 4ms backoff reaches the limit
+        assert_eq!(attempts.get(), 3);
+
+        let attempts = Cell::new(0);
+        let failures: [fn() -> DbError; 1] = [|| DbError::NotFound];
+        let result = retry_while_busy(
+            || attempt(&attempts, &failures),
+            Duration::from_millis(1),
+            Duration::from_secs(1),
+        )
+        .await;
+        assert!(matches!(result, Err(DbError::NotFound)));
+        assert_eq!(attempts.get(), 1);
+    }
+}
@@ -0,0 +1,148 @@
+/// What the Steam store knows about an app, used to enrich a game added
+/// with just an appid and a rough name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SteamAppDetails {
+    /// Official title, in the first language the client asks for
+    pub name: String,
+    /// Titles in the other languages, may repeat `name`
+    pub localized_names: Vec<String>,
+}
+
+/// Source of Steam app details, a trait so the enrichment can run against
+/// a canned client instead of the store
+pub trait SteamClient {
+    /// `None` when Steam does not know the app
+    fn app_details(&self, appid: &str) -> std::io::Result<Option<SteamAppDetails>>;
+}
+
+#[cfg(feature = "steam")]
+pub use http::HttpSteamClient;
+
+#[cfg(feature = "steam")]
+mod http {
+    use super::{SteamAppDetails, SteamClient};
+    use crate::const_var::{
+        DEFAULT_STEAM_STORE_URL, STEAM_LANGUAGES_ENV, STEAM_REQUEST_TIMEOUT_SECS,
+        STEAM_STORE_URL_ENV,
+    };
+    use std::io::{Error, ErrorKind, Read, Write};
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    /// Store `appdetails` API over plain HTTP, like webhooks TLS is left to
+    /// a relay set through `STEAM_STORE_URL`
+    pub struct HttpSteamClient {
+        host: String,
+        port: u16,
+        /// Steam language names, the first gives the official title
+        languages: Vec<String>,
+    }
+
+    impl HttpSteamClient {
+        pub fn from_env() -> Result<Self, String> {
+            let url = std::env::var(STEAM_STORE_URL_ENV)
+                .unwrap_or_else(|_| DEFAULT_STEAM_STORE_URL.to_string());
+            let authority = url
+                .strip_prefix("http://")
+                .ok_or("only http:// store urls are supported")?
+                .trim_end_matches('/');
+            let (host, port) = match authority.rsplit_once(':') {
+                Some((host, port)) => (
+                    host,
+                    port.parse::<u16>()
+                        .map_err(|_| format!("invalid port: {port}"))?,
+                ),
+                None => (authority, 80),
+            };
+            if host.is_empty() || host.contains('/') {
+                return Err(format!("invalid store url: {url}"));
+            }
+
+            let mut languages: Vec<String> = std::env::var(STEAM_LANGUAGES_ENV)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|language| !language.is_empty())
+                .map(str::to_string)
+                .collect();
+            if languages.is_empty() {
+                languages.push("english".to_string());
+            }
+            Ok(Self {
+                host: host.to_string(),
+                port,
+                languages,
+            })
+        }
+
+        /// Body of a `GET`, HTTP/1.0 so the answer is never chunked
+        fn get(&self, path: &str) -> std::io::Result<Vec<u8>> {
+            let mut stream = TcpStream::connect((self.host.as_str(), self.port))?;
+            let timeout = Some(Duration::from_secs(STEAM_REQUEST_TIMEOUT_SECS));
+            stream.set_read_timeout(timeout)?;
+            stream.set_write_timeout(timeout)?;
+            write!(
+                stream,
+                "GET {path} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\n\r\n",
+                self.host
+            )?;
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response)?;
+
+            let header_end = response
+                .windows(4)
+                .position(|window| window == b"\r\n\r\n")
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "malformed store response"))?;
+            let status_line = String::from_utf8_lossy(&response[..header_end]);
+            let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+            if status != "200" {
+                return Err(Error::other(format!("store answered {status}")));
+            }
+            Ok(response.split_off(header_end + 4))
+        }
+
+        fn app_name(&self, appid: &str, language: &str) -> std::io::Result<Option<String>> {
+            let body = self.get(&format!(
+                "/api/appdetails?appids={appid}&l={language}&filters=basic"
+            ))?;
+            let json: serde_json::Value =
+                serde_json::from_slice(&body).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+            let app = &json[appid];
+            if app["success"] != serde_json::Value::Bool(true) {
+                return Ok(None);
+            }
+            Ok(app["data"]["name"]
+                .as_str()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string))
+        }
+    }
+
+    impl SteamClient for HttpSteamClient {
+        fn app_details(&self, appid: &str) -> std::io::Result<Option<SteamAppDetails>> {
+            if appid.is_empty() || !appid.bytes().all(|byte| byte.is_ascii_digit()) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("invalid steam appid: {appid}"),
+                ));
+            }
+            let Some((language, others)) = self.languages.split_first() else {
+                return Ok(None);
+            };
+            let Some(name) = self.app_name(appid, language)? else {
+                return Ok(None);
+            };
+            let mut localized_names = Vec::new();
+            for language in others {
+                if let Some(localized) = self.app_name(appid, language)? {
+                    localized_names.push(localized);
+                }
+            }
+            Ok(Some(SteamAppDetails {
+                name,
+                localized_names,
+            }))
+        }
+    }
+}
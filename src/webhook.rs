@@ -0,0 +1,239 @@
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::const_var::{
+    WEBHOOK_ATTEMPT_TIMEOUT_SECS, WEBHOOK_INITIAL_BACKOFF_MS, WEBHOOK_MAX_ATTEMPTS,
+    WEBHOOK_SECRET_ENV, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_URLS_ENV,
+};
+use crate::datatype_endpoint::SaveCreatedEvent;
+
+pub struct WebhookConfig {
+    pub urls: Vec<WebhookUrl>,
+    pub secret: Option<String>,
+}
+
+pub struct WebhookUrl {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl WebhookUrl {
+    /// `Host` header value, the port is left out only when it is the default
+    fn authority(&self) -> String {
+        if self.port == 80 {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+pub static WEBHOOK_CONFIG: Lazy<WebhookConfig> = Lazy::new(|| {
+    let urls = std::env::var(WEBHOOK_URLS_ENV)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .filter_map(|url| match parse_webhook_url(url) {
+            Ok(url) => Some(url),
+            Err(e) => {
+                eprintln!("Ignoring webhook url {}: {}", url, e);
+                None
+            }
+        })
+        .collect();
+    let secret = std::env::var(WEBHOOK_SECRET_ENV)
+        .ok()
+        .filter(|s| !s.is_empty());
+    WebhookConfig { urls, secret }
+});
+
+/// Only plain `http://` is supported, TLS termination is left to a relay.
+fn parse_webhook_url(url: &str) -> Result<WebhookUrl, String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or("only http:// webhook urls are supported")?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse::<u16>()
+                .map_err(|_| format!("invalid port: {port}"))?,
+        ),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err("missing host".to_string());
+    }
+    Ok(WebhookUrl {
+        host: host.to_string(),
+        port,
+        path: path.to_string(),
+    })
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|byte| byte ^ 0x36));
+    inner.update(message);
+    let inner = inner.finalize();
+
+    let mut outer = Sha256::new();
+    outer.update(block.map(|byte| byte ^ 0x5c));
+    outer.update(inner);
+    outer.finalize().into()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// One delivery attempt, connecting, sending and reading the answer all
+/// count towards `timeout` so an endpoint that never closes the connection
+/// cannot hold the task
+async fn post_json(
+    url: &WebhookUrl,
+    body: &[u8],
+    signature: Option<&str>,
+    timeout: Duration,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    tokio::time::timeout(timeout, send_request(url, body, signature))
+        .await
+        .map_err(|_| format!("no answer within {} seconds", timeout.as_secs_f32()))?
+}
+
+async fn send_request(
+    url: &WebhookUrl,
+    body: &[u8],
+    signature: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut stream = TcpStream::connect((url.host.as_str(), url.port)).await?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        url.path,
+        url.authority(),
+        body.len()
+    );
+    if let Some(signature) = signature {
+        request.push_str(&format!(
+            "{}: sha256={}\r\n",
+            WEBHOOK_SIGNATURE_HEADER, signature
+        ));
+    }
+    request.push_str("\r\n");
+
+    stream.write_all(request.as_bytes()).await?;
+    stream.write_all(body).await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let status_line = response
+        .split(|byte| *byte == b'\n')
+        .next()
+        .map(String::from_utf8_lossy)
+        .unwrap_or_default();
+    let status: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or("invalid http response")?;
+
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(format!("webhook answered with status {status}").into())
+    }
+}
+
+async fn deliver(url: &WebhookUrl, body: &[u8], signature: Option<&str>) {
+    let mut backoff = Duration::from_millis(WEBHOOK_INITIAL_BACKOFF_MS);
+    let timeout = Duration::from_secs(WEBHOOK_ATTEMPT_TIMEOUT_SECS);
+    for attempt in 1..=WEBHOOK_MAX_ATTEMPTS {
+        match post_json(url, body, signature, timeout).await {
+            Ok(()) => return,
+            Err(e) => {
+                eprintln!(
+                    "Error delivering webhook to {}:{}{} (attempt {}/{}): {}",
+                    url.host, url.port, url.path, attempt, WEBHOOK_MAX_ATTEMPTS, e
+                );
+            }
+        }
+        if attempt < WEBHOOK_MAX_ATTEMPTS {
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+}
+
+/// Fire and forget, delivery failures are only logged.
+pub fn notify_save_created(event: SaveCreatedEvent) {
+    if WEBHOOK_CONFIG.urls.is_empty() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let body = match serde_json::to_vec(&event) {
+            Ok(body) => body,
+            Err(e) => {
+                eprintln!("Error serializing webhook payload: {}", e);
+                return;
+            }
+        };
+        let signature = WEBHOOK_CONFIG
+            .secret
+            .as_ref()
+            .map(|secret| to_hex(&hmac_sha256(secret.as_bytes(), &body)));
+
+        for url in &WEBHOOK_CONFIG.urls {
+            let body = body.clone();
+            let signature = signature.clone();
+            tokio::spawn(async move { deliver(url, &body, signature.as_deref()).await });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn host_header_keeps_a_non_default_port() {
+        let url = parse_webhook_url("http://relay.local:8080/hook").unwrap();
+        assert_eq!(url.authority(), "relay.local:8080");
+        let url = parse_webhook_url("http://relay.local/hook").unwrap();
+        assert_eq!(url.authority(), "relay.local");
+    }
+
+    #[tokio::test]
+    async fn attempt_gives_up_on_a_silent_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // Accepts and holds the connection without ever answering
+        let server = tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await
+        });
+
+        let url = parse_webhook_url(&format!("http://127.0.0.1:{port}/hook")).unwrap();
+        let result = post_json(&url, b"{}", None, Duration::from_millis(200)).await;
+        assert!(result.is_err());
+        server.abort();
+    }
+}
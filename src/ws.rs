@@ -0,0 +1,165 @@
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code};
+use axum::extract::{Extension, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use once_cell::sync::Lazy;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::auth::Caller;
+use crate::const_var::{
+    ROOT_API_PATH, SAVE_EVENTS_BUFFER, SAVE_EVENTS_MAX_CLIENT_FRAME, SAVE_EVENTS_WRITE_TIMEOUT_SECS,
+};
+use crate::datatype_endpoint::{SaveEventsQuery, SaveNotification};
+use crate::{ASYNC_DATABASE, DATABASE};
+use const_format::concatcp;
+
+/// Fed by the save created callback, sending never waits on a subscriber.
+/// One that falls more than `SAVE_EVENTS_BUFFER` events behind is closed
+/// and has to catch up by polling before it subscribes again
+pub static SAVE_EVENTS: Lazy<broadcast::Sender<SaveNotification>> =
+    Lazy::new(|| broadcast::channel(SAVE_EVENTS_BUFFER).0);
+
+/// Forwards every committed save to `SAVE_EVENTS`
+pub fn register_save_events() {
+    DATABASE.on_save_created(Box::new(|save| {
+        // An error only means nobody is subscribed
+        let _ = SAVE_EVENTS.send(SaveNotification::from(save));
+    }));
+}
+
+enum Subscription {
+    Path(i32),
+    Game(i32),
+}
+
+impl Subscription {
+    /// The game of a path is looked up for every event since a merge moves
+    /// paths to another game
+    async fn matches(&self, path_id: i32) -> bool {
+        match self {
+            Subscription::Path(subscribed) => *subscribed == path_id,
+            Subscription::Game(subscribed) => {
+                match ASYNC_DATABASE.get_game_id_by_path_id(path_id).await {
+                    Ok(game_id) => game_id == Some(*subscribed),
+                    Err(e) => {
+                        eprintln!("Error looking up the game of path {}: {}", path_id, e);
+                        false
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A user token only hears about its own saves, a shared token about all
+async fn is_visible(caller: Caller, notification: &SaveNotification) -> bool {
+    let Some(user_id) = caller.user_id() else {
+        return true;
+    };
+    let Ok(uuid) = Uuid::parse_str(&notification.uuid) else {
+        return false;
+    };
+    match ASYNC_DATABASE.get_save_owner(uuid).await {
+        Ok(owner) => owner == Some(Some(user_id)),
+        Err(e) => {
+            eprintln!("Error looking up the owner of save {}: {}", uuid, e);
+            false
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = concatcp!(ROOT_API_PATH, "/saves/events"),
+    params(SaveEventsQuery),
+    responses(
+        (status = 101, description = "WebSocket opened, every save created for the path or game is sent as a text frame, only the caller's own saves for a user token", body = SaveNotification),
+        (status = 400, description = "not a WebSocket handshake, or not exactly one of path_id and game_id"),
+        (status = 404, description = "no such path or game")
+    )
+)]
+pub async fn get_save_events(
+    Query(query): Query<SaveEventsQuery>,
+    Extension(caller): Extension<Caller>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let subscription = match (query.path_id, query.game_id) {
+        (Some(path_id), None) => match ASYNC_DATABASE.get_game_id_by_path_id(path_id).await {
+            Ok(Some(_)) => Subscription::Path(path_id),
+            Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+            Err(e) => {
+                eprintln!("Error looking up path {}: {}", path_id, e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        },
+        (None, Some(game_id)) => match ASYNC_DATABASE.get_game_metadata_by_id(game_id).await {
+            Ok(Some(_)) => Subscription::Game(game_id),
+            Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+            Err(e) => {
+                eprintln!("Error looking up game {}: {}", game_id, e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        },
+        _ => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    // Subscribed before answering so no save committed after the 101 is missed
+    let events = SAVE_EVENTS.subscribe();
+    upgrade
+        .max_message_size(SAVE_EVENTS_MAX_CLIENT_FRAME)
+        .max_frame_size(SAVE_EVENTS_MAX_CLIENT_FRAME)
+        .on_failed_upgrade(|e| eprintln!("Error upgrading save events connection: {}", e))
+        .on_upgrade(move |socket| serve_subscriber(socket, subscription, caller, events))
+}
+
+async fn serve_subscriber(
+    mut socket: WebSocket,
+    subscription: Subscription,
+    caller: Caller,
+    mut events: broadcast::Receiver<SaveNotification>,
+) {
+    let write_timeout = Duration::from_secs(SAVE_EVENTS_WRITE_TIMEOUT_SECS);
+    loop {
+        let message = tokio::select! {
+            event = events.recv() => match event {
+                Ok(notification) => {
+                    if !subscription.matches(notification.path_id).await
+                        || !is_visible(caller, &notification).await
+                    {
+                        continue;
+                    }
+                    match serde_json::to_string(&notification) {
+                        Ok(body) => Message::Text(body.into()),
+                        Err(e) => {
+                            eprintln!("Error serializing save notification: {}", e);
+                            continue;
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("Closing save events subscriber {} events behind", skipped);
+                    Message::Close(Some(CloseFrame {
+                        code: close_code::AGAIN,
+                        reason: "too far behind, poll before subscribing again".into(),
+                    }))
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            // Pings are answered by the socket itself and subscribers have
+            // nothing to send, only the end of the stream matters
+            received = socket.recv() => match received {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+        let closing = matches!(message, Message::Close(_));
+        // A subscriber that stops reading is dropped instead of piling up
+        // events in its socket buffer
+        let sent = tokio::time::timeout(write_timeout, socket.send(message)).await;
+        if !matches!(sent, Ok(Ok(()))) || closing {
+            break;
+        }
+    }
+}